@@ -25,9 +25,25 @@
  * structure using clap.
  */
 
-use clap::{Parser, ValueEnum};
+use clap::{Args, Parser, ValueEnum};
 use serde::Serialize;
-use tonic_music_core::{ChordType, HarmonicFormula, ScaleType};
+use std::path::PathBuf;
+use tonic_music_core::{
+    ChordType, ScaleType,
+    chordpro::ChordStyle,
+    generator::{EndingCadence, GeneratorStyle},
+    instrument::Instrument,
+    keyboard::Hand,
+    midi::MidiOptions,
+    parser::parse_time_signature,
+    recognition::Segmentation,
+    reharmonize::SubstitutionRule,
+    scale_shapes::ShapeSystem,
+    synth::{Envelope, SynthOptions, Waveform},
+    voice_leading::VoiceLeadingOptions,
+    voicing::VoicingStyle,
+    wav::SampleFormat,
+};
 
 /// Available output formats
 #[derive(ValueEnum, Clone, Debug, Default, Serialize)]
@@ -37,6 +53,20 @@ pub enum OutputFormat {
     Text,
     Json,
     Markdown,
+    /// Guitar/bass tablature (see --tab-tuning)
+    Tab,
+    /// Standard MIDI File (requires --output)
+    Midi,
+    /// MusicXML score for notation programs (MuseScore, Finale...)
+    #[value(alias("xml"))]
+    Musicxml,
+    /// LilyPond score (.ly) to engrave with `lilypond`
+    #[value(alias("ly"))]
+    Lilypond,
+    /// ABC notation (.abc) for abcjs, EasyABC or abcm2ps
+    Abc,
+    /// WAV audio rendered by the built-in synthesizer (requires --output)
+    Wav,
 }
 
 /// A command-line music theory calculator
@@ -50,6 +80,36 @@ pub struct Cli {
     /// Output format
     #[arg(long, value_enum, default_value_t = OutputFormat::Text, global = true)]
     pub format: OutputFormat,
+
+    /// Extra progression library file (.toml/.json) or directory of them.
+    /// Files in the config directory (e.g. ~/.config/tonic-music/progressions) load automatically.
+    #[arg(long, global = true)]
+    pub library: Vec<PathBuf>,
+
+    /// The tuning of --format tab: a preset (standard, drop-d, bass4, ukulele...)
+    /// or pitches from the lowest string ("D2 A2 D3 G3 B3 E4")
+    #[arg(long, global = true, default_value = "standard")]
+    pub tab_tuning: String,
+
+    /// Write scales, chords and progressions for a transposing instrument
+    /// (bb-trumpet, alto-sax, tenor-sax, f-horn...) instead of concert pitch
+    #[arg(long, value_enum, global = true)]
+    pub instrument: Option<Instrument>,
+
+    /// Write the output to a file instead of the terminal
+    #[arg(short, long, global = true)]
+    pub output: Option<PathBuf>,
+
+    /// Write chord degrees as Roman numerals, Nashville numbers ("1 6- 2- 5")
+    /// or chord names in the key
+    #[arg(long, value_enum, global = true)]
+    pub degrees: Option<ChordStyle>,
+
+    #[command(flatten)]
+    pub midi: MidiArgs,
+
+    #[command(flatten)]
+    pub audio: AudioArgs,
 }
 
 /// Defines the available subcommands
@@ -64,6 +124,10 @@ pub enum Commands {
         /// The type of scale (e.g., major, minor, harmonic)
         #[arg(short, long, value_enum)]
         scale_type: ScaleType,
+
+        /// Show the standard piano fingering of one hand (right or left)
+        #[arg(long, value_enum)]
+        fingering: Option<Hand>,
     },
 
     /// Generate the notes of a chord
@@ -79,6 +143,24 @@ pub enum Commands {
         /// Also display the chord's inversions
         #[arg(long)]
         inversions: bool,
+
+        /// Also display a piano voicing with octaves (e.g., drop2, shell, rootless-a, quartal)
+        #[arg(long, value_enum)]
+        voicing: Option<VoicingStyle>,
+
+        /// The octave of the root for --voicing (-1 to 8, e.g., 3 for C3)
+        #[arg(
+            long,
+            default_value_t = 3,
+            requires = "voicing",
+            allow_negative_numbers = true,
+            value_parser = clap::value_parser!(i8).range(-1..=8)
+        )]
+        octave: i8,
+
+        /// Also draw the chord on a piano keyboard (the voicing, if any)
+        #[arg(long)]
+        piano: bool,
     },
 
     /// Harmonize a scale (find all its diatonic chords)
@@ -98,16 +180,529 @@ pub enum Commands {
 
     /// Generate a harmonic progression (chord formula)
     Progression {
-        /// The root note of the progression (e.g., C, Bb)
+        /// The root note of the progression (e.g., C, Bb).
+        /// Optional for library progressions that define a default key.
         #[arg(short, long)]
-        root: String,
+        root: Option<String>,
 
-        /// The name of the formula (e.g., block, circle)
-        #[arg(short, long, value_enum, required_unless_present = "custom")]
-        formula: Option<HarmonicFormula>,
+        /// The name of a library progression (e.g., block, circle, ii-v-i-major)
+        #[arg(short, long, required_unless_present_any = ["custom", "list"])]
+        formula: Option<String>,
 
         /// A custom formula string (e.g. "I-IV-V7")
+        #[arg(long, required_unless_present_any = ["formula", "list"])]
+        custom: Option<String>,
+
+        /// List the progressions in the library instead of building one
+        #[arg(long, conflicts_with_all = ["formula", "custom"])]
+        list: bool,
+
+        /// Only list progressions with this tag (e.g., jazz, blues, pop)
+        #[arg(long, requires = "list")]
+        tag: Option<String>,
+
+        /// Also voice the progression for four voices (bass, tenor, alto, soprano)
+        #[arg(long, conflicts_with = "list")]
+        satb: bool,
+
+        /// Also voice every chord in a piano voicing style (e.g., drop2, rootless-a)
+        #[arg(long, value_enum, conflicts_with_all = ["list", "satb"])]
+        voicing: Option<VoicingStyle>,
+
+        /// The octave of the roots for --voicing (-1 to 8, e.g., 3 for C3)
+        #[arg(
+            long,
+            default_value_t = 3,
+            requires = "voicing",
+            allow_negative_numbers = true,
+            value_parser = clap::value_parser!(i8).range(-1..=8)
+        )]
+        octave: i8,
+
+        #[command(flatten)]
+        voice_leading: VoiceLeadingArgs,
+    },
+
+    /// Suggest reharmonizations of a progression using substitution rules
+    Reharmonize {
+        /// The root note of the progression (e.g., C, Bb).
+        /// Optional for library progressions that define a default key.
+        #[arg(short, long)]
+        root: Option<String>,
+
+        /// The name of a library progression (e.g., block, circle, ii-v-i-major)
+        #[arg(short, long, required_unless_present = "custom")]
+        formula: Option<String>,
+
+        /// A custom formula string (e.g. "ii7-V7-Imaj7")
         #[arg(long, required_unless_present = "formula")]
         custom: Option<String>,
+
+        /// The substitution rules to try (comma separated). Defaults to all rules.
+        #[arg(long, value_enum, value_delimiter = ',')]
+        rules: Vec<SubstitutionRule>,
+
+        /// How many substitutions may be chained on top of each other (1-3)
+        #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=3))]
+        depth: u8,
+
+        #[command(flatten)]
+        voice_leading: VoiceLeadingArgs,
+    },
+
+    /// Generates a random progression from a Markov chain of chord moves
+    Generate {
+        /// The key of the progression (e.g., C, Bb)
+        #[arg(short, long)]
+        root: String,
+
+        /// The built-in transition table to walk
+        #[arg(short, long, value_enum, default_value = "pop")]
+        style: GeneratorStyle,
+
+        /// Number of chords, cadence included
+        #[arg(short, long, default_value_t = 8)]
+        length: usize,
+
+        /// The seed for the random walk. The same seed gives the same progression.
+        #[arg(long)]
+        seed: Option<u64>,
+
+        /// The cadence the progression ends with
+        #[arg(short, long, value_enum, default_value = "authentic")]
+        cadence: EndingCadence,
+
+        /// Learn the transition table from these progressions instead of --style
+        /// (library names or formulas like "I-vi-IV-V"; repeatable)
+        #[arg(short, long)]
+        train: Vec<String>,
+
+        #[command(flatten)]
+        voice_leading: VoiceLeadingArgs,
+    },
+
+    /// Show where the notes of a scale or chord lie on a fretted instrument
+    Fretboard {
+        /// The root note of the scale or chord
+        #[arg(short, long)]
+        root: String,
+
+        /// The type of scale to show
+        #[arg(
+            short,
+            long,
+            value_enum,
+            required_unless_present = "chord_type",
+            conflicts_with = "chord_type"
+        )]
+        scale_type: Option<ScaleType>,
+
+        /// The type of chord to show
+        #[arg(short, long, value_enum)]
+        chord_type: Option<ChordType>,
+
+        /// A tuning preset (standard, drop-d, dadgad, open-g, open-d, open-e, seven-string,
+        /// bass4, bass5, ukulele, mandolin) or pitches from the lowest string ("D2 A2 D3 G3 B3 E4")
+        #[arg(short, long, default_value = "standard")]
+        tuning: String,
+
+        /// The fret clamped by a capo
+        #[arg(long, default_value_t = 0)]
+        capo: u8,
+
+        /// The last fret to show
+        #[arg(long, default_value_t = 12)]
+        frets: u8,
+    },
+
+    /// Split a scale into guitar positions: CAGED shapes, pentatonic boxes or 3NPS patterns
+    Shapes {
+        /// The root note of the scale
+        #[arg(short, long)]
+        root: String,
+
+        /// The type of scale (e.g., major, pentatonic-minor)
+        #[arg(short, long, value_enum)]
+        scale_type: ScaleType,
+
+        /// The shape system (default: pentatonic boxes for five-note scales, CAGED otherwise)
+        #[arg(long, value_enum)]
+        system: Option<ShapeSystem>,
+
+        /// A tuning preset (standard, drop-d, dadgad...) or pitches from the lowest string
+        #[arg(short, long, default_value = "standard")]
+        tuning: String,
+
+        /// The fret clamped by a capo
+        #[arg(long, default_value_t = 0)]
+        capo: u8,
+
+        /// Only show this shape (1 = the first)
+        #[arg(long)]
+        shape: Option<usize>,
+    },
+
+    /// Find playable fingerings of a chord on a fretted instrument, easiest first
+    Fingering {
+        /// The root note of the chord
+        #[arg(short, long)]
+        root: String,
+
+        /// The type of chord (e.g., major, min, 7, maj7)
+        #[arg(short, long, value_enum)]
+        chord_type: ChordType,
+
+        /// A tuning preset (standard, drop-d, dadgad, ukulele...) or pitches from the lowest string
+        #[arg(short, long, default_value = "standard")]
+        tuning: String,
+
+        /// The fret clamped by a capo
+        #[arg(long, default_value_t = 0)]
+        capo: u8,
+
+        /// The lowest sounding note (default: the root)
+        #[arg(long, conflicts_with = "any_bass")]
+        bass: Option<String>,
+
+        /// Allow any chord tone in the bass (inversions)
+        #[arg(long)]
+        any_bass: bool,
+
+        /// The widest span in frets between the lowest and highest fretted notes (1-12)
+        #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u8).range(1..=12))]
+        max_stretch: u8,
+
+        /// The fingers available (a barre counts as one)
+        #[arg(long, default_value_t = 4)]
+        max_fingers: u8,
+
+        /// The highest fret to search
+        #[arg(long, default_value_t = 12)]
+        max_fret: u8,
+
+        /// The fewest strings that must sound (default: all but two)
+        #[arg(long)]
+        min_strings: Option<usize>,
+
+        /// Do not use open strings
+        #[arg(long)]
+        no_open: bool,
+
+        /// Allow muted strings between sounding strings
+        #[arg(long)]
+        inner_mutes: bool,
+
+        /// The fifth may be left out
+        #[arg(long)]
+        omit_fifth: bool,
+
+        /// How many fingerings to show
+        #[arg(short = 'n', long, default_value_t = 5)]
+        count: usize,
+    },
+
+    /// Checks four-part voicings for part-writing errors (parallel fifths, crossing...)
+    CheckVoicing {
+        /// A JSON file with the voicings to check:
+        /// { "key": "C", "chords": [["C3", "G3", "E4", "C5"], ...] } (lowest voice first)
+        #[arg(short, long, conflicts_with_all = ["root", "formula", "custom"])]
+        input: Option<PathBuf>,
+
+        /// The key of the progression to voice and check (e.g., C, Bb)
+        #[arg(short, long, required_unless_present = "input")]
+        root: Option<String>,
+
+        /// The name of a library progression to voice and check
+        #[arg(short, long, required_unless_present_any = ["custom", "input"])]
+        formula: Option<String>,
+
+        /// A custom formula string to voice and check (e.g. "I-IV-V7-I")
+        #[arg(long, required_unless_present_any = ["formula", "input"])]
+        custom: Option<String>,
+    },
+
+    /// Recognizes the chords of a MIDI file and analyzes them with Roman numerals
+    AnalyzeMidi {
+        /// The Standard MIDI File (.mid) to analyze
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// How to split the notes before naming chords: beat, bar or onset
+        #[arg(short, long, value_enum, default_value_t = Segmentation::Beat)]
+        segment: Segmentation,
+
+        /// The key of the analysis (e.g. C, Am, "Eb major"). Estimated from the notes if omitted
+        #[arg(short, long)]
+        key: Option<String>,
+    },
+
+    /// Detects the pitch of a mono WAV recording over time, like a tuner
+    AnalyzePitch {
+        /// The WAV file (.wav) to analyze. Stereo files are mixed down to mono
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// The frequency of A4 in Hz
+        #[arg(long, default_value_t = 440.0)]
+        a4: f64,
+
+        /// Samples per analysis frame
+        #[arg(long, default_value_t = 2048)]
+        window: usize,
+
+        /// Samples between frames
+        #[arg(long, default_value_t = 512)]
+        hop: usize,
+
+        /// YIN threshold (lower rejects more noisy frames)
+        #[arg(long, default_value_t = 0.15)]
+        threshold: f64,
+
+        /// The lowest frequency searched, in Hz
+        #[arg(long, default_value_t = 50.0)]
+        min_frequency: f64,
+
+        /// The highest frequency searched, in Hz
+        #[arg(long, default_value_t = 2000.0)]
+        max_frequency: f64,
+    },
+
+    /// Recognizes the chords of a WAV recording from its chromagram and analyzes them
+    AnalyzeAudio {
+        /// The WAV file (.wav) to analyze. Stereo files are mixed down to mono
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// The key of the analysis (e.g. C, Am, "Eb major"). Estimated from the audio if omitted
+        #[arg(short, long)]
+        key: Option<String>,
+
+        /// The frequency of A4 in Hz
+        #[arg(long, default_value_t = 440.0)]
+        a4: f64,
+
+        /// Samples per FFT frame (a power of two)
+        #[arg(long, default_value_t = 8192)]
+        window: usize,
+
+        /// Samples between frames
+        #[arg(long, default_value_t = 2048)]
+        hop: usize,
+
+        /// Frames that vote on each chord; more frames ignore shorter chords
+        #[arg(long, default_value_t = 9)]
+        smoothing: usize,
+    },
+
+    /// Reads the chord symbols of a MusicXML chart (or the chords of its notes) as a progression
+    ImportMusicxml {
+        /// The uncompressed MusicXML file (.musicxml, .xml) to read
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// The key of the progression (e.g. C, Am, "Eb major"). Read from the key signature,
+        /// or estimated from the chords, if omitted
+        #[arg(short, long)]
+        key: Option<String>,
+
+        /// Transpose the chart by this many semitones (e.g. -2, 5)
+        #[arg(short, long, default_value_t = 0, allow_negative_numbers = true)]
+        transpose: i16,
+
+        /// Also voice the progression for four voices (bass, tenor, alto, soprano)
+        #[arg(long)]
+        satb: bool,
+
+        /// Also voice every chord in a piano voicing style (e.g., drop2, rootless-a)
+        #[arg(long, value_enum, conflicts_with = "satb")]
+        voicing: Option<VoicingStyle>,
+
+        /// The octave of the roots for --voicing (-1 to 8, e.g., 3 for C3)
+        #[arg(
+            long,
+            default_value_t = 3,
+            requires = "voicing",
+            allow_negative_numbers = true,
+            value_parser = clap::value_parser!(i8).range(-1..=8)
+        )]
+        octave: i8,
+    },
+
+    /// Works with songs in ChordPro format ([C]lyrics [G7]more)
+    Song {
+        #[command(subcommand)]
+        action: SongAction,
     },
 }
+
+/// The `song` subcommands
+#[derive(Parser, Debug)]
+pub enum SongAction {
+    /// Transposes a ChordPro song, or writes its chords as Nashville numbers or Roman numerals
+    Transpose {
+        /// The ChordPro file (.cho, .chordpro, .pro) to read
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// The new key (e.g. Bb, F#m). The old key is the song's {key:} directive,
+        /// or the key that best fits its chords
+        #[arg(short, long, conflicts_with = "semitones")]
+        to: Option<String>,
+
+        /// Transpose by this many semitones instead (e.g. -2, 5)
+        #[arg(short, long, allow_negative_numbers = true)]
+        semitones: Option<i16>,
+
+        /// Write a plain-text lyric sheet (chords over the lyrics) instead of ChordPro
+        #[arg(long)]
+        sheet: bool,
+    },
+}
+
+/// Timing and playback of --format midi and wav.
+#[derive(Args, Debug, Clone)]
+pub struct MidiArgs {
+    /// Tempo in beats per minute
+    #[arg(long, global = true, default_value_t = 120)]
+    pub tempo: u32,
+
+    /// Time signature (e.g. 4/4, 3/4, 6/8)
+    #[arg(long, global = true, default_value = "4/4")]
+    pub time_signature: String,
+
+    /// Length of each note in beats (quarter notes). Defaults to one beat per
+    /// scale or arpeggio note and one bar per chord
+    #[arg(long, global = true)]
+    pub note_length: Option<f64>,
+
+    /// Note velocity (1-127)
+    #[arg(long, global = true, default_value_t = 100, value_parser = clap::value_parser!(u8).range(1..=127))]
+    pub velocity: u8,
+
+    /// MIDI channel (1-16)
+    #[arg(long, global = true, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=16))]
+    pub channel: u8,
+
+    /// MIDI file type: 0 (single track) or 1 (one track per part)
+    #[arg(long, global = true, default_value_t = 1, value_parser = clap::value_parser!(u8).range(0..=1))]
+    pub midi_type: u8,
+
+    /// Play chords as ascending arpeggios instead of blocks
+    #[arg(long, global = true)]
+    pub arpeggio: bool,
+}
+
+impl MidiArgs {
+    /// Converts the flags into the MIDI writer options.
+    pub fn options(&self) -> Result<MidiOptions, String> {
+        Ok(MidiOptions {
+            tempo: self.tempo,
+            time_signature: parse_time_signature(&self.time_signature)?,
+            ticks_per_quarter: MidiOptions::default().ticks_per_quarter,
+            velocity: self.velocity,
+            channel: self.channel - 1,
+            file_type: self.midi_type,
+        })
+    }
+}
+
+/// The sound of --format wav.
+#[derive(Args, Debug, Clone)]
+pub struct AudioArgs {
+    /// Waveform of the synthesizer
+    #[arg(long, value_enum, global = true, default_value_t = Waveform::Sine)]
+    pub waveform: Waveform,
+
+    /// Envelope attack in seconds
+    #[arg(long, global = true, default_value_t = 0.01)]
+    pub attack: f64,
+
+    /// Envelope decay in seconds
+    #[arg(long, global = true, default_value_t = 0.1)]
+    pub decay: f64,
+
+    /// Envelope sustain level (0-1)
+    #[arg(long, global = true, default_value_t = 0.7)]
+    pub sustain: f64,
+
+    /// Envelope release in seconds
+    #[arg(long, global = true, default_value_t = 0.2)]
+    pub release: f64,
+
+    /// Strum chords from the lowest note up, this many milliseconds apart
+    #[arg(long, global = true, default_value_t = 0)]
+    pub strum: u32,
+
+    /// Samples per second
+    #[arg(long, global = true, default_value_t = 44100)]
+    pub sample_rate: u32,
+
+    /// Sample format: 16-bit PCM or 32-bit float
+    #[arg(long, value_enum, global = true, default_value_t = SampleFormat::Pcm16)]
+    pub sample_format: SampleFormat,
+}
+
+impl AudioArgs {
+    /// Converts the flags into the synthesizer options.
+    pub fn options(&self, tempo: u32) -> SynthOptions {
+        SynthOptions {
+            sample_rate: self.sample_rate,
+            tempo: tempo as f64,
+            waveform: self.waveform,
+            envelope: Envelope {
+                attack: self.attack,
+                decay: self.decay,
+                sustain: self.sustain,
+                release: self.release,
+            },
+            strum: self.strum as f64 / 1000.0,
+            ..SynthOptions::default()
+        }
+    }
+}
+
+/// Weights of the inversion choice when building progressions.
+/// Unset flags keep the default weights.
+#[derive(Args, Debug, Clone, Default)]
+pub struct VoiceLeadingArgs {
+    /// Cost per semitone of bass motion between chords (default 7)
+    #[arg(long)]
+    pub bass_motion: Option<u32>,
+
+    /// Cost per semitone the bass sits away from the tonic (default 1)
+    #[arg(long)]
+    pub range_weight: Option<u32>,
+
+    /// Reward per chord tone held in the same voice (default 0)
+    #[arg(long)]
+    pub common_tones: Option<u32>,
+
+    /// Reward when the bass and the top voice move in opposite directions (default 0)
+    #[arg(long)]
+    pub contrary_motion: Option<u32>,
+
+    /// Cost per step away from --preferred-inversion (default 0)
+    #[arg(long)]
+    pub inversion_weight: Option<u32>,
+
+    /// The preferred inversion: 0 = root position, 1 = first inversion... (default 0)
+    #[arg(long)]
+    pub preferred_inversion: Option<usize>,
+}
+
+impl VoiceLeadingArgs {
+    /// Applies the given flags on top of the default options.
+    pub fn options(&self) -> VoiceLeadingOptions {
+        let defaults: VoiceLeadingOptions = VoiceLeadingOptions::default();
+        VoiceLeadingOptions {
+            bass_motion: self.bass_motion.unwrap_or(defaults.bass_motion),
+            range: self.range_weight.unwrap_or(defaults.range),
+            common_tones: self.common_tones.unwrap_or(defaults.common_tones),
+            contrary_motion: self.contrary_motion.unwrap_or(defaults.contrary_motion),
+            inversion: self.inversion_weight.unwrap_or(defaults.inversion),
+            preferred_inversion: self
+                .preferred_inversion
+                .unwrap_or(defaults.preferred_inversion),
+        }
+    }
+}
@@ -27,16 +27,50 @@
 
 use clap::Parser;
 use serde::Serialize;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 // Import our library's functions and structs
 use tonic_music_core::{
-    ChordType, HarmonizedDegree, Note, ProgressionChord, build_chord, build_custom_progression,
-    build_progression, build_scale, get_inversions, harmonize_scale, parser::parse_note,
-    parser::parse_roman_chord,
+    ChordType, HarmonizedDegree, Note, ProgressionChord, ScaleType,
+    abc::write_abc,
+    analysis::{Cadence, chord_degree, detect_cadences, renumber_progression},
+    build_chord, build_custom_progression, build_scale,
+    chordpro::{
+        ChordStyle, Song, parse_chordpro, parse_key_directive, write_chordpro, write_lyric_sheet,
+    },
+    chroma::{ChromaOptions, analyze_audio},
+    fingering::{Fingering, FingeringOptions, chord_positions, find_fingerings},
+    fretboard::{FretPosition, Fretboard, resolve_tuning},
+    generator::{GeneratorOptions, TransitionTable, generate_progression},
+    get_inversions, harmonize_scale,
+    instrument::{Instrument, InstrumentDefinition},
+    keyboard::{Hand, is_black_key, keyboard_range, scale_fingering, scale_pitches},
+    library::{LibraryFormat, ProgressionDefinition, ProgressionLibrary},
+    lilypond::write_lilypond,
+    midi::{
+        MidiFile, MidiNote, MidiOptions, MidiTrack, arpeggios, block_chords, read_midi, sequence,
+        write_midi,
+    },
+    musicxml::{ImportedScore, read_musicxml, write_musicxml},
+    notation::{Score, SpelledNote, spell_tonic},
+    parser::{
+        ParsedRomanChord, parse_key, parse_note, parse_progression_formula, parse_spelled_note,
+    },
+    part_writing::{Violation, check_part_writing, parse_voicing_json, voice_name},
+    pitch::{Pitch, stack_notes},
+    recognition::{AnalyzedChord, HarmonicAnalysis, Segment, analyze_segments, segment_notes},
+    reharmonize::{Reharmonization, SubstitutionRule, SubstitutionStep, reharmonize},
+    scale_shapes::{ScaleShape, ShapeSystem, scale_shapes},
+    synth::render,
+    tuner::{PitchFrame, TunerOptions, detect_pitches},
+    voice_leading::{VoiceLeadingOptions, VoiceRange, VoicedChord, voice_progression},
+    voicing::{VoicingStyle, voice_chord, voice_progression_in_style},
+    wav::{Wav, read_wav, write_wav},
 };
 
 // Declare the CLI module
 mod cli;
-use cli::{Cli, Commands, OutputFormat};
+use cli::{AudioArgs, Cli, Commands, MidiArgs, OutputFormat, SongAction};
 
 // --- Response Structs for Output Strategy ---
 
@@ -44,26 +78,274 @@ trait Markdown {
     fn to_markdown(&self) -> String;
 }
 
+trait Tablature {
+    /// Renders the response as tablature for a fretboard.
+    fn to_tab(&self, _fretboard: &Fretboard) -> Result<String, String> {
+        Err(
+            "Tab output is available for scale, chord, progression, generate, shapes and fingering"
+                .to_string(),
+        )
+    }
+}
+
+trait Playback {
+    /// The notes played by --format midi, as one or more parts.
+    fn tracks(&self, _timing: &Timing) -> Result<Vec<MidiTrack>, String> {
+        Err(
+            "MIDI and WAV output is available for scale, chord, harmonize, progression and generate"
+                .to_string(),
+        )
+    }
+}
+
+trait Notation {
+    /// The score written by --format musicxml, lilypond and abc.
+    fn score(&self) -> Result<Score, String> {
+        Err(
+            "Score output (musicxml, lilypond, abc) is available for scale, chord, harmonize, progression and generate"
+                .to_string(),
+        )
+    }
+}
+
+/// Keeps the spelling of the root typed by the user (Eb minor is not D# minor).
+/// Other names, such as the written root of a transposing instrument, get the
+/// spelling of the major key with the fewest accidentals.
+fn spelled_root(name: &str, note: Note) -> SpelledNote {
+    parse_spelled_note(name)
+        .ok()
+        .filter(|s: &SpelledNote| s.note() == note)
+        .unwrap_or_else(|| spell_tonic(note, ScaleType::Major))
+}
+
+/// Note lengths (in beats) and chord style of --format midi.
+struct Timing {
+    /// Length of each scale or arpeggio note
+    note: f64,
+    /// Length of each chord (a bar unless --note-length is given)
+    chord: f64,
+    arpeggio: bool,
+}
+
+impl Timing {
+    /// Plays chords as blocks or arpeggios. Arpeggios take as many whole chord
+    /// lengths (bars) as the longest one needs, so they stay on the bar lines.
+    fn chords(&self, chords: &[Vec<Pitch>]) -> Vec<MidiNote> {
+        if self.arpeggio {
+            let size: usize = chords.iter().map(Vec::len).max().unwrap_or(0);
+            let lengths: f64 = (self.note * size as f64 / self.chord).ceil().max(1.0);
+            arpeggios(chords, self.note, self.chord * lengths)
+        } else {
+            block_chords(chords, self.chord)
+        }
+    }
+}
+
 #[derive(Serialize)]
 struct ScaleResponse {
     root: String,
     scale_type: String,
+    #[serde(skip)]
+    scale: ScaleType,
     notes: Vec<Note>,
+    /// One octave from the root, as drawn on the keyboard
+    #[serde(skip)]
+    pitches: Vec<Pitch>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hand: Option<Hand>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fingering: Option<Vec<u8>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instrument: Option<InstrumentDefinition>,
+}
+
+/// Describes a part written for a transposing instrument (e.g. "Written for Bb
+/// Trumpet: D sounds C concert").
+fn instrument_line(instrument: &InstrumentDefinition, written_root: &str) -> String {
+    match parse_note(written_root) {
+        Ok(note) => format!(
+            "Written for {}: {} sounds {} concert",
+            instrument.name,
+            note,
+            instrument.concert_pitch(Pitch::new(note, 4)).note
+        ),
+        Err(_) => format!("Written for {}", instrument.name),
+    }
+}
+
+/// Warns about the written pitches the instrument cannot play.
+fn warn_out_of_range(instrument: &Option<InstrumentDefinition>, written: &[Pitch]) {
+    if let Some(instrument) = instrument {
+        let outside: Vec<Pitch> = instrument.out_of_range(written);
+        if !outside.is_empty() {
+            eprintln!(
+                "Warning: {} outside the {} range ({}-{})",
+                join_pitches(&outside),
+                instrument.name,
+                instrument.written_low,
+                instrument.written_high
+            );
+        }
+    }
+}
+
+/// Joins notes with spaces (e.g. "C D E F G A B").
+fn join_notes(notes: &[Note]) -> String {
+    notes
+        .iter()
+        .map(|n: &Note| n.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Draws a piano keyboard over whole octaves, marking the highlighted pitches
+/// with "*" (or with the first character of their label, e.g. a finger number).
+fn ascii_keyboard(highlighted: &[Pitch], labels: Option<&[u8]>) -> Vec<String> {
+    let (low, high): (Pitch, Pitch) = keyboard_range(highlighted);
+    let marker = |pitch: Pitch| -> Option<char> {
+        let index: usize = highlighted.iter().position(|p: &Pitch| *p == pitch)?;
+        Some(
+            labels
+                .and_then(|l: &[u8]| l.get(index))
+                .and_then(|f: &u8| char::from_digit(*f as u32, 10))
+                .unwrap_or('*'),
+        )
+    };
+    let whites: Vec<Pitch> = (low.midi()..=high.midi())
+        .map(Pitch::from_midi)
+        .filter(|p: &Pitch| !is_black_key(p.note))
+        .collect();
+    let width: usize = whites.len() * 4 + 1;
+
+    // 1. Rows of white keys: borders every four columns
+    let row = |fill: char| -> Vec<char> {
+        (0..width)
+            .map(|c: usize| if c.is_multiple_of(4) { '|' } else { fill })
+            .collect()
+    };
+    let (mut upper, mut lower, mut marks, bottom): (Vec<char>, Vec<char>, Vec<char>, Vec<char>) =
+        (row(' '), row(' '), row(' '), row('_'));
+
+    // 2. Each black key covers the border after its white key
+    for (i, white) in whites.iter().enumerate() {
+        if let Some(m) = marker(*white) {
+            marks[i * 4 + 2] = m;
+        }
+        let black: Pitch = white.transpose(1);
+        if i + 1 < whites.len() && is_black_key(black.note) {
+            let border: usize = (i + 1) * 4;
+            for c in border - 1..=border + 1 {
+                upper[c] = '#';
+                lower[c] = '#';
+            }
+            if let Some(m) = marker(black) {
+                lower[border] = m;
+            }
+        }
+    }
+
+    let mut names: String = String::new();
+    for (i, white) in whites.iter().enumerate() {
+        if white.note == Note::C {
+            names.push_str(&" ".repeat(i * 4 + 1 - names.len()));
+            names.push_str(&white.to_string());
+        }
+    }
+
+    vec![
+        format!(" {} ", "_".repeat(width - 2)),
+        upper.iter().collect(),
+        lower.iter().collect(),
+        row(' ').iter().collect(),
+        marks.iter().collect(),
+        bottom.iter().collect(),
+        names.trim_end().to_string(),
+    ]
 }
 
 impl std::fmt::Display for ScaleResponse {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "--- {} {} Scale ---", self.root, self.scale_type)?;
-        write!(f, "{:?}", self.notes)
+        if let Some(instrument) = &self.instrument {
+            writeln!(f, "{}", instrument_line(instrument, &self.root))?;
+        }
+        writeln!(f, "Notes: {}", join_notes(&self.notes))?;
+        if let (Some(hand), Some(fingering)) = (&self.hand, &self.fingering) {
+            writeln!(
+                f,
+                "{:?} hand: {}",
+                hand,
+                fingering
+                    .iter()
+                    .map(|n: &u8| n.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            )?;
+        }
+        for line in ascii_keyboard(&self.pitches, self.fingering.as_deref()) {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
     }
 }
 
 impl Markdown for ScaleResponse {
     fn to_markdown(&self) -> String {
-        format!(
-            "# {} {} Scale\n\n**Notes:**\n{:?}",
-            self.root, self.scale_type, self.notes
-        )
+        let mut md: String = format!("# {} {} Scale\n\n", self.root, self.scale_type);
+        if let Some(instrument) = &self.instrument {
+            md.push_str(&format!(
+                "*{}*\n\n",
+                instrument_line(instrument, &self.root)
+            ));
+        }
+        md.push_str(&format!("**Notes:**\n{:?}", self.notes));
+        if let (Some(hand), Some(fingering)) = (&self.hand, &self.fingering) {
+            md.push_str(&format!(
+                "\n\n**{:?} Hand Fingering:**\n\n| {} |\n|{}\n| {} |",
+                hand,
+                join_pitches(&self.pitches).replace(' ', " | "),
+                "---|".repeat(fingering.len()),
+                fingering
+                    .iter()
+                    .map(|n: &u8| n.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" | ")
+            ));
+        }
+        md
+    }
+}
+
+impl Tablature for ScaleResponse {
+    fn to_tab(&self, fretboard: &Fretboard) -> Result<String, String> {
+        let positions: Vec<FretPosition> = fretboard.place_scale(&self.notes)?;
+        let lines: Vec<String> = tab_lines(&fretboard.tuning, &[TabMeasure::run("", &positions)]);
+        Ok(format!(
+            "{} {} Scale\n{}\n",
+            self.root,
+            self.scale_type,
+            lines.join("\n")
+        ))
+    }
+}
+
+impl Playback for ScaleResponse {
+    fn tracks(&self, timing: &Timing) -> Result<Vec<MidiTrack>, String> {
+        Ok(vec![MidiTrack {
+            name: format!("{} {} Scale", self.root, self.scale_type),
+            notes: sequence(&self.pitches, timing.note),
+        }])
+    }
+}
+
+impl Notation for ScaleResponse {
+    fn score(&self) -> Result<Score, String> {
+        Ok(Score::scale(
+            &format!("{} {} Scale", self.root, self.scale_type),
+            spelled_root(&self.root, self.notes[0]),
+            self.scale,
+            &self.pitches,
+        ))
     }
 }
 
@@ -71,14 +353,39 @@ impl Markdown for ScaleResponse {
 struct ChordResponse {
     root: String,
     chord_type: String,
+    #[serde(skip)]
+    chord: ChordType,
     notes: Vec<Note>,
     #[serde(skip_serializing_if = "Option::is_none")]
     inversions: Option<Vec<Vec<Note>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    voicing_style: Option<VoicingStyle>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    voicing: Option<Vec<Pitch>>,
+    /// The voicing, or the close position from the 4th octave (keyboard and MIDI)
+    #[serde(skip)]
+    pitches: Vec<Pitch>,
+    #[serde(skip)]
+    piano: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instrument: Option<InstrumentDefinition>,
+}
+
+/// Joins pitches with spaces (e.g. "G3 C4 E4 B4").
+fn join_pitches(pitches: &[Pitch]) -> String {
+    pitches
+        .iter()
+        .map(|p: &Pitch| p.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
 }
 
 impl std::fmt::Display for ChordResponse {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "--- {} {} Chord ---", self.root, self.chord_type)?;
+        if let Some(instrument) = &self.instrument {
+            writeln!(f, "{}", instrument_line(instrument, &self.root))?;
+        }
 
         if let Some(inversions) = &self.inversions {
             let titles: [&str; 5] = ["Root:", "1st Inv:", "2nd Inv:", "3rd Inv:", "4th Inv:"];
@@ -88,16 +395,35 @@ impl std::fmt::Display for ChordResponse {
                 let title: &str = titles.get(i).cloned().unwrap_or("Inv:");
                 writeln!(f, "{} \t{:?}", title, inv)?;
             }
-            Ok(())
         } else {
-            write!(f, "{:?}", self.notes)
+            write!(f, "{:?}", self.notes)?;
+        }
+
+        if let (Some(style), Some(voicing)) = (&self.voicing_style, &self.voicing) {
+            if self.inversions.is_none() {
+                writeln!(f)?;
+            }
+            write!(f, "{:?} Voicing: \t{}", style, join_pitches(voicing))?;
+        }
+
+        if self.piano {
+            for line in ascii_keyboard(&self.pitches, None) {
+                write!(f, "\n{}", line)?;
+            }
         }
+        Ok(())
     }
 }
 
 impl Markdown for ChordResponse {
     fn to_markdown(&self) -> String {
         let mut md: String = format!("# {} {} Chord\n\n", self.root, self.chord_type);
+        if let Some(instrument) = &self.instrument {
+            md.push_str(&format!(
+                "*{}*\n\n",
+                instrument_line(instrument, &self.root)
+            ));
+        }
         if let Some(inversions) = &self.inversions {
             md.push_str("## Inversions\n");
             let titles: [&str; 5] = ["Root", "1st Inv", "2nd Inv", "3rd Inv", "4th Inv"];
@@ -110,20 +436,104 @@ impl Markdown for ChordResponse {
         } else {
             md.push_str(&format!("**Notes:** {:?}\n", self.notes));
         }
+        if let (Some(style), Some(voicing)) = (&self.voicing_style, &self.voicing) {
+            md.push_str(&format!(
+                "\n**{:?} Voicing:** {}\n",
+                style,
+                join_pitches(voicing)
+            ));
+        }
         md
     }
 }
 
+impl Tablature for ChordResponse {
+    fn to_tab(&self, fretboard: &Fretboard) -> Result<String, String> {
+        let mut measures: Vec<TabMeasure> = Vec::<TabMeasure>::new();
+        match (&self.voicing_style, &self.voicing, &self.inversions) {
+            (Some(style), Some(voicing), _) => {
+                measures.push(TabMeasure::chord(
+                    &format!("{:?}", style),
+                    &fretboard.place_chord(voicing)?,
+                ));
+            }
+            (_, _, Some(inversions)) => {
+                let titles: [&str; 5] = ["Root", "1st Inv", "2nd Inv", "3rd Inv", "4th Inv"];
+                for (i, inversion) in inversions.iter().enumerate() {
+                    measures.push(TabMeasure::chord(
+                        titles.get(i).cloned().unwrap_or("Inv"),
+                        &chord_positions(fretboard, inversion)?,
+                    ));
+                }
+            }
+            _ => measures.push(TabMeasure::chord(
+                "",
+                &chord_positions(fretboard, &self.notes)?,
+            )),
+        }
+        Ok(format!(
+            "{} {} Chord\n{}\n",
+            self.root,
+            self.chord_type,
+            tab_lines(&fretboard.tuning, &measures).join("\n")
+        ))
+    }
+}
+
+impl ChordResponse {
+    /// The chord (or its voicing), followed by its inversions with --inversions.
+    fn chords(&self) -> Vec<Vec<Pitch>> {
+        let mut chords: Vec<Vec<Pitch>> = vec![self.pitches.clone()];
+        if let Some(inversions) = &self.inversions {
+            chords.extend(
+                inversions
+                    .iter()
+                    .skip(1)
+                    .map(|inversion: &Vec<Note>| stack_notes(inversion, 4)),
+            );
+        }
+        chords
+    }
+}
+
+impl Playback for ChordResponse {
+    fn tracks(&self, timing: &Timing) -> Result<Vec<MidiTrack>, String> {
+        Ok(vec![MidiTrack {
+            name: format!("{} {} Chord", self.root, self.chord_type),
+            notes: timing.chords(&self.chords()),
+        }])
+    }
+}
+
+impl Notation for ChordResponse {
+    /// One measure per chord, as played by --format midi.
+    fn score(&self) -> Result<Score, String> {
+        Ok(Score::chord(
+            &format!("{} {} Chord", self.root, self.chord_type),
+            spelled_root(&self.root, self.notes[0]),
+            self.chord,
+            &self.chords(),
+        ))
+    }
+}
+
 #[derive(Serialize)]
 struct HarmonizeResponse {
     root: String,
     scale_type: String,
+    #[serde(skip)]
+    scale: ScaleType,
     harmony: Vec<HarmonizedDegree>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instrument: Option<InstrumentDefinition>,
 }
 
 impl std::fmt::Display for HarmonizeResponse {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "--- {} {} Harmonization ---", self.root, self.scale_type)?;
+        if let Some(instrument) = &self.instrument {
+            writeln!(f, "{}", instrument_line(instrument, &self.root))?;
+        }
 
         let roman_numerals: [&str; 7] = ["I", "II", "III", "IV", "V", "VI", "VII"];
 
@@ -144,10 +554,14 @@ impl std::fmt::Display for HarmonizeResponse {
 
 impl Markdown for HarmonizeResponse {
     fn to_markdown(&self) -> String {
-        let mut md: String = format!(
-            "# {} {} Harmonization\n\n| Degree | Note | Chord | Notes |\n|---|---|---|---|\n",
-            self.root, self.scale_type
-        );
+        let mut md: String = format!("# {} {} Harmonization\n\n", self.root, self.scale_type);
+        if let Some(instrument) = &self.instrument {
+            md.push_str(&format!(
+                "*{}*\n\n",
+                instrument_line(instrument, &self.root)
+            ));
+        }
+        md.push_str("| Degree | Note | Chord | Notes |\n|---|---|---|---|\n");
         let roman_numerals: [&str; 7] = ["I", "II", "III", "IV", "V", "VI", "VII"];
         for degree in &self.harmony {
             let degree: &HarmonizedDegree = degree;
@@ -162,177 +576,2345 @@ impl Markdown for HarmonizeResponse {
     }
 }
 
+impl Tablature for HarmonizeResponse {}
+
+impl Playback for HarmonizeResponse {
+    fn tracks(&self, timing: &Timing) -> Result<Vec<MidiTrack>, String> {
+        let chords: Vec<Vec<Pitch>> = self
+            .harmony
+            .iter()
+            .map(|degree: &HarmonizedDegree| stack_notes(&degree.notes, 4))
+            .collect();
+        Ok(vec![MidiTrack {
+            name: format!("{} {} Harmonization", self.root, self.scale_type),
+            notes: timing.chords(&chords),
+        }])
+    }
+}
+
+impl Notation for HarmonizeResponse {
+    fn score(&self) -> Result<Score, String> {
+        Ok(Score::harmony(
+            &format!("{} {} Harmonization", self.root, self.scale_type),
+            spelled_root(&self.root, self.harmony[0].root_note),
+            self.scale,
+            &self.harmony,
+        ))
+    }
+}
+
 #[derive(Serialize)]
 struct ProgressionResponse {
     root: String,
     formula: String,
+    /// The mode of the key, when known (otherwise guessed from the tonic chord)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mode: Option<ScaleType>,
     progression: Vec<ProgressionChord>,
+    /// The cadence closing each phrase: (index of the arrival chord, cadence)
+    cadences: Vec<(usize, Cadence)>,
+    /// "SATB" or the piano voicing style
+    #[serde(skip_serializing_if = "Option::is_none")]
+    voicing_style: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    voicing: Option<Vec<VoicedChord>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    instrument: Option<InstrumentDefinition>,
+}
+
+/// Joins the pitches of a voiced chord, lowest voice first (e.g. "C3 G3 E4 C5").
+fn format_pitches(chord: &VoicedChord) -> String {
+    chord
+        .pitches
+        .iter()
+        .map(|p: &Pitch| p.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
 }
 
 impl std::fmt::Display for ProgressionResponse {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "--- {} {} Progression ---", self.root, self.formula)?;
+        if let Some(instrument) = &self.instrument {
+            writeln!(f, "{}", instrument_line(instrument, &self.root))?;
+        }
         for chord in &self.progression {
             let chord: &ProgressionChord = chord;
             let quality: &str = get_chord_quality_symbol(chord.chord_type);
             writeln!(
                 f,
-                "{}:\t{:?} {} \t-> {:?}",
-                chord.degree, chord.root_note, quality, chord.notes
+                "{}:\t{:?} {} \t[{}]\t-> {:?}",
+                chord.degree,
+                chord.root_note,
+                quality,
+                chord.function.symbol(),
+                chord.notes
+            )?;
+        }
+        for (i, cadence) in &self.cadences {
+            writeln!(
+                f,
+                "Cadence (chord {}, {}): {}",
+                i + 1,
+                self.progression[*i].degree,
+                cadence
             )?;
         }
+        if let (Some(style), Some(voicing)) = (&self.voicing_style, &self.voicing) {
+            if style == "SATB" {
+                writeln!(f, "--- SATB Voicing (B T A S) ---")?;
+            } else {
+                writeln!(f, "--- {} Voicing ---", style)?;
+            }
+            for chord in voicing {
+                writeln!(f, "{}:\t{}", chord.degree, format_pitches(chord))?;
+            }
+        }
         Ok(())
     }
 }
 
 impl Markdown for ProgressionResponse {
     fn to_markdown(&self) -> String {
-        let mut md: String = format!(
-            "# {} {} Progression\n\n| Degree | Chord | Notes |\n|---|---|---|\n",
-            self.root, self.formula
-        );
+        let mut md: String = format!("# {} {} Progression\n\n", self.root, self.formula);
+        if let Some(instrument) = &self.instrument {
+            md.push_str(&format!(
+                "*{}*\n\n",
+                instrument_line(instrument, &self.root)
+            ));
+        }
+        md.push_str("| Degree | Chord | Function | Notes |\n|---|---|---|---|\n");
         for chord in &self.progression {
             let chord: &ProgressionChord = chord;
             let quality: &str = get_chord_quality_symbol(chord.chord_type);
             md.push_str(&format!(
-                "| {} | {:?}{} | {:?} |\n",
-                chord.degree, chord.root_note, quality, chord.notes
+                "| {} | {:?}{} | {} | {:?} |\n",
+                chord.degree,
+                chord.root_note,
+                quality,
+                chord.function.symbol(),
+                chord.notes
+            ));
+        }
+        if !self.cadences.is_empty() {
+            md.push('\n');
+        }
+        for (i, cadence) in &self.cadences {
+            md.push_str(&format!(
+                "**Cadence (chord {}, {}):** {}  \n",
+                i + 1,
+                self.progression[*i].degree,
+                cadence
+            ));
+        }
+        if let (Some(style), Some(voicing)) = (&self.voicing_style, &self.voicing)
+            && style != "SATB"
+        {
+            md.push_str(&format!(
+                "\n## {} Voicing\n\n| Degree | Pitches |\n|---|---|\n",
+                style
+            ));
+            for chord in voicing {
+                md.push_str(&format!(
+                    "| {} | {} |\n",
+                    chord.degree,
+                    format_pitches(chord)
+                ));
+            }
+        } else if let Some(voicing) = &self.voicing {
+            let names: Vec<String> = VoiceRange::satb()
+                .into_iter()
+                .map(|v: VoiceRange| v.name)
+                .collect();
+            md.push_str(&format!(
+                "\n## SATB Voicing\n\n| Degree | {} |\n|---|{}\n",
+                names.join(" | "),
+                "---|".repeat(names.len())
             ));
+            for chord in voicing {
+                let pitches: Vec<String> = chord
+                    .pitches
+                    .iter()
+                    .map(|p: &Pitch| p.to_string())
+                    .collect();
+                md.push_str(&format!("| {} | {} |\n", chord.degree, pitches.join(" | ")));
+            }
         }
         md
     }
 }
 
-fn get_chord_quality_symbol(chord_type: ChordType) -> &'static str {
-    match chord_type {
-        ChordType::Major => "",
-        ChordType::Minor => "m",
-        ChordType::Diminished => "°",
-        ChordType::Augmented => "+",
-        ChordType::Major7 => "maj7",
-        ChordType::Minor7 => "m7",
-        ChordType::Dominant7 => "7",
-        ChordType::Minor7b5 => "m7b5",
-        ChordType::Diminished7 => "°7",
-        ChordType::MinorMajor7 => "m(maj7)",
-        ChordType::AugmentedMajor7 => "aug(maj7)",
+impl Tablature for ProgressionResponse {
+    /// One measure per chord: the computed voicing if there is one, otherwise
+    /// the chord notes in the inversion the voice leading chose.
+    fn to_tab(&self, fretboard: &Fretboard) -> Result<String, String> {
+        let mut measures: Vec<TabMeasure> = Vec::<TabMeasure>::new();
+        for (i, chord) in self.progression.iter().enumerate() {
+            let placed: Result<Vec<FretPosition>, String> = match &self.voicing {
+                Some(voicing) => fretboard.place_chord(&voicing[i].pitches),
+                None => chord_positions(fretboard, &chord.notes),
+            };
+            let positions: Vec<FretPosition> =
+                placed.map_err(|e: String| format!("Chord {} ({}): {}", i + 1, chord.degree, e))?;
+            measures.push(TabMeasure::chord(&chord.degree, &positions));
+        }
+        Ok(format!(
+            "{} {} Progression\n{}\n",
+            self.root,
+            self.formula,
+            tab_lines(&fretboard.tuning, &measures).join("\n")
+        ))
     }
 }
 
-fn print_output<T: Serialize + std::fmt::Display + Markdown>(data: &T, format: OutputFormat) {
-    match format {
-        OutputFormat::Text => print!("{}", data),
-        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(data).unwrap()),
-        OutputFormat::Markdown => println!("{}", data.to_markdown()),
+impl Playback for ProgressionResponse {
+    /// One chord per bar: the voicing if there is one, otherwise the chord notes
+    /// from the 4th octave. SATB voicings get one part per voice.
+    fn tracks(&self, timing: &Timing) -> Result<Vec<MidiTrack>, String> {
+        let chords: Vec<Vec<Pitch>> = match &self.voicing {
+            Some(voicing) => voicing
+                .iter()
+                .map(|chord: &VoicedChord| chord.pitches.clone())
+                .collect(),
+            None => self
+                .progression
+                .iter()
+                .map(|chord: &ProgressionChord| stack_notes(&chord.notes, 4))
+                .collect(),
+        };
+        let name: String = format!("{} {} Progression", self.root, self.formula);
+
+        if self.voicing_style.as_deref() == Some("SATB") && !timing.arpeggio {
+            return Ok(VoiceRange::satb()
+                .into_iter()
+                .enumerate()
+                .map(|(voice, range): (usize, VoiceRange)| {
+                    let line: Vec<Vec<Pitch>> = chords
+                        .iter()
+                        .map(|chord: &Vec<Pitch>| vec![chord[voice]])
+                        .collect();
+                    MidiTrack {
+                        name: range.name,
+                        notes: timing.chords(&line),
+                    }
+                })
+                .collect());
+        }
+        Ok(vec![MidiTrack {
+            name,
+            notes: timing.chords(&chords),
+        }])
     }
 }
 
-fn main() {
-    let cli_args: Cli = Cli::parse();
+impl Notation for ProgressionResponse {
+    fn score(&self) -> Result<Score, String> {
+        Ok(Score::progression(
+            &format!("{} {} Progression", self.root, self.formula),
+            spelled_root(&self.root, parse_note(&self.root)?),
+            self.mode,
+            &self.progression,
+            self.voicing.as_deref(),
+        ))
+    }
+}
 
-    match &cli_args.command {
-        Commands::Scale { root, scale_type } => {
-            let root_note: Note = parse_note(root).unwrap_or_else(|e| {
-                eprintln!("Error: {}", e);
-                std::process::exit(1);
-            });
-            // scale_type is already typed
-            let notes: Vec<Note> = build_scale(root_note, *scale_type);
+#[derive(Serialize)]
+struct ProgressionListResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tag: Option<String>,
+    progressions: Vec<ProgressionDefinition>,
+}
 
-            let response: ScaleResponse = ScaleResponse {
-                root: root.clone(),
-                scale_type: format!("{:?}", scale_type),
-                notes,
-            };
-            print_output(&response, cli_args.format);
+impl std::fmt::Display for ProgressionListResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.tag {
+            Some(tag) => writeln!(f, "--- Progression Library ({}) ---", tag)?,
+            None => writeln!(f, "--- Progression Library ---")?,
         }
-        Commands::Chord {
-            root,
-            chord_type,
-            inversions,
-        } => {
-            let root_note: Note = parse_note(root).unwrap_or_else(|e| {
-                eprintln!("Error: {}", e);
-                std::process::exit(1);
-            });
-            let notes: Vec<Note> = build_chord(root_note, *chord_type);
-
-            let invs: Option<Vec<Vec<Note>>> = if *inversions {
-                Some(get_inversions(&notes))
-            } else {
-                None
-            };
-
-            let response: ChordResponse = ChordResponse {
-                root: root.clone(),
-                chord_type: format!("{:?}", chord_type),
-                notes,
-                inversions: invs,
-            };
-            print_output(&response, cli_args.format);
+        for definition in &self.progressions {
+            let definition: &ProgressionDefinition = definition;
+            writeln!(
+                f,
+                "{:<18}{:<24}{}",
+                definition.name,
+                format!("[{}]", definition.tags.join(", ")),
+                definition.formula
+            )?;
         }
-        Commands::Harmonize {
-            root,
-            scale_type,
-            sevenths,
-        } => {
-            let root_note: Note = parse_note(root).unwrap_or_else(|e| {
-                eprintln!("Error: {}", e);
-                std::process::exit(1);
-            });
-            let scale_notes: Vec<Note> = build_scale(root_note, *scale_type);
-            let harmony: Vec<HarmonizedDegree> = harmonize_scale(&scale_notes, *sevenths);
+        Ok(())
+    }
+}
 
-            let response: HarmonizeResponse = HarmonizeResponse {
-                root: root.clone(),
-                scale_type: format!("{:?}", scale_type),
-                harmony,
-            };
-            print_output(&response, cli_args.format);
+impl Markdown for ProgressionListResponse {
+    fn to_markdown(&self) -> String {
+        let mut md: String = match &self.tag {
+            Some(tag) => format!("# Progression Library ({})\n\n", tag),
+            None => "# Progression Library\n\n".to_string(),
+        };
+        md.push_str("| Name | Formula | Tags | Description |\n|---|---|---|---|\n");
+        for definition in &self.progressions {
+            let definition: &ProgressionDefinition = definition;
+            md.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                definition.name,
+                definition.formula,
+                definition.tags.join(", "),
+                definition.description
+            ));
         }
-        Commands::Progression {
-            root,
-            formula,
-            custom,
-        } => {
-            let root_note: Note = parse_note(root).unwrap_or_else(|e| {
-                eprintln!("Error: {}", e);
-                std::process::exit(1);
-            });
-
-            let (progression, formula_name): (Vec<ProgressionChord>, String) =
-                if let Some(f) = formula {
-                    (build_progression(root_note, *f), format!("{:?}", f))
-                } else if let Some(c) = custom {
-                    // Split by '-' or space
-                    let parts: Vec<&str> = c
-                        .split(&['-', ' '][..])
-                        .filter(|s: &&str| !s.is_empty())
-                        .collect();
+        md
+    }
+}
 
-                    let specs_res: Result<Vec<tonic_music_core::parser::ParsedRomanChord>, String> =
-                        parts
-                            .into_iter()
-                            .map(|s: &str| parse_roman_chord(s))
-                            .collect();
+impl Tablature for ProgressionListResponse {}
 
-                    let specs: Vec<tonic_music_core::parser::ParsedRomanChord> = specs_res
-                        .unwrap_or_else(|e| {
-                            eprintln!("Error parsing custom progression: {}", e);
-                            std::process::exit(1);
-                        });
+impl Playback for ProgressionListResponse {}
 
-                    (build_custom_progression(root_note, specs), c.clone())
-                } else {
-                    unreachable!("Clap ensures one is present");
-                };
+impl Notation for ProgressionListResponse {}
 
-            let response: ProgressionResponse = ProgressionResponse {
+#[derive(Serialize)]
+struct ReharmonizeResponse {
+    root: String,
+    formula: String,
+    depth: usize,
+    original: Vec<ProgressionChord>,
+    suggestions: Vec<Reharmonization>,
+}
+
+#[derive(Serialize)]
+struct CheckVoicingResponse {
+    key: Note,
+    source: String,
+    chords: Vec<VoicedChord>,
+    violations: Vec<Violation>,
+}
+
+/// Formats the location of a violation as "Chord 2 (V7), Tenor/Alto".
+fn format_location(violation: &Violation, chords: &[VoicedChord]) -> String {
+    let count: usize = chords
+        .first()
+        .map(|c: &VoicedChord| c.pitches.len())
+        .unwrap_or(0);
+    let voices: Vec<String> = violation
+        .voices
+        .iter()
+        .map(|v: &usize| voice_name(*v, count))
+        .collect();
+    format!(
+        "Chord {} ({}), {}",
+        violation.chord + 1,
+        chords[violation.chord].degree,
+        voices.join("/")
+    )
+}
+
+impl std::fmt::Display for CheckVoicingResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "--- {} Voice Leading Check (key of {}) ---",
+            self.source, self.key
+        )?;
+        for (i, chord) in self.chords.iter().enumerate() {
+            writeln!(f, "{}. {}:\t{}", i + 1, chord.degree, format_pitches(chord))?;
+        }
+        if self.violations.is_empty() {
+            writeln!(f, "No part-writing errors found.")?;
+        } else {
+            writeln!(f, "--- {} Problem(s) ---", self.violations.len())?;
+            for violation in &self.violations {
+                writeln!(
+                    f,
+                    "{}: {} - {}",
+                    format_location(violation, &self.chords),
+                    violation.rule,
+                    violation.description
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Markdown for CheckVoicingResponse {
+    fn to_markdown(&self) -> String {
+        let mut md: String = format!(
+            "# {} Voice Leading Check (key of {})\n\n| # | Degree | Pitches |\n|---|---|---|\n",
+            self.source, self.key
+        );
+        for (i, chord) in self.chords.iter().enumerate() {
+            md.push_str(&format!(
+                "| {} | {} | {} |\n",
+                i + 1,
+                chord.degree,
+                format_pitches(chord)
+            ));
+        }
+        if self.violations.is_empty() {
+            md.push_str("\nNo part-writing errors found.\n");
+        } else {
+            md.push_str("\n| Location | Rule | Details |\n|---|---|---|\n");
+            for violation in &self.violations {
+                md.push_str(&format!(
+                    "| {} | {} | {} |\n",
+                    format_location(violation, &self.chords),
+                    violation.rule,
+                    violation.description
+                ));
+            }
+        }
+        md
+    }
+}
+
+impl Tablature for CheckVoicingResponse {}
+
+impl Playback for CheckVoicingResponse {}
+
+impl Notation for CheckVoicingResponse {}
+
+#[derive(Serialize)]
+struct FretboardResponse {
+    root: Note,
+    name: String,
+    notes: Vec<Note>,
+    fretboard: Fretboard,
+    positions: Vec<FretPosition>,
+}
+
+impl FretboardResponse {
+    /// The label of a fret cell: the note, in parentheses for the root, or empty.
+    fn cell(&self, string: usize, fret: u8) -> String {
+        match self
+            .positions
+            .iter()
+            .find(|p: &&FretPosition| p.string == string && p.fret == fret)
+        {
+            Some(p) if p.pitch.note == self.root => format!("({})", p.pitch.note),
+            Some(p) => p.pitch.note.to_string(),
+            None => String::new(),
+        }
+    }
+}
+
+impl std::fmt::Display for FretboardResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let board: &Fretboard = &self.fretboard;
+        writeln!(
+            f,
+            "--- {} ({}{}) ---",
+            self.name,
+            join_pitches(&board.tuning),
+            if board.capo > 0 {
+                format!(", capo {}", board.capo)
+            } else {
+                String::new()
+            }
+        )?;
+
+        // 1. Fret numbers (the capo fret is the "open" column, left of the nut)
+        let mut header: String = format!("    {:^4} ", board.capo);
+        for fret in board.capo + 1..=board.frets {
+            header.push_str(&format!(" {:^4}", fret));
+        }
+        writeln!(f, "{}", header.trim_end())?;
+
+        // 2. One row per string, the 1st string on top (as in tablature)
+        for string in (0..board.tuning.len()).rev() {
+            let cells: Vec<String> = (board.capo + 1..=board.frets)
+                .map(|fret: u8| format!("{:^4}", self.cell(string, fret)))
+                .collect();
+            writeln!(
+                f,
+                "{:<4}{:^4}||{}|",
+                board.tuning[string].to_string(),
+                self.cell(string, board.capo),
+                cells.join("|")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Markdown for FretboardResponse {
+    fn to_markdown(&self) -> String {
+        let board: &Fretboard = &self.fretboard;
+        let frets: Vec<u8> = (board.capo..=board.frets).collect();
+        let mut md: String = format!(
+            "# {}
+
+**Tuning:** {}{}
+
+| String |",
+            self.name,
+            join_pitches(&board.tuning),
+            if board.capo > 0 {
+                format!(" (capo {})", board.capo)
+            } else {
+                String::new()
+            }
+        );
+        for fret in &frets {
+            md.push_str(&format!(" {} |", fret));
+        }
+        md.push_str(&format!(
+            "
+|---|{}
+",
+            "---|".repeat(frets.len())
+        ));
+        for string in (0..board.tuning.len()).rev() {
+            md.push_str(&format!("| {} |", board.tuning[string]));
+            for fret in &frets {
+                md.push_str(&format!(" {} |", self.cell(string, *fret)));
+            }
+            md.push('\n');
+        }
+        md
+    }
+}
+
+impl Tablature for FretboardResponse {}
+
+impl Playback for FretboardResponse {}
+
+impl Notation for FretboardResponse {}
+
+#[derive(Serialize)]
+struct ShapesResponse {
+    root: String,
+    scale_type: ScaleType,
+    system: ShapeSystem,
+    fretboard: Fretboard,
+    shapes: Vec<ScaleShape>,
+}
+
+/// One measure of tablature: a label printed above it (e.g. the chord degree)
+/// and columns of (string, fret) pairs played together.
+struct TabMeasure {
+    label: String,
+    columns: Vec<Vec<(usize, u8)>>,
+}
+
+impl TabMeasure {
+    /// A measure of single notes, one column each.
+    fn run(label: &str, positions: &[FretPosition]) -> Self {
+        TabMeasure {
+            label: label.to_string(),
+            columns: positions
+                .iter()
+                .map(|p: &FretPosition| vec![(p.string, p.fret)])
+                .collect(),
+        }
+    }
+
+    /// A measure with one block chord.
+    fn chord(label: &str, positions: &[FretPosition]) -> Self {
+        TabMeasure {
+            label: label.to_string(),
+            columns: vec![
+                positions
+                    .iter()
+                    .map(|p: &FretPosition| (p.string, p.fret))
+                    .collect(),
+            ],
+        }
+    }
+}
+
+/// Writes tablature: one line per string, the 1st string on top, with the
+/// measure labels (if any) on a line above.
+fn tab_lines(tuning: &[Pitch], measures: &[TabMeasure]) -> Vec<String> {
+    let label_width: usize = tuning
+        .iter()
+        .map(|p: &Pitch| p.to_string().len())
+        .max()
+        .unwrap_or(0);
+
+    // 1. The width of each column, and of each measure (wide enough for its label)
+    let widths: Vec<Vec<usize>> = measures
+        .iter()
+        .map(|m: &TabMeasure| {
+            m.columns
+                .iter()
+                .map(|c: &Vec<(usize, u8)>| {
+                    c.iter()
+                        .map(|(_, f): &(usize, u8)| f.to_string().len())
+                        .max()
+                        .unwrap_or(1)
+                })
+                .collect()
+        })
+        .collect();
+    let measure_widths: Vec<usize> = measures
+        .iter()
+        .zip(&widths)
+        .map(|(m, w): (&TabMeasure, &Vec<usize>)| {
+            (1 + w.iter().map(|w: &usize| w + 1).sum::<usize>()).max(m.label.len() + 1)
+        })
+        .collect();
+
+    let mut lines: Vec<String> = Vec::<String>::new();
+    if measures.iter().any(|m: &TabMeasure| !m.label.is_empty()) {
+        let mut line: String = " ".repeat(label_width + 1);
+        for (measure, width) in measures.iter().zip(&measure_widths) {
+            line.push_str(&format!("{:<width$} ", measure.label, width = width));
+        }
+        lines.push(line.trim_end().to_string());
+    }
+
+    // 2. One line per string
+    for string in (0..tuning.len()).rev() {
+        let mut line: String = format!("{:<width$}|", tuning[string], width = label_width);
+        for ((measure, widths), measure_width) in measures.iter().zip(&widths).zip(&measure_widths)
+        {
+            let mut cells: String = "-".to_string();
+            for (column, width) in measure.columns.iter().zip(widths) {
+                match column.iter().find(|(s, _): &&(usize, u8)| *s == string) {
+                    Some((_, fret)) => cells.push_str(&format!("{:-<width$}", fret, width = width)),
+                    None => cells.push_str(&"-".repeat(*width)),
+                }
+                cells.push('-');
+            }
+            line.push_str(&format!("{:-<width$}|", cells, width = measure_width));
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+impl ShapesResponse {
+    /// The tab of a shape played note by note, from the lowest string up.
+    fn shape_tab(&self, shape: &ScaleShape) -> Vec<String> {
+        tab_lines(
+            &self.fretboard.tuning,
+            &[TabMeasure::run("", &shape.positions)],
+        )
+    }
+}
+
+impl std::fmt::Display for ShapesResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "--- {} {:?} Scale: {:?} Shapes ({}) ---",
+            self.root,
+            self.scale_type,
+            self.system,
+            join_pitches(&self.fretboard.tuning)
+        )?;
+        if self.shapes.is_empty() {
+            writeln!(f, "No shape fits on the neck.")?;
+        }
+        for shape in &self.shapes {
+            writeln!(
+                f,
+                "\n{} (from {}, frets {}-{})",
+                shape.name, shape.start, shape.lowest_fret, shape.highest_fret
+            )?;
+            for line in self.shape_tab(shape) {
+                writeln!(f, "{}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Markdown for ShapesResponse {
+    fn to_markdown(&self) -> String {
+        let mut md: String = format!(
+            "# {} {:?} Scale: {:?} Shapes\n\n**Tuning:** {}\n",
+            self.root,
+            self.scale_type,
+            self.system,
+            join_pitches(&self.fretboard.tuning)
+        );
+        for shape in &self.shapes {
+            md.push_str(&format!(
+                "\n## {} (from {}, frets {}-{})\n\n```text\n{}\n```\n",
+                shape.name,
+                shape.start,
+                shape.lowest_fret,
+                shape.highest_fret,
+                self.shape_tab(shape).join("\n")
+            ));
+        }
+        md
+    }
+}
+
+impl Playback for ShapesResponse {}
+
+impl Notation for ShapesResponse {}
+
+impl Tablature for ShapesResponse {
+    /// The shapes use the command's own --tuning.
+    fn to_tab(&self, _fretboard: &Fretboard) -> Result<String, String> {
+        let mut tab: String = String::new();
+        for shape in &self.shapes {
+            tab.push_str(&format!(
+                "{}\n{}\n\n",
+                shape.name,
+                self.shape_tab(shape).join("\n")
+            ));
+        }
+        Ok(tab)
+    }
+}
+
+#[derive(Serialize)]
+struct FingeringResponse {
+    root: String,
+    chord_type: ChordType,
+    fretboard: Fretboard,
+    fingerings: Vec<Fingering>,
+}
+
+/// Formats frets per string as "x 3 2 0 1 0".
+fn format_frets(frets: &[Option<u8>]) -> String {
+    frets
+        .iter()
+        .map(|f: &Option<u8>| match f {
+            Some(f) => f.to_string(),
+            None => "x".to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Draws a chord box: strings run down, the lowest string on the left,
+/// with the finger numbers on the frets ("x" = muted, "o" = open).
+fn chord_box(fingering: &Fingering, capo: u8) -> Vec<String> {
+    let fretted: Vec<u8> = fingering
+        .frets
+        .iter()
+        .filter_map(|f: &Option<u8>| f.filter(|f: &u8| *f > capo))
+        .collect();
+    let low: u8 = fretted.iter().min().copied().unwrap_or(capo + 1);
+    let high: u8 = fretted.iter().max().copied().unwrap_or(capo + 1);
+    // 1. Start at the nut when the shape fits in the first four frets
+    let start: u8 = if high <= capo + 4 { capo + 1 } else { low };
+    let end: u8 = (start + 3).max(high);
+    let width: usize = fingering.frets.len() * 2 - 1;
+
+    let marks: Vec<&str> = fingering
+        .frets
+        .iter()
+        .map(|f: &Option<u8>| match f {
+            None => "x",
+            Some(f) if *f == capo => "o",
+            Some(_) => " ",
+        })
+        .collect();
+    let mut lines: Vec<String> = vec![
+        format!("   {}", marks.join(" ")).trim_end().to_string(),
+        format!(
+            "   {}",
+            if start == capo + 1 { "=" } else { "-" }.repeat(width)
+        ),
+    ];
+
+    // 2. One row per fret; a barre joins its strings with dashes
+    for fret in start..=end {
+        let mut row: String = format!("{:>2} ", fret);
+        for (string, f) in fingering.frets.iter().enumerate() {
+            let barred: Option<(usize, usize)> = fingering
+                .barre
+                .filter(|b| b.fret == fret)
+                .map(|b| (b.from_string, b.to_string));
+            let cell: String = match (f, fingering.fingers[string]) {
+                (Some(f), Some(finger)) if *f == fret => finger.to_string(),
+                _ if barred.is_some_and(|(a, b): (usize, usize)| (a..=b).contains(&string)) => {
+                    "-".to_string()
+                }
+                _ => "|".to_string(),
+            };
+            row.push_str(&cell);
+            if string + 1 < fingering.frets.len() {
+                let joined: bool =
+                    barred.is_some_and(|(a, b): (usize, usize)| string >= a && string < b);
+                row.push(if joined { '-' } else { ' ' });
+            }
+        }
+        lines.push(row);
+    }
+    lines
+}
+
+impl std::fmt::Display for FingeringResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "--- {} {:?} Chord Fingerings ({}) ---",
+            self.root,
+            self.chord_type,
+            join_pitches(&self.fretboard.tuning)
+        )?;
+        if self.fingerings.is_empty() {
+            writeln!(f, "No playable fingering found.")?;
+        }
+        for (i, fingering) in self.fingerings.iter().enumerate() {
+            writeln!(
+                f,
+                "\n#{}: {}  (bass {}, difficulty {}{})",
+                i + 1,
+                format_frets(&fingering.frets),
+                fingering.bass,
+                fingering.difficulty,
+                match fingering.barre {
+                    Some(b) => format!(", barre at fret {}", b.fret),
+                    None => String::new(),
+                }
+            )?;
+            for line in chord_box(fingering, self.fretboard.capo) {
+                writeln!(f, "{}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Markdown for FingeringResponse {
+    fn to_markdown(&self) -> String {
+        let mut md: String = format!(
+            "# {} {:?} Chord Fingerings\n\n**Tuning:** {}\n\n| # | Frets | Fingers | Bass | Barre | Difficulty |\n|---|---|---|---|---|---|\n",
+            self.root,
+            self.chord_type,
+            join_pitches(&self.fretboard.tuning)
+        );
+        for (i, fingering) in self.fingerings.iter().enumerate() {
+            let fingers: String = fingering
+                .fingers
+                .iter()
+                .map(|f: &Option<u8>| f.map(|f: u8| f.to_string()).unwrap_or("-".to_string()))
+                .collect::<Vec<String>>()
+                .join(" ");
+            md.push_str(&format!(
+                "| {} | {} | {} | {} | {} | {} |\n",
+                i + 1,
+                format_frets(&fingering.frets),
+                fingers,
+                fingering.bass,
+                fingering
+                    .barre
+                    .map(|b| format!("Fret {}", b.fret))
+                    .unwrap_or("-".to_string()),
+                fingering.difficulty
+            ));
+        }
+        md
+    }
+}
+
+impl Playback for FingeringResponse {}
+
+impl Notation for FingeringResponse {}
+
+impl Tablature for FingeringResponse {
+    /// The fingerings use the command's own --tuning.
+    fn to_tab(&self, _fretboard: &Fretboard) -> Result<String, String> {
+        let measures: Vec<TabMeasure> = self
+            .fingerings
+            .iter()
+            .enumerate()
+            .map(|(i, fingering): (usize, &Fingering)| TabMeasure {
+                label: format!("#{}", i + 1),
+                columns: vec![
+                    fingering
+                        .frets
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(s, f): (usize, &Option<u8>)| f.map(|f: u8| (s, f)))
+                        .collect(),
+                ],
+            })
+            .collect();
+        Ok(format!(
+            "{} {:?} Chord\n{}\n",
+            self.root,
+            self.chord_type,
+            tab_lines(&self.fretboard.tuning, &measures).join("\n")
+        ))
+    }
+}
+
+/// Formats a progression as "I - vi - ii - V7   (C | Am | Dm | G7)".
+fn format_progression_line(progression: &[ProgressionChord]) -> String {
+    let degrees: Vec<&str> = progression
+        .iter()
+        .map(|c: &ProgressionChord| c.degree.as_str())
+        .collect();
+    let chords: Vec<String> = progression
+        .iter()
+        .map(|c: &ProgressionChord| {
+            format!(
+                "{:?}{}",
+                c.root_note,
+                get_chord_quality_symbol(c.chord_type)
+            )
+        })
+        .collect();
+    format!("{}   ({})", degrees.join(" - "), chords.join(" | "))
+}
+
+/// Formats the steps of a suggestion as "Tritone Substitution (chord 4) + ...".
+fn format_steps(steps: &[SubstitutionStep]) -> String {
+    steps
+        .iter()
+        .map(|s: &SubstitutionStep| format!("{} (chord {})", s.rule, s.position + 1))
+        .collect::<Vec<String>>()
+        .join(" + ")
+}
+
+impl std::fmt::Display for ReharmonizeResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "--- {} {} Reharmonizations (depth {}) ---",
+            self.root, self.formula, self.depth
+        )?;
+        writeln!(f, "Original:\t{}", format_progression_line(&self.original))?;
+        for (i, suggestion) in self.suggestions.iter().enumerate() {
+            let suggestion: &Reharmonization = suggestion;
+            writeln!(f, "{}. {}", i + 1, format_steps(&suggestion.steps))?;
+            writeln!(f, "\t{}", format_progression_line(&suggestion.progression))?;
+        }
+        Ok(())
+    }
+}
+
+impl Markdown for ReharmonizeResponse {
+    fn to_markdown(&self) -> String {
+        let mut md: String = format!(
+            "# {} {} Reharmonizations\n\n**Original:** {}\n\n| # | Rules | Progression |\n|---|---|---|\n",
+            self.root,
+            self.formula,
+            format_progression_line(&self.original)
+        );
+        for (i, suggestion) in self.suggestions.iter().enumerate() {
+            let suggestion: &Reharmonization = suggestion;
+            md.push_str(&format!(
+                "| {} | {} | {} |\n",
+                i + 1,
+                format_steps(&suggestion.steps),
+                format_progression_line(&suggestion.progression)
+            ));
+        }
+        md
+    }
+}
+
+impl Tablature for ReharmonizeResponse {}
+
+impl Playback for ReharmonizeResponse {}
+
+impl Notation for ReharmonizeResponse {}
+
+#[derive(Serialize)]
+struct MidiAnalysisResponse {
+    source: String,
+    tempo: f64,
+    time_signature: String,
+    #[serde(skip)]
+    meter: (u8, u8),
+    key: Note,
+    mode: String,
+    /// Start and end in beats (quarter notes)
+    chords: Vec<AnalyzedChord>,
+}
+
+/// Formats a time in beats as "bar.beat" (e.g. "3.1", "3.2.5" for half past beat 2).
+fn bar_beat(beats: f64, meter: (u8, u8)) -> String {
+    let unit: f64 = 4.0 / meter.1 as f64;
+    let bar_length: f64 = unit * meter.0 as f64;
+    let bar: u32 = (beats / bar_length + 1e-9).floor() as u32 + 1;
+    let beat: f64 = (beats - (bar - 1) as f64 * bar_length) / unit + 1.0;
+    if (beat - beat.round()).abs() < 1e-6 {
+        format!("{}.{}", bar, beat.round())
+    } else {
+        format!("{}.{}", bar, (beat * 100.0).round() / 100.0)
+    }
+}
+
+/// The chord symbol of a recognized chord, with its bass when inverted (e.g. "C/E").
+fn analyzed_symbol(chord: &AnalyzedChord) -> String {
+    let symbol: String = format!(
+        "{}{}",
+        chord.root_note,
+        get_chord_quality_symbol(chord.chord_type)
+    );
+    match chord.bass {
+        Some(bass) if bass != chord.root_note => format!("{}/{}", symbol, bass),
+        _ => symbol,
+    }
+}
+
+impl std::fmt::Display for MidiAnalysisResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "--- {} Analysis (key of {} {}, {}, {:.0} BPM) ---",
+            self.source, self.key, self.mode, self.time_signature, self.tempo
+        )?;
+        if self.chords.is_empty() {
+            return writeln!(f, "No chords found.");
+        }
+        for chord in &self.chords {
+            writeln!(
+                f,
+                "{}\t{}\t{}\t[{}]",
+                bar_beat(chord.start, self.meter),
+                analyzed_symbol(chord),
+                chord.degree,
+                chord.function.symbol()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Markdown for MidiAnalysisResponse {
+    fn to_markdown(&self) -> String {
+        let mut md: String = format!(
+            "# {} Analysis\n\n**Key:** {} {} | **Meter:** {} | **Tempo:** {:.0} BPM\n\n| Bar.Beat | Chord | Degree | Function |\n|---|---|---|---|\n",
+            self.source, self.key, self.mode, self.time_signature, self.tempo
+        );
+        for chord in &self.chords {
+            md.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                bar_beat(chord.start, self.meter),
+                analyzed_symbol(chord),
+                chord.degree,
+                chord.function.symbol()
+            ));
+        }
+        md
+    }
+}
+
+impl Tablature for MidiAnalysisResponse {}
+
+impl Playback for MidiAnalysisResponse {
+    /// The recognized chords from the 4th octave, with their original timing.
+    fn tracks(&self, _timing: &Timing) -> Result<Vec<MidiTrack>, String> {
+        let mut notes: Vec<MidiNote> = Vec::<MidiNote>::new();
+        for chord in &self.chords {
+            let mut tones: Vec<Note> = build_chord(chord.root_note, chord.chord_type);
+            if let Some(bass) = chord.bass
+                && let Some(index) = tones.iter().position(|n: &Note| *n == bass)
+            {
+                tones.rotate_left(index);
+            }
+            for pitch in stack_notes(&tones, 4) {
+                notes.push(MidiNote {
+                    pitch,
+                    start: chord.start,
+                    length: chord.end - chord.start,
+                });
+            }
+        }
+        Ok(vec![MidiTrack {
+            name: format!("{} Chords", self.source),
+            notes,
+        }])
+    }
+}
+
+impl Notation for MidiAnalysisResponse {}
+
+#[derive(Serialize)]
+struct AudioAnalysisResponse {
+    source: String,
+    /// Length of the recording in seconds
+    duration: f64,
+    key: Note,
+    mode: String,
+    /// Start and end in seconds
+    chords: Vec<AnalyzedChord>,
+}
+
+impl std::fmt::Display for AudioAnalysisResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "--- {} Analysis (key of {} {}, {:.2} s) ---",
+            self.source, self.key, self.mode, self.duration
+        )?;
+        if self.chords.is_empty() {
+            return writeln!(f, "No chords found.");
+        }
+        for chord in &self.chords {
+            writeln!(
+                f,
+                "{:.2}-{:.2}\t{}\t{}\t[{}]",
+                chord.start,
+                chord.end,
+                analyzed_symbol(chord),
+                chord.degree,
+                chord.function.symbol()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Markdown for AudioAnalysisResponse {
+    fn to_markdown(&self) -> String {
+        let mut md: String = format!(
+            "# {} Analysis\n\n**Key:** {} {} | **Length:** {:.2} s\n\n| Time (s) | Chord | Degree | Function |\n|---|---|---|---|\n",
+            self.source, self.key, self.mode, self.duration
+        );
+        for chord in &self.chords {
+            md.push_str(&format!(
+                "| {:.2}-{:.2} | {} | {} | {} |\n",
+                chord.start,
+                chord.end,
+                analyzed_symbol(chord),
+                chord.degree,
+                chord.function.symbol()
+            ));
+        }
+        md
+    }
+}
+
+impl Tablature for AudioAnalysisResponse {}
+
+impl Playback for AudioAnalysisResponse {}
+
+impl Notation for AudioAnalysisResponse {}
+
+#[derive(Serialize)]
+struct PitchAnalysisResponse {
+    source: String,
+    sample_rate: u32,
+    /// Length of the recording in seconds
+    duration: f64,
+    a4: f64,
+    frames: Vec<PitchFrame>,
+}
+
+/// The frequency, pitch and cents of a frame, or dashes when it has no pitch.
+fn frame_columns(frame: &PitchFrame) -> [String; 3] {
+    match (frame.frequency, frame.pitch, frame.cents) {
+        (Some(frequency), Some(pitch), Some(cents)) => [
+            format!("{:.2} Hz", frequency),
+            pitch.to_string(),
+            format!("{:+.1}", cents),
+        ],
+        _ => ["-".to_string(), "-".to_string(), "-".to_string()],
+    }
+}
+
+impl std::fmt::Display for PitchAnalysisResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "--- {} Pitch (A4 = {} Hz, {} Hz, {:.2} s) ---",
+            self.source, self.a4, self.sample_rate, self.duration
+        )?;
+        if self.frames.is_empty() {
+            return writeln!(f, "The recording is shorter than one frame.");
+        }
+        writeln!(f, "Time	Frequency	Pitch	Cents")?;
+        for frame in &self.frames {
+            let [frequency, pitch, cents]: [String; 3] = frame_columns(frame);
+            writeln!(f, "{:.3}	{:<10}	{}	{}", frame.time, frequency, pitch, cents)?;
+        }
+        Ok(())
+    }
+}
+
+impl Markdown for PitchAnalysisResponse {
+    fn to_markdown(&self) -> String {
+        let mut md: String = format!(
+            "# {} Pitch\n\n**A4:** {} Hz | **Sample rate:** {} Hz | **Length:** {:.2} s\n\n| Time (s) | Frequency | Pitch | Cents |\n|---|---|---|---|\n",
+            self.source, self.a4, self.sample_rate, self.duration
+        );
+        for frame in &self.frames {
+            let [frequency, pitch, cents]: [String; 3] = frame_columns(frame);
+            md.push_str(&format!(
+                "| {:.3} | {} | {} | {} |\n",
+                frame.time, frequency, pitch, cents
+            ));
+        }
+        md
+    }
+}
+
+impl Tablature for PitchAnalysisResponse {}
+
+impl Playback for PitchAnalysisResponse {}
+
+impl Notation for PitchAnalysisResponse {}
+
+#[derive(Serialize)]
+struct SongResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    key: String,
+    /// The distinct chords of the song, in order of appearance
+    chords: Vec<String>,
+    /// The song as ChordPro or as a lyric sheet
+    text: String,
+}
+
+impl std::fmt::Display for SongResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl Markdown for SongResponse {
+    fn to_markdown(&self) -> String {
+        format!(
+            "# {} (key of {})\n\n```text\n{}```",
+            self.title.as_deref().unwrap_or("Song"),
+            self.key,
+            self.text
+        )
+    }
+}
+
+impl Tablature for SongResponse {}
+
+impl Playback for SongResponse {}
+
+impl Notation for SongResponse {}
+
+fn get_chord_quality_symbol(chord_type: ChordType) -> &'static str {
+    match chord_type {
+        ChordType::Major => "",
+        ChordType::Minor => "m",
+        ChordType::Diminished => "°",
+        ChordType::Augmented => "+",
+        ChordType::Major7 => "maj7",
+        ChordType::Minor7 => "m7",
+        ChordType::Dominant7 => "7",
+        ChordType::Minor7b5 => "m7b5",
+        ChordType::Diminished7 => "°7",
+        ChordType::MinorMajor7 => "m(maj7)",
+        ChordType::AugmentedMajor7 => "aug(maj7)",
+    }
+}
+
+fn print_output<T: Serialize + std::fmt::Display + Markdown + Tablature + Playback + Notation>(
+    data: &T,
+    cli_args: &Cli,
+) {
+    if cli_args.output.is_none() {
+        match cli_args.format {
+            OutputFormat::Midi => {
+                eprintln!("Error: --format midi writes a file: add --output (e.g. -o out.mid)");
+                std::process::exit(1);
+            }
+            OutputFormat::Wav => {
+                eprintln!("Error: --format wav writes a file: add --output (e.g. -o out.wav)");
+                std::process::exit(1);
+            }
+            _ => {}
+        }
+    }
+
+    // 1. Render the chosen format
+    let rendered: Result<Vec<u8>, String> = match cli_args.format {
+        OutputFormat::Text => Ok(data.to_string().into_bytes()),
+        OutputFormat::Json => {
+            Ok(format!("{}\n", serde_json::to_string_pretty(data).unwrap()).into_bytes())
+        }
+        OutputFormat::Markdown => Ok(format!("{}\n", data.to_markdown()).into_bytes()),
+        OutputFormat::Tab => resolve_tuning(&cli_args.tab_tuning)
+            .and_then(|t: Vec<Pitch>| Fretboard::new(t, 24, 0))
+            .and_then(|fretboard: Fretboard| data.to_tab(&fretboard))
+            .map(String::into_bytes),
+        OutputFormat::Midi => render_midi(data, &cli_args.midi),
+        OutputFormat::Musicxml => data
+            .score()
+            .map(|score: Score| write_musicxml(&score).into_bytes()),
+        OutputFormat::Lilypond => data
+            .score()
+            .map(|score: Score| write_lilypond(&score).into_bytes()),
+        OutputFormat::Abc => data
+            .score()
+            .map(|score: Score| write_abc(&score).into_bytes()),
+        OutputFormat::Wav => render_wav(data, &cli_args.midi, &cli_args.audio),
+    };
+    let bytes: Vec<u8> = rendered.unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    });
+
+    // 2. Write it to --output, or to the terminal
+    let written: std::io::Result<()> = match &cli_args.output {
+        Some(path) => std::fs::write(path, &bytes),
+        None => std::io::stdout().write_all(&bytes),
+    };
+    if let Err(e) = written {
+        eprintln!("Error writing the output: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// The longest --note-length, in beats (64 bars of 4/4).
+const MAX_NOTE_LENGTH: f64 = 256.0;
+
+/// The note lengths of --format midi and wav: a beat per note and a bar per
+/// chord unless --note-length is given.
+fn playback_timing(args: &MidiArgs, options: &MidiOptions) -> Result<Timing, String> {
+    let timing: Timing = Timing {
+        note: args.note_length.unwrap_or(1.0),
+        chord: args.note_length.unwrap_or(options.bar_length()),
+        arpeggio: args.arpeggio,
+    };
+    if !(timing.note > 0.0 && timing.note <= MAX_NOTE_LENGTH) {
+        return Err(format!(
+            "--note-length must be greater than 0 and at most {} beats",
+            MAX_NOTE_LENGTH
+        ));
+    }
+    Ok(timing)
+}
+
+/// Builds the Standard MIDI File of --format midi.
+fn render_midi<T: Playback>(data: &T, args: &MidiArgs) -> Result<Vec<u8>, String> {
+    let options: MidiOptions = args.options()?;
+    let timing: Timing = playback_timing(args, &options)?;
+    write_midi(&data.tracks(&timing)?, &options)
+}
+
+/// Renders the notes of --format midi to the WAV file of --format wav, every
+/// part mixed into one mono signal.
+fn render_wav<T: Playback>(
+    data: &T,
+    args: &MidiArgs,
+    audio: &AudioArgs,
+) -> Result<Vec<u8>, String> {
+    let timing: Timing = playback_timing(args, &args.options()?)?;
+    let notes: Vec<MidiNote> = data
+        .tracks(&timing)?
+        .into_iter()
+        .flat_map(|t: MidiTrack| t.notes)
+        .collect();
+    let samples: Vec<f32> = render(&notes, &audio.options(args.tempo))?;
+    Ok(write_wav(&samples, audio.sample_rate, audio.sample_format))
+}
+
+/// Voices a progression for --satb or --voicing, returning the style name
+/// ("SATB" or the piano voicing style) and the voiced chords.
+fn voice_for_output(
+    progression: &[ProgressionChord],
+    satb: bool,
+    voicing: Option<VoicingStyle>,
+    octave: i8,
+) -> (Option<String>, Option<Vec<VoicedChord>>) {
+    let voiced: Result<Option<(String, Vec<VoicedChord>)>, String> = if satb {
+        voice_progression(progression, &VoiceRange::satb())
+            .map(|v: Vec<VoicedChord>| Some(("SATB".to_string(), v)))
+    } else if let Some(style) = voicing {
+        voice_progression_in_style(progression, style, octave)
+            .map(|v: Vec<VoicedChord>| Some((format!("{:?}", style), v)))
+    } else {
+        Ok(None)
+    };
+    match voiced {
+        Ok(Some((style, chords))) => (Some(style), Some(chords)),
+        Ok(None) => (None, None),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The highest note of each voiced chord: the sopranos of the cadences.
+fn top_voices(voicing: &Option<Vec<VoicedChord>>) -> Option<Vec<Note>> {
+    voicing.as_ref().map(|v: &Vec<VoicedChord>| {
+        v.iter()
+            .filter_map(|c: &VoicedChord| c.pitches.iter().max())
+            .map(|p: &Pitch| p.note)
+            .collect()
+    })
+}
+
+/// Rewrites the degrees of a progression in the style chosen with --degrees.
+fn with_degrees(
+    key_center: Note,
+    progression: Vec<ProgressionChord>,
+    degrees: Option<ChordStyle>,
+) -> Vec<ProgressionChord> {
+    match degrees {
+        Some(style) => renumber_progression(key_center, &progression, style),
+        None => progression,
+    }
+}
+
+/// Rewrites the degrees of recognized chords in the style chosen with --degrees.
+fn with_chord_degrees(
+    mut analysis: HarmonicAnalysis,
+    degrees: Option<ChordStyle>,
+) -> HarmonicAnalysis {
+    if let Some(style) = degrees {
+        for chord in analysis.chords.iter_mut() {
+            chord.degree = chord_degree(
+                analysis.key,
+                chord.root_note,
+                chord.chord_type,
+                chord.bass,
+                &chord.degree,
+                style,
+            );
+        }
+    }
+    analysis
+}
+
+/// Returns the directory scanned automatically for user progression files:
+/// `$XDG_CONFIG_HOME/tonic-music/progressions` (or `~/.config/...`, or `%APPDATA%\\...`).
+fn config_library_dir() -> Option<PathBuf> {
+    let config_home: PathBuf = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(config_home.join("tonic-music").join("progressions"))
+}
+
+/// Loads every `.toml` / `.json` library at `path` (a file, or a directory of files).
+fn load_library_path(library: &mut ProgressionLibrary, path: &Path) -> Result<(), String> {
+    if path.is_dir() {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(path)
+            .map_err(|e: std::io::Error| format!("{}: {}", path.display(), e))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p: &PathBuf| p.is_file() && LibraryFormat::from_path(p).is_some())
+            .collect();
+        files.sort();
+        for file in files {
+            library.merge(ProgressionLibrary::load_file(&file)?);
+        }
+        Ok(())
+    } else {
+        library.merge(ProgressionLibrary::load_file(path)?);
+        Ok(())
+    }
+}
+
+/// Builds the progression library: the built-in progressions, then the
+/// config directory, then every `--library` path (later names win).
+fn load_library(extra_paths: &[PathBuf]) -> ProgressionLibrary {
+    let mut library: ProgressionLibrary = ProgressionLibrary::builtin();
+
+    let config_dir: Option<PathBuf> = config_library_dir().filter(|d: &PathBuf| d.is_dir());
+    for path in config_dir.iter().chain(extra_paths.iter()) {
+        let path: &PathBuf = path;
+        load_library_path(&mut library, path).unwrap_or_else(|e| {
+            eprintln!("Error loading progression library: {}", e);
+            std::process::exit(1);
+        });
+    }
+
+    library
+}
+
+/// A progression built from the command-line options.
+struct ResolvedProgression {
+    root: String,
+    root_note: Note,
+    name: String,
+    /// The mode of a library progression
+    mode: Option<ScaleType>,
+    progression: Vec<ProgressionChord>,
+}
+
+/// Builds the progression requested by either `--formula` or `--custom`.
+/// The root falls back to the library progression's default key.
+/// Exits the process with an error message if anything is unknown or invalid.
+fn resolve_progression(
+    library: &ProgressionLibrary,
+    root: &Option<String>,
+    formula: &Option<String>,
+    custom: &Option<String>,
+    options: &VoiceLeadingOptions,
+) -> ResolvedProgression {
+    let definition: Option<&ProgressionDefinition> = formula.as_ref().map(|f: &String| {
+        library.find(f).unwrap_or_else(|| {
+            eprintln!(
+                "Error: Unknown progression '{}'. Use `progression --list` to see the library.",
+                f
+            );
+            std::process::exit(1);
+        })
+    });
+
+    let default_key: Option<Note> = definition.and_then(|d: &ProgressionDefinition| d.key);
+    let mode: Option<ScaleType> = definition.and_then(|d: &ProgressionDefinition| d.mode);
+    let root: String = match (root, default_key) {
+        (Some(r), _) => r.clone(),
+        (None, Some(key)) => spell_tonic(key, mode.unwrap_or(ScaleType::Major)).to_string(),
+        (None, None) => {
+            eprintln!("Error: --root is required (this progression has no default key)");
+            std::process::exit(1);
+        }
+    };
+    let root_note: Note = parse_note(&root).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    });
+
+    if let Some(definition) = definition {
+        let progression: Vec<ProgressionChord> =
+            definition.build(root_note, options).unwrap_or_else(|e| {
+                eprintln!("Error parsing progression '{}': {}", definition.name, e);
+                std::process::exit(1);
+            });
+        ResolvedProgression {
+            root,
+            root_note,
+            name: definition.name.clone(),
+            mode,
+            progression,
+        }
+    } else if let Some(c) = custom {
+        let specs: Vec<ParsedRomanChord> = parse_progression_formula(c).unwrap_or_else(|e| {
+            eprintln!("Error parsing custom progression: {}", e);
+            std::process::exit(1);
+        });
+
+        ResolvedProgression {
+            root,
+            root_note,
+            name: c.clone(),
+            mode: None,
+            progression: build_custom_progression(root_note, specs, options),
+        }
+    } else {
+        unreachable!("Clap ensures one is present");
+    }
+}
+
+fn main() {
+    let cli_args: Cli = Cli::parse();
+    let instrument: Option<InstrumentDefinition> =
+        cli_args.instrument.map(|i: Instrument| i.definition());
+    // Concert notes and pitches are written for the instrument (unchanged without one)
+    let written_note = |note: Note| -> Note {
+        instrument
+            .as_ref()
+            .map_or(note, |i: &InstrumentDefinition| i.written_note(note))
+    };
+    let written_pitches = |pitches: Vec<Pitch>| -> Vec<Pitch> {
+        instrument
+            .as_ref()
+            .map_or(pitches.clone(), |i: &InstrumentDefinition| {
+                i.written_pitches(&pitches)
+            })
+    };
+    let written_root = |root: &str, note: Note| -> String {
+        match instrument {
+            Some(_) => written_note(note).to_string(),
+            None => root.to_string(),
+        }
+    };
+
+    match &cli_args.command {
+        Commands::Scale {
+            root,
+            scale_type,
+            fingering,
+        } => {
+            let root_note: Note = parse_note(root).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            });
+            // The written scale of a transposing instrument is the scale of the written root
+            let written: Note = written_note(root_note);
+            // scale_type is already typed
+            let notes: Vec<Note> = build_scale(written, *scale_type);
+            let fingering_numbers: Option<Vec<u8>> = fingering.map(|hand: Hand| {
+                scale_fingering(written, *scale_type, hand).unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                })
+            });
+
+            let response: ScaleResponse = ScaleResponse {
+                root: written_root(root, root_note),
+                scale_type: format!("{:?}", scale_type),
+                scale: *scale_type,
+                notes,
+                pitches: scale_pitches(written, *scale_type, 4),
+                hand: *fingering,
+                fingering: fingering_numbers,
+                instrument: instrument.clone(),
+            };
+            print_output(&response, &cli_args);
+        }
+        Commands::Chord {
+            root,
+            chord_type,
+            inversions,
+            voicing,
+            octave,
+            piano,
+        } => {
+            let root_note: Note = parse_note(root).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            });
+            let notes: Vec<Note> = build_chord(written_note(root_note), *chord_type);
+
+            let invs: Option<Vec<Vec<Note>>> = if *inversions {
+                Some(get_inversions(&notes))
+            } else {
+                None
+            };
+
+            let voiced: Option<Vec<Pitch>> = voicing.map(|style: VoicingStyle| {
+                let concert: Vec<Pitch> = voice_chord(root_note, *chord_type, style, *octave)
+                    .unwrap_or_else(|e| {
+                        eprintln!("Error: {}", e);
+                        std::process::exit(1);
+                    });
+                written_pitches(concert)
+            });
+            if let Some(pitches) = &voiced {
+                warn_out_of_range(&instrument, pitches);
+            }
+
+            // The keyboard and MIDI play the voicing, or the close position from the 4th octave
+            let pitches: Vec<Pitch> = voiced.clone().unwrap_or_else(|| stack_notes(&notes, 4));
+
+            let response: ChordResponse = ChordResponse {
+                root: written_root(root, root_note),
+                chord_type: format!("{:?}", chord_type),
+                chord: *chord_type,
+                notes,
+                inversions: invs,
+                voicing_style: *voicing,
+                voicing: voiced,
+                pitches,
+                piano: *piano,
+                instrument: instrument.clone(),
+            };
+            print_output(&response, &cli_args);
+        }
+        Commands::Harmonize {
+            root,
+            scale_type,
+            sevenths,
+        } => {
+            let root_note: Note = parse_note(root).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            });
+            let scale_notes: Vec<Note> = build_scale(root_note, *scale_type);
+            let mut harmony: Vec<HarmonizedDegree> = harmonize_scale(&scale_notes, *sevenths);
+            if let Some(i) = &instrument {
+                harmony = i.written_harmony(&harmony);
+            }
+
+            let response: HarmonizeResponse = HarmonizeResponse {
+                root: written_root(root, root_note),
+                scale_type: format!("{:?}", scale_type),
+                scale: *scale_type,
+                harmony,
+                instrument: instrument.clone(),
+            };
+            print_output(&response, &cli_args);
+        }
+        Commands::Progression {
+            root,
+            formula,
+            custom,
+            list,
+            tag,
+            satb,
+            voicing,
+            octave,
+            voice_leading,
+        } => {
+            let library: ProgressionLibrary = load_library(&cli_args.library);
+
+            if *list {
+                let progressions: Vec<ProgressionDefinition> = match tag {
+                    Some(t) => library.with_tag(t).into_iter().cloned().collect(),
+                    None => library.definitions,
+                };
+                let response: ProgressionListResponse = ProgressionListResponse {
+                    tag: tag.clone(),
+                    progressions,
+                };
+                print_output(&response, &cli_args);
+                return;
+            }
+
+            let resolved: ResolvedProgression =
+                resolve_progression(&library, root, formula, custom, &voice_leading.options());
+            let progression: Vec<ProgressionChord> =
+                with_degrees(resolved.root_note, resolved.progression, cli_args.degrees);
+
+            let (voicing_style, mut voicing): (Option<String>, Option<Vec<VoicedChord>>) =
+                voice_for_output(&progression, *satb, *voicing, *octave);
+            let cadences: Vec<(usize, Cadence)> = detect_cadences(
+                resolved.root_note,
+                &progression,
+                top_voices(&voicing).as_deref(),
+            );
+
+            let mut progression: Vec<ProgressionChord> = progression;
+            if let Some(i) = &instrument {
+                progression = i.written_progression(&progression);
+                voicing = voicing.map(|v: Vec<VoicedChord>| i.written_voicing(&v));
+            }
+            for chord in voicing.iter().flatten() {
+                warn_out_of_range(&instrument, &chord.pitches);
+            }
+
+            let response: ProgressionResponse = ProgressionResponse {
+                root: written_root(&resolved.root, resolved.root_note),
+                formula: resolved.name,
+                mode: resolved.mode,
+                progression,
+                cadences,
+                voicing_style,
+                voicing,
+                instrument: instrument.clone(),
+            };
+            print_output(&response, &cli_args);
+        }
+        Commands::Reharmonize {
+            root,
+            formula,
+            custom,
+            rules,
+            depth,
+            voice_leading,
+        } => {
+            let library: ProgressionLibrary = load_library(&cli_args.library);
+            let resolved: ResolvedProgression =
+                resolve_progression(&library, root, formula, custom, &voice_leading.options());
+
+            let rules: &[SubstitutionRule] = if rules.is_empty() {
+                SubstitutionRule::all()
+            } else {
+                rules
+            };
+            let suggestions: Vec<Reharmonization> = reharmonize(
+                resolved.root_note,
+                &resolved.progression,
+                rules,
+                *depth as usize,
+                &voice_leading.options(),
+            )
+            .into_iter()
+            .map(|s: Reharmonization| Reharmonization {
+                progression: with_degrees(resolved.root_note, s.progression, cli_args.degrees),
+                ..s
+            })
+            .collect();
+
+            let response: ReharmonizeResponse = ReharmonizeResponse {
+                root: resolved.root,
+                formula: resolved.name,
+                depth: *depth as usize,
+                original: with_degrees(resolved.root_note, resolved.progression, cli_args.degrees),
+                suggestions,
+            };
+            print_output(&response, &cli_args);
+        }
+        Commands::Fretboard {
+            root,
+            scale_type,
+            chord_type,
+            tuning,
+            capo,
+            frets,
+        } => {
+            let root_note: Note = parse_note(root).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            });
+            let (notes, name): (Vec<Note>, String) = match (scale_type, chord_type) {
+                (Some(scale_type), _) => (
+                    build_scale(root_note, *scale_type),
+                    format!("{} {:?} Scale", root, scale_type),
+                ),
+                (None, Some(chord_type)) => (
+                    build_chord(root_note, *chord_type),
+                    format!("{} {:?} Chord", root, chord_type),
+                ),
+                (None, None) => unreachable!("Clap ensures one is present"),
+            };
+
+            let fretboard: Fretboard = resolve_tuning(tuning)
+                .and_then(|t: Vec<Pitch>| Fretboard::new(t, *frets, *capo))
+                .unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                });
+
+            let response: FretboardResponse = FretboardResponse {
+                root: root_note,
+                name,
+                positions: fretboard.positions_of(&notes),
+                notes,
+                fretboard,
+            };
+            print_output(&response, &cli_args);
+        }
+        Commands::Shapes {
+            root,
+            scale_type,
+            system,
+            tuning,
+            capo,
+            shape,
+        } => {
+            let root_note: Note = parse_note(root).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            });
+            let fretboard: Fretboard = resolve_tuning(tuning)
+                .and_then(|t: Vec<Pitch>| Fretboard::new(t, 24, *capo))
+                .unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                });
+            let system: ShapeSystem =
+                system.unwrap_or(if build_scale(root_note, *scale_type).len() == 5 {
+                    ShapeSystem::Pentatonic
+                } else {
+                    ShapeSystem::Caged
+                });
+
+            let mut shapes: Vec<ScaleShape> =
+                scale_shapes(&fretboard, root_note, *scale_type, system).unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                });
+            if let Some(number) = shape {
+                if *number == 0 || *number > shapes.len() {
+                    eprintln!(
+                        "Error: There are {} shapes (use 1-{})",
+                        shapes.len(),
+                        shapes.len()
+                    );
+                    std::process::exit(1);
+                }
+                shapes = vec![shapes.remove(number - 1)];
+            }
+
+            let response: ShapesResponse = ShapesResponse {
+                root: root.clone(),
+                scale_type: *scale_type,
+                system,
+                fretboard,
+                shapes,
+            };
+            print_output(&response, &cli_args);
+        }
+        Commands::Fingering {
+            root,
+            chord_type,
+            tuning,
+            capo,
+            bass,
+            any_bass,
+            max_stretch,
+            max_fingers,
+            max_fret,
+            min_strings,
+            no_open,
+            inner_mutes,
+            omit_fifth,
+            count,
+        } => {
+            let root_note: Note = parse_note(root).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            });
+            let fretboard: Fretboard = resolve_tuning(tuning)
+                .and_then(|t: Vec<Pitch>| Fretboard::new(t, 24, *capo))
+                .unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                });
+
+            // 1. The bass defaults to the root
+            let bass_note: Option<Note> = match (bass, any_bass) {
+                (_, true) => None,
+                (Some(b), _) => Some(parse_note(b).unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                })),
+                (None, false) => Some(root_note),
+            };
+            let tones: Vec<Note> = build_chord(root_note, *chord_type);
+            let required: Option<Vec<Note>> = if *omit_fifth {
+                Some(
+                    tones
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, _): &(usize, &Note)| *i != 2)
+                        .map(|(_, n): (usize, &Note)| *n)
+                        .collect(),
+                )
+            } else {
+                None
+            };
+
+            let options: FingeringOptions = FingeringOptions {
+                max_stretch: *max_stretch,
+                max_fingers: *max_fingers,
+                max_fret: *max_fret,
+                min_strings: *min_strings,
+                open_strings: !no_open,
+                inner_mutes: *inner_mutes,
+                required,
+                bass: bass_note,
+            };
+            let mut fingerings: Vec<Fingering> =
+                find_fingerings(&fretboard, root_note, *chord_type, &options).unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                });
+            fingerings.truncate(*count);
+
+            let response: FingeringResponse = FingeringResponse {
                 root: root.clone(),
-                formula: formula_name,
+                chord_type: *chord_type,
+                fretboard,
+                fingerings,
+            };
+            print_output(&response, &cli_args);
+        }
+        Commands::CheckVoicing {
+            input,
+            root,
+            formula,
+            custom,
+        } => {
+            let (key, source, chords): (Note, String, Vec<VoicedChord>) = match input {
+                Some(path) => {
+                    let contents: String = std::fs::read_to_string(path).unwrap_or_else(|e| {
+                        eprintln!("Error: Cannot read '{}': {}", path.display(), e);
+                        std::process::exit(1);
+                    });
+                    let (key, chords): (Note, Vec<VoicedChord>) = parse_voicing_json(&contents)
+                        .unwrap_or_else(|e| {
+                            eprintln!("Error: {}", e);
+                            std::process::exit(1);
+                        });
+                    (key, path.display().to_string(), chords)
+                }
+                None => {
+                    // Check the project's own SATB voicing of the progression
+                    let library: ProgressionLibrary = load_library(&cli_args.library);
+                    let resolved: ResolvedProgression = resolve_progression(
+                        &library,
+                        root,
+                        formula,
+                        custom,
+                        &VoiceLeadingOptions::default(),
+                    );
+                    let chords: Vec<VoicedChord> =
+                        voice_progression(&resolved.progression, &VoiceRange::satb())
+                            .unwrap_or_else(|e| {
+                                eprintln!("Error: {}", e);
+                                std::process::exit(1);
+                            });
+                    (resolved.root_note, resolved.name, chords)
+                }
+            };
+
+            let response: CheckVoicingResponse = CheckVoicingResponse {
+                key,
+                source,
+                violations: check_part_writing(key, &chords),
+                chords,
+            };
+            print_output(&response, &cli_args);
+        }
+        Commands::AnalyzeMidi {
+            input,
+            segment,
+            key,
+        } => {
+            let bytes: Vec<u8> = std::fs::read(input).unwrap_or_else(|e| {
+                eprintln!("Error: Cannot read '{}': {}", input.display(), e);
+                std::process::exit(1);
+            });
+            let file: MidiFile = read_midi(&bytes).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            });
+            let key: Option<(Note, ScaleType)> = key.as_ref().map(|k: &String| {
+                parse_key(k).unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                })
+            });
+
+            let segments: Vec<Segment> =
+                segment_notes(&file.notes(), *segment, file.time_signature).unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                });
+            let analysis: HarmonicAnalysis =
+                with_chord_degrees(analyze_segments(&segments, key), cli_args.degrees);
+
+            let response: MidiAnalysisResponse = MidiAnalysisResponse {
+                source: input
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| input.display().to_string()),
+                tempo: file.tempo,
+                time_signature: format!("{}/{}", file.time_signature.0, file.time_signature.1),
+                meter: file.time_signature,
+                key: analysis.key,
+                mode: match analysis.mode {
+                    ScaleType::Major => "major".to_string(),
+                    _ => "minor".to_string(),
+                },
+                chords: analysis.chords,
+            };
+            print_output(&response, &cli_args);
+        }
+        Commands::AnalyzeAudio {
+            input,
+            key,
+            a4,
+            window,
+            hop,
+            smoothing,
+        } => {
+            let bytes: Vec<u8> = std::fs::read(input).unwrap_or_else(|e| {
+                eprintln!("Error: Cannot read '{}': {}", input.display(), e);
+                std::process::exit(1);
+            });
+            let wav: Wav = read_wav(&bytes).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            });
+            let key: Option<(Note, ScaleType)> = key.as_ref().map(|k: &String| {
+                parse_key(k).unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                })
+            });
+            let options: ChromaOptions = ChromaOptions {
+                window: *window,
+                hop: *hop,
+                smoothing: *smoothing,
+                a4: *a4,
+                ..ChromaOptions::default()
+            };
+            let analysis: HarmonicAnalysis =
+                analyze_audio(&wav.samples, wav.sample_rate, &options, key).unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                });
+            let analysis: HarmonicAnalysis = with_chord_degrees(analysis, cli_args.degrees);
+
+            let response: AudioAnalysisResponse = AudioAnalysisResponse {
+                source: input
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| input.display().to_string()),
+                duration: wav.duration(),
+                key: analysis.key,
+                mode: match analysis.mode {
+                    ScaleType::Major => "major".to_string(),
+                    _ => "minor".to_string(),
+                },
+                chords: analysis.chords,
+            };
+            print_output(&response, &cli_args);
+        }
+        Commands::AnalyzePitch {
+            input,
+            a4,
+            window,
+            hop,
+            threshold,
+            min_frequency,
+            max_frequency,
+        } => {
+            let bytes: Vec<u8> = std::fs::read(input).unwrap_or_else(|e| {
+                eprintln!("Error: Cannot read '{}': {}", input.display(), e);
+                std::process::exit(1);
+            });
+            let wav: Wav = read_wav(&bytes).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            });
+            let options: TunerOptions = TunerOptions {
+                window: *window,
+                hop: *hop,
+                threshold: *threshold,
+                min_frequency: *min_frequency,
+                max_frequency: *max_frequency,
+                a4: *a4,
+                ..TunerOptions::default()
+            };
+            let frames: Vec<PitchFrame> = detect_pitches(&wav.samples, wav.sample_rate, &options)
+                .unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                });
+
+            let response: PitchAnalysisResponse = PitchAnalysisResponse {
+                source: input
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| input.display().to_string()),
+                sample_rate: wav.sample_rate,
+                duration: wav.duration(),
+                a4: *a4,
+                frames,
+            };
+            print_output(&response, &cli_args);
+        }
+        Commands::Song {
+            action:
+                SongAction::Transpose {
+                    input,
+                    to,
+                    semitones,
+                    sheet,
+                },
+        } => {
+            let text: String = std::fs::read_to_string(input).unwrap_or_else(|e| {
+                eprintln!("Error: Cannot read '{}': {}", input.display(), e);
+                std::process::exit(1);
+            });
+            let song: Song = parse_chordpro(&text).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            });
+            for warning in &song.warnings {
+                eprintln!("Warning: {}", warning);
+            }
+
+            // 1. The new key: a key name, a number of semitones, or the same key
+            let song: Song = match (to, semitones) {
+                (Some(key), _) => {
+                    let (tonic, mode): (SpelledNote, ScaleType) = parse_key_directive(key)
+                        .unwrap_or_else(|| {
+                            eprintln!("Error: Invalid key: '{}' (e.g. Bb, F#m)", key);
+                            std::process::exit(1);
+                        });
+                    let (from, from_mode): (SpelledNote, ScaleType) = song.key();
+                    if mode != from_mode {
+                        let mode_name: &str = if from_mode == ScaleType::Major {
+                            "major"
+                        } else {
+                            "minor"
+                        };
+                        eprintln!(
+                            "Error: The song is in {} {}: give a {} key",
+                            from, mode_name, mode_name
+                        );
+                        std::process::exit(1);
+                    }
+                    song.transpose(tonic)
+                }
+                (None, Some(semitones)) => song.transpose_semitones(*semitones),
+                (None, None) => song,
+            };
+
+            // 2. Write it back in the --degrees style (chord names by default)
+            let chords: ChordStyle = cli_args.degrees.unwrap_or(ChordStyle::Letters);
+            let (tonic, mode): (SpelledNote, ScaleType) = song.key();
+            let mut names: Vec<String> = Vec::<String>::new();
+            for chord in song.chords() {
+                let name: String = chord.to_string();
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+            let response: SongResponse = SongResponse {
+                title: song.title().map(str::to_string),
+                key: format!(
+                    "{}{}",
+                    tonic,
+                    if mode == ScaleType::Major { "" } else { "m" }
+                ),
+                chords: names,
+                text: if *sheet {
+                    write_lyric_sheet(&song, chords)
+                } else {
+                    write_chordpro(&song, chords)
+                },
+            };
+            print_output(&response, &cli_args);
+        }
+        Commands::ImportMusicxml {
+            input,
+            key,
+            transpose,
+            satb,
+            voicing,
+            octave,
+        } => {
+            let xml: String = std::fs::read_to_string(input).unwrap_or_else(|e| {
+                eprintln!("Error: Cannot read '{}': {}", input.display(), e);
+                std::process::exit(1);
+            });
+            let score: ImportedScore = read_musicxml(&xml).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            });
+            for warning in &score.warnings {
+                eprintln!("Warning: {}", warning);
+            }
+
+            // 1. The key: --key, the key signature, or the best fit
+            let (key_note, mode): (Note, ScaleType) = match key {
+                Some(k) => parse_key(k).unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }),
+                None => score.key(),
+            };
+            let concert: Vec<ProgressionChord> =
+                score.progression((key_note, mode)).unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                });
+            if concert.is_empty() {
+                eprintln!(
+                    "Error: No chord symbols or notes found in '{}'",
+                    input.display()
+                );
+                std::process::exit(1);
+            }
+
+            // 2. Transpose: the degrees and functions stay the same
+            let shift = |note: Note| -> Note { Pitch::new(note, 4).transpose(*transpose).note };
+            let root_note: Note = shift(key_note);
+            let progression: Vec<ProgressionChord> = concert
+                .iter()
+                .map(|c: &ProgressionChord| ProgressionChord {
+                    degree: c.degree.clone(),
+                    root_note: shift(c.root_note),
+                    chord_type: c.chord_type,
+                    function: c.function,
+                    notes: c.notes.iter().map(|n: &Note| shift(*n)).collect(),
+                })
+                .collect();
+            let root: String = match score.key {
+                Some((tonic, _)) if key.is_none() && *transpose == 0 => tonic.to_string(),
+                _ => spell_tonic(root_note, mode).to_string(),
+            };
+
+            // 3. Re-voice and write for the instrument, as `progression` does
+            let progression: Vec<ProgressionChord> =
+                with_degrees(root_note, progression, cli_args.degrees);
+            let (voicing_style, mut voicing): (Option<String>, Option<Vec<VoicedChord>>) =
+                voice_for_output(&progression, *satb, *voicing, *octave);
+            let cadences: Vec<(usize, Cadence)> =
+                detect_cadences(root_note, &progression, top_voices(&voicing).as_deref());
+            let mut progression: Vec<ProgressionChord> = progression;
+            if let Some(i) = &instrument {
+                progression = i.written_progression(&progression);
+                voicing = voicing.map(|v: Vec<VoicedChord>| i.written_voicing(&v));
+            }
+            for chord in voicing.iter().flatten() {
+                warn_out_of_range(&instrument, &chord.pitches);
+            }
+
+            let response: ProgressionResponse = ProgressionResponse {
+                root: written_root(&root, root_note),
+                mode: Some(mode),
+                formula: score.title.clone().unwrap_or_else(|| {
+                    input
+                        .file_name()
+                        .map(|n| n.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| input.display().to_string())
+                }),
                 progression,
+                cadences,
+                voicing_style,
+                voicing,
+                instrument: instrument.clone(),
+            };
+            print_output(&response, &cli_args);
+        }
+        Commands::Generate {
+            root,
+            style,
+            length,
+            seed,
+            cadence,
+            train,
+            voice_leading,
+        } => {
+            let root_note: Note = parse_note(root).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            });
+
+            // 1. Pick the transition table
+            let (table, source): (TransitionTable, String) = if train.is_empty() {
+                (
+                    TransitionTable::builtin(*style),
+                    format!("{:?}", style).to_lowercase(),
+                )
+            } else {
+                let library: ProgressionLibrary = load_library(&cli_args.library);
+                let formulas: Vec<&str> = train
+                    .iter()
+                    .map(|t: &String| match library.find(t) {
+                        Some(definition) => definition.formula.as_str(),
+                        None => t.as_str(),
+                    })
+                    .collect();
+                let table: TransitionTable =
+                    TransitionTable::train(&formulas).unwrap_or_else(|e| {
+                        eprintln!("Error training the transition table: {}", e);
+                        std::process::exit(1);
+                    });
+                (table, "trained".to_string())
+            };
+
+            // 2. Without a seed, use the clock (the seed is printed so it can be replayed)
+            let seed: u64 = seed.unwrap_or_else(|| {
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d: std::time::Duration| d.as_nanos() as u64)
+                    .unwrap_or(0)
+            });
+
+            // 3. Walk the chain
+            let options: GeneratorOptions = GeneratorOptions {
+                length: *length,
+                seed,
+                cadence: *cadence,
+                voice_leading: voice_leading.options(),
+            };
+            let progression: Vec<ProgressionChord> =
+                generate_progression(root_note, &table, &options).unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                });
+
+            let progression: Vec<ProgressionChord> =
+                with_degrees(root_note, progression, cli_args.degrees);
+            let cadences: Vec<(usize, Cadence)> = detect_cadences(root_note, &progression, None);
+            let response: ProgressionResponse = ProgressionResponse {
+                root: written_root(root, root_note),
+                formula: format!("Generated {} (seed {})", source, seed),
+                mode: None,
+                cadences,
+                progression: match &instrument {
+                    Some(i) => i.written_progression(&progression),
+                    None => progression,
+                },
+                voicing_style: None,
+                voicing: None,
+                instrument: instrument.clone(),
             };
-            print_output(&response, cli_args.format);
+            print_output(&response, &cli_args);
         }
     }
 }
@@ -0,0 +1,222 @@
+/*
+ * tonic-music-core
+ * Copyright (C) 2025 Israel Alberto Roldan Vega
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * Repository: https://github.com/airvzxf/music-theory-calculator/
+ */
+
+/*
+ * crates/tonic-music-core/src/abc.rs
+ *
+ * This module writes a score in ABC notation (.abc), the plain-text format
+ * read by abcjs, EasyABC and abcm2ps: chord symbols above the notes and
+ * Roman numerals as annotations below them.
+ */
+
+use crate::notation::{Accidentals, ChordSymbol, Measure, Score, SpelledPitch, Staves};
+
+/// The ABC name of a pitch: C is middle C (C4), c the octave above, c' the
+/// next one and C, the octave below. `accidental` is written before it
+/// (`^` sharp, `_` flat, `=` natural).
+pub fn pitch_name(pitch: &SpelledPitch, accidental: Option<i8>) -> String {
+    let sign: &str = match accidental {
+        Some(-2) => "__",
+        Some(-1) => "_",
+        Some(0) => "=",
+        Some(1) => "^",
+        Some(2) => "^^",
+        _ => "",
+    };
+    if pitch.octave >= 5 {
+        format!(
+            "{}{}{}",
+            sign,
+            pitch.note.step.to_ascii_lowercase(),
+            "'".repeat((pitch.octave - 5) as usize)
+        )
+    } else {
+        format!(
+            "{}{}{}",
+            sign,
+            pitch.note.step,
+            ",".repeat((4 - pitch.octave) as usize)
+        )
+    }
+}
+
+/// Text inside an ABC chord symbol or annotation cannot contain quotes.
+fn quoted(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', ""))
+}
+
+/// A whole-measure note, chord or rest, with the chord symbol above and the
+/// label below it.
+fn measure_event(
+    pitches: &[&SpelledPitch],
+    symbol: Option<String>,
+    label: Option<&str>,
+    fifths: i8,
+) -> String {
+    let mut event: String = String::new();
+    if let Some(symbol) = symbol {
+        event.push_str(&quoted(&symbol));
+    }
+    if let Some(label) = label {
+        event.push_str(&quoted(&format!("_{}", label)));
+    }
+    let mut accidentals: Accidentals = Accidentals::new(fifths);
+    let notes: Vec<String> = pitches
+        .iter()
+        .map(|p: &&SpelledPitch| pitch_name(p, accidentals.show(p)))
+        .collect();
+    match notes.len() {
+        0 => event.push('z'),
+        1 => event.push_str(&notes[0]),
+        _ => event.push_str(&format!("[{}]", notes.concat())),
+    }
+    event
+}
+
+/// Writes the body of one voice, four measures to a line, with a final bar line.
+/// `symbols` and `labels` choose whether the chord symbols and the Roman
+/// numerals go with this voice.
+fn push_voice(
+    abc: &mut String,
+    score: &Score,
+    pitches: fn(&Measure) -> Vec<&SpelledPitch>,
+    symbols: bool,
+    labels: bool,
+) {
+    let events: Vec<String> = score
+        .measures
+        .iter()
+        .map(|m: &Measure| {
+            measure_event(
+                &pitches(m),
+                m.symbol
+                    .as_ref()
+                    .filter(|_: &&ChordSymbol| symbols)
+                    .map(|s: &ChordSymbol| s.to_string()),
+                m.label.as_deref().filter(|_: &&str| labels),
+                score.fifths,
+            )
+        })
+        .collect();
+    let count: usize = events.len();
+    for (i, event) in events.iter().enumerate() {
+        let bar: &str = if i + 1 == count { " |]" } else { " |" };
+        abc.push_str(event);
+        abc.push_str(bar);
+        abc.push(if (i + 1).is_multiple_of(4) || i + 1 == count {
+            '\n'
+        } else {
+            ' '
+        });
+    }
+}
+
+/// The pitches of a measure written on a single staff.
+fn all_pitches(measure: &Measure) -> Vec<&SpelledPitch> {
+    measure.pitches.iter().collect()
+}
+
+/// Writes the score as an ABC tune: one whole-note measure per note or
+/// chord in 4/4, chord symbols above and Roman numerals below. Parts that
+/// go above and below middle C get two voices on a grand staff.
+pub fn write_abc(score: &Score) -> String {
+    // 1. Header: the key line comes last
+    let mut abc: String = String::from("X:1\n");
+    abc.push_str(&format!("T:{}\n", score.title.replace('\n', " ")));
+    abc.push_str("M:4/4\nL:1/1\n");
+    let key: String = format!(
+        "K:{}{}",
+        score.tonic,
+        if score.is_minor() { "m" } else { "" }
+    );
+
+    // 2. One voice, or two voices braced as a grand staff
+    match score.staves() {
+        Staves::Grand => {
+            abc.push_str("%%score {1 | 2}\nV:1 clef=treble\nV:2 clef=bass\n");
+            abc.push_str(&format!("{}\nV:1\n", key));
+            push_voice(&mut abc, score, Measure::treble, true, false);
+            abc.push_str("V:2\n");
+            push_voice(&mut abc, score, Measure::bass, false, true);
+        }
+        Staves::Bass => {
+            abc.push_str(&format!("{} clef=bass\n", key));
+            push_voice(&mut abc, score, all_pitches, true, true);
+        }
+        Staves::Treble => {
+            abc.push_str(&format!("{}\n", key));
+            push_voice(&mut abc, score, all_pitches, true, true);
+        }
+    }
+    abc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::{SpelledNote, spell_tonic};
+    use crate::parser::parse_spelled_note;
+    use crate::pitch::{Pitch, stack_notes};
+    use crate::{ChordType, Note, ScaleType, build_scale};
+
+    #[test]
+    fn test_abc_scale() {
+        let pitches: Vec<Pitch> = stack_notes(&build_scale(Note::A, ScaleType::MinorHarmonic), 2);
+        let score: Score = Score::scale(
+            "A Harmonic Minor",
+            spell_tonic(Note::A, ScaleType::MinorHarmonic),
+            ScaleType::MinorHarmonic,
+            &pitches,
+        );
+        let abc: String = write_abc(&score);
+        assert!(abc.starts_with("X:1\nT:A Harmonic Minor\nM:4/4\nL:1/1\nK:Am clef=bass\n"));
+        // The raised seventh needs its sharp, the rest follow the key
+        assert!(abc.contains("A,, | B,, | C, | D, |\nE, | F, | ^G, |]\n"));
+    }
+
+    #[test]
+    fn test_abc_chords() {
+        // F# major inversions across middle C: a grand staff
+        let root: SpelledNote = parse_spelled_note("F#").unwrap();
+        let voicings: Vec<Vec<Pitch>> = vec![
+            stack_notes(&[Note::FSharp, Note::ASharp, Note::CSharp], 3),
+            stack_notes(&[Note::ASharp, Note::CSharp, Note::FSharp], 3),
+        ];
+        let score: Score = Score::chord("F# Major", root, ChordType::Major, &voicings);
+        let abc: String = write_abc(&score);
+        assert!(abc.contains("%%score {1 | 2}\nV:1 clef=treble\nV:2 clef=bass\nK:F#\nV:1\n"));
+        assert!(abc.contains("V:1\n\"F#\"C | \"F#/A#\"[CF] |]\nV:2\n[F,A,] | A, |]\n"));
+
+        // Notes outside the key get their accidental, here a natural
+        let pitches: Vec<Pitch> = vec![
+            Pitch::new(Note::G, 4),
+            Pitch::new(Note::B, 4),
+            Pitch::new(Note::D, 5),
+            Pitch::new(Note::F, 5),
+        ];
+        let score: Score = Score::chord(
+            "G7",
+            spell_tonic(Note::G, ScaleType::Major),
+            ChordType::Dominant7,
+            &[pitches],
+        );
+        assert!(write_abc(&score).contains("K:G\n\"G7\"[GBd=f] |]\n"));
+    }
+}
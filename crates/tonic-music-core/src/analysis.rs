@@ -0,0 +1,759 @@
+/*
+ * tonic-music-core
+ * Copyright (C) 2025 Israel Alberto Roldan Vega
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * Repository: https://github.com/airvzxf/music-theory-calculator/
+ */
+
+/*
+ * crates/tonic-music-core/src/analysis.rs
+ *
+ * This module labels chords with their harmonic function, writes their
+ * degrees as Roman numerals or Nashville numbers and classifies the
+ * cadence that closes a progression.
+ */
+
+use crate::chordpro::ChordStyle;
+use crate::notation::{SpelledNote, chord_suffix, spell_in_key, spell_tonic};
+use crate::parser::{NashvilleMark, ParsedNashvilleChord, parse_nashville_chord};
+use crate::{ChordType, Interval, Note, ProgressionChord, ScaleType, transpose};
+use serde::Serialize;
+
+/// The role a chord plays relative to the key center.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub enum HarmonicFunction {
+    /// The key center itself (I / i).
+    Tonic,
+    /// Chords that extend the tonic without replacing it (iii, vi, bIII).
+    TonicProlongation,
+    /// The IV / iv family and the backdoor bVII.
+    Subdominant,
+    /// Chords that lead into the dominant (ii, bII, bVI, #iv).
+    Predominant,
+    /// V, vii° and any dominant-quality chord (secondary dominants).
+    Dominant,
+}
+
+impl HarmonicFunction {
+    /// Returns the short analysis label (e.g. "T", "PD").
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            HarmonicFunction::Tonic => "T",
+            HarmonicFunction::TonicProlongation => "Tp",
+            HarmonicFunction::Subdominant => "S",
+            HarmonicFunction::Predominant => "PD",
+            HarmonicFunction::Dominant => "D",
+        }
+    }
+}
+
+/// The kind of cadence formed by the last two chords of a phrase.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub enum Cadence {
+    /// V-I with both chords in root position and the tonic on top.
+    PerfectAuthentic,
+    /// V-I (or vii°-I) missing one of the perfect authentic conditions.
+    ImperfectAuthentic,
+    /// IV-I, the "Amen" cadence.
+    Plagal,
+    /// Any phrase that stops on V.
+    Half,
+    /// V resolving to vi (or bVI) instead of I.
+    Deceptive,
+    /// iv6-V, a half cadence with the bass descending by half step.
+    Phrygian,
+}
+
+impl std::fmt::Display for Cadence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s: &str = match self {
+            Cadence::PerfectAuthentic => "Perfect Authentic",
+            Cadence::ImperfectAuthentic => "Imperfect Authentic",
+            Cadence::Plagal => "Plagal",
+            Cadence::Half => "Half",
+            Cadence::Deceptive => "Deceptive",
+            Cadence::Phrygian => "Phrygian",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Distance in semitones (0-11) from the key center up to a chord root.
+fn degree_semitones(key_center: Note, chord_root: Note) -> u8 {
+    (chord_root.as_u8() + 12 - key_center.as_u8()) % 12
+}
+
+/// Returns true for chord qualities that pull toward a resolution a fifth below.
+fn has_dominant_quality(chord_type: ChordType) -> bool {
+    matches!(chord_type, ChordType::Dominant7 | ChordType::Diminished7)
+}
+
+/// Labels a chord with its harmonic function relative to `key_center`.
+///
+/// Dominant-seventh and fully-diminished chords are labeled `Dominant`
+/// wherever they appear, so secondary dominants (e.g. I7, VI7) read as
+/// tension rather than as the function of their root.
+pub fn harmonic_function(
+    key_center: Note,
+    chord_root: Note,
+    chord_type: ChordType,
+) -> HarmonicFunction {
+    let semitones: u8 = degree_semitones(key_center, chord_root);
+
+    // A V7 is a dominant; a I7 is the dominant of IV, etc.
+    if has_dominant_quality(chord_type) {
+        return HarmonicFunction::Dominant;
+    }
+
+    match semitones {
+        0 => HarmonicFunction::Tonic,
+        1 | 2 | 6 | 8 => HarmonicFunction::Predominant,
+        3 | 4 | 9 => HarmonicFunction::TonicProlongation,
+        5 | 10 => HarmonicFunction::Subdominant,
+        7 | 11 => HarmonicFunction::Dominant,
+        _ => unreachable!(),
+    }
+}
+
+/// Spells a chord as a Roman numeral relative to `key_center` (e.g. "bII7", "viidim").
+/// The result can be read back with `parser::parse_roman_chord`.
+pub fn roman_numeral(key_center: Note, chord_root: Note, chord_type: ChordType) -> String {
+    let numeral: &str = match degree_semitones(key_center, chord_root) {
+        0 => "I",
+        1 => "bII",
+        2 => "II",
+        3 => "bIII",
+        4 => "III",
+        5 => "IV",
+        6 => "#IV",
+        7 => "V",
+        8 => "bVI",
+        9 => "VI",
+        10 => "bVII",
+        11 => "VII",
+        _ => unreachable!(),
+    };
+
+    // Minor-family chords use lowercase numerals ("bII" -> "bii").
+    let is_minor_family: bool = matches!(
+        chord_type,
+        ChordType::Minor
+            | ChordType::Diminished
+            | ChordType::Minor7
+            | ChordType::Minor7b5
+            | ChordType::Diminished7
+            | ChordType::MinorMajor7
+    );
+    let numeral: String = if is_minor_family {
+        numeral.to_lowercase()
+    } else {
+        numeral.to_string()
+    };
+
+    let suffix: &str = match chord_type {
+        ChordType::Major | ChordType::Minor => "",
+        ChordType::Diminished => "dim",
+        ChordType::Augmented => "aug",
+        ChordType::Major7 => "maj7",
+        ChordType::Minor7 | ChordType::Dominant7 => "7",
+        ChordType::Minor7b5 => "m7b5",
+        ChordType::Diminished7 => "dim7",
+        ChordType::MinorMajor7 => "mmaj7",
+        ChordType::AugmentedMajor7 => "augmaj7",
+    };
+
+    format!("{}{}", numeral, suffix)
+}
+
+/// The Nashville number of a note relative to `key_center` ("1", "b3", "#4"...).
+pub fn nashville_degree(key_center: Note, note: Note) -> &'static str {
+    match degree_semitones(key_center, note) {
+        0 => "1",
+        1 => "b2",
+        2 => "2",
+        3 => "b3",
+        4 => "3",
+        5 => "4",
+        6 => "#4",
+        7 => "5",
+        8 => "b6",
+        9 => "6",
+        10 => "b7",
+        11 => "7",
+        _ => unreachable!(),
+    }
+}
+
+/// Spells a chord as a Nashville number relative to `key_center` (e.g. "1",
+/// "6-", "57", "b7maj7"): minor chords take a dash, other qualities their suffix.
+pub fn nashville_number(key_center: Note, chord_root: Note, chord_type: ChordType) -> String {
+    let suffix: &str = match chord_type {
+        ChordType::Major => "",
+        ChordType::Minor => "-",
+        ChordType::Diminished => "dim",
+        ChordType::Augmented => "+",
+        ChordType::Major7 => "maj7",
+        ChordType::Minor7 => "-7",
+        ChordType::Dominant7 => "7",
+        ChordType::Minor7b5 => "-7b5",
+        ChordType::Diminished7 => "dim7",
+        ChordType::MinorMajor7 => "-maj7",
+        ChordType::AugmentedMajor7 => "+maj7",
+    };
+    format!("{}{}", nashville_degree(key_center, chord_root), suffix)
+}
+
+/// Writes the degree of a chord relative to `key_center` in a style: a Roman
+/// numeral ("V7"), a Nashville number ("57") or the chord name spelled in the
+/// key ("G7"). A bass other than the root follows a slash, as a number
+/// ("V7/7", "57/7") or a note ("G7/F"). When `degree` is a Nashville number,
+/// its bass and its diamond or push are kept.
+pub fn chord_degree(
+    key_center: Note,
+    chord_root: Note,
+    chord_type: ChordType,
+    bass: Option<Note>,
+    degree: &str,
+    style: ChordStyle,
+) -> String {
+    let tonic: SpelledNote = spell_tonic(key_center, ScaleType::Major);
+    let (marks, written_bass): (Vec<NashvilleMark>, Option<Interval>) =
+        parse_nashville_chord(degree).map_or(
+            (Vec::<NashvilleMark>::new(), None),
+            |n: ParsedNashvilleChord| (n.marks, n.chord.bass),
+        );
+    let bass: Option<Note> = written_bass
+        .map(|i: Interval| transpose(key_center, i))
+        .or(bass)
+        .filter(|b: &Note| *b != chord_root);
+
+    // 1. The chord, then its bass
+    let mut text: String = match style {
+        ChordStyle::Letters => format!(
+            "{}{}",
+            spell_in_key(chord_root, tonic),
+            chord_suffix(chord_type)
+        ),
+        ChordStyle::Nashville => nashville_number(key_center, chord_root, chord_type),
+        ChordStyle::Roman => roman_numeral(key_center, chord_root, chord_type),
+    };
+    if let Some(bass) = bass {
+        let bass: String = match style {
+            ChordStyle::Letters => spell_in_key(bass, tonic).to_string(),
+            _ => nashville_degree(key_center, bass).to_string(),
+        };
+        text = format!("{}/{}", text, bass);
+    }
+
+    // 2. Rhythm marks of a Nashville chart
+    if marks.contains(&NashvilleMark::Diamond) {
+        text = format!("<{}>", text);
+    }
+    if marks.contains(&NashvilleMark::Push) {
+        text = format!("^{}", text);
+    }
+    text
+}
+
+/// Rewrites the degrees of a progression with `chord_degree`. Voice leading
+/// inverts chords freely, so only the slash basses written in a Nashville
+/// chart are kept.
+pub fn renumber_progression(
+    key_center: Note,
+    progression: &[ProgressionChord],
+    style: ChordStyle,
+) -> Vec<ProgressionChord> {
+    progression
+        .iter()
+        .map(|c: &ProgressionChord| ProgressionChord {
+            degree: chord_degree(
+                key_center,
+                c.root_note,
+                c.chord_type,
+                None,
+                &c.degree,
+                style,
+            ),
+            root_note: c.root_note,
+            chord_type: c.chord_type,
+            function: c.function,
+            notes: c.notes.clone(),
+        })
+        .collect()
+}
+
+/// Splits a progression into phrases and classifies the cadence that closes
+/// each one. Returns `(index of the arrival chord, cadence)` pairs in order.
+///
+/// A phrase ends on a cadence once a subdominant or predominant chord has been
+/// heard since the previous ending; the last two chords always close the final
+/// phrase. A half cadence that resolves straight to I (or vi) is not an ending:
+/// the phrase goes on to that resolution (I-IV-V-I is one phrase).
+///
+/// `sopranos` holds the top voice of each chord once the progression has been
+/// voiced (see `classify_cadence`).
+pub fn detect_cadences(
+    key_center: Note,
+    progression: &[ProgressionChord],
+    sopranos: Option<&[Note]>,
+) -> Vec<(usize, Cadence)> {
+    let classify = |i: usize| -> Option<Cadence> {
+        let soprano: Option<Note> = sopranos.and_then(|s: &[Note]| s.get(i).copied());
+        classify_cadence(key_center, &progression[i - 1], &progression[i], soprano)
+    };
+
+    let mut cadences: Vec<(usize, Cadence)> = Vec::<(usize, Cadence)>::new();
+    let mut predominant_heard: bool = false;
+    for i in 1..progression.len() {
+        let i: usize = i;
+        if matches!(
+            progression[i - 1].function,
+            HarmonicFunction::Subdominant | HarmonicFunction::Predominant
+        ) {
+            predominant_heard = true;
+        }
+        let is_last: bool = i == progression.len() - 1;
+        if !predominant_heard && !is_last {
+            continue;
+        }
+        let Some(cadence) = classify(i) else {
+            continue;
+        };
+        // 1. V that moves on to its resolution: wait for it
+        let resolves: bool = matches!(cadence, Cadence::Half | Cadence::Phrygian)
+            && !is_last
+            && matches!(
+                classify(i + 1),
+                Some(Cadence::PerfectAuthentic | Cadence::ImperfectAuthentic | Cadence::Deceptive)
+            );
+        if resolves {
+            continue;
+        }
+        // 2. The phrase ends here
+        cadences.push((i, cadence));
+        predominant_heard = false;
+    }
+    cadences
+}
+
+/// Classifies the cadence formed by two chords (`penultimate` -> `last`).
+/// Returns `None` if they form no cadence.
+///
+/// `soprano` is the top voice of `last`, once the progression has been
+/// voiced. The chords alone (close position, bass first) don't say which
+/// note is on top, so without it a root-position V-I counts as perfect.
+pub fn classify_cadence(
+    key_center: Note,
+    penultimate: &ProgressionChord,
+    last: &ProgressionChord,
+    soprano: Option<Note>,
+) -> Option<Cadence> {
+    let penultimate_degree: u8 = degree_semitones(key_center, penultimate.root_note);
+    let last_degree: u8 = degree_semitones(key_center, last.root_note);
+
+    let is_major_or_dominant = |chord_type: ChordType| -> bool {
+        matches!(chord_type, ChordType::Major | ChordType::Dominant7)
+    };
+
+    // 1. Phrases that stop on V
+    if last_degree == 7 && is_major_or_dominant(last.chord_type) {
+        // iv6 - V: the bass moves down by half step (b6 -> 5)
+        let penultimate_bass: Option<u8> = penultimate
+            .notes
+            .first()
+            .map(|n: &Note| degree_semitones(key_center, *n));
+        let is_minor_iv: bool = penultimate_degree == 5
+            && matches!(penultimate.chord_type, ChordType::Minor | ChordType::Minor7);
+        if is_minor_iv && penultimate_bass == Some(8) {
+            return Some(Cadence::Phrygian);
+        }
+        return Some(Cadence::Half);
+    }
+
+    let penultimate_is_v: bool =
+        penultimate_degree == 7 && is_major_or_dominant(penultimate.chord_type);
+    let penultimate_is_leading_tone: bool = penultimate_degree == 11
+        && matches!(
+            penultimate.chord_type,
+            ChordType::Diminished | ChordType::Minor7b5 | ChordType::Diminished7
+        );
+
+    // 2. Resolutions to the tonic
+    if last_degree == 0 {
+        if penultimate_is_v {
+            let in_root_position = |chord: &ProgressionChord| -> bool {
+                chord.notes.first() == Some(&chord.root_note)
+            };
+            let tonic_on_top: bool = soprano.is_none_or(|n: Note| n == key_center);
+
+            if in_root_position(penultimate) && in_root_position(last) && tonic_on_top {
+                return Some(Cadence::PerfectAuthentic);
+            }
+            return Some(Cadence::ImperfectAuthentic);
+        }
+        if penultimate_is_leading_tone {
+            return Some(Cadence::ImperfectAuthentic);
+        }
+        if penultimate_degree == 5 {
+            return Some(Cadence::Plagal);
+        }
+        return None;
+    }
+
+    // 3. V resolving somewhere other than the tonic
+    if penultimate_is_v && (last_degree == 9 || last_degree == 8) {
+        return Some(Cadence::Deceptive);
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{ParsedRomanChord, parse_progression_formula, parse_roman_chord};
+    use crate::voice_leading::VoiceLeadingOptions;
+    use crate::{HarmonicFormula, build_chord, build_custom_progression, build_progression};
+
+    fn final_cadence(
+        key_center: Note,
+        progression: &[ProgressionChord],
+        soprano: Option<Note>,
+    ) -> Option<Cadence> {
+        let n: usize = progression.len();
+        classify_cadence(
+            key_center,
+            &progression[n - 2],
+            &progression[n - 1],
+            soprano,
+        )
+    }
+
+    fn custom(root: Note, formula: &str) -> Vec<ProgressionChord> {
+        let specs: Vec<ParsedRomanChord> = formula
+            .split('-')
+            .map(|s: &str| parse_roman_chord(s).unwrap())
+            .collect();
+        build_custom_progression(root, specs, &VoiceLeadingOptions::default())
+    }
+
+    fn chord(
+        degree: &str,
+        root: Note,
+        chord_type: ChordType,
+        notes: Vec<Note>,
+    ) -> ProgressionChord {
+        ProgressionChord {
+            degree: degree.to_string(),
+            root_note: root,
+            chord_type,
+            function: harmonic_function(Note::C, root, chord_type),
+            notes,
+        }
+    }
+
+    #[test]
+    fn test_analysis_functions_c_major() {
+        assert_eq!(
+            harmonic_function(Note::C, Note::C, ChordType::Major),
+            HarmonicFunction::Tonic
+        );
+        assert_eq!(
+            harmonic_function(Note::C, Note::A, ChordType::Minor),
+            HarmonicFunction::TonicProlongation
+        );
+        assert_eq!(
+            harmonic_function(Note::C, Note::F, ChordType::Major),
+            HarmonicFunction::Subdominant
+        );
+        assert_eq!(
+            harmonic_function(Note::C, Note::D, ChordType::Minor),
+            HarmonicFunction::Predominant
+        );
+        assert_eq!(
+            harmonic_function(Note::C, Note::B, ChordType::Diminished),
+            HarmonicFunction::Dominant
+        );
+    }
+
+    #[test]
+    fn test_analysis_secondary_dominants() {
+        // I7 and VI7 are tension chords, not tonic / tonic prolongation
+        assert_eq!(
+            harmonic_function(Note::C, Note::C, ChordType::Dominant7),
+            HarmonicFunction::Dominant
+        );
+        assert_eq!(
+            harmonic_function(Note::C, Note::A, ChordType::Dominant7),
+            HarmonicFunction::Dominant
+        );
+    }
+
+    #[test]
+    fn test_analysis_progression_carries_functions() {
+        let progression: Vec<ProgressionChord> = build_progression(
+            Note::C,
+            HarmonicFormula::Circle,
+            &VoiceLeadingOptions::default(),
+        );
+        let functions: Vec<HarmonicFunction> = progression
+            .iter()
+            .map(|c: &ProgressionChord| c.function)
+            .collect();
+        assert_eq!(
+            functions,
+            vec![
+                HarmonicFunction::Tonic,
+                HarmonicFunction::TonicProlongation,
+                HarmonicFunction::Predominant,
+                HarmonicFunction::Dominant,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_analysis_roman_numeral_round_trip() {
+        assert_eq!(roman_numeral(Note::C, Note::G, ChordType::Dominant7), "V7");
+        assert_eq!(
+            roman_numeral(Note::C, Note::CSharp, ChordType::Dominant7),
+            "bII7"
+        );
+        assert_eq!(roman_numeral(Note::C, Note::D, ChordType::Minor7), "ii7");
+        assert_eq!(
+            roman_numeral(Note::C, Note::CSharp, ChordType::Diminished7),
+            "biidim7"
+        );
+
+        let parsed: ParsedRomanChord =
+            parse_roman_chord(&roman_numeral(Note::C, Note::B, ChordType::Minor7b5)).unwrap();
+        assert_eq!(parsed.interval_from_root, crate::Interval::MajorSeventh);
+        assert_eq!(parsed.chord_type, ChordType::Minor7b5);
+    }
+
+    #[test]
+    fn test_analysis_nashville_numbers() {
+        assert_eq!(nashville_number(Note::G, Note::G, ChordType::Major), "1");
+        assert_eq!(nashville_number(Note::G, Note::E, ChordType::Minor), "6-");
+        assert_eq!(
+            nashville_number(Note::G, Note::D, ChordType::Dominant7),
+            "57"
+        );
+        assert_eq!(
+            nashville_number(Note::G, Note::F, ChordType::Major7),
+            "b7maj7"
+        );
+        assert_eq!(nashville_degree(Note::C, Note::FSharp), "#4");
+    }
+
+    #[test]
+    fn test_analysis_chord_degree_styles() {
+        let progression: Vec<ProgressionChord> = build_custom_progression(
+            Note::DSharp,
+            parse_progression_formula("1 <4/5> ^57 1/3").unwrap(),
+            &VoiceLeadingOptions::default(),
+        );
+        // The slash bass of "1/3" is put lowest
+        assert_eq!(progression[3].notes[0], Note::G);
+
+        let degrees = |style: ChordStyle| -> Vec<String> {
+            renumber_progression(Note::DSharp, &progression, style)
+                .into_iter()
+                .map(|c: ProgressionChord| c.degree)
+                .collect()
+        };
+        assert_eq!(degrees(ChordStyle::Roman), ["I", "<IV/5>", "^V7", "I/3"]);
+        assert_eq!(
+            degrees(ChordStyle::Letters),
+            ["Eb", "<Ab/Bb>", "^Bb7", "Eb/G"]
+        );
+
+        // Roman numerals become Nashville numbers
+        let progression: Vec<ProgressionChord> = build_progression(
+            Note::G,
+            HarmonicFormula::Circle,
+            &VoiceLeadingOptions::default(),
+        );
+        assert_eq!(
+            renumber_progression(Note::G, &progression, ChordStyle::Nashville)
+                .iter()
+                .map(|c: &ProgressionChord| c.degree.as_str())
+                .collect::<Vec<&str>>(),
+            ["1", "6-", "2-", "57"]
+        );
+    }
+
+    #[test]
+    fn test_analysis_cadence_half() {
+        let progression: Vec<ProgressionChord> = build_progression(
+            Note::C,
+            HarmonicFormula::Circle,
+            &VoiceLeadingOptions::default(),
+        );
+        assert_eq!(
+            final_cadence(Note::C, &progression, None),
+            Some(Cadence::Half)
+        );
+    }
+
+    #[test]
+    fn test_analysis_cadence_authentic() {
+        let perfect: Vec<ProgressionChord> = vec![
+            chord(
+                "V",
+                Note::G,
+                ChordType::Major,
+                build_chord(Note::G, ChordType::Major),
+            ),
+            chord(
+                "I",
+                Note::C,
+                ChordType::Major,
+                vec![Note::C, Note::E, Note::G, Note::C],
+            ),
+        ];
+        assert_eq!(
+            final_cadence(Note::C, &perfect, None),
+            Some(Cadence::PerfectAuthentic)
+        );
+
+        // Builder output is unvoiced: root-position V-I is perfect...
+        let root_position: VoiceLeadingOptions = VoiceLeadingOptions {
+            inversion: 100,
+            ..VoiceLeadingOptions::default()
+        };
+        let built: Vec<ProgressionChord> = build_custom_progression(
+            Note::C,
+            parse_progression_formula("V-I").unwrap(),
+            &root_position,
+        );
+        assert_eq!(built[0].notes, vec![Note::G, Note::B, Note::D]);
+        assert_eq!(built[1].notes, vec![Note::C, Note::E, Note::G]);
+        assert_eq!(
+            final_cadence(Note::C, &built, None),
+            Some(Cadence::PerfectAuthentic)
+        );
+        // ...unless the voicing puts the third or fifth on top
+        assert_eq!(
+            final_cadence(Note::C, &built, Some(Note::E)),
+            Some(Cadence::ImperfectAuthentic)
+        );
+        assert_eq!(
+            final_cadence(Note::C, &built, Some(Note::C)),
+            Some(Cadence::PerfectAuthentic)
+        );
+
+        // Voice leading puts the G7 in an inversion -> imperfect
+        let imperfect: Vec<ProgressionChord> = custom(Note::C, "ii-V7-I");
+        assert_eq!(
+            final_cadence(Note::C, &imperfect, None),
+            Some(Cadence::ImperfectAuthentic)
+        );
+    }
+
+    #[test]
+    fn test_analysis_cadence_plagal_and_deceptive() {
+        assert_eq!(
+            final_cadence(Note::C, &custom(Note::C, "I-IV-I"), None),
+            Some(Cadence::Plagal)
+        );
+        assert_eq!(
+            final_cadence(Note::C, &custom(Note::C, "I-V7-vi"), None),
+            Some(Cadence::Deceptive)
+        );
+        assert_eq!(final_cadence(Note::C, &custom(Note::C, "I-IV"), None), None);
+    }
+
+    #[test]
+    fn test_analysis_phrase_cadences() {
+        // Two phrases: a half cadence, then an authentic one
+        let period: Vec<ProgressionChord> = custom(Note::C, "I-IV-V-I-vi-ii-V-vi-IV-V-I");
+        let cadences: Vec<(usize, Cadence)> = detect_cadences(Note::C, &period, None);
+        assert_eq!(
+            cadences
+                .iter()
+                .map(|(i, _): &(usize, Cadence)| *i)
+                .collect::<Vec<usize>>(),
+            [3, 7, 10]
+        );
+        assert_eq!(cadences[1].1, Cadence::Deceptive);
+        assert!(matches!(
+            cadences[2].1,
+            Cadence::PerfectAuthentic | Cadence::ImperfectAuthentic
+        ));
+
+        // A V that does not resolve closes its phrase with a half cadence
+        let half: Vec<ProgressionChord> = custom(Note::C, "I-ii-V-I-IV-I");
+        assert_eq!(
+            detect_cadences(Note::C, &half, None),
+            vec![
+                (3, final_cadence(Note::C, &half[..4], None).unwrap()),
+                (5, Cadence::Plagal)
+            ]
+        );
+        let open: Vec<ProgressionChord> = custom(Note::C, "I-IV-V-vi-ii-V");
+        assert_eq!(
+            detect_cadences(Note::C, &open, None),
+            vec![(3, Cadence::Deceptive), (5, Cadence::Half)]
+        );
+
+        // No predominant: only the end of the progression counts
+        let pop: Vec<ProgressionChord> = custom(Note::C, "I-V-vi-V-I");
+        assert_eq!(detect_cadences(Note::C, &pop, None).len(), 1);
+        assert!(detect_cadences(Note::C, &custom(Note::C, "I"), None).is_empty());
+
+        // The sopranos of a voicing decide perfect or imperfect
+        let root_position: VoiceLeadingOptions = VoiceLeadingOptions {
+            inversion: 100,
+            ..VoiceLeadingOptions::default()
+        };
+        let built: Vec<ProgressionChord> = build_custom_progression(
+            Note::C,
+            parse_progression_formula("IV-V-I").unwrap(),
+            &root_position,
+        );
+        assert_eq!(
+            detect_cadences(Note::C, &built, Some(&[Note::C, Note::D, Note::E])),
+            vec![(2, Cadence::ImperfectAuthentic)]
+        );
+        assert_eq!(
+            detect_cadences(Note::C, &built, Some(&[Note::C, Note::D, Note::C])),
+            vec![(2, Cadence::PerfectAuthentic)]
+        );
+    }
+
+    #[test]
+    fn test_analysis_cadence_phrygian() {
+        // A minor: iv6 (D minor over F) -> V (E major)
+        let progression: Vec<ProgressionChord> = vec![
+            chord(
+                "iv6",
+                Note::D,
+                ChordType::Minor,
+                vec![Note::F, Note::A, Note::D],
+            ),
+            chord(
+                "V",
+                Note::E,
+                ChordType::Major,
+                build_chord(Note::E, ChordType::Major),
+            ),
+        ];
+        assert_eq!(
+            final_cadence(Note::A, &progression, None),
+            Some(Cadence::Phrygian)
+        );
+    }
+}
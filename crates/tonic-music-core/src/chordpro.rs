@@ -0,0 +1,684 @@
+/*
+ * tonic-music-core
+ * Copyright (C) 2025 Israel Alberto Roldan Vega
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * Repository: https://github.com/airvzxf/music-theory-calculator/
+ */
+
+/*
+ * crates/tonic-music-core/src/chordpro.rs
+ *
+ * This module reads songs in ChordPro format (`{title: ...}` directives and
+ * `[C]lyrics [G7]with chords`), transposes them keeping the spelling of
+ * each chord relative to the key, and writes them back as ChordPro or as a
+ * plain-text lyric sheet, with chord names, Nashville numbers or Roman numerals.
+ */
+
+use crate::analysis::{nashville_degree, nashville_number, roman_numeral};
+use crate::notation::{SpelledNote, spell_in_key, spell_tonic};
+use crate::parser::{parse_chord_quality, parse_spelled_note};
+use crate::recognition::estimate_key;
+use crate::{ChordType, Note, ScaleType, build_chord};
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// How the chords of a song are written.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, ValueEnum, Serialize)]
+#[clap(rename_all = "kebab-case")]
+pub enum ChordStyle {
+    /// Chord names (C, G7/B, F#m)
+    #[default]
+    Letters,
+    /// Nashville numbers relative to the key (1, 57/7, 6-)
+    #[value(alias("nns"))]
+    Nashville,
+    /// Roman numerals relative to the key (I, V7/7, vi)
+    Roman,
+}
+
+/// Letters allowed after the root of a chord (maj, min, dim, aug, sus, add, alt, omit, no).
+const SUFFIX_LETTERS: &str = "majindugslto";
+
+/// Splits the note name at the start of a chord symbol ("Bb" and "m7" from "Bbm7").
+fn split_root(text: &str) -> Option<(SpelledNote, &str)> {
+    if !text.starts_with(|c: char| ('A'..='G').contains(&c)) {
+        return None;
+    }
+    let accidentals: usize = text[1..]
+        .chars()
+        .take_while(|c: &char| *c == '#' || *c == 'b')
+        .count()
+        .min(2);
+    let (name, rest): (&str, &str) = text.split_at(1 + accidentals);
+    parse_spelled_note(name)
+        .ok()
+        .map(|n: SpelledNote| (n, rest))
+}
+
+/// A chord written in a song. The root and bass are spelled notes; the rest of
+/// the symbol is kept as written, so chords outside the eleven chord types
+/// ("sus4", "add9", "6/9") still transpose.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SongChord {
+    pub root: SpelledNote,
+    /// Everything between the root and the bass ("m7", "sus4")
+    pub suffix: String,
+    pub bass: Option<SpelledNote>,
+}
+
+impl SongChord {
+    /// Parses a chord symbol such as "C", "Bbmaj7", "F#m7b5", "Gsus4" or "D/F#".
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid chord: {}", text);
+        let text: &str = text.trim();
+        // 1. A slash followed by a note is a bass ("D/F#"); "C6/9" keeps its slash
+        let (symbol, bass): (&str, Option<SpelledNote>) = match text.rsplit_once('/') {
+            Some((symbol, bass)) => match split_root(bass) {
+                Some((note, "")) => (symbol, Some(note)),
+                _ => (text, None),
+            },
+            None => (text, None),
+        };
+        // 2. The root, then a suffix made of chord words, digits and signs
+        let (root, suffix): (SpelledNote, &str) = split_root(symbol).ok_or_else(invalid)?;
+        let valid: bool = suffix.chars().all(|c: char| {
+            c.is_ascii_digit() || SUFFIX_LETTERS.contains(c) || "M#b+-()/,°øΔ^".contains(c)
+        });
+        if !valid {
+            return Err(invalid());
+        }
+        Ok(SongChord {
+            root,
+            suffix: suffix.to_string(),
+            bass,
+        })
+    }
+
+    /// The chord type, if the suffix names one of the eleven chord types.
+    pub fn chord_type(&self) -> Option<ChordType> {
+        parse_chord_quality(&self.suffix)
+    }
+
+    /// Splits the suffix into its quality and the rest, for chords outside
+    /// the chord types ("m9" is minor with a 9, "sus4" is major with a sus4).
+    fn quality(&self) -> (ChordType, &str) {
+        let suffix: &str = self.suffix.as_str();
+        if suffix.starts_with("maj") {
+            return (ChordType::Major, suffix);
+        }
+        ["min", "m", "-"]
+            .iter()
+            .find_map(|prefix: &&str| suffix.strip_prefix(*prefix))
+            .map_or((ChordType::Major, suffix), |rest: &str| {
+                (ChordType::Minor, rest)
+            })
+    }
+
+    /// The triad or seventh chord the symbol stands for, used to estimate keys.
+    fn chord_notes(&self) -> Vec<Note> {
+        let chord_type: ChordType = self.chord_type().unwrap_or(self.quality().0);
+        build_chord(self.root.note(), chord_type)
+    }
+
+    /// Writes the chord in a style: as a name, or as a Nashville number or
+    /// Roman numeral relative to `key`, with the bass as a number ("1/3", "I/3").
+    pub fn written(&self, key: Note, style: ChordStyle) -> String {
+        let root: Note = self.root.note();
+        let (quality, rest): (ChordType, &str) = self.quality();
+        let text: String = match (style, self.chord_type()) {
+            (ChordStyle::Letters, _) => return self.to_string(),
+            (ChordStyle::Nashville, Some(chord_type)) => nashville_number(key, root, chord_type),
+            (ChordStyle::Roman, Some(chord_type)) => roman_numeral(key, root, chord_type),
+            (ChordStyle::Nashville, None) => {
+                format!("{}{}", nashville_number(key, root, quality), rest)
+            }
+            (ChordStyle::Roman, None) => format!("{}{}", roman_numeral(key, root, quality), rest),
+        };
+        match self.bass {
+            Some(bass) => format!("{}/{}", text, nashville_degree(key, bass.note())),
+            None => text,
+        }
+    }
+
+    /// Moves the chord by `steps` letters and `semitones`, in the new `key`.
+    fn transposed(&self, steps: usize, semitones: i16, key: SpelledNote) -> Self {
+        SongChord {
+            root: move_note(self.root, steps, semitones, key),
+            suffix: self.suffix.clone(),
+            bass: self
+                .bass
+                .map(|b: SpelledNote| move_note(b, steps, semitones, key)),
+        }
+    }
+}
+
+impl std::fmt::Display for SongChord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.root, self.suffix)?;
+        if let Some(bass) = self.bass {
+            write!(f, "/{}", bass)?;
+        }
+        Ok(())
+    }
+}
+
+/// Moves a note up by semitones.
+fn shift(note: Note, semitones: i16) -> Note {
+    Note::from_u8((note.as_u8() as i16 + semitones).rem_euclid(12) as u8)
+}
+
+/// Moves a spelled note by `steps` letters and `semitones`, so it keeps its
+/// place in the key (the bVII of G, F, becomes Bb in C). Notes that would
+/// need a double sharp or flat are spelled in the new key instead.
+fn move_note(note: SpelledNote, steps: usize, semitones: i16, key: SpelledNote) -> SpelledNote {
+    let target: Note = shift(note.note(), semitones);
+    let moved: SpelledNote = note.above(steps, target);
+    if moved.alter.abs() > 1 && note.alter.abs() <= 1 {
+        spell_in_key(target, key)
+    } else {
+        moved
+    }
+}
+
+/// The text in brackets of a lyric line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChordMark {
+    Chord(SongChord),
+    /// Annotations (`[*Riff]`), no-chord marks (`[N.C.]`) and anything else
+    /// that is not a chord, written back unchanged
+    Text(String),
+}
+
+/// A chord and the lyrics sung from it until the next chord.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub chord: Option<ChordMark>,
+    pub text: String,
+}
+
+/// One line of a ChordPro song.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SongLine {
+    /// `{name: value}` or `{name}`
+    Directive {
+        name: String,
+        value: Option<String>,
+    },
+    Lyrics(Vec<Segment>),
+    /// A `#` comment, kept as written
+    Comment(String),
+    Empty,
+}
+
+/// A ChordPro song.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Song {
+    pub lines: Vec<SongLine>,
+    /// Bracketed text that could not be read as a chord
+    pub warnings: Vec<String>,
+}
+
+/// The full name of a directive (`t` is `title`, `soc` is `start_of_chorus`).
+pub fn directive_name(name: &str) -> String {
+    let name: String = name.trim().to_lowercase();
+    let full: &str = match name.as_str() {
+        "t" => "title",
+        "st" => "subtitle",
+        "c" => "comment",
+        "ci" => "comment_italic",
+        "cb" => "comment_box",
+        "soc" => "start_of_chorus",
+        "eoc" => "end_of_chorus",
+        "sov" => "start_of_verse",
+        "eov" => "end_of_verse",
+        "sob" => "start_of_bridge",
+        "eob" => "end_of_bridge",
+        "sot" => "start_of_tab",
+        "eot" => "end_of_tab",
+        "sog" => "start_of_grid",
+        "eog" => "end_of_grid",
+        _ => return name,
+    };
+    full.to_string()
+}
+
+/// Parses the value of a `{key:}` directive ("G", "Bb", "F#m", "E minor").
+pub fn parse_key_directive(value: &str) -> Option<(SpelledNote, ScaleType)> {
+    let compact: String = value.split_whitespace().collect();
+    let (tonic, mode): (SpelledNote, &str) = split_root(&compact)?;
+    match mode.to_lowercase().as_str() {
+        "" | "maj" | "major" => Some((tonic, ScaleType::Major)),
+        "m" | "min" | "minor" => Some((tonic, ScaleType::MinorNatural)),
+        _ => None,
+    }
+}
+
+/// Reads the text in brackets: a chord, or text kept as written.
+fn chord_mark(text: &str, number: usize, warnings: &mut Vec<String>) -> ChordMark {
+    if text.starts_with('*') || matches!(text.trim(), "" | "N.C." | "N.C" | "NC" | "x" | "|") {
+        return ChordMark::Text(text.to_string());
+    }
+    match SongChord::parse(text) {
+        Ok(chord) => ChordMark::Chord(chord),
+        Err(_) => {
+            warnings.push(format!(
+                "Line {}: '{}' is not a chord and was left as written",
+                number, text
+            ));
+            ChordMark::Text(text.to_string())
+        }
+    }
+}
+
+/// Splits a lyric line at its chords.
+fn parse_lyrics(
+    line: &str,
+    number: usize,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<Segment>, String> {
+    let mut segments: Vec<Segment> = Vec::<Segment>::new();
+    let mut rest: &str = line;
+    loop {
+        let (before, after): (&str, Option<&str>) = match rest.split_once('[') {
+            Some((before, after)) => (before, Some(after)),
+            None => (rest, None),
+        };
+        // 1. The lyrics go with the last chord (or open the line)
+        if !before.is_empty() {
+            match segments.last_mut() {
+                Some(segment) => segment.text.push_str(before),
+                None => segments.push(Segment {
+                    chord: None,
+                    text: before.to_string(),
+                }),
+            }
+        }
+        // 2. The next chord starts a segment
+        let Some(after) = after else {
+            return Ok(segments);
+        };
+        let (chord, next): (&str, &str) = after
+            .split_once(']')
+            .ok_or_else(|| format!("Line {}: missing ']'", number))?;
+        segments.push(Segment {
+            chord: Some(chord_mark(chord, number, warnings)),
+            text: String::new(),
+        });
+        rest = next;
+    }
+}
+
+/// Parses a ChordPro song. Text in brackets that is not a chord is kept as
+/// written, with a warning.
+pub fn parse_chordpro(text: &str) -> Result<Song, String> {
+    let mut song: Song = Song {
+        lines: Vec::<SongLine>::new(),
+        warnings: Vec::<String>::new(),
+    };
+    // Tabs and grids are copied as written
+    let mut verbatim: bool = false;
+    for (index, line) in text.lines().enumerate() {
+        let number: usize = index + 1;
+        let trimmed: &str = line.trim();
+        let parsed: SongLine = if trimmed.starts_with('{') {
+            let inner: &str = trimmed
+                .strip_prefix('{')
+                .and_then(|t: &str| t.strip_suffix('}'))
+                .ok_or_else(|| format!("Line {}: missing '}}'", number))?;
+            let (name, value): (&str, Option<String>) = match inner.split_once(':') {
+                Some((name, value)) => (name.trim(), Some(value.trim().to_string())),
+                None => (inner.trim(), None),
+            };
+            match directive_name(name).as_str() {
+                "start_of_tab" | "start_of_grid" => verbatim = true,
+                "end_of_tab" | "end_of_grid" => verbatim = false,
+                _ => {}
+            }
+            SongLine::Directive {
+                name: name.to_string(),
+                value,
+            }
+        } else if trimmed.starts_with('#') {
+            SongLine::Comment(line.to_string())
+        } else if trimmed.is_empty() {
+            SongLine::Empty
+        } else if verbatim {
+            SongLine::Lyrics(vec![Segment {
+                chord: None,
+                text: line.to_string(),
+            }])
+        } else {
+            SongLine::Lyrics(parse_lyrics(line, number, &mut song.warnings)?)
+        };
+        song.lines.push(parsed);
+    }
+    Ok(song)
+}
+
+impl Song {
+    /// The value of the first directive called `name` (or one of its short names).
+    pub fn directive(&self, name: &str) -> Option<&str> {
+        self.lines.iter().find_map(|line: &SongLine| match line {
+            SongLine::Directive {
+                name: found,
+                value: Some(value),
+            } if directive_name(found) == name => Some(value.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.directive("title")
+    }
+
+    /// Every chord of the song, in order.
+    pub fn chords(&self) -> Vec<&SongChord> {
+        self.lines
+            .iter()
+            .filter_map(|line: &SongLine| match line {
+                SongLine::Lyrics(segments) => Some(segments),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|segment: &Segment| match &segment.chord {
+                Some(ChordMark::Chord(chord)) => Some(chord),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The key of the song: its first `{key:}` directive, or the key that
+    /// best fits its chords, spelled as the song spells that chord.
+    pub fn key(&self) -> (SpelledNote, ScaleType) {
+        if let Some(key) = self.directive("key").and_then(parse_key_directive) {
+            return key;
+        }
+        let chords: Vec<&SongChord> = self.chords();
+        let mut profile: [f64; 12] = [0.0; 12];
+        for chord in &chords {
+            for note in chord.chord_notes() {
+                profile[note.as_u8() as usize] += 1.0;
+            }
+        }
+        let (tonic, mode): (Note, ScaleType) = estimate_key(&profile);
+        let spelled: SpelledNote = chords
+            .iter()
+            .find(|c: &&&SongChord| c.root.note() == tonic)
+            .map_or(spell_tonic(tonic, mode), |c: &&SongChord| c.root);
+        (spelled, mode)
+    }
+
+    /// Transposes the song so its key becomes `tonic`. Each chord keeps its
+    /// degree (the bVII of G, F, is Bb in C, not A#), and later `{key:}`
+    /// directives move with the song.
+    pub fn transpose(&self, tonic: SpelledNote) -> Song {
+        let from: SpelledNote = self.key().0;
+        let steps: usize = from.steps_to(tonic);
+        let semitones: i16 = tonic.note().as_u8() as i16 - from.note().as_u8() as i16;
+        let mut key: SpelledNote = tonic;
+        let lines: Vec<SongLine> = self
+            .lines
+            .iter()
+            .map(|line: &SongLine| match line {
+                SongLine::Directive {
+                    name,
+                    value: Some(value),
+                } if directive_name(name) == "key" => {
+                    let Some((old, _)) = parse_key_directive(value) else {
+                        return line.clone();
+                    };
+                    key = move_note(old, steps, semitones, key);
+                    let mode: &str = &value.trim()[old.to_string().len()..];
+                    SongLine::Directive {
+                        name: name.clone(),
+                        value: Some(format!("{}{}", key, mode)),
+                    }
+                }
+                SongLine::Lyrics(segments) => SongLine::Lyrics(
+                    segments
+                        .iter()
+                        .map(|segment: &Segment| Segment {
+                            chord: segment.chord.as_ref().map(|mark: &ChordMark| match mark {
+                                ChordMark::Chord(chord) => {
+                                    ChordMark::Chord(chord.transposed(steps, semitones, key))
+                                }
+                                ChordMark::Text(text) => ChordMark::Text(text.clone()),
+                            }),
+                            text: segment.text.clone(),
+                        })
+                        .collect(),
+                ),
+                _ => line.clone(),
+            })
+            .collect();
+        Song {
+            lines,
+            warnings: self.warnings.clone(),
+        }
+    }
+
+    /// Transposes the song by semitones, to the spelling of the new key with
+    /// the fewest sharps or flats.
+    pub fn transpose_semitones(&self, semitones: i16) -> Song {
+        let (from, mode): (SpelledNote, ScaleType) = self.key();
+        self.transpose(spell_tonic(shift(from.note(), semitones), mode))
+    }
+}
+
+/// The key a `{key:}` directive changes to, if it is one.
+fn key_change(line: &SongLine) -> Option<Note> {
+    match line {
+        SongLine::Directive {
+            name,
+            value: Some(value),
+        } if directive_name(name) == "key" => {
+            parse_key_directive(value).map(|(tonic, _)| tonic.note())
+        }
+        _ => None,
+    }
+}
+
+fn mark_text(mark: &ChordMark, key: Note, style: ChordStyle) -> String {
+    match mark {
+        ChordMark::Chord(chord) => chord.written(key, style),
+        ChordMark::Text(text) => text.clone(),
+    }
+}
+
+/// Writes the song as ChordPro, with the chords in the given style.
+/// Numbers follow the `{key:}` directives.
+pub fn write_chordpro(song: &Song, style: ChordStyle) -> String {
+    let mut key: Note = song.key().0.note();
+    let mut out: String = String::new();
+    for line in &song.lines {
+        key = key_change(line).unwrap_or(key);
+        match line {
+            SongLine::Directive { name, value } => match value {
+                Some(value) => out.push_str(&format!("{{{}: {}}}", name, value)),
+                None => out.push_str(&format!("{{{}}}", name)),
+            },
+            SongLine::Lyrics(segments) => {
+                for segment in segments {
+                    if let Some(mark) = &segment.chord {
+                        out.push_str(&format!("[{}]", mark_text(mark, key, style)));
+                    }
+                    out.push_str(&segment.text);
+                }
+            }
+            SongLine::Comment(text) => out.push_str(text),
+            SongLine::Empty => {}
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Writes a lyric line as a line of chords over a line of lyrics. The lyrics
+/// are stretched where the chords need more room (with a hyphen inside a word).
+fn push_chord_lines(out: &mut String, segments: &[Segment], key: Note, style: ChordStyle) {
+    let mut chords: String = String::new();
+    let mut lyrics: String = String::new();
+    let (mut chord_width, mut lyric_width): (usize, usize) = (0, 0);
+    for segment in segments {
+        if let Some(mark) = &segment.chord {
+            let column: usize = if chord_width == 0 {
+                lyric_width
+            } else {
+                lyric_width.max(chord_width + 1)
+            };
+            let in_word: bool = lyrics.ends_with(|c: char| c.is_alphanumeric())
+                && segment.text.starts_with(|c: char| c.is_alphanumeric());
+            let fill: &str = if in_word { "-" } else { " " };
+            lyrics.push_str(&fill.repeat(column - lyric_width));
+            chords.push_str(&" ".repeat(column - chord_width));
+            let name: String = mark_text(mark, key, style);
+            chords.push_str(&name);
+            chord_width = column + name.chars().count();
+            lyric_width = column;
+        }
+        lyrics.push_str(&segment.text);
+        lyric_width += segment.text.chars().count();
+    }
+    for line in [chords, lyrics] {
+        if !line.trim().is_empty() {
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+    }
+}
+
+/// Writes the song as a plain-text lyric sheet: chords over the lyrics,
+/// the title and comments as text, and section labels such as "Chorus:".
+pub fn write_lyric_sheet(song: &Song, style: ChordStyle) -> String {
+    let mut key: Note = song.key().0.note();
+    let mut out: String = String::new();
+    for line in &song.lines {
+        key = key_change(line).unwrap_or(key);
+        match line {
+            SongLine::Directive { name, value } => {
+                let value: &str = value.as_deref().unwrap_or("").trim();
+                let label = |default: &str| -> String {
+                    format!("{}:\n", if value.is_empty() { default } else { value })
+                };
+                let text: String = match directive_name(name).as_str() {
+                    "title" | "subtitle" | "artist" | "comment" | "comment_italic"
+                    | "comment_box" | "highlight"
+                        if !value.is_empty() =>
+                    {
+                        format!("{}\n", value)
+                    }
+                    "key" => format!("Key: {}\n", value),
+                    "capo" => format!("Capo: {}\n", value),
+                    "tempo" => format!("Tempo: {}\n", value),
+                    "start_of_chorus" => label("Chorus"),
+                    "start_of_verse" if !value.is_empty() => label("Verse"),
+                    "start_of_bridge" => label("Bridge"),
+                    "chorus" => "(Chorus)\n".to_string(),
+                    _ => String::new(),
+                };
+                out.push_str(&text);
+            }
+            SongLine::Lyrics(segments) => push_chord_lines(&mut out, segments, key, style),
+            SongLine::Comment(_) => {}
+            SongLine::Empty => out.push('\n'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SONG: &str = "{title: Test Song}\n{key: G}\n# A comment\n\n{soc}\n[G]Amazing [C/G]grace, how [G]sweet\nthe [Em7]sound that [D7/F#]saved a [F]wretch [*Riff]\n{eoc}\n";
+
+    #[test]
+    fn test_parse_chordpro() {
+        let song: Song = parse_chordpro(SONG).unwrap();
+        assert_eq!(song.title(), Some("Test Song"));
+        assert_eq!(song.key().0.to_string(), "G");
+        assert!(song.warnings.is_empty());
+        let chords: Vec<String> = song
+            .chords()
+            .iter()
+            .map(|c: &&SongChord| c.to_string())
+            .collect();
+        assert_eq!(chords, ["G", "C/G", "G", "Em7", "D7/F#", "F"]);
+        let SongLine::Lyrics(segments) = &song.lines[5] else {
+            panic!("Expected lyrics");
+        };
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[1].text, "grace, how ");
+    }
+
+    #[test]
+    fn test_song_chords() {
+        let chord: SongChord = SongChord::parse("Bbm7b5/Ab").unwrap();
+        assert_eq!(chord.root.to_string(), "Bb");
+        assert_eq!(chord.chord_type(), Some(ChordType::Minor7b5));
+        assert_eq!(chord.bass.unwrap().to_string(), "Ab");
+        // Extensions are kept as written
+        let chord: SongChord = SongChord::parse("C6/9").unwrap();
+        assert_eq!((chord.suffix.as_str(), chord.bass), ("6/9", None));
+        assert_eq!(chord.written(Note::F, ChordStyle::Nashville), "56/9");
+        assert!(SongChord::parse("Chorus").is_err());
+        assert!(SongChord::parse("Amazing").is_err());
+        assert!(SongChord::parse("Asus4").is_ok());
+    }
+
+    #[test]
+    fn test_transpose_song() {
+        let song: Song = parse_chordpro(SONG).unwrap();
+        // The bVII (F) stays a flat seventh: Bb in C, Ab in Bb
+        let bb: Song = song.transpose(parse_spelled_note("Bb").unwrap());
+        let chords: Vec<String> = bb
+            .chords()
+            .iter()
+            .map(|c: &&SongChord| c.to_string())
+            .collect();
+        assert_eq!(chords, ["Bb", "Eb/Bb", "Bb", "Gm7", "F7/A", "Ab"]);
+        assert_eq!(bb.directive("key"), Some("Bb"));
+
+        // Even an unusual key keeps every degree
+        let a_sharp: Song = song.transpose(parse_spelled_note("A#").unwrap());
+        assert_eq!(a_sharp.chords()[4].to_string(), "E#7/G##");
+        let down: Song = song.transpose_semitones(-1);
+        assert_eq!(down.directive("key"), Some("F#"));
+        assert_eq!(down.chords()[5].to_string(), "E");
+    }
+
+    #[test]
+    fn test_write_song() {
+        let song: Song = parse_chordpro(SONG).unwrap();
+        let chordpro: String = write_chordpro(&song, ChordStyle::Nashville);
+        assert!(chordpro.contains("[1]Amazing [4/1]grace, how [1]sweet\n"));
+        assert!(chordpro.contains("the [6-7]sound that [57/7]saved a [b7]wretch [*Riff]\n"));
+        assert_eq!(write_chordpro(&song, ChordStyle::Letters), SONG);
+
+        let sheet: String = write_lyric_sheet(&song, ChordStyle::Roman);
+        assert!(sheet.starts_with("Test Song\nKey: G\n\nChorus:\n"));
+        assert!(sheet.contains("I       IV/1       I\nAmazing grace, how sweet\n"));
+        assert!(sheet.contains("    vi7        V7/7    bVII   *Riff\n"));
+
+        // Chords wider than their syllable stretch the word
+        let song: Song = parse_chordpro("[C]Hal[Gsus4]le[Am]lujah").unwrap();
+        assert_eq!(
+            write_lyric_sheet(&song, ChordStyle::Letters),
+            "C  Gsus4 Am\nHalle----lujah\n"
+        );
+        assert!(parse_chordpro("[C]broken [G").is_err());
+    }
+}
@@ -0,0 +1,436 @@
+/*
+ * tonic-music-core
+ * Copyright (C) 2025 Israel Alberto Roldan Vega
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * Repository: https://github.com/airvzxf/music-theory-calculator/
+ */
+
+/*
+ * crates/tonic-music-core/src/chroma.rs
+ *
+ * This module recognizes the chords of a recording: it computes a
+ * chromagram (the energy of each pitch class over time) with a short-time
+ * Fourier transform, matches it against ChordType templates for every root
+ * and labels the smoothed chord sequence in the key.
+ */
+
+use crate::pitch::Pitch;
+use crate::recognition::{AnalyzedChord, ChordMatch, HarmonicAnalysis, estimate_key};
+use crate::{ChordType, Note, ScaleType};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::f64::consts::TAU;
+
+/// Semitones above a note of its first harmonics (octave, twelfth, double octave).
+const HARMONICS: [u8; 4] = [0, 0, 7, 0];
+/// Each harmonic of a chord tone is this much weaker than the one below.
+const HARMONIC_DECAY: f64 = 0.6;
+
+/// How the recording is cut into frames and smoothed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChromaOptions {
+    /// Samples per FFT frame (a power of two)
+    pub window: usize,
+    /// Samples between frame centers
+    pub hop: usize,
+    /// The lowest and highest frequencies counted, in Hz
+    pub min_frequency: f64,
+    pub max_frequency: f64,
+    /// Frames averaged around each frame before naming its chord
+    pub smoothing: usize,
+    /// Frames quieter than this RMS level have no chord
+    pub silence: f64,
+    /// The frequency of A4 in Hz
+    pub a4: f64,
+}
+
+impl Default for ChromaOptions {
+    fn default() -> Self {
+        ChromaOptions {
+            window: 8192,
+            hop: 2048,
+            min_frequency: 65.0,
+            max_frequency: 2100.0,
+            smoothing: 9,
+            silence: 0.01,
+            a4: 440.0,
+        }
+    }
+}
+
+/// The pitch-class energy (C = 0) around one instant, scaled so the strongest
+/// class is 1.0. Silent frames are all zeros.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ChromaFrame {
+    /// Center of the frame in seconds
+    pub time: f64,
+    pub chroma: [f64; 12],
+}
+
+/// In-place radix-2 FFT of a complex signal whose length is a power of two.
+fn fft(re: &mut [f64], im: &mut [f64]) {
+    let n: usize = re.len();
+    // 1. Bit-reversal permutation
+    let mut j: usize = 0;
+    for i in 1..n {
+        let mut bit: usize = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+    // 2. Butterflies
+    let mut size: usize = 2;
+    while size <= n {
+        let angle: f64 = -TAU / size as f64;
+        for start in (0..n).step_by(size) {
+            for k in 0..size / 2 {
+                let (sin, cos): (f64, f64) = (angle * k as f64).sin_cos();
+                let (a, b): (usize, usize) = (start + k, start + k + size / 2);
+                let t_re: f64 = re[b] * cos - im[b] * sin;
+                let t_im: f64 = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+            }
+        }
+        size <<= 1;
+    }
+}
+
+/// Computes the chromagram of a mono recording. Frames are centered every
+/// `hop` samples from the start, with a Hann window; only the spectral peaks
+/// between the frequency limits count, each added to its nearest pitch class.
+pub fn chromagram(
+    samples: &[f32],
+    sample_rate: u32,
+    options: &ChromaOptions,
+) -> Result<Vec<ChromaFrame>, String> {
+    // 1. Validate the options
+    if !options.window.is_power_of_two() || options.window < 256 {
+        return Err(format!(
+            "The window must be a power of two of at least 256 samples, not {}",
+            options.window
+        ));
+    }
+    if options.hop == 0 {
+        return Err("The hop must be at least one sample".to_string());
+    }
+    if options.min_frequency <= 0.0 || options.max_frequency <= options.min_frequency {
+        return Err("The frequency range must be positive and not empty".to_string());
+    }
+    if options.a4 <= 0.0 {
+        return Err("The A4 reference must be greater than 0".to_string());
+    }
+
+    // 2. The pitch class of each FFT bin in range
+    let rate: f64 = sample_rate as f64;
+    let classes: Vec<Option<usize>> = (0..=options.window / 2)
+        .map(|k: usize| {
+            let frequency: f64 = k as f64 * rate / options.window as f64;
+            if frequency < options.min_frequency || frequency > options.max_frequency {
+                None
+            } else {
+                Some(Pitch::from_frequency(frequency, options.a4).0.note.as_u8() as usize)
+            }
+        })
+        .collect();
+    let hann: Vec<f64> = (0..options.window)
+        .map(|n: usize| 0.5 - 0.5 * (TAU * n as f64 / options.window as f64).cos())
+        .collect();
+
+    // 3. One frame per hop, centered on its time
+    let half: usize = options.window / 2;
+    let mut frames: Vec<ChromaFrame> = Vec::<ChromaFrame>::new();
+    let mut center: usize = 0;
+    while center < samples.len() {
+        let sample = |n: usize| -> f64 {
+            (center + n)
+                .checked_sub(half)
+                .and_then(|i: usize| samples.get(i))
+                .map_or(0.0, |s: &f32| *s as f64)
+        };
+        let mut re: Vec<f64> = (0..options.window).map(sample).collect();
+        let rms: f64 = (re.iter().map(|s: &f64| s * s).sum::<f64>() / options.window as f64).sqrt();
+        let mut chroma: [f64; 12] = [0.0; 12];
+        if rms >= options.silence {
+            for (value, weight) in re.iter_mut().zip(&hann) {
+                *value *= weight;
+            }
+            let mut im: Vec<f64> = vec![0.0; options.window];
+            fft(&mut re, &mut im);
+            let magnitude: Vec<f64> = (0..=half).map(|k: usize| re[k].hypot(im[k])).collect();
+            for k in 1..half {
+                if let Some(class) = classes[k]
+                    && magnitude[k] >= magnitude[k - 1]
+                    && magnitude[k] > magnitude[k + 1]
+                {
+                    chroma[class] += magnitude[k];
+                }
+            }
+            let peak: f64 = chroma.iter().fold(0.0, |p: f64, c: &f64| p.max(*c));
+            if peak > 0.0 {
+                chroma.iter_mut().for_each(|c: &mut f64| *c /= peak);
+            }
+        }
+        frames.push(ChromaFrame {
+            time: center as f64 / rate,
+            chroma,
+        });
+        center += options.hop;
+    }
+    Ok(frames)
+}
+
+/// The expected chroma of a chord: each chord tone (from `ChordType::intervals`)
+/// with its first harmonics, which add energy to the fifth above.
+pub fn chord_template(root: Note, chord_type: ChordType) -> [f64; 12] {
+    let mut template: [f64; 12] = [0.0; 12];
+    for interval in chord_type.intervals() {
+        let tone: u8 = root.as_u8() + interval.as_u8();
+        let mut weight: f64 = 1.0;
+        for offset in HARMONICS {
+            template[((tone + offset) % 12) as usize] += weight;
+            weight *= HARMONIC_DECAY;
+        }
+    }
+    template
+}
+
+/// Finds the chord template (any root, any ChordType) closest to a chroma
+/// vector by cosine similarity. Returns None for a silent frame.
+pub fn match_chroma(chroma: &[f64; 12]) -> Option<ChordMatch> {
+    let norm: f64 = chroma.iter().map(|c: &f64| c * c).sum::<f64>().sqrt();
+    if norm == 0.0 {
+        return None;
+    }
+    let mut best: Option<ChordMatch> = None;
+    for root in 0..12 {
+        let root: Note = Note::from_u8(root);
+        for chord_type in ChordType::value_variants() {
+            let template: [f64; 12] = chord_template(root, *chord_type);
+            let dot: f64 = template
+                .iter()
+                .zip(chroma)
+                .map(|(t, c): (&f64, &f64)| t * c)
+                .sum();
+            let length: f64 = template.iter().map(|t: &f64| t * t).sum::<f64>().sqrt();
+            let score: f64 = dot / (length * norm);
+            if best.is_none_or(|b: ChordMatch| score > b.score + 1e-9) {
+                best = Some(ChordMatch {
+                    root,
+                    chord_type: *chord_type,
+                    score,
+                });
+            }
+        }
+    }
+    best
+}
+
+/// Names the chords of a chromagram. Each frame is matched on its own, then
+/// takes the chord most frequent among its neighbours (`smoothing` frames in
+/// all), which removes the blends heard at chord changes. Repeated chords are
+/// merged and labeled in the key (estimated from the whole chromagram unless
+/// given). Times are in seconds.
+pub fn analyze_chroma(
+    frames: &[ChromaFrame],
+    hop_seconds: f64,
+    smoothing: usize,
+    key: Option<(Note, ScaleType)>,
+) -> HarmonicAnalysis {
+    let (key, mode): (Note, ScaleType) = key.unwrap_or_else(|| {
+        let mut total: [f64; 12] = [0.0; 12];
+        for frame in frames {
+            for (i, weight) in frame.chroma.iter().enumerate() {
+                total[i] += weight;
+            }
+        }
+        estimate_key(&total)
+    });
+
+    // 1. Match every frame, then vote among the neighbours
+    let matches: Vec<Option<ChordMatch>> = frames
+        .iter()
+        .map(|f: &ChromaFrame| match_chroma(&f.chroma))
+        .collect();
+    let same = |a: &Option<ChordMatch>, b: &Option<ChordMatch>| -> bool {
+        a.map(|m: ChordMatch| (m.root, m.chord_type))
+            == b.map(|m: ChordMatch| (m.root, m.chord_type))
+    };
+    let reach: usize = smoothing.max(1) / 2;
+    let mut chords: Vec<AnalyzedChord> = Vec::<AnalyzedChord>::new();
+    for (i, frame) in frames.iter().enumerate() {
+        let neighbours: &[Option<ChordMatch>] =
+            &matches[i.saturating_sub(reach)..(i + reach + 1).min(matches.len())];
+        let votes = |m: &Option<ChordMatch>| -> usize {
+            neighbours
+                .iter()
+                .filter(|n: &&Option<ChordMatch>| same(n, m))
+                .count()
+        };
+        // Ties keep the frame's own chord
+        let mut winner: Option<ChordMatch> = matches[i];
+        for candidate in neighbours {
+            if votes(candidate) > votes(&winner) {
+                winner = *candidate;
+            }
+        }
+        let Some(found) = winner else {
+            continue;
+        };
+
+        // 2. Extend the last chord or start a new one
+        let start: f64 = (frame.time - hop_seconds / 2.0).max(0.0);
+        let end: f64 = frame.time + hop_seconds / 2.0;
+        if let Some(last) = chords.last_mut()
+            && last.root_note == found.root
+            && last.chord_type == found.chord_type
+            && (last.end - start).abs() < 1e-9
+        {
+            last.end = end;
+            last.score = last.score.max(found.score);
+            continue;
+        }
+        chords.push(AnalyzedChord::new(key, start, end, found, None));
+    }
+
+    HarmonicAnalysis { key, mode, chords }
+}
+
+/// Recognizes the chords of a mono recording: `chromagram` then `analyze_chroma`.
+pub fn analyze_audio(
+    samples: &[f32],
+    sample_rate: u32,
+    options: &ChromaOptions,
+    key: Option<(Note, ScaleType)>,
+) -> Result<HarmonicAnalysis, String> {
+    let frames: Vec<ChromaFrame> = chromagram(samples, sample_rate, options)?;
+    Ok(analyze_chroma(
+        &frames,
+        options.hop as f64 / sample_rate as f64,
+        options.smoothing,
+        key,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::midi::block_chords;
+    use crate::pitch::stack_notes;
+    use crate::synth::{SynthOptions, Waveform, render};
+    use crate::voice_leading::VoiceLeadingOptions;
+    use crate::{build_chord, build_custom_progression, parser::parse_progression_formula};
+
+    #[test]
+    fn test_chord_templates() {
+        // A C major triad: C, E and G, plus the fifths of their harmonics (G, B, D)
+        let template: [f64; 12] = chord_template(Note::C, ChordType::Major);
+        assert!(template[0] > template[7] - 0.5 && template[0] < template[7]);
+        assert!(template[11] > 0.0 && template[2] > 0.0);
+        assert_eq!(template[1], 0.0);
+
+        // Every template is recognized as itself (or, for the symmetric
+        // diminished seventh and augmented chords, as the same notes)
+        for root in 0..12 {
+            let root: Note = Note::from_u8(root);
+            for chord_type in ChordType::value_variants() {
+                let template: [f64; 12] = chord_template(root, *chord_type);
+                let found: ChordMatch = match_chroma(&template).unwrap();
+                assert_eq!(chord_template(found.root, found.chord_type), template);
+                assert!((found.score - 1.0).abs() < 1e-9);
+            }
+        }
+        assert!(match_chroma(&[0.0; 12]).is_none());
+    }
+
+    #[test]
+    fn test_analyze_synthesized_progression() {
+        // I-vi-IV-V7 in D, two seconds a chord, rendered on a sawtooth
+        let progression = build_custom_progression(
+            Note::D,
+            parse_progression_formula("I-vi-IV-V7").unwrap(),
+            &VoiceLeadingOptions::default(),
+        );
+        let chords: Vec<Vec<Pitch>> = progression
+            .iter()
+            .map(|c| stack_notes(&build_chord(c.root_note, c.chord_type), 3))
+            .collect();
+        let synth: SynthOptions = SynthOptions {
+            sample_rate: 8000,
+            waveform: Waveform::Sawtooth,
+            ..SynthOptions::default()
+        };
+        let samples: Vec<f32> = render(&block_chords(&chords, 4.0), &synth).unwrap();
+        let options: ChromaOptions = ChromaOptions {
+            window: 2048,
+            hop: 1000,
+            ..ChromaOptions::default()
+        };
+
+        let analysis: HarmonicAnalysis = analyze_audio(&samples, 8000, &options, None).unwrap();
+        assert_eq!((analysis.key, analysis.mode), (Note::D, ScaleType::Major));
+        let found: Vec<(Note, ChordType, &str)> = analysis
+            .chords
+            .iter()
+            .map(|c: &AnalyzedChord| (c.root_note, c.chord_type, c.degree.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (Note::D, ChordType::Major, "I"),
+                (Note::B, ChordType::Minor, "vi"),
+                (Note::G, ChordType::Major, "IV"),
+                (Note::A, ChordType::Dominant7, "V7"),
+            ]
+        );
+        // The changes land within a frame of the bar lines
+        for (chord, bar) in analysis.chords.iter().zip([0.0, 2.0, 4.0, 6.0]) {
+            assert!((chord.start - bar).abs() <= 0.25, "{:?}", chord);
+        }
+    }
+
+    #[test]
+    fn test_chromagram_silence_and_options() {
+        let frames: Vec<ChromaFrame> = chromagram(
+            &[0.0; 4000],
+            8000,
+            &ChromaOptions {
+                window: 1024,
+                hop: 1000,
+                ..ChromaOptions::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[1].time, 0.125);
+        assert!(frames.iter().all(|f: &ChromaFrame| f.chroma == [0.0; 12]));
+        assert!(analyze_chroma(&frames, 0.125, 3, None).chords.is_empty());
+
+        let odd: ChromaOptions = ChromaOptions {
+            window: 1000,
+            ..ChromaOptions::default()
+        };
+        assert!(chromagram(&[0.0; 4000], 8000, &odd).is_err());
+    }
+}
@@ -0,0 +1,549 @@
+/*
+ * tonic-music-core
+ * Copyright (C) 2025 Israel Alberto Roldan Vega
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * Repository: https://github.com/airvzxf/music-theory-calculator/
+ */
+
+/*
+ * crates/tonic-music-core/src/fingering.rs
+ *
+ * This module searches playable chord fingerings on a fretboard
+ * and ranks them by difficulty.
+ */
+
+use crate::fretboard::{FretPosition, Fretboard};
+use crate::pitch::Pitch;
+use crate::{ChordType, Note, build_chord};
+use serde::Serialize;
+
+/// The most strings the search handles: it tries every combination of
+/// frets, so each extra string multiplies the work.
+pub const MAX_STRINGS: usize = 8;
+
+/// Difficulty cost of each finger pressing a string.
+const FINGER_COST: u32 = 4;
+/// Difficulty cost of each fret between the lowest and highest fretted notes.
+const STRETCH_COST: u32 = 5;
+/// Difficulty cost of a barre.
+const BARRE_COST: u32 = 15;
+/// Difficulty cost of each string that must be muted.
+const MUTED_COST: u32 = 10;
+
+/// The constraints of the fingering search.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FingeringOptions {
+    /// The widest span, in frets, between the lowest and highest fretted notes
+    pub max_stretch: u8,
+    /// The fingers available (a barre counts as one)
+    pub max_fingers: u8,
+    /// The highest fret the search reaches
+    pub max_fret: u8,
+    /// The fewest strings that must sound (None = all but two, at least three)
+    pub min_strings: Option<usize>,
+    /// Allow open strings
+    pub open_strings: bool,
+    /// Allow muted strings between sounding strings
+    pub inner_mutes: bool,
+    /// The chord tones that must sound (None = all of them)
+    pub required: Option<Vec<Note>>,
+    /// The lowest sounding note (None = any chord tone)
+    pub bass: Option<Note>,
+}
+
+impl Default for FingeringOptions {
+    fn default() -> Self {
+        FingeringOptions {
+            max_stretch: 3,
+            max_fingers: 4,
+            max_fret: 12,
+            min_strings: None,
+            open_strings: true,
+            inner_mutes: false,
+            required: None,
+            bass: None,
+        }
+    }
+}
+
+/// A barre: one finger pressing several strings at the same fret.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub struct Barre {
+    pub fret: u8,
+    /// The first and last barred strings (indexes into the tuning)
+    pub from_string: usize,
+    pub to_string: usize,
+}
+
+/// A playable chord shape, ready to be drawn as a chord diagram.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Fingering {
+    /// The fret of each string, lowest string first (None = muted, the capo fret = open)
+    pub frets: Vec<Option<u8>>,
+    /// The finger on each string: 1 = index ... 4 = pinky (None = open or muted)
+    pub fingers: Vec<Option<u8>>,
+    pub barre: Option<Barre>,
+    /// The sounding pitches, string by string
+    pub pitches: Vec<Pitch>,
+    /// The lowest sounding note
+    pub bass: Note,
+    /// Lower is easier
+    pub difficulty: u32,
+}
+
+/// Assigns fingers to the fretted strings, with a barre on the lowest fret
+/// when it is pressed on several strings and nothing between them rings open.
+/// Returns the fingers, the barre and the number of fingers used.
+fn assign_fingers(frets: &[Option<u8>], capo: u8) -> (Vec<Option<u8>>, Option<Barre>, u8) {
+    let mut fingers: Vec<Option<u8>> = vec![None; frets.len()];
+    let fretted: Vec<(usize, u8)> = frets
+        .iter()
+        .enumerate()
+        .filter_map(|(string, fret): (usize, &Option<u8>)| match fret {
+            Some(f) if *f > capo => Some((string, *f)),
+            _ => None,
+        })
+        .collect();
+    let Some(lowest) = fretted.iter().map(|(_, f): &(usize, u8)| *f).min() else {
+        return (fingers, None, 0);
+    };
+
+    // 1. Barre: from the lowest string on the lowest fret up to the 1st string side,
+    //    every string under it must be pressed at that fret or above
+    let on_lowest: Vec<usize> = fretted
+        .iter()
+        .filter(|(_, f): &&(usize, u8)| *f == lowest)
+        .map(|(s, _): &(usize, u8)| *s)
+        .collect();
+    let mut barre: Option<Barre> = None;
+    if on_lowest.len() >= 2 {
+        let (from, to): (usize, usize) = (on_lowest[0], *on_lowest.last().unwrap());
+        let covered: bool = (from..=to).all(|s: usize| matches!(frets[s], Some(f) if f >= lowest));
+        if covered {
+            barre = Some(Barre {
+                fret: lowest,
+                from_string: from,
+                to_string: to,
+            });
+        }
+    }
+
+    // 2. The other notes take the next fingers, by fret and then from the lowest string
+    let mut rest: Vec<(usize, u8)> = fretted.clone();
+    let mut next: u8 = 1;
+    if let Some(b) = barre {
+        for string in &on_lowest {
+            fingers[*string] = Some(1);
+        }
+        rest.retain(|(s, f): &(usize, u8)| !(*f == b.fret && on_lowest.contains(s)));
+        next = 2;
+    }
+    rest.sort_by_key(|(s, f): &(usize, u8)| (*f, *s));
+    for (string, _) in rest {
+        fingers[string] = Some(next);
+        next += 1;
+    }
+
+    (fingers, barre, next - 1)
+}
+
+/// Scores a valid shape; lower is easier.
+fn difficulty(frets: &[Option<u8>], capo: u8, barre: bool, fingers: u8) -> u32 {
+    let fretted: Vec<u8> = frets
+        .iter()
+        .filter_map(|f: &Option<u8>| f.filter(|f: &u8| *f > capo))
+        .collect();
+    let span: u32 = match (fretted.iter().min(), fretted.iter().max()) {
+        (Some(low), Some(high)) => (high - low) as u32,
+        _ => 0,
+    };
+    // Higher positions are a little harder to find
+    let position: u32 = fretted
+        .iter()
+        .min()
+        .map(|f: &u8| (f - capo) as u32)
+        .unwrap_or(0);
+    let muted: u32 = frets.iter().filter(|f: &&Option<u8>| f.is_none()).count() as u32;
+
+    fingers as u32 * FINGER_COST
+        + span * STRETCH_COST
+        + if barre { BARRE_COST } else { 0 }
+        + muted * MUTED_COST
+        + position
+}
+
+/// Checks the shape against the chord and the options. Returns the fingering if it is playable.
+fn evaluate(
+    fretboard: &Fretboard,
+    frets: &[Option<u8>],
+    required: &[Note],
+    options: &FingeringOptions,
+    min_strings: usize,
+) -> Option<Fingering> {
+    // 1. Enough strings, and muted strings only at the edges unless allowed
+    let sounding: Vec<usize> = (0..frets.len())
+        .filter(|s: &usize| frets[*s].is_some())
+        .collect();
+    if sounding.is_empty() || sounding.len() < min_strings {
+        return None;
+    }
+    if !options.inner_mutes {
+        let (first, last): (usize, usize) = (sounding[0], *sounding.last().unwrap());
+        if (first..=last).any(|s: usize| frets[s].is_none()) {
+            return None;
+        }
+    }
+
+    // 2. Every required tone sounds, and the bass is right
+    let pitches: Vec<Pitch> = sounding
+        .iter()
+        .map(|s: &usize| fretboard.pitch_at(*s, frets[*s].unwrap()).unwrap())
+        .collect();
+    if !required
+        .iter()
+        .all(|n: &Note| pitches.iter().any(|p: &Pitch| p.note == *n))
+    {
+        return None;
+    }
+    let bass: Note = pitches.iter().min().unwrap().note;
+    if options.bass.is_some_and(|b: Note| b != bass) {
+        return None;
+    }
+
+    // 3. Enough fingers
+    let (fingers, barre, used): (Vec<Option<u8>>, Option<Barre>, u8) =
+        assign_fingers(frets, fretboard.capo);
+    if used > options.max_fingers {
+        return None;
+    }
+
+    Some(Fingering {
+        frets: frets.to_vec(),
+        fingers,
+        barre,
+        pitches,
+        bass,
+        difficulty: difficulty(frets, fretboard.capo, barre.is_some(), used),
+    })
+}
+
+/// Finds the playable fingerings of a chord, easiest first.
+/// Ties go to shapes with the root in the bass, then to lower positions.
+pub fn find_fingerings(
+    fretboard: &Fretboard,
+    root: Note,
+    chord_type: ChordType,
+    options: &FingeringOptions,
+) -> Result<Vec<Fingering>, String> {
+    search_fingerings(fretboard, root, &build_chord(root, chord_type), options)
+}
+
+/// Places chord notes (bass first, as in `build_chord` or an inversion) with the
+/// easiest fingering that keeps the first note in the bass (any note on
+/// re-entrant tunings). Falls back to the
+/// notes stacked in close position when no fingering is playable, or when
+/// the tuning has more than `MAX_STRINGS` strings.
+pub fn chord_positions(fretboard: &Fretboard, notes: &[Note]) -> Result<Vec<FretPosition>, String> {
+    let bass: Note = *notes.first().ok_or("No notes to place")?;
+    // Re-entrant tunings (ukulele) have no bass string to keep it on
+    let ascending: bool = fretboard.tuning.windows(2).all(|w: &[Pitch]| w[0] < w[1]);
+    if fretboard.tuning.len() > MAX_STRINGS {
+        return fretboard.place_notes(notes);
+    }
+    let options: FingeringOptions = FingeringOptions {
+        bass: Some(bass).filter(|_| ascending),
+        ..FingeringOptions::default()
+    };
+    match search_fingerings(fretboard, bass, notes, &options)?.first() {
+        Some(fingering) => Ok(fingering
+            .frets
+            .iter()
+            .enumerate()
+            .filter_map(|(string, fret): (usize, &Option<u8>)| {
+                let fret: u8 = (*fret)?;
+                Some(FretPosition {
+                    string,
+                    fret,
+                    pitch: fretboard.pitch_at(string, fret)?,
+                })
+            })
+            .collect()),
+        None => fretboard.place_notes(notes),
+    }
+}
+
+/// The search behind `find_fingerings`, for any set of chord tones.
+fn search_fingerings(
+    fretboard: &Fretboard,
+    root: Note,
+    tones: &[Note],
+    options: &FingeringOptions,
+) -> Result<Vec<Fingering>, String> {
+    let required: Vec<Note> = options.required.clone().unwrap_or_else(|| tones.to_vec());
+    if let Some(n) = required.iter().find(|n: &&Note| !tones.contains(n)) {
+        return Err(format!("{} is not a tone of the chord", n));
+    }
+    if let Some(b) = options.bass.filter(|b: &Note| !tones.contains(b)) {
+        return Err(format!("The bass {} is not a tone of the chord", b));
+    }
+    let strings: usize = fretboard.tuning.len();
+    if strings > MAX_STRINGS {
+        return Err(format!(
+            "Cannot search fingerings on {} strings ({} at most)",
+            strings, MAX_STRINGS
+        ));
+    }
+    let min_strings: usize = options
+        .min_strings
+        .unwrap_or_else(|| strings.saturating_sub(2).max(3))
+        .min(strings);
+    let capo: u8 = fretboard.capo;
+    let top: u8 = options.max_fret.min(fretboard.frets);
+
+    let mut found: Vec<Fingering> = Vec::<Fingering>::new();
+    // 1. Slide a window of `max_stretch` frets up the neck
+    for start in capo + 1..=top.max(capo + 1) {
+        let end: u8 = start.saturating_add(options.max_stretch).min(top);
+
+        // 2. The choices of each string: muted, open, or a chord tone inside the window
+        let choices: Vec<Vec<Option<u8>>> = (0..strings)
+            .map(|s: usize| {
+                let mut c: Vec<Option<u8>> = vec![None];
+                if options.open_strings {
+                    c.push(Some(capo));
+                }
+                c.extend((start..=end).map(Some));
+                c.into_iter()
+                    .filter(|f: &Option<u8>| match f {
+                        None => true,
+                        Some(f) => fretboard
+                            .pitch_at(s, *f)
+                            .is_some_and(|p: Pitch| tones.contains(&p.note)),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // 3. Every combination (an odometer over the strings)
+        let mut index: Vec<usize> = vec![0; strings];
+        loop {
+            let frets: Vec<Option<u8>> =
+                (0..strings).map(|s: usize| choices[s][index[s]]).collect();
+            // Shapes without a note at the window start belong to a later window
+            let starts_here: bool = frets.contains(&Some(start))
+                || (start == capo + 1
+                    && frets
+                        .iter()
+                        .all(|f: &Option<u8>| f.is_none_or(|f: u8| f == capo)));
+            if starts_here
+                && let Some(fingering) =
+                    evaluate(fretboard, &frets, &required, options, min_strings)
+            {
+                found.push(fingering);
+            }
+
+            let mut s: usize = 0;
+            while s < strings {
+                index[s] += 1;
+                if index[s] < choices[s].len() {
+                    break;
+                }
+                index[s] = 0;
+                s += 1;
+            }
+            if s == strings {
+                break;
+            }
+        }
+    }
+
+    // 4. Easiest first
+    found.sort_by_key(|f: &Fingering| {
+        let lowest: u8 = f
+            .frets
+            .iter()
+            .filter_map(|f: &Option<u8>| *f)
+            .filter(|f: &u8| *f > capo)
+            .min()
+            .unwrap_or(capo);
+        (f.difficulty, f.bass != root, lowest)
+    });
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fretboard::{TuningPreset, resolve_tuning};
+
+    fn shape(frets: &str) -> Vec<Option<u8>> {
+        frets
+            .split(' ')
+            .map(|f: &str| f.parse::<u8>().ok())
+            .collect()
+    }
+
+    fn guitar() -> Fretboard {
+        Fretboard::from_preset(TuningPreset::Standard)
+    }
+
+    #[test]
+    fn test_fingering_open_chords_rank_first() {
+        let root_bass: FingeringOptions = FingeringOptions {
+            bass: Some(Note::C),
+            ..FingeringOptions::default()
+        };
+        let c: Vec<Fingering> =
+            find_fingerings(&guitar(), Note::C, ChordType::Major, &root_bass).unwrap();
+        assert_eq!(c[0].frets, shape("x 3 2 0 1 0"));
+        assert_eq!(c[0].fingers, shape("x 3 2 x 1 x"));
+        assert_eq!(c[0].bass, Note::C);
+
+        let e: Vec<Fingering> = find_fingerings(
+            &guitar(),
+            Note::E,
+            ChordType::Major,
+            &FingeringOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(e[0].frets, shape("0 2 2 1 0 0"));
+        assert_eq!(e[0].fingers, shape("x 2 3 1 x x"));
+    }
+
+    #[test]
+    fn test_fingering_barre() {
+        let options: FingeringOptions = FingeringOptions {
+            open_strings: false,
+            bass: Some(Note::F),
+            ..FingeringOptions::default()
+        };
+        let f: Vec<Fingering> =
+            find_fingerings(&guitar(), Note::F, ChordType::Major, &options).unwrap();
+        let full: &Fingering = f
+            .iter()
+            .find(|f: &&Fingering| f.frets == shape("1 3 3 2 1 1"))
+            .unwrap();
+        assert_eq!(
+            full.barre,
+            Some(Barre {
+                fret: 1,
+                from_string: 0,
+                to_string: 5
+            })
+        );
+        assert_eq!(full.fingers, shape("1 3 4 2 1 1"));
+        // No shape uses an open string or more than four fingers
+        assert!(f.iter().all(|f: &Fingering| !f.frets.contains(&Some(0))));
+    }
+
+    #[test]
+    fn test_fingering_constraints() {
+        let options: FingeringOptions = FingeringOptions::default();
+        let g7: Vec<Fingering> =
+            find_fingerings(&guitar(), Note::G, ChordType::Dominant7, &options).unwrap();
+        assert!(!g7.is_empty());
+        let tones: Vec<Note> = build_chord(Note::G, ChordType::Dominant7);
+        for fingering in &g7 {
+            for tone in &tones {
+                assert!(fingering.pitches.iter().any(|p: &Pitch| p.note == *tone));
+            }
+            let fretted: Vec<u8> = fingering
+                .frets
+                .iter()
+                .filter_map(|f: &Option<u8>| *f)
+                .filter(|f: &u8| *f > 0)
+                .collect();
+            if let (Some(low), Some(high)) = (fretted.iter().min(), fretted.iter().max()) {
+                assert!(high - low <= options.max_stretch);
+            }
+        }
+        for pair in g7.windows(2) {
+            assert!(pair[0].difficulty <= pair[1].difficulty);
+        }
+
+        // A stretch past the top of the neck is clamped, not overflowed
+        let wide: FingeringOptions = FingeringOptions {
+            max_stretch: u8::MAX,
+            max_fret: 5,
+            ..FingeringOptions::default()
+        };
+        assert!(find_fingerings(&guitar(), Note::G, ChordType::Major, &wide).is_ok());
+
+        // First inversion only: B in the bass
+        let slash: FingeringOptions = FingeringOptions {
+            bass: Some(Note::B),
+            ..FingeringOptions::default()
+        };
+        let g_b: Vec<Fingering> =
+            find_fingerings(&guitar(), Note::G, ChordType::Major, &slash).unwrap();
+        assert!(g_b.iter().all(|f: &Fingering| f.bass == Note::B));
+        assert!(
+            find_fingerings(
+                &guitar(),
+                Note::G,
+                ChordType::Major,
+                &FingeringOptions {
+                    bass: Some(Note::C),
+                    ..FingeringOptions::default()
+                }
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_fingering_chord_positions() {
+        // First inversion of C: E in the bass, on the open low E string
+        let positions: Vec<FretPosition> =
+            chord_positions(&guitar(), &[Note::E, Note::G, Note::C]).unwrap();
+        assert_eq!(positions[0].pitch, Pitch::new(Note::E, 2));
+        let frets: Vec<u8> = positions.iter().map(|p: &FretPosition| p.fret).collect();
+        assert_eq!(frets, [0, 3, 2, 0, 1, 0]);
+
+        // Too many strings to search: an error, and close position for diagrams
+        let harp: Fretboard = Fretboard::new(
+            resolve_tuning("E2 A2 D3 G3 B3 E4 A4 D5 G5 C6 F6 B6").unwrap(),
+            12,
+            0,
+        )
+        .unwrap();
+        assert!(
+            find_fingerings(
+                &harp,
+                Note::C,
+                ChordType::Major,
+                &FingeringOptions::default()
+            )
+            .is_err()
+        );
+        assert!(chord_positions(&harp, &[Note::C, Note::E, Note::G]).is_ok());
+    }
+
+    #[test]
+    fn test_fingering_reentrant_bass() {
+        // On a ukulele the lowest sounding note is not on the first string
+        let ukulele: Fretboard = Fretboard::from_preset(TuningPreset::Ukulele);
+        let c: Vec<Fingering> = find_fingerings(
+            &ukulele,
+            Note::C,
+            ChordType::Major,
+            &FingeringOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(c[0].frets, shape("0 0 0 3"));
+        assert_eq!(c[0].bass, Note::C);
+    }
+}
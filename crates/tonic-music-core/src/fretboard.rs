@@ -0,0 +1,489 @@
+/*
+ * tonic-music-core
+ * Copyright (C) 2025 Israel Alberto Roldan Vega
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * Repository: https://github.com/airvzxf/music-theory-calculator/
+ */
+
+/*
+ * crates/tonic-music-core/src/fretboard.rs
+ *
+ * This module models fretted instruments (tuning, frets, capo)
+ * and finds where notes lie on the neck.
+ */
+
+use crate::Note;
+use crate::parser::parse_tuning;
+use crate::pitch::Pitch;
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// Common tunings of fretted instruments.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum, Serialize)]
+#[clap(rename_all = "kebab-case")]
+pub enum TuningPreset {
+    /// Guitar: E A D G B E
+    Standard,
+    /// Guitar: D A D G B E
+    DropD,
+    /// Guitar: D A D G A D
+    Dadgad,
+    /// Guitar: D G D G B D
+    OpenG,
+    /// Guitar: D A D F# A D
+    OpenD,
+    /// Guitar: E B E G# B E
+    OpenE,
+    /// 7-string guitar: B E A D G B E
+    #[value(alias("7-string"))]
+    SevenString,
+    /// 4-string bass: E A D G
+    #[value(alias("bass"))]
+    Bass4,
+    /// 5-string bass: B E A D G
+    Bass5,
+    /// Ukulele (re-entrant): G C E A
+    Ukulele,
+    /// Mandolin: G D A E
+    Mandolin,
+}
+
+impl TuningPreset {
+    /// The open strings, from the lowest string of a chord diagram
+    /// (the 6th string on a guitar) to the 1st string.
+    pub fn tuning(&self) -> Vec<Pitch> {
+        let tuning: &str = match self {
+            TuningPreset::Standard => "E2 A2 D3 G3 B3 E4",
+            TuningPreset::DropD => "D2 A2 D3 G3 B3 E4",
+            TuningPreset::Dadgad => "D2 A2 D3 G3 A3 D4",
+            TuningPreset::OpenG => "D2 G2 D3 G3 B3 D4",
+            TuningPreset::OpenD => "D2 A2 D3 F#3 A3 D4",
+            TuningPreset::OpenE => "E2 B2 E3 G#3 B3 E4",
+            TuningPreset::SevenString => "B1 E2 A2 D3 G3 B3 E4",
+            TuningPreset::Bass4 => "E1 A1 D2 G2",
+            TuningPreset::Bass5 => "B0 E1 A1 D2 G2",
+            TuningPreset::Ukulele => "G4 C4 E4 A4",
+            TuningPreset::Mandolin => "G3 D4 A4 E5",
+        };
+        parse_tuning(tuning).expect("Preset tunings are valid")
+    }
+
+    /// The usual number of frets of the instrument.
+    pub fn frets(&self) -> u8 {
+        match self {
+            TuningPreset::SevenString | TuningPreset::Bass5 => 24,
+            TuningPreset::Bass4 | TuningPreset::Mandolin => 20,
+            TuningPreset::Ukulele => 15,
+            _ => 22,
+        }
+    }
+}
+
+/// Resolves a tuning given either as a preset name ("drop-d", "ukulele")
+/// or as pitches from the lowest string ("D2 A2 D3 G3 B3 E4").
+pub fn resolve_tuning(s: &str) -> Result<Vec<Pitch>, String> {
+    match TuningPreset::from_str(s.trim(), true) {
+        Ok(preset) => Ok(preset.tuning()),
+        Err(_) => parse_tuning(s).map_err(|e: String| {
+            format!(
+                "Unknown tuning '{}' (use a preset like standard, drop-d, dadgad, or pitches like \"E2 A2 D3 G3 B3 E4\"): {}",
+                s, e
+            )
+        }),
+    }
+}
+
+/// A fretted instrument neck.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Fretboard {
+    /// The open strings, from the lowest string of a chord diagram to the 1st string
+    pub tuning: Vec<Pitch>,
+    /// The highest fret
+    pub frets: u8,
+    /// The fret clamped by a capo (0 = no capo)
+    pub capo: u8,
+}
+
+/// A note on the neck.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub struct FretPosition {
+    /// Index into the tuning (0 = lowest string of a chord diagram)
+    pub string: usize,
+    /// The absolute fret number (with a capo, the capo fret is the "open" string)
+    pub fret: u8,
+    pub pitch: Pitch,
+}
+
+impl Fretboard {
+    pub fn new(tuning: Vec<Pitch>, frets: u8, capo: u8) -> Result<Self, String> {
+        if tuning.is_empty() {
+            return Err("A fretboard needs at least one string".to_string());
+        }
+        if capo >= frets {
+            return Err(format!(
+                "The capo (fret {}) must be below the last fret ({})",
+                capo, frets
+            ));
+        }
+        Ok(Fretboard {
+            tuning,
+            frets,
+            capo,
+        })
+    }
+
+    /// A fretboard with a preset tuning, its usual frets and no capo.
+    pub fn from_preset(preset: TuningPreset) -> Self {
+        Fretboard {
+            tuning: preset.tuning(),
+            frets: preset.frets(),
+            capo: 0,
+        }
+    }
+
+    /// The pitch heard on a string at a fret, or None if the fret cannot be
+    /// played (past the last fret, or behind the capo).
+    pub fn pitch_at(&self, string: usize, fret: u8) -> Option<Pitch> {
+        let open: Pitch = *self.tuning.get(string)?;
+        if fret < self.capo || fret > self.frets {
+            return None;
+        }
+        Some(open.transpose(fret as i16))
+    }
+
+    /// The pitch of a string when played "open" (at the capo).
+    pub fn open_pitch(&self, string: usize) -> Option<Pitch> {
+        self.pitch_at(string, self.capo)
+    }
+
+    /// Every playable position whose pitch class is one of `notes`
+    /// (e.g. the output of `build_scale` or `build_chord`), string by string.
+    pub fn positions_of(&self, notes: &[Note]) -> Vec<FretPosition> {
+        let mut positions: Vec<FretPosition> = Vec::<FretPosition>::new();
+        for string in 0..self.tuning.len() {
+            for fret in self.capo..=self.frets {
+                let pitch: Pitch = self.pitch_at(string, fret).unwrap();
+                if notes.contains(&pitch.note) {
+                    positions.push(FretPosition {
+                        string,
+                        fret,
+                        pitch,
+                    });
+                }
+            }
+        }
+        positions
+    }
+
+    /// Every playable position of an exact pitch.
+    pub fn positions_of_pitch(&self, pitch: Pitch) -> Vec<FretPosition> {
+        self.positions_of(&[pitch.note])
+            .into_iter()
+            .filter(|p: &FretPosition| p.pitch == pitch)
+            .collect()
+    }
+
+    /// The strings and frets where a pitch can be played.
+    fn candidates(&self, pitch: Pitch) -> Vec<FretPosition> {
+        (0..self.tuning.len())
+            .filter_map(|string: usize| {
+                let fret: i16 = pitch.midi() - self.tuning[string].midi();
+                if fret < self.capo as i16 || fret > self.frets as i16 {
+                    return None;
+                }
+                Some(FretPosition {
+                    string,
+                    fret: fret as u8,
+                    pitch,
+                })
+            })
+            .collect()
+    }
+
+    /// Places pitches sounding together on different strings, keeping the
+    /// fretted notes within reach and as low on the neck as possible.
+    pub fn place_chord(&self, pitches: &[Pitch]) -> Result<Vec<FretPosition>, String> {
+        if pitches.len() > self.tuning.len() {
+            return Err(format!(
+                "{} notes do not fit on {} strings",
+                pitches.len(),
+                self.tuning.len()
+            ));
+        }
+        let candidates: Vec<Vec<FretPosition>> = pitches
+            .iter()
+            .map(|p: &Pitch| self.candidates(*p))
+            .collect();
+        if let Some(i) = candidates
+            .iter()
+            .position(|c: &Vec<FretPosition>| c.is_empty())
+        {
+            return Err(format!("{} is out of the range of the strings", pitches[i]));
+        }
+
+        // Depth-first over the choices of each pitch, keeping the cheapest full assignment
+        fn search(
+            candidates: &[Vec<FretPosition>],
+            capo: u8,
+            chosen: &mut Vec<FretPosition>,
+            best: &mut Option<((u8, u8), Vec<FretPosition>)>,
+        ) {
+            if chosen.len() == candidates.len() {
+                let fretted: Vec<u8> = chosen
+                    .iter()
+                    .map(|p: &FretPosition| p.fret)
+                    .filter(|f: &u8| *f > capo)
+                    .collect();
+                let low: u8 = fretted.iter().min().copied().unwrap_or(capo);
+                let high: u8 = fretted.iter().max().copied().unwrap_or(capo);
+                // Any span up to four frets is comfortable; then the lower the better
+                let cost: (u8, u8) = ((high - low).max(3), high);
+                if best.as_ref().is_none_or(|(c, _)| cost < *c) {
+                    *best = Some((cost, chosen.clone()));
+                }
+                return;
+            }
+            for position in &candidates[chosen.len()] {
+                if chosen
+                    .iter()
+                    .all(|p: &FretPosition| p.string != position.string)
+                {
+                    chosen.push(*position);
+                    search(candidates, capo, chosen, best);
+                    chosen.pop();
+                }
+            }
+        }
+
+        let mut best: Option<((u8, u8), Vec<FretPosition>)> = None;
+        search(
+            &candidates,
+            self.capo,
+            &mut Vec::<FretPosition>::new(),
+            &mut best,
+        );
+        best.map(|(_, positions)| positions)
+            .ok_or_else(|| "The notes cannot be played on different strings".to_string())
+    }
+
+    /// The lowest pitch of a note that can be played on the instrument.
+    fn lowest_pitch_of(&self, note: Note) -> Pitch {
+        let lowest: Pitch = (0..self.tuning.len())
+            .filter_map(|s: usize| self.open_pitch(s))
+            .min()
+            .unwrap();
+        let up: i16 = (note.as_u8() as i16 - lowest.note.as_u8() as i16).rem_euclid(12);
+        lowest.transpose(up)
+    }
+
+    /// Places chord notes without octaves (bass first, as in `build_chord` or an
+    /// inversion): each note is stacked above the previous one, from the lowest
+    /// octave that fits on the strings.
+    pub fn place_notes(&self, notes: &[Note]) -> Result<Vec<FretPosition>, String> {
+        let mut error: String = "No notes to place".to_string();
+        for octave in 0..3 {
+            let mut pitches: Vec<Pitch> = Vec::<Pitch>::new();
+            for note in notes {
+                let pitch: Pitch = match pitches.last() {
+                    None => self.lowest_pitch_of(*note).transpose(12 * octave),
+                    Some(below) => {
+                        let up: i16 =
+                            (note.as_u8() as i16 - below.note.as_u8() as i16).rem_euclid(12);
+                        below.transpose(if up == 0 { 12 } else { up })
+                    }
+                };
+                pitches.push(pitch);
+            }
+            match self.place_chord(&pitches) {
+                Ok(positions) => return Ok(positions),
+                Err(e) => error = e,
+            }
+        }
+        Err(error)
+    }
+
+    /// Places an ascending run of pitches in one position (one finger per fret over
+    /// four frets, open strings only in the first position), moving up the strings.
+    fn place_run(&self, pitches: &[Pitch]) -> Option<Vec<FretPosition>> {
+        (self.capo + 1..=self.frets.saturating_sub(3)).find_map(|start: u8| {
+            let mut string: usize = 0;
+            let mut positions: Vec<FretPosition> = Vec::<FretPosition>::new();
+            for pitch in pitches {
+                let position: FretPosition = self
+                    .candidates(*pitch)
+                    .into_iter()
+                    .filter(|p: &FretPosition| {
+                        let open: bool = p.fret == self.capo && start == self.capo + 1;
+                        p.string >= string && (open || (start..=start + 3).contains(&p.fret))
+                    })
+                    // Open strings ring better than a stretch to the fourth fret
+                    .min_by_key(|p: &FretPosition| (p.fret != self.capo, p.string))?;
+                string = position.string;
+                positions.push(position);
+            }
+            Some(positions)
+        })
+    }
+
+    /// Places one ascending octave of a scale (root to root) in the lowest position it fits.
+    pub fn place_scale(&self, notes: &[Note]) -> Result<Vec<FretPosition>, String> {
+        let root: Note = *notes.first().ok_or("No notes to place")?;
+        for octave in 0..3 {
+            let mut pitches: Vec<Pitch> = vec![self.lowest_pitch_of(root).transpose(12 * octave)];
+            for note in notes.iter().skip(1).chain(std::iter::once(&root)) {
+                let below: Pitch = *pitches.last().unwrap();
+                let up: i16 = (note.as_u8() as i16 - below.note.as_u8() as i16).rem_euclid(12);
+                pitches.push(below.transpose(if up == 0 { 12 } else { up }));
+            }
+            if let Some(positions) = self.place_run(&pitches) {
+                return Ok(positions);
+            }
+        }
+        Err("The scale does not fit in one position on this instrument".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChordType, ScaleType, build_chord, build_scale};
+
+    #[test]
+    fn test_fretboard_presets() {
+        for preset in TuningPreset::value_variants() {
+            let fretboard: Fretboard = Fretboard::from_preset(*preset);
+            assert!(!fretboard.tuning.is_empty());
+        }
+        assert_eq!(TuningPreset::SevenString.tuning().len(), 7);
+        assert_eq!(TuningPreset::Bass5.tuning()[0], Pitch::new(Note::B, 0));
+        // Ukulele is re-entrant: the 4th string is higher than the 3rd
+        let ukulele: Vec<Pitch> = TuningPreset::Ukulele.tuning();
+        assert!(ukulele[0] > ukulele[1]);
+    }
+
+    #[test]
+    fn test_fretboard_resolve_tuning() {
+        assert_eq!(resolve_tuning("Drop-D"), Ok(TuningPreset::DropD.tuning()));
+        assert_eq!(resolve_tuning("7-string").unwrap().len(), 7);
+        assert_eq!(
+            resolve_tuning("C2 G2 D3 A3 E4 G4").unwrap()[0],
+            Pitch::new(Note::C, 2)
+        );
+        assert!(resolve_tuning("banjo").is_err());
+    }
+
+    #[test]
+    fn test_fretboard_pitch_at_and_capo() {
+        let guitar: Fretboard = Fretboard::from_preset(TuningPreset::Standard);
+        assert_eq!(guitar.pitch_at(0, 5), Some(Pitch::new(Note::A, 2)));
+        assert_eq!(guitar.pitch_at(5, 12), Some(Pitch::new(Note::E, 5)));
+        assert_eq!(guitar.pitch_at(5, 23), None);
+        assert_eq!(guitar.pitch_at(6, 0), None);
+
+        let capo: Fretboard = Fretboard::new(TuningPreset::Standard.tuning(), 22, 2).unwrap();
+        assert_eq!(capo.pitch_at(0, 1), None);
+        assert_eq!(capo.open_pitch(0), Some(Pitch::new(Note::FSharp, 2)));
+        assert!(Fretboard::new(Vec::<Pitch>::new(), 22, 0).is_err());
+        assert!(Fretboard::new(TuningPreset::Standard.tuning(), 12, 12).is_err());
+    }
+
+    #[test]
+    fn test_fretboard_place_chords_and_scales() {
+        let guitar: Fretboard = Fretboard::from_preset(TuningPreset::Standard);
+        let frets = |positions: &[FretPosition]| -> Vec<(usize, u8)> {
+            positions
+                .iter()
+                .map(|p: &FretPosition| (p.string, p.fret))
+                .collect()
+        };
+
+        // C3 E3 G3: A string 3rd fret, D string 2nd, G open
+        let c: Vec<FretPosition> = guitar
+            .place_chord(&[
+                Pitch::new(Note::C, 3),
+                Pitch::new(Note::E, 3),
+                Pitch::new(Note::G, 3),
+            ])
+            .unwrap();
+        assert_eq!(frets(&c), [(1, 3), (2, 2), (3, 0)]);
+        // First inversion B D G, stacked from the lowest B
+        let g6: Vec<FretPosition> = guitar.place_notes(&[Note::B, Note::D, Note::G]).unwrap();
+        assert_eq!(g6[0].pitch, Pitch::new(Note::B, 2));
+        assert_eq!(frets(&g6), [(1, 2), (2, 0), (3, 0)]);
+        assert!(guitar.place_chord(&[Pitch::new(Note::C, 2)]).is_err());
+
+        // The open-position C major scale
+        let scale: Vec<FretPosition> = guitar
+            .place_scale(&build_scale(Note::C, ScaleType::Major))
+            .unwrap();
+        assert_eq!(
+            frets(&scale),
+            [
+                (1, 3),
+                (2, 0),
+                (2, 2),
+                (2, 3),
+                (3, 0),
+                (3, 2),
+                (4, 0),
+                (4, 1)
+            ]
+        );
+        let bass: Fretboard = Fretboard::from_preset(TuningPreset::Bass4);
+        assert_eq!(
+            bass.place_scale(&build_scale(Note::G, ScaleType::Major))
+                .unwrap()[0]
+                .pitch,
+            Pitch::new(Note::G, 1)
+        );
+    }
+
+    #[test]
+    fn test_fretboard_chord_and_scale_positions() {
+        let guitar: Fretboard = Fretboard::new(TuningPreset::Standard.tuning(), 3, 0).unwrap();
+        let c_major: Vec<Note> = build_chord(Note::C, ChordType::Major);
+        let positions: Vec<FretPosition> = guitar.positions_of(&c_major);
+
+        // The open C chord shape is in there: A string 3rd fret, D 2nd, G open, B 1st
+        for (string, fret) in [(1, 3), (2, 2), (3, 0), (4, 1)] {
+            assert!(
+                positions
+                    .iter()
+                    .any(|p: &FretPosition| p.string == string && p.fret == fret)
+            );
+        }
+        assert!(
+            positions
+                .iter()
+                .all(|p: &FretPosition| c_major.contains(&p.pitch.note))
+        );
+
+        // 7 notes over 12 frets + open: every string has 7 or 8 hits
+        let full: Fretboard = Fretboard::new(TuningPreset::Standard.tuning(), 12, 0).unwrap();
+        let scale: Vec<FretPosition> = full.positions_of(&build_scale(Note::G, ScaleType::Major));
+        for string in 0..6 {
+            let count: usize = scale
+                .iter()
+                .filter(|p: &&FretPosition| p.string == string)
+                .count();
+            assert!((7..=8).contains(&count));
+        }
+
+        let e4: Vec<FretPosition> = full.positions_of_pitch(Pitch::new(Note::E, 4));
+        // 1st string open, B string 5th fret, G string 9th fret (D string 14th is out of range)
+        assert_eq!(e4.len(), 3);
+    }
+}
@@ -0,0 +1,511 @@
+/*
+ * tonic-music-core
+ * Copyright (C) 2025 Israel Alberto Roldan Vega
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * Repository: https://github.com/airvzxf/music-theory-calculator/
+ */
+
+/*
+ * crates/tonic-music-core/src/generator.rs
+ *
+ * This module generates practice progressions with a
+ * Markov chain over Roman numeral chords. Generation is
+ * deterministic for a given seed.
+ */
+
+use crate::parser::{
+    ParsedRomanChord, formula_tokens, is_nashville_formula, parse_progression_formula,
+};
+use crate::voice_leading::VoiceLeadingOptions;
+use crate::{Note, ProgressionChord, build_custom_progression};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// The built-in transition tables.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum, Serialize)]
+#[clap(rename_all = "kebab-case")]
+pub enum GeneratorStyle {
+    Pop,
+    Jazz,
+    Classical,
+}
+
+/// The cadence a generated progression must end with.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum, Serialize)]
+#[clap(rename_all = "kebab-case")]
+pub enum EndingCadence {
+    /// V7 - I
+    Authentic,
+    /// IV - I
+    Plagal,
+    /// Stop on V
+    Half,
+    /// V7 - vi
+    Deceptive,
+}
+
+/// (from, to, weight) rows of the built-in pop table.
+const POP_TRANSITIONS: &[(&str, &str, u32)] = &[
+    ("I", "V", 4),
+    ("I", "vi", 4),
+    ("I", "IV", 5),
+    ("I", "ii", 1),
+    ("V", "vi", 4),
+    ("V", "I", 3),
+    ("V", "IV", 3),
+    ("vi", "IV", 6),
+    ("vi", "V", 2),
+    ("vi", "ii", 2),
+    ("vi", "iii", 1),
+    ("IV", "I", 4),
+    ("IV", "V", 5),
+    ("IV", "vi", 2),
+    ("IV", "ii", 1),
+    ("ii", "V", 5),
+    ("ii", "IV", 2),
+    ("iii", "vi", 3),
+    ("iii", "IV", 3),
+];
+
+/// (from, to, weight) rows of the built-in jazz table.
+const JAZZ_TRANSITIONS: &[(&str, &str, u32)] = &[
+    ("Imaj7", "vi7", 4),
+    ("Imaj7", "ii7", 3),
+    ("Imaj7", "VI7", 3),
+    ("Imaj7", "iii7", 2),
+    ("Imaj7", "IVmaj7", 2),
+    ("vi7", "ii7", 6),
+    ("vi7", "II7", 2),
+    ("ii7", "V7", 8),
+    ("ii7", "bII7", 2),
+    ("V7", "Imaj7", 6),
+    ("V7", "iii7", 1),
+    ("V7", "vi7", 1),
+    ("bII7", "Imaj7", 5),
+    ("VI7", "ii7", 6),
+    ("II7", "ii7", 2),
+    ("II7", "V7", 4),
+    ("iii7", "VI7", 4),
+    ("iii7", "vi7", 3),
+    ("IVmaj7", "iv7", 2),
+    ("IVmaj7", "iii7", 2),
+    ("IVmaj7", "bVII7", 2),
+    ("IVmaj7", "V7", 2),
+    ("iv7", "bVII7", 3),
+    ("iv7", "Imaj7", 2),
+    ("bVII7", "Imaj7", 4),
+];
+
+/// (from, to, weight) rows of the built-in classical (common practice) table.
+const CLASSICAL_TRANSITIONS: &[(&str, &str, u32)] = &[
+    ("I", "IV", 3),
+    ("I", "V", 3),
+    ("I", "vi", 2),
+    ("I", "ii", 2),
+    ("I", "iii", 1),
+    ("I", "viidim", 1),
+    ("ii", "V", 5),
+    ("ii", "V7", 2),
+    ("ii", "viidim", 2),
+    ("iii", "vi", 3),
+    ("iii", "IV", 2),
+    ("IV", "V", 4),
+    ("IV", "I", 2),
+    ("IV", "ii", 2),
+    ("IV", "viidim", 1),
+    ("V", "I", 5),
+    ("V", "vi", 2),
+    ("V", "V7", 1),
+    ("V7", "I", 6),
+    ("V7", "vi", 1),
+    ("vi", "ii", 4),
+    ("vi", "IV", 4),
+    ("vi", "V", 1),
+    ("viidim", "I", 5),
+    ("viidim", "iii", 1),
+];
+
+/// A weighted transition table between Roman numeral chords.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TransitionTable {
+    /// The chord a progression starts on (and its tonic, e.g. "I" or "Imaj7")
+    pub tonic: String,
+    /// from -> (to -> weight)
+    pub transitions: BTreeMap<String, BTreeMap<String, u32>>,
+}
+
+impl TransitionTable {
+    fn from_rows(tonic: &str, rows: &[(&str, &str, u32)]) -> Self {
+        let mut table: TransitionTable = TransitionTable {
+            tonic: tonic.to_string(),
+            transitions: BTreeMap::<String, BTreeMap<String, u32>>::new(),
+        };
+        for (from, to, weight) in rows {
+            table.add(from, to, *weight);
+        }
+        table
+    }
+
+    /// Returns one of the built-in tables.
+    pub fn builtin(style: GeneratorStyle) -> Self {
+        match style {
+            GeneratorStyle::Pop => Self::from_rows("I", POP_TRANSITIONS),
+            GeneratorStyle::Jazz => Self::from_rows("Imaj7", JAZZ_TRANSITIONS),
+            GeneratorStyle::Classical => Self::from_rows("I", CLASSICAL_TRANSITIONS),
+        }
+    }
+
+    /// Learns a table by counting chord-to-chord moves in the given formulas
+    /// (e.g. "I-vi-IV-V" or "1 6- 4 5"). The first chord of the first formula
+    /// is the tonic, and all formulas must use the same notation.
+    pub fn train(formulas: &[&str]) -> Result<Self, String> {
+        let mut table: Option<TransitionTable> = None;
+
+        for formula in formulas {
+            let formula: &str = formula;
+            // Validate every chord before using the raw tokens as states
+            parse_progression_formula(formula)?;
+            let tokens: Vec<&str> = formula_tokens(formula);
+            if let Some(table) = &table
+                && is_nashville_formula(&table.tonic) != is_nashville_formula(formula)
+            {
+                return Err(format!(
+                    "Cannot mix Nashville numbers and Roman numerals when training: '{}'",
+                    formula
+                ));
+            }
+
+            let table: &mut TransitionTable = table.get_or_insert_with(|| TransitionTable {
+                tonic: tokens[0].to_string(),
+                transitions: BTreeMap::<String, BTreeMap<String, u32>>::new(),
+            });
+            for pair in tokens.windows(2) {
+                table.add(pair[0], pair[1], 1);
+            }
+        }
+
+        table.ok_or_else(|| "Cannot train a transition table without progressions".to_string())
+    }
+
+    /// Adds `weight` to the move `from` -> `to`. A weight of 0 adds nothing
+    /// (the move stays impossible).
+    pub fn add(&mut self, from: &str, to: &str, weight: u32) {
+        if weight == 0 {
+            return;
+        }
+        let count: &mut u32 = self
+            .transitions
+            .entry(from.to_string())
+            .or_default()
+            .entry(to.to_string())
+            .or_insert(0);
+        *count = count.saturating_add(weight);
+    }
+
+    /// Returns the weight of the move `from` -> `to` (0 if never seen).
+    pub fn weight(&self, from: &str, to: &str) -> u32 {
+        self.transitions
+            .get(from)
+            .and_then(|row: &BTreeMap<String, u32>| row.get(to))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+/// Options for `generate_progression`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub struct GeneratorOptions {
+    /// Total number of chords, cadence included
+    pub length: usize,
+    pub seed: u64,
+    pub cadence: EndingCadence,
+    /// How the generated chords are voiced
+    pub voice_leading: VoiceLeadingOptions,
+}
+
+/// A small SplitMix64 generator, so results are identical on every platform.
+struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z: u64 = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Picks an index with probability proportional to its weight.
+    /// Returns `None` when every weight is 0.
+    fn pick_weighted(&mut self, weights: &[u64]) -> Option<usize> {
+        let total: u64 = weights.iter().sum();
+        if total == 0 {
+            return None;
+        }
+        let mut target: u64 = self.next_u64() % total;
+        for (i, weight) in weights.iter().enumerate() {
+            if target < *weight {
+                return Some(i);
+            }
+            target -= weight;
+        }
+        None
+    }
+}
+
+/// The closing chords for each cadence, with `tonic` as the chord of resolution.
+/// They are spelled in the same notation as `tonic`.
+fn cadence_chords(cadence: EndingCadence, tonic: &str) -> Vec<String> {
+    let (dominant7, dominant, subdominant, submediant): (&str, &str, &str, &str) =
+        if is_nashville_formula(tonic) {
+            ("57", "5", "4", "6-")
+        } else {
+            ("V7", "V", "IV", "vi")
+        };
+    match cadence {
+        EndingCadence::Authentic => vec![dominant7.to_string(), tonic.to_string()],
+        EndingCadence::Plagal => vec![subdominant.to_string(), tonic.to_string()],
+        EndingCadence::Half => vec![dominant.to_string()],
+        EndingCadence::Deceptive => vec![dominant7.to_string(), submediant.to_string()],
+    }
+}
+
+/// Generates the Roman numeral chords of a progression (see `generate_progression`).
+pub fn generate_formula(
+    table: &TransitionTable,
+    options: &GeneratorOptions,
+) -> Result<Vec<String>, String> {
+    let ending: Vec<String> = cadence_chords(options.cadence, &table.tonic);
+    if options.length < ending.len() {
+        return Err(format!(
+            "A {:?} cadence needs at least {} chords",
+            options.cadence,
+            ending.len()
+        ));
+    }
+
+    let mut rng: SplitMix64 = SplitMix64 {
+        state: options.seed,
+    };
+    let body_length: usize = options.length - ending.len();
+    let mut chords: Vec<String> = Vec::<String>::new();
+
+    for i in 0..body_length {
+        let i: usize = i;
+        if i == 0 {
+            chords.push(table.tonic.clone());
+            continue;
+        }
+
+        let current: &str = chords.last().unwrap();
+        let row: Option<&BTreeMap<String, u32>> = table.transitions.get(current);
+
+        let next: String = match row {
+            Some(row) if !row.is_empty() => {
+                let candidates: Vec<(&String, &u32)> = row.iter().collect();
+                let is_last_body_chord: bool = i == body_length - 1;
+                let weights: Vec<u64> = candidates
+                    .iter()
+                    .map(|(chord, weight): &(&String, &u32)| {
+                        // Lean the last free chord toward the cadence
+                        if is_last_body_chord {
+                            **weight as u64 * (1 + table.weight(chord, &ending[0]) as u64)
+                        } else {
+                            **weight as u64
+                        }
+                    })
+                    .collect();
+                match rng.pick_weighted(&weights) {
+                    Some(i) => candidates[i].0.clone(),
+                    None => table.tonic.clone(),
+                }
+            }
+            // Dead end (possible with trained tables): restart from the tonic
+            _ => table.tonic.clone(),
+        };
+        chords.push(next);
+    }
+
+    chords.extend(ending);
+    Ok(chords)
+}
+
+/// Generates a `length`-chord progression in the key of `root` by walking
+/// the transition table from its tonic, then closing with the chosen cadence.
+/// The same seed always produces the same progression.
+pub fn generate_progression(
+    root: Note,
+    table: &TransitionTable,
+    options: &GeneratorOptions,
+) -> Result<Vec<ProgressionChord>, String> {
+    let chords: Vec<String> = generate_formula(table, options)?;
+    let specs: Vec<ParsedRomanChord> = parse_progression_formula(&chords.join(" "))?;
+    Ok(build_custom_progression(
+        root,
+        specs,
+        &options.voice_leading,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ChordType;
+    use crate::analysis::{Cadence, detect_cadences};
+
+    fn options(length: usize, seed: u64, cadence: EndingCadence) -> GeneratorOptions {
+        GeneratorOptions {
+            length,
+            seed,
+            cadence,
+            voice_leading: VoiceLeadingOptions::default(),
+        }
+    }
+
+    #[test]
+    fn test_generator_is_deterministic() {
+        let table: TransitionTable = TransitionTable::builtin(GeneratorStyle::Pop);
+        let a: Vec<String> =
+            generate_formula(&table, &options(16, 42, EndingCadence::Authentic)).unwrap();
+        let b: Vec<String> =
+            generate_formula(&table, &options(16, 42, EndingCadence::Authentic)).unwrap();
+        assert_eq!(a, b);
+
+        // Different seeds explore different paths
+        let others: Vec<Vec<String>> = (0..10)
+            .map(|seed: u64| {
+                generate_formula(&table, &options(16, seed, EndingCadence::Authentic)).unwrap()
+            })
+            .collect();
+        assert!(others.iter().any(|o: &Vec<String>| *o != a));
+    }
+
+    #[test]
+    fn test_generator_length_and_cadence() {
+        for style in GeneratorStyle::value_variants() {
+            let table: TransitionTable = TransitionTable::builtin(*style);
+            for seed in 0..20 {
+                let progression: Vec<ProgressionChord> = generate_progression(
+                    Note::C,
+                    &table,
+                    &options(8, seed, EndingCadence::Authentic),
+                )
+                .unwrap();
+                assert_eq!(progression.len(), 8);
+                assert_eq!(progression[0].root_note, Note::C);
+                assert!(matches!(
+                    detect_cadences(Note::C, &progression, None).last(),
+                    Some((7, Cadence::PerfectAuthentic | Cadence::ImperfectAuthentic))
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn test_generator_other_cadences() {
+        let table: TransitionTable = TransitionTable::builtin(GeneratorStyle::Classical);
+
+        let half: Vec<ProgressionChord> =
+            generate_progression(Note::G, &table, &options(5, 7, EndingCadence::Half)).unwrap();
+        assert_eq!(
+            detect_cadences(Note::G, &half, None).last(),
+            Some(&(4, Cadence::Half))
+        );
+
+        let deceptive: Vec<ProgressionChord> =
+            generate_progression(Note::G, &table, &options(5, 7, EndingCadence::Deceptive))
+                .unwrap();
+        assert_eq!(
+            detect_cadences(Note::G, &deceptive, None).last(),
+            Some(&(4, Cadence::Deceptive))
+        );
+        assert_eq!(deceptive[4].chord_type, ChordType::Minor);
+
+        assert!(generate_formula(&table, &options(1, 7, EndingCadence::Plagal)).is_err());
+    }
+
+    #[test]
+    fn test_generator_trained_table() {
+        let table: TransitionTable = TransitionTable::train(&["I-IV-I-V", "I-IV-V"]).unwrap();
+        assert_eq!(table.tonic, "I");
+        assert_eq!(table.weight("I", "IV"), 2);
+        assert_eq!(table.weight("IV", "I"), 1);
+        assert_eq!(table.weight("IV", "V"), 1);
+        assert_eq!(table.weight("V", "I"), 0);
+
+        // Only learned moves appear in the body (V has no moves: restart at I)
+        let chords: Vec<String> =
+            generate_formula(&table, &options(12, 3, EndingCadence::Plagal)).unwrap();
+        for pair in chords[..10].windows(2) {
+            assert!(table.weight(&pair[0], &pair[1]) > 0 || pair[1] == "I");
+        }
+
+        assert!(TransitionTable::train(&[]).is_err());
+        assert!(TransitionTable::train(&["I-Q"]).is_err());
+    }
+
+    #[test]
+    fn test_generator_zero_weights() {
+        let mut table: TransitionTable = TransitionTable::train(&["I-IV"]).unwrap();
+        table.add("I", "V", 0);
+        table.add("V", "I", 0);
+        assert_eq!(table.weight("I", "V"), 0);
+        assert!(!table.transitions.contains_key("V"));
+
+        let mut rng: SplitMix64 = SplitMix64 { state: 1 };
+        assert_eq!(rng.pick_weighted(&[0, 0]), None);
+        assert_eq!(rng.pick_weighted(&[]), None);
+        assert_eq!(rng.pick_weighted(&[0, 3]), Some(1));
+
+        let chords: Vec<String> =
+            generate_formula(&table, &options(6, 1, EndingCadence::Plagal)).unwrap();
+        assert!(!chords.contains(&"V".to_string()));
+    }
+
+    #[test]
+    fn test_generator_trained_on_nashville() {
+        // The dash is part of a Nashville chord (minor), not a separator
+        let table: TransitionTable =
+            TransitionTable::train(&["1 6- 2- 5", "| 1 | 4 | 5 | 1 |"]).unwrap();
+        assert_eq!(table.tonic, "1");
+        assert_eq!(table.weight("1", "6-"), 1);
+        assert_eq!(table.weight("6-", "2-"), 1);
+        assert_eq!(table.weight("2-", "5"), 1);
+        assert_eq!(table.weight("5", "1"), 1);
+        assert_eq!(table.weight("6", "2"), 0);
+
+        let progression: Vec<ProgressionChord> =
+            generate_progression(Note::C, &table, &options(8, 1, EndingCadence::Authentic))
+                .unwrap();
+        assert_eq!(progression.len(), 8);
+        assert!(
+            progression
+                .iter()
+                .all(|c: &ProgressionChord| c.root_note != Note::A
+                    || c.chord_type == ChordType::Minor)
+        );
+        assert_eq!(progression[6].chord_type, ChordType::Dominant7);
+
+        let deceptive: Vec<String> =
+            generate_formula(&table, &options(4, 1, EndingCadence::Deceptive)).unwrap();
+        assert_eq!(deceptive[2..], ["57", "6-"]);
+
+        assert!(TransitionTable::train(&["1 4 5 1", "I-IV-V"]).is_err());
+    }
+}
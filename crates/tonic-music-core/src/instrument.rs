@@ -0,0 +1,255 @@
+/*
+ * tonic-music-core
+ * Copyright (C) 2025 Israel Alberto Roldan Vega
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * Repository: https://github.com/airvzxf/music-theory-calculator/
+ */
+
+/*
+ * crates/tonic-music-core/src/instrument.rs
+ *
+ * This module defines transposing instruments (Bb, Eb, F...)
+ * and converts concert pitch into written parts.
+ */
+
+use crate::pitch::Pitch;
+use crate::voice_leading::VoicedChord;
+use crate::{HarmonizedDegree, Note, ProgressionChord};
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// Instruments whose parts are written in concert pitch or transposed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum, Serialize)]
+#[clap(rename_all = "kebab-case")]
+pub enum Instrument {
+    /// Concert pitch (piano, flute, violin...)
+    #[value(alias("c"))]
+    Concert,
+    #[value(alias("trumpet"))]
+    BbTrumpet,
+    #[value(alias("clarinet"))]
+    BbClarinet,
+    SopranoSax,
+    AltoSax,
+    TenorSax,
+    #[value(alias("bari-sax"))]
+    BaritoneSax,
+    #[value(alias("horn"))]
+    FHorn,
+    EbClarinet,
+    BassClarinet,
+    Piccolo,
+    Guitar,
+    #[value(alias("bass"))]
+    DoubleBass,
+}
+
+/// How an instrument's part is written.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InstrumentDefinition {
+    pub name: String,
+    /// The concert note sounded by a written C (Bb on a Bb trumpet)
+    pub key: Note,
+    /// Semitones from the written to the sounding pitch (-2 on a Bb trumpet: a written D sounds C)
+    pub transposition: i16,
+    /// The lowest written note of the practical range
+    pub written_low: Pitch,
+    /// The highest written note of the practical range
+    pub written_high: Pitch,
+}
+
+impl Instrument {
+    /// Returns the transposition and written range of the instrument.
+    pub fn definition(&self) -> InstrumentDefinition {
+        let (name, transposition, low, high): (&str, i16, (Note, i8), (Note, i8)) = match self {
+            Instrument::Concert => ("Concert pitch", 0, (Note::A, 0), (Note::C, 8)),
+            Instrument::BbTrumpet => ("Bb Trumpet", -2, (Note::FSharp, 3), (Note::C, 6)),
+            Instrument::BbClarinet => ("Bb Clarinet", -2, (Note::E, 3), (Note::C, 7)),
+            Instrument::SopranoSax => ("Bb Soprano Sax", -2, (Note::ASharp, 3), (Note::FSharp, 6)),
+            Instrument::AltoSax => ("Eb Alto Sax", -9, (Note::ASharp, 3), (Note::FSharp, 6)),
+            Instrument::TenorSax => ("Bb Tenor Sax", -14, (Note::ASharp, 3), (Note::FSharp, 6)),
+            Instrument::BaritoneSax => ("Eb Baritone Sax", -21, (Note::A, 3), (Note::FSharp, 6)),
+            Instrument::FHorn => ("F Horn", -7, (Note::FSharp, 2), (Note::C, 6)),
+            Instrument::EbClarinet => ("Eb Clarinet", 3, (Note::E, 3), (Note::G, 6)),
+            Instrument::BassClarinet => ("Bb Bass Clarinet", -14, (Note::DSharp, 3), (Note::G, 6)),
+            Instrument::Piccolo => ("Piccolo", 12, (Note::D, 4), (Note::C, 7)),
+            Instrument::Guitar => ("Guitar", -12, (Note::E, 3), (Note::D, 7)),
+            Instrument::DoubleBass => ("Double Bass", -12, (Note::E, 2), (Note::G, 5)),
+        };
+        InstrumentDefinition {
+            name: name.to_string(),
+            key: Pitch::new(Note::C, 4).transpose(transposition).note,
+            transposition,
+            written_low: Pitch::new(low.0, low.1),
+            written_high: Pitch::new(high.0, high.1),
+        }
+    }
+}
+
+impl InstrumentDefinition {
+    /// Returns true if the written part is the concert pitch (or octaves away from it).
+    pub fn is_transposing(&self) -> bool {
+        self.transposition.rem_euclid(12) != 0
+    }
+
+    /// The written pitch that sounds the given concert pitch.
+    pub fn written_pitch(&self, concert: Pitch) -> Pitch {
+        concert.transpose(-self.transposition)
+    }
+
+    /// The concert pitch sounded by the given written pitch.
+    pub fn concert_pitch(&self, written: Pitch) -> Pitch {
+        written.transpose(self.transposition)
+    }
+
+    /// The written note (pitch class) of a concert note.
+    pub fn written_note(&self, concert: Note) -> Note {
+        self.written_pitch(Pitch::new(concert, 4)).note
+    }
+
+    pub fn written_notes(&self, concert: &[Note]) -> Vec<Note> {
+        concert
+            .iter()
+            .map(|n: &Note| self.written_note(*n))
+            .collect()
+    }
+
+    pub fn written_pitches(&self, concert: &[Pitch]) -> Vec<Pitch> {
+        concert
+            .iter()
+            .map(|p: &Pitch| self.written_pitch(*p))
+            .collect()
+    }
+
+    /// Returns true if the written pitch lies within the instrument's range.
+    pub fn in_range(&self, written: Pitch) -> bool {
+        self.written_low <= written && written <= self.written_high
+    }
+
+    /// The written pitches (of a written part) that the instrument cannot play.
+    pub fn out_of_range(&self, written: &[Pitch]) -> Vec<Pitch> {
+        written
+            .iter()
+            .filter(|p: &&Pitch| !self.in_range(**p))
+            .copied()
+            .collect()
+    }
+
+    /// Writes a progression for the instrument. Degrees and functions do not change.
+    pub fn written_progression(&self, concert: &[ProgressionChord]) -> Vec<ProgressionChord> {
+        concert
+            .iter()
+            .map(|c: &ProgressionChord| ProgressionChord {
+                degree: c.degree.clone(),
+                root_note: self.written_note(c.root_note),
+                chord_type: c.chord_type,
+                function: c.function,
+                notes: self.written_notes(&c.notes),
+            })
+            .collect()
+    }
+
+    /// Writes a harmonized scale for the instrument.
+    pub fn written_harmony(&self, concert: &[HarmonizedDegree]) -> Vec<HarmonizedDegree> {
+        concert
+            .iter()
+            .map(|d: &HarmonizedDegree| HarmonizedDegree {
+                degree: d.degree,
+                root_note: self.written_note(d.root_note),
+                chord_type: d.chord_type,
+                notes: self.written_notes(&d.notes),
+            })
+            .collect()
+    }
+
+    /// Writes voiced chords for the instrument, keeping their octaves.
+    pub fn written_voicing(&self, concert: &[VoicedChord]) -> Vec<VoicedChord> {
+        concert
+            .iter()
+            .map(|c: &VoicedChord| VoicedChord {
+                root_note: self.written_note(c.root_note),
+                pitches: self.written_pitches(&c.pitches),
+                ..c.clone()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voice_leading::VoiceLeadingOptions;
+    use crate::{ChordType, HarmonicFormula, build_chord, build_progression};
+
+    #[test]
+    fn test_instrument_definitions() {
+        let trumpet: InstrumentDefinition = Instrument::BbTrumpet.definition();
+        assert_eq!(trumpet.key, Note::ASharp);
+        assert!(trumpet.is_transposing());
+        assert_eq!(Instrument::AltoSax.definition().key, Note::DSharp);
+        assert_eq!(Instrument::FHorn.definition().key, Note::F);
+        assert_eq!(Instrument::TenorSax.definition().key, Note::ASharp);
+        assert_eq!(Instrument::Guitar.definition().key, Note::C);
+        assert!(!Instrument::Guitar.definition().is_transposing());
+        assert!(!Instrument::Concert.definition().is_transposing());
+    }
+
+    #[test]
+    fn test_instrument_written_pitches() {
+        // Concert Bb4 is a written C5 on trumpet, C6 on tenor sax and G5 on alto sax
+        let bb4: Pitch = Pitch::new(Note::ASharp, 4);
+        let trumpet: InstrumentDefinition = Instrument::BbTrumpet.definition();
+        assert_eq!(trumpet.written_pitch(bb4), Pitch::new(Note::C, 5));
+        assert_eq!(trumpet.concert_pitch(Pitch::new(Note::C, 5)), bb4);
+        assert_eq!(
+            Instrument::TenorSax.definition().written_pitch(bb4),
+            Pitch::new(Note::C, 6)
+        );
+        assert_eq!(
+            Instrument::AltoSax.definition().written_pitch(bb4),
+            Pitch::new(Note::G, 5)
+        );
+        assert_eq!(
+            Instrument::FHorn
+                .definition()
+                .written_notes(&build_chord(Note::C, ChordType::Major)),
+            [Note::G, Note::B, Note::D]
+        );
+
+        assert!(trumpet.in_range(Pitch::new(Note::C, 5)));
+        assert_eq!(
+            trumpet.out_of_range(&[Pitch::new(Note::E, 3), Pitch::new(Note::G, 4)]),
+            [Pitch::new(Note::E, 3)]
+        );
+    }
+
+    #[test]
+    fn test_instrument_written_progression() {
+        let concert: Vec<ProgressionChord> = build_progression(
+            Note::F,
+            HarmonicFormula::Circle,
+            &VoiceLeadingOptions::default(),
+        );
+        let written: Vec<ProgressionChord> = Instrument::BbClarinet
+            .definition()
+            .written_progression(&concert);
+        assert_eq!(written.len(), concert.len());
+        assert_eq!(written[0].root_note, Note::G);
+        assert_eq!(written[0].degree, concert[0].degree);
+        assert_eq!(written[0].notes, [Note::G, Note::B, Note::D]);
+        assert_eq!(written[3].root_note, Note::D);
+    }
+}
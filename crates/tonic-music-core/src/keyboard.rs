@@ -0,0 +1,267 @@
+/*
+ * tonic-music-core
+ * Copyright (C) 2025 Israel Alberto Roldan Vega
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * Repository: https://github.com/airvzxf/music-theory-calculator/
+ */
+
+/*
+ * crates/tonic-music-core/src/keyboard.rs
+ *
+ * This module draws piano keyboards (SVG) with highlighted keys
+ * and provides the standard scale fingerings.
+ */
+
+use crate::pitch::Pitch;
+use crate::{Note, ScaleType, build_scale};
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// The hand playing a scale.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum, Serialize)]
+#[clap(rename_all = "kebab-case")]
+pub enum Hand {
+    #[value(alias("rh"))]
+    Right,
+    #[value(alias("lh"))]
+    Left,
+}
+
+const WHITE_WIDTH: u32 = 24;
+const WHITE_HEIGHT: u32 = 120;
+const BLACK_WIDTH: u32 = 14;
+const BLACK_HEIGHT: u32 = 75;
+
+/// Returns true for the sharps/flats (the black keys).
+pub fn is_black_key(note: Note) -> bool {
+    matches!(note.as_u8(), 1 | 3 | 6 | 8 | 10)
+}
+
+/// The whole octaves (C to B) that hold all the pitches (at least one octave).
+pub fn keyboard_range(pitches: &[Pitch]) -> (Pitch, Pitch) {
+    let low: i8 = pitches.iter().map(|p: &Pitch| p.octave).min().unwrap_or(4);
+    let high: i8 = pitches.iter().map(|p: &Pitch| p.octave).max().unwrap_or(4);
+    (Pitch::new(Note::C, low), Pitch::new(Note::B, high))
+}
+
+/// One ascending octave of a scale from the root (root to root).
+pub fn scale_pitches(root: Note, scale_type: ScaleType, octave: i8) -> Vec<Pitch> {
+    let mut pitches: Vec<Pitch> = vec![Pitch::new(root, octave)];
+    for note in build_scale(root, scale_type)
+        .iter()
+        .skip(1)
+        .chain(std::iter::once(&root))
+    {
+        let below: Pitch = *pitches.last().unwrap();
+        let up: i16 = (note.as_u8() as i16 - below.note.as_u8() as i16).rem_euclid(12);
+        pitches.push(below.transpose(if up == 0 { 12 } else { up }));
+    }
+    pitches
+}
+
+/// The standard one-octave fingering of a major or minor scale (root to root,
+/// ascending; 1 = thumb). Natural and harmonic minor share their fingerings.
+pub fn scale_fingering(root: Note, scale_type: ScaleType, hand: Hand) -> Result<Vec<u8>, String> {
+    // Right hand, left hand
+    const GROUP_A: ([u8; 8], [u8; 8]) = ([1, 2, 3, 1, 2, 3, 4, 5], [5, 4, 3, 2, 1, 3, 2, 1]);
+    const GROUP_B: ([u8; 8], [u8; 8]) = ([1, 2, 3, 1, 2, 3, 4, 5], [4, 3, 2, 1, 4, 3, 2, 1]);
+    const GROUP_F: ([u8; 8], [u8; 8]) = ([1, 2, 3, 4, 1, 2, 3, 4], [5, 4, 3, 2, 1, 3, 2, 1]);
+
+    let (right, left): ([u8; 8], [u8; 8]) = match scale_type {
+        ScaleType::Major => match root.as_u8() {
+            0 | 2 | 4 | 7 | 9 => GROUP_A,
+            11 => GROUP_B,
+            5 => GROUP_F,
+            1 => ([2, 3, 1, 2, 3, 4, 1, 2], [3, 2, 1, 4, 3, 2, 1, 3]),
+            3 => ([3, 1, 2, 3, 4, 1, 2, 3], [3, 2, 1, 4, 3, 2, 1, 3]),
+            6 => ([2, 3, 4, 1, 2, 3, 1, 2], [4, 3, 2, 1, 3, 2, 1, 4]),
+            8 => ([3, 4, 1, 2, 3, 1, 2, 3], [3, 2, 1, 4, 3, 2, 1, 3]),
+            _ => ([4, 1, 2, 3, 1, 2, 3, 4], [3, 2, 1, 4, 3, 2, 1, 3]),
+        },
+        ScaleType::MinorNatural | ScaleType::MinorHarmonic => match root.as_u8() {
+            0 | 2 | 4 | 7 | 9 => GROUP_A,
+            11 => GROUP_B,
+            5 => GROUP_F,
+            1 => ([3, 4, 1, 2, 3, 1, 2, 3], [3, 2, 1, 4, 3, 2, 1, 3]),
+            3 => ([3, 1, 2, 3, 4, 1, 2, 3], [2, 1, 4, 3, 2, 1, 3, 2]),
+            6 => ([3, 4, 1, 2, 3, 1, 2, 3], [4, 3, 2, 1, 3, 2, 1, 4]),
+            8 => ([3, 4, 1, 2, 3, 1, 2, 3], [3, 2, 1, 3, 2, 1, 3, 2]),
+            _ => ([2, 1, 2, 3, 1, 2, 3, 4], [2, 1, 3, 2, 1, 4, 3, 2]),
+        },
+        _ => {
+            return Err(format!(
+                "No standard fingering for {:?} scales (major and minor only)",
+                scale_type
+            ));
+        }
+    };
+
+    Ok(match hand {
+        Hand::Right => right.to_vec(),
+        Hand::Left => left.to_vec(),
+    })
+}
+
+/// Draws the keyboard as SVG, highlighting the given pitches. `labels` (one per
+/// highlighted pitch, e.g. finger numbers) are written on their keys.
+pub fn render_svg(highlighted: &[Pitch], labels: Option<&[String]>) -> String {
+    let (low, high): (Pitch, Pitch) = keyboard_range(highlighted);
+    let label_of = |pitch: Pitch| -> Option<&str> {
+        let index: usize = highlighted.iter().position(|p: &Pitch| *p == pitch)?;
+        labels
+            .and_then(|l: &[String]| l.get(index))
+            .map(|s: &String| s.as_str())
+    };
+
+    // 1. The white keys, then the black keys on top of them
+    let mut white: Vec<String> = Vec::<String>::new();
+    let mut black: Vec<String> = Vec::<String>::new();
+    let mut x: u32 = 0;
+    for midi in low.midi()..=high.midi() {
+        let pitch: Pitch = Pitch::from_midi(midi);
+        let on: bool = highlighted.contains(&pitch);
+        let label: Option<&str> = label_of(pitch);
+        if is_black_key(pitch.note) {
+            let left: u32 = x - BLACK_WIDTH / 2;
+            black.push(format!(
+                r##"<rect x="{}" y="0" width="{}" height="{}" fill="{}" stroke="#000"><title>{}</title></rect>"##,
+                left,
+                BLACK_WIDTH,
+                BLACK_HEIGHT,
+                if on { "#2c6fb7" } else { "#000" },
+                pitch
+            ));
+            if let Some(label) = label {
+                black.push(format!(
+                    r##"<text x="{}" y="{}" font-size="10" text-anchor="middle" fill="#fff">{}</text>"##,
+                    x,
+                    BLACK_HEIGHT - 8,
+                    label
+                ));
+            }
+        } else {
+            white.push(format!(
+                r##"<rect x="{}" y="0" width="{}" height="{}" fill="{}" stroke="#000"><title>{}</title></rect>"##,
+                x,
+                WHITE_WIDTH,
+                WHITE_HEIGHT,
+                if on { "#4a90d9" } else { "#fff" },
+                pitch
+            ));
+            if let Some(label) = label {
+                white.push(format!(
+                    r##"<text x="{}" y="{}" font-size="12" text-anchor="middle">{}</text>"##,
+                    x + WHITE_WIDTH / 2,
+                    WHITE_HEIGHT - 10,
+                    label
+                ));
+            }
+            x += WHITE_WIDTH;
+        }
+    }
+
+    // 2. Label each C with its octave
+    let mut names: Vec<String> = Vec::<String>::new();
+    for octave in low.octave..=high.octave {
+        names.push(format!(
+            r##"<text x="{}" y="{}" font-size="10" text-anchor="middle">C{}</text>"##,
+            (octave - low.octave) as u32 * 7 * WHITE_WIDTH + WHITE_WIDTH / 2,
+            WHITE_HEIGHT + 14,
+            octave
+        ));
+    }
+
+    format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">{}{}{}</svg>"#,
+        white.join(""),
+        black.join(""),
+        names.join(""),
+        w = x,
+        h = WHITE_HEIGHT + 20
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keyboard_scale_pitches_and_range() {
+        let d: Vec<Pitch> = scale_pitches(Note::D, ScaleType::Major, 4);
+        assert_eq!(d.len(), 8);
+        assert_eq!(d[0], Pitch::new(Note::D, 4));
+        assert_eq!(d[6], Pitch::new(Note::CSharp, 5));
+        assert_eq!(d[7], Pitch::new(Note::D, 5));
+        assert_eq!(
+            keyboard_range(&d),
+            (Pitch::new(Note::C, 4), Pitch::new(Note::B, 5))
+        );
+    }
+
+    #[test]
+    fn test_keyboard_fingerings_keep_thumbs_on_white_keys() {
+        for root in 0..12 {
+            let root: Note = Note::from_u8(root);
+            for scale_type in [
+                ScaleType::Major,
+                ScaleType::MinorNatural,
+                ScaleType::MinorHarmonic,
+            ] {
+                let pitches: Vec<Pitch> = scale_pitches(root, scale_type, 4);
+                for hand in [Hand::Right, Hand::Left] {
+                    let fingers: Vec<u8> = scale_fingering(root, scale_type, hand).unwrap();
+                    assert_eq!(fingers.len(), pitches.len());
+                    for (pitch, finger) in pitches.iter().zip(&fingers) {
+                        assert!(
+                            *finger != 1 || !is_black_key(pitch.note),
+                            "{:?} {:?} {:?}: thumb on {}",
+                            root,
+                            scale_type,
+                            hand,
+                            pitch
+                        );
+                    }
+                }
+            }
+        }
+        assert_eq!(
+            scale_fingering(Note::C, ScaleType::Major, Hand::Right).unwrap(),
+            [1, 2, 3, 1, 2, 3, 4, 5]
+        );
+        assert!(scale_fingering(Note::C, ScaleType::PentatonicMajor, Hand::Right).is_err());
+    }
+
+    #[test]
+    fn test_keyboard_svg() {
+        let chord: Vec<Pitch> = vec![
+            Pitch::new(Note::C, 4),
+            Pitch::new(Note::E, 4),
+            Pitch::new(Note::G, 4),
+            Pitch::new(Note::ASharp, 4),
+        ];
+        let svg: String = render_svg(&chord, None);
+        assert!(svg.starts_with("<svg"));
+        // One octave: 7 white and 5 black keys, 4 of them highlighted
+        assert_eq!(svg.matches("<rect").count(), 12);
+        assert_eq!(svg.matches("#4a90d9").count(), 3);
+        assert_eq!(svg.matches("#2c6fb7").count(), 1);
+        assert!(svg.contains(">C4</text>"));
+
+        let labels: Vec<String> = vec!["1".to_string(), "2".to_string()];
+        let fingered: String = render_svg(&chord[..2], Some(&labels));
+        assert_eq!(fingered.matches("</text>").count(), 3);
+    }
+}
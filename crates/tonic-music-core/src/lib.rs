@@ -28,12 +28,37 @@
 use clap::ValueEnum;
 use serde::Serialize;
 
+pub mod abc;
+pub mod analysis;
+pub mod chordpro;
+pub mod chroma;
+pub mod fingering;
+pub mod fretboard;
+pub mod generator;
+pub mod instrument;
+pub mod keyboard;
+pub mod library;
+pub mod lilypond;
+pub mod midi;
+pub mod musicxml;
+pub mod notation;
 pub mod parser;
+pub mod part_writing;
+pub mod pitch;
+pub mod recognition;
+pub mod reharmonize;
+pub mod scale_shapes;
+pub mod synth;
+pub mod tuner;
+pub mod voice_leading;
+pub mod voicing;
+pub mod wav;
 
 // 'derive' gives us "free" functionality for this enum.
 // Copy/Clone: Lets us easily copy the note (e.g., let note2 = note1;)
 // PartialEq/Eq: Lets us compare them (e.g., if note1 == Note::C { ... })
-#[derive(Copy, Clone, PartialEq, Eq, Serialize)]
+// Hash: Lets us use them in sets and as map keys
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Serialize)]
 pub enum Note {
     C,
     #[serde(rename = "C#")]
@@ -270,7 +295,7 @@ pub fn build_scale(root: Note, scale_type: ScaleType) -> Vec<Note> {
 }
 
 /// Represents different types of chords (triads for now).
-#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum, Serialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, ValueEnum, Serialize)]
 #[clap(rename_all = "kebab-case")]
 pub enum ChordType {
     #[value(alias("maj"))]
@@ -349,7 +374,7 @@ impl ChordType {
                 Interval::Unison,     // 1
                 Interval::MinorThird, // b3
                 Interval::Tritone,    // b5
-                Interval::MinorSixth, // bb7 (o 6)
+                Interval::MajorSixth, // bb7: 9 semitones, the same pitch as a 6
             ],
             ChordType::MinorMajor7 => &[
                 Interval::Unison,       // 1
@@ -454,6 +479,18 @@ pub enum HarmonicFormula {
     MinorBlock,
 }
 
+impl HarmonicFormula {
+    /// Returns the name of this formula's entry in the progression library.
+    pub fn library_name(&self) -> &'static str {
+        match self {
+            HarmonicFormula::Block => "block",
+            HarmonicFormula::Circle => "circle",
+            HarmonicFormula::Guajira => "guajira",
+            HarmonicFormula::MinorBlock => "minor-block",
+        }
+    }
+}
+
 /// Represents a single chord within a progression
 #[derive(Debug, PartialEq, Eq, Serialize)]
 pub struct ProgressionChord {
@@ -462,6 +499,8 @@ pub struct ProgressionChord {
     /// The specific chord (e.g., "Cmaj", "G7")
     pub root_note: Note,
     pub chord_type: ChordType,
+    /// The harmonic function relative to the key center (T, S, D...)
+    pub function: HarmonicFunction,
     /// The notes of the chord
     pub notes: Vec<Note>,
 }
@@ -526,23 +565,40 @@ pub fn harmonize_scale(scale: &[Note], build_sevenths: bool) -> Vec<HarmonizedDe
     harmonized_scale
 }
 
+use analysis::{HarmonicFunction, harmonic_function};
+use library::{ProgressionDefinition, ProgressionLibrary};
 use parser::ParsedRomanChord;
+use voice_leading::VoiceLeadingOptions;
 
 /// Builds a chord progression from a root note and a formula.
-pub fn build_progression(root: Note, formula: HarmonicFormula) -> Vec<ProgressionChord> {
-    // 1. Get the sequence of (degree, root, type) from the formula-specific function
-    let chord_specs: Vec<(String, Note, ChordType)> = match formula {
-        HarmonicFormula::Block => get_block_progression_spec(root),
-        HarmonicFormula::Circle => get_circle_progression_spec(root),
-        HarmonicFormula::Guajira => get_guajira_progression_spec(root),
-        HarmonicFormula::MinorBlock => get_minor_block_progression_spec(root),
-    };
-
-    solve_voice_leading(root, chord_specs)
+pub fn build_progression(
+    root: Note,
+    formula: HarmonicFormula,
+    options: &VoiceLeadingOptions,
+) -> Vec<ProgressionChord> {
+    // 1. Look the formula up in the built-in progression library
+    let library: ProgressionLibrary = ProgressionLibrary::builtin();
+    let definition: &ProgressionDefinition = library
+        .find(formula.library_name())
+        .expect("Every HarmonicFormula has a library entry");
+
+    // 2. Parse its roman numerals and apply voice leading
+    definition
+        .build(root, options)
+        .expect("Built-in library formulas are valid")
 }
 
 /// Builds a custom chord progression from a root note and a list of parsed chord specs.
-pub fn build_custom_progression(root: Note, specs: Vec<ParsedRomanChord>) -> Vec<ProgressionChord> {
+/// Chords with a slash bass are inverted to put it lowest.
+pub fn build_custom_progression(
+    root: Note,
+    specs: Vec<ParsedRomanChord>,
+    options: &VoiceLeadingOptions,
+) -> Vec<ProgressionChord> {
+    let basses: Vec<Option<Note>> = specs
+        .iter()
+        .map(|spec: &ParsedRomanChord| spec.bass.map(|i: Interval| transpose(root, i)))
+        .collect();
     let chord_specs: Vec<(String, Note, ChordType)> = specs
         .into_iter()
         .map(|spec: ParsedRomanChord| {
@@ -554,16 +610,31 @@ pub fn build_custom_progression(root: Note, specs: Vec<ParsedRomanChord>) -> Vec
         })
         .collect();
 
-    solve_voice_leading(root, chord_specs)
+    // A slash bass that belongs to the chord sets its inversion ("1/3")
+    let mut progression: Vec<ProgressionChord> = solve_voice_leading(root, chord_specs, options);
+    for (chord, bass) in progression.iter_mut().zip(basses) {
+        if let Some(i) = bass.and_then(|b: Note| chord.notes.iter().position(|n: &Note| *n == b)) {
+            chord.notes.rotate_left(i);
+        }
+    }
+    progression
+}
+
+/// Shortest signed motion in semitones from one note to another (-5 to +6).
+fn signed_motion(from: Note, to: Note) -> i8 {
+    let up: i8 = ((to.as_u8() + 12 - from.as_u8()) % 12) as i8;
+    if up > 6 { up - 12 } else { up }
 }
 
 /// Helper to apply voice leading logic to a sequence of chords.
 fn solve_voice_leading(
     key_center: Note,
     chord_specs: Vec<(String, Note, ChordType)>,
+    options: &VoiceLeadingOptions,
 ) -> Vec<ProgressionChord> {
     let mut progression: Vec<ProgressionChord> = Vec::<ProgressionChord>::new();
     let mut previous_bass_note: Note = key_center;
+    let mut previous_notes: Option<Vec<Note>> = None;
 
     // 2. Loop through ALL chords and apply voice leading
     // Note: We include the first chord in this logic so it can start in an inversion
@@ -576,30 +647,50 @@ fn solve_voice_leading(
         let root_chord: Vec<Note> = build_chord(*chord_root, *chord_type);
         let inversions: Vec<Vec<Note>> = get_inversions(&root_chord);
 
+        // Score every inversion with the configured weights (lower is better)
+        let cost = |inversion_index: usize, inversion: &Vec<Note>| -> i64 {
+            let bass: Note = *inversion.first().unwrap();
+            let mut cost: i64 = 0;
+
+            cost += options.bass_motion as i64 * semitone_distance(bass, previous_bass_note) as i64;
+            cost += options.range as i64 * semitone_distance(bass, key_center) as i64;
+            cost += options.inversion as i64
+                * inversion_index.abs_diff(options.preferred_inversion) as i64;
+
+            if let Some(previous) = &previous_notes {
+                let common: usize = previous
+                    .iter()
+                    .zip(inversion)
+                    .filter(|(a, b): &(&Note, &Note)| a == b)
+                    .count();
+                cost -= options.common_tones as i64 * common as i64;
+
+                let bass_motion: i8 = signed_motion(previous_bass_note, bass);
+                let top_motion: i8 =
+                    signed_motion(*previous.last().unwrap(), *inversion.last().unwrap());
+                if bass_motion.signum() * top_motion.signum() < 0 {
+                    cost -= options.contrary_motion as i64;
+                }
+            }
+
+            cost
+        };
+
         let best_inversion: Vec<Note> = inversions
             .into_iter()
-            .min_by(|inv_a: &Vec<Note>, inv_b: &Vec<Note>| {
-                let bass_a: &Note = inv_a.first().unwrap();
-                let bass_b: &Note = inv_b.first().unwrap();
-
-                let dist_a_root: u8 = semitone_distance(*bass_a, key_center);
-                let dist_b_root: u8 = semitone_distance(*bass_b, key_center);
-
-                let dist_a_prev: u8 = semitone_distance(*bass_a, previous_bass_note);
-                let dist_b_prev: u8 = semitone_distance(*bass_b, previous_bass_note);
-
-                // PRIORITIZE SMOOTHNESS (dist to previous bass) over CENTERING (dist to tonic)
-                // This creates better voice leading lines (e.g., walking bass).
-                (dist_a_prev, dist_a_root).cmp(&(dist_b_prev, dist_b_root))
-            })
+            .enumerate()
+            .min_by_key(|(i, inversion): &(usize, Vec<Note>)| cost(*i, inversion))
+            .map(|(_, inversion): (usize, Vec<Note>)| inversion)
             .unwrap_or(root_chord); // Fallback
 
         previous_bass_note = *best_inversion.first().unwrap();
+        previous_notes = Some(best_inversion.clone());
 
         progression.push(ProgressionChord {
             degree: degree.clone(),
             root_note: *chord_root,
             chord_type: *chord_type,
+            function: harmonic_function(key_center, *chord_root, *chord_type),
             notes: best_inversion,
         });
     }
@@ -607,89 +698,6 @@ fn solve_voice_leading(
     progression
 }
 
-/// Returns the chord specifications for the I-V7-I7-IV "Block" (Blues) progression.
-fn get_block_progression_spec(root: Note) -> Vec<(String, Note, ChordType)> {
-    vec![
-        ("I".to_string(), root, ChordType::Major),
-        (
-            "V7".to_string(),
-            transpose(root, Interval::PerfectFifth),
-            ChordType::Dominant7,
-        ),
-        ("I7".to_string(), root, ChordType::Dominant7),
-        (
-            "IV".to_string(),
-            transpose(root, Interval::PerfectFourth),
-            ChordType::Major,
-        ),
-    ]
-}
-
-/// Returns the chord specifications for the I-vi-ii-V7 "Circle" progression.
-fn get_circle_progression_spec(root: Note) -> Vec<(String, Note, ChordType)> {
-    vec![
-        ("I".to_string(), root, ChordType::Major),
-        (
-            "vi".to_string(),
-            transpose(root, Interval::MajorSixth),
-            ChordType::Minor,
-        ),
-        (
-            "ii".to_string(),
-            transpose(root, Interval::MajorSecond),
-            ChordType::Minor,
-        ),
-        (
-            "V7".to_string(),
-            transpose(root, Interval::PerfectFifth),
-            ChordType::Dominant7,
-        ),
-    ]
-}
-
-/// Returns the chord specifications for the I-IV-V7 "Guajira" progression.
-fn get_guajira_progression_spec(root: Note) -> Vec<(String, Note, ChordType)> {
-    vec![
-        ("I".to_string(), root, ChordType::Major),
-        (
-            "IV".to_string(),
-            transpose(root, Interval::PerfectFourth),
-            ChordType::Major,
-        ),
-        (
-            "V7".to_string(),
-            transpose(root, Interval::PerfectFifth),
-            ChordType::Dominant7,
-        ),
-    ]
-}
-
-/// Returns the chord specifications for the vi-VI7-ii-III7 "Relative Minor Block" progression.
-fn get_minor_block_progression_spec(root: Note) -> Vec<(String, Note, ChordType)> {
-    vec![
-        (
-            "vi".to_string(),
-            transpose(root, Interval::MajorSixth),
-            ChordType::Minor,
-        ),
-        (
-            "VI7".to_string(),
-            transpose(root, Interval::MajorSixth),
-            ChordType::Dominant7,
-        ),
-        (
-            "ii".to_string(),
-            transpose(root, Interval::MajorSecond),
-            ChordType::Minor,
-        ),
-        (
-            "III7".to_string(),
-            transpose(root, Interval::MajorThird),
-            ChordType::Dominant7,
-        ),
-    ]
-}
-
 #[cfg(test)]
 mod tests {
     // We import everything from the parent module (our lib.rs)
@@ -847,6 +855,18 @@ mod tests {
         assert_eq!(chord, expected);
     }
 
+    #[test]
+    fn test_lib_build_chord_b_diminished_seventh() {
+        // Stacked minor thirds: the bb7 is 9 semitones above the root, not 8
+        let chord: Vec<Note> = build_chord(Note::B, ChordType::Diminished7);
+        let expected: Vec<Note> = vec![Note::B, Note::D, Note::F, Note::GSharp];
+        assert_eq!(chord, expected);
+        assert_eq!(
+            ChordType::from_intervals(3, 6, Some(9)),
+            ChordType::Diminished7
+        );
+    }
+
     #[test]
     fn test_lib_harmonize_c_major_sevenths() {
         let scale: Vec<Note> = build_scale(Note::C, ScaleType::Major);
@@ -912,8 +932,11 @@ mod tests {
 
     #[test]
     fn test_lib_build_block_progression_fsharp() {
-        let progression: Vec<ProgressionChord> =
-            build_progression(Note::FSharp, HarmonicFormula::Block);
+        let progression: Vec<ProgressionChord> = build_progression(
+            Note::FSharp,
+            HarmonicFormula::Block,
+            &VoiceLeadingOptions::default(),
+        );
 
         let roots: Vec<Note> = progression
             .iter()
@@ -962,8 +985,11 @@ mod tests {
 
     #[test]
     fn test_lib_build_circle_progression_c_major() {
-        let progression: Vec<ProgressionChord> =
-            build_progression(Note::C, HarmonicFormula::Circle);
+        let progression: Vec<ProgressionChord> = build_progression(
+            Note::C,
+            HarmonicFormula::Circle,
+            &VoiceLeadingOptions::default(),
+        );
 
         // Check the roots: C, A, D, G
         let roots: Vec<Note> = progression
@@ -1001,8 +1027,11 @@ mod tests {
 
     #[test]
     fn test_lib_build_guajira_progression_c() {
-        let progression: Vec<ProgressionChord> =
-            build_progression(Note::C, HarmonicFormula::Guajira);
+        let progression: Vec<ProgressionChord> = build_progression(
+            Note::C,
+            HarmonicFormula::Guajira,
+            &VoiceLeadingOptions::default(),
+        );
 
         // I, IV, V7
         let roots: Vec<Note> = progression
@@ -1023,8 +1052,11 @@ mod tests {
 
     #[test]
     fn test_lib_build_minor_block_progression_c() {
-        let progression: Vec<ProgressionChord> =
-            build_progression(Note::C, HarmonicFormula::MinorBlock);
+        let progression: Vec<ProgressionChord> = build_progression(
+            Note::C,
+            HarmonicFormula::MinorBlock,
+            &VoiceLeadingOptions::default(),
+        );
 
         // vi, VI7, ii, III7
         // C Major -> A, A, D, E
@@ -0,0 +1,598 @@
+/*
+ * tonic-music-core
+ * Copyright (C) 2025 Israel Alberto Roldan Vega
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * Repository: https://github.com/airvzxf/music-theory-calculator/
+ */
+
+/*
+ * crates/tonic-music-core/src/library.rs
+ *
+ * This module holds the library of named progressions.
+ * Every progression is plain data: a Roman numeral formula
+ * plus genre tags, parsed on demand with `parse_roman_chord`.
+ * User libraries can be loaded from TOML or JSON files.
+ */
+
+use crate::parser::{ParsedRomanChord, parse_note, parse_progression_formula};
+use crate::voice_leading::VoiceLeadingOptions;
+use crate::{HarmonicFormula, Note, ProgressionChord, ScaleType, build_custom_progression};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// The built-in progressions: (name, formula, tags, description).
+/// The first four entries back the `HarmonicFormula` enum.
+const BUILTIN_PROGRESSIONS: &[(&str, &str, &[&str], &str)] = &[
+    (
+        "block",
+        "I-V7-I7-IV",
+        &["blues", "latin"],
+        "A 12-bar blues-style block progression",
+    ),
+    (
+        "circle",
+        "I-vi-ii-V7",
+        &["pop", "jazz"],
+        "The diatonic I-vi-ii-V7 circle progression",
+    ),
+    (
+        "guajira",
+        "I-IV-V7",
+        &["latin", "folk"],
+        "The I-IV-V7 progression common in Guajira music",
+    ),
+    (
+        "minor-block",
+        "vi-VI7-ii-III7",
+        &["latin"],
+        "A relative minor block",
+    ),
+    (
+        "ii-v-i-major",
+        "ii7-V7-Imaj7",
+        &["jazz"],
+        "The major ii-V-I cadence",
+    ),
+    (
+        "ii-v-i-minor",
+        "iim7b5-V7-i",
+        &["jazz"],
+        "The minor ii-V-i cadence",
+    ),
+    (
+        "twelve-bar-blues",
+        "I7-I7-I7-I7-IV7-IV7-I7-I7-V7-IV7-I7-V7",
+        &["blues", "rock"],
+        "The standard 12-bar blues, one chord per bar",
+    ),
+    (
+        "minor-blues",
+        "i7-i7-i7-i7-iv7-iv7-i7-i7-bVI7-V7-i7-V7",
+        &["blues", "jazz"],
+        "A 12-bar minor blues",
+    ),
+    (
+        "jazz-blues",
+        "I7-IV7-I7-v7-I7-IV7-#ivdim7-I7-VI7-ii7-V7-I7-VI7-ii7-V7",
+        &["jazz", "blues"],
+        "The 12-bar jazz blues with ii-V turnarounds",
+    ),
+    (
+        "andalusian",
+        "i-bVII-bVI-V",
+        &["flamenco", "classical"],
+        "The Andalusian cadence (descending tetrachord in minor)",
+    ),
+    (
+        "pachelbel",
+        "I-V-vi-iii-IV-I-IV-V",
+        &["classical", "pop"],
+        "The chord sequence of Pachelbel's Canon in D",
+    ),
+    (
+        "axis",
+        "I-V-vi-IV",
+        &["pop", "rock"],
+        "The I-V-vi-IV \"four chords\" pop progression",
+    ),
+    (
+        "sensitive",
+        "vi-IV-I-V",
+        &["pop"],
+        "The vi-IV-I-V rotation of the axis progression",
+    ),
+    (
+        "doo-wop",
+        "I-vi-IV-V",
+        &["pop", "doo-wop"],
+        "The 50s doo-wop progression",
+    ),
+    (
+        "royal-road",
+        "IVmaj7-V7-iii7-vi",
+        &["pop", "j-pop"],
+        "The Royal Road progression, a staple of Japanese pop",
+    ),
+    (
+        "rhythm-changes-a",
+        "I-VI7-ii7-V7-I-VI7-ii7-V7-I-I7-IV-#ivdim7-I-VI7-ii7-V7",
+        &["jazz"],
+        "The A section of Gershwin's \"I Got Rhythm\" changes",
+    ),
+    (
+        "montgomery-ward",
+        "I7-IV-II7-V7",
+        &["jazz"],
+        "The Montgomery-Ward bridge",
+    ),
+    (
+        "ragtime",
+        "III7-VI7-II7-V7-I",
+        &["jazz", "ragtime"],
+        "A chain of secondary dominants resolving home",
+    ),
+    (
+        "autumn-leaves",
+        "ii7-V7-Imaj7-IVmaj7-viim7b5-III7-vi",
+        &["jazz"],
+        "The relative major / minor ii-V cycle of Autumn Leaves",
+    ),
+    (
+        "coltrane-changes",
+        "Imaj7-bIII7-bVImaj7-VII7-IIImaj7-V7-Imaj7",
+        &["jazz"],
+        "John Coltrane's major-thirds cycle (Giant Steps)",
+    ),
+    (
+        "mixolydian-vamp",
+        "I-bVII-IV-I",
+        &["rock"],
+        "The bVII-IV rock vamp",
+    ),
+    (
+        "minor-plagal",
+        "I-IV-iv-I",
+        &["pop", "classical"],
+        "A plagal cadence borrowing iv from the parallel minor",
+    ),
+];
+
+/// A named progression: a Roman numeral formula plus search tags.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProgressionDefinition {
+    /// The kebab-case identifier (e.g., "ii-v-i-major")
+    pub name: String,
+    /// The Roman numeral formula (e.g., "ii7-V7-Imaj7")
+    pub formula: String,
+    /// Genre / style tags (e.g., "jazz", "blues")
+    pub tags: Vec<String>,
+    pub description: String,
+    /// The default key, used when no root note is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<Note>,
+    /// The mode the formula is written in (e.g., major, minor): the key of notation output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<ScaleType>,
+}
+
+impl ProgressionDefinition {
+    /// Returns true if the progression carries `tag` (case-insensitive).
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags
+            .iter()
+            .any(|t: &String| t.eq_ignore_ascii_case(tag))
+    }
+
+    /// Parses the formula into chord specifications.
+    pub fn specs(&self) -> Result<Vec<ParsedRomanChord>, String> {
+        parse_progression_formula(&self.formula)
+    }
+
+    /// Builds the voice-led progression for the given key.
+    pub fn build(
+        &self,
+        root: Note,
+        options: &VoiceLeadingOptions,
+    ) -> Result<Vec<ProgressionChord>, String> {
+        Ok(build_custom_progression(root, self.specs()?, options))
+    }
+}
+
+/// One `[[progression]]` entry of a user library file, before validation.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProgressionFileEntry {
+    name: String,
+    formula: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    description: String,
+    key: Option<String>,
+    mode: Option<String>,
+}
+
+/// The top level of a user library file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProgressionFile {
+    #[serde(alias = "progressions")]
+    progression: Vec<ProgressionFileEntry>,
+}
+
+/// The file formats a user library can be written in.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LibraryFormat {
+    Toml,
+    Json,
+}
+
+impl LibraryFormat {
+    /// Picks the format from a file extension (".toml" or ".json").
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension: String = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "toml" => Some(LibraryFormat::Toml),
+            "json" => Some(LibraryFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+impl ProgressionFileEntry {
+    /// Validates the entry: the formula must parse with `parse_roman_chord`,
+    /// and the key and mode (if present) must be valid names.
+    fn validate(self) -> Result<ProgressionDefinition, String> {
+        let name: String = self.name.trim().to_string();
+        if name.is_empty() {
+            return Err("Progression with an empty name".to_string());
+        }
+
+        parse_progression_formula(&self.formula)
+            .map_err(|e: String| format!("Progression '{}': {}", name, e))?;
+
+        let key: Option<Note> = self
+            .key
+            .as_deref()
+            .map(parse_note)
+            .transpose()
+            .map_err(|e: String| format!("Progression '{}': {}", name, e))?;
+
+        let mode: Option<ScaleType> = self
+            .mode
+            .as_deref()
+            .map(|m: &str| {
+                ScaleType::from_str(m, true)
+                    .map_err(|_| format!("Progression '{}': Invalid mode: {}", name, m))
+            })
+            .transpose()?;
+
+        Ok(ProgressionDefinition {
+            name,
+            formula: self.formula,
+            tags: self.tags,
+            description: self.description,
+            key,
+            mode,
+        })
+    }
+}
+
+/// A searchable collection of named progressions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProgressionLibrary {
+    pub definitions: Vec<ProgressionDefinition>,
+}
+
+impl ProgressionLibrary {
+    /// Returns the library shipped with tonic-music.
+    pub fn builtin() -> Self {
+        let definitions: Vec<ProgressionDefinition> = BUILTIN_PROGRESSIONS
+            .iter()
+            .map(
+                |(name, formula, tags, description): &(&str, &str, &[&str], &str)| {
+                    ProgressionDefinition {
+                        name: name.to_string(),
+                        formula: formula.to_string(),
+                        tags: tags.iter().map(|t: &&str| t.to_string()).collect(),
+                        description: description.to_string(),
+                        key: None,
+                        mode: None,
+                    }
+                },
+            )
+            .collect();
+
+        ProgressionLibrary { definitions }
+    }
+
+    /// Parses and validates a user library.
+    ///
+    /// The file holds a list of `progression` entries (also accepted as
+    /// `progressions`), each with a `name` and a Roman numeral `formula`,
+    /// plus optional `tags`, `description`, `key` and `mode`:
+    ///
+    /// ```toml
+    /// [[progression]]
+    /// name = "our-verse"
+    /// formula = "I-vi-IV-V7"
+    /// tags = ["band"]
+    /// key = "G"
+    /// ```
+    pub fn parse(contents: &str, format: LibraryFormat) -> Result<Self, String> {
+        let file: ProgressionFile = match format {
+            LibraryFormat::Toml => toml::from_str(contents).map_err(|e| e.to_string())?,
+            LibraryFormat::Json => serde_json::from_str(contents).map_err(|e| e.to_string())?,
+        };
+
+        let mut library: ProgressionLibrary = ProgressionLibrary {
+            definitions: Vec::<ProgressionDefinition>::new(),
+        };
+        for entry in file.progression {
+            let definition: ProgressionDefinition = entry.validate()?;
+            if library.find(&definition.name).is_some() {
+                return Err(format!("Duplicate progression name: {}", definition.name));
+            }
+            library.definitions.push(definition);
+        }
+
+        Ok(library)
+    }
+
+    /// Reads a user library from a `.toml` or `.json` file.
+    pub fn load_file(path: &Path) -> Result<Self, String> {
+        let format: LibraryFormat = LibraryFormat::from_path(path).ok_or_else(|| {
+            format!(
+                "Unsupported library file (expected .toml or .json): {}",
+                path.display()
+            )
+        })?;
+        let contents: String = std::fs::read_to_string(path)
+            .map_err(|e: std::io::Error| format!("{}: {}", path.display(), e))?;
+
+        Self::parse(&contents, format).map_err(|e: String| format!("{}: {}", path.display(), e))
+    }
+
+    /// Adds the progressions of `other` to this library.
+    /// A progression with the same name as an existing one replaces it.
+    pub fn merge(&mut self, other: ProgressionLibrary) {
+        for definition in other.definitions {
+            let existing: Option<&mut ProgressionDefinition> =
+                self.definitions
+                    .iter_mut()
+                    .find(|d: &&mut ProgressionDefinition| {
+                        d.name.eq_ignore_ascii_case(&definition.name)
+                    });
+            match existing {
+                Some(slot) => *slot = definition,
+                None => self.definitions.push(definition),
+            }
+        }
+    }
+
+    /// Finds a progression by name (case-insensitive).
+    /// The `HarmonicFormula` aliases (e.g. "blues") are accepted as well.
+    pub fn find(&self, name: &str) -> Option<&ProgressionDefinition> {
+        let by_name = |name: &str| -> Option<&ProgressionDefinition> {
+            self.definitions
+                .iter()
+                .find(|d: &&ProgressionDefinition| d.name.eq_ignore_ascii_case(name))
+        };
+
+        by_name(name).or_else(|| {
+            HarmonicFormula::from_str(name, true)
+                .ok()
+                .and_then(|f: HarmonicFormula| by_name(f.library_name()))
+        })
+    }
+
+    /// Returns every progression carrying `tag`.
+    pub fn with_tag(&self, tag: &str) -> Vec<&ProgressionDefinition> {
+        self.definitions
+            .iter()
+            .filter(|d: &&ProgressionDefinition| d.has_tag(tag))
+            .collect()
+    }
+
+    /// Returns the sorted, de-duplicated list of tags in the library.
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self
+            .definitions
+            .iter()
+            .flat_map(|d: &ProgressionDefinition| d.tags.iter().cloned())
+            .collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChordType, build_progression};
+
+    #[test]
+    fn test_library_builtin_formulas_parse() {
+        let library: ProgressionLibrary = ProgressionLibrary::builtin();
+        for definition in &library.definitions {
+            let definition: &ProgressionDefinition = definition;
+            assert!(
+                definition.specs().is_ok(),
+                "{} has an invalid formula: {}",
+                definition.name,
+                definition.formula
+            );
+            assert!(
+                !definition.tags.is_empty(),
+                "{} has no tags",
+                definition.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_library_find_by_name_and_alias() {
+        let library: ProgressionLibrary = ProgressionLibrary::builtin();
+        assert_eq!(library.find("Circle").unwrap().formula, "I-vi-ii-V7");
+        // "blues" is the legacy alias of the Block formula
+        assert_eq!(library.find("blues").unwrap().name, "block");
+        assert!(library.find("not-a-progression").is_none());
+    }
+
+    #[test]
+    fn test_library_with_tag() {
+        let library: ProgressionLibrary = ProgressionLibrary::builtin();
+        let jazz: Vec<&ProgressionDefinition> = library.with_tag("JAZZ");
+        assert!(
+            jazz.iter()
+                .any(|d: &&ProgressionDefinition| d.name == "ii-v-i-major")
+        );
+        assert!(
+            jazz.iter()
+                .all(|d: &&ProgressionDefinition| d.has_tag("jazz"))
+        );
+        assert!(library.tags().contains(&"flamenco".to_string()));
+    }
+
+    #[test]
+    fn test_library_harmonic_formulas_match_library() {
+        let library: ProgressionLibrary = ProgressionLibrary::builtin();
+        for formula in HarmonicFormula::value_variants() {
+            let formula: &HarmonicFormula = formula;
+            let from_library: Vec<ProgressionChord> = library
+                .find(formula.library_name())
+                .unwrap()
+                .build(Note::D, &VoiceLeadingOptions::default())
+                .unwrap();
+            assert_eq!(
+                build_progression(Note::D, *formula, &VoiceLeadingOptions::default()),
+                from_library
+            );
+        }
+    }
+
+    #[test]
+    fn test_library_andalusian_a_minor() {
+        let library: ProgressionLibrary = ProgressionLibrary::builtin();
+        let progression: Vec<ProgressionChord> = library
+            .find("andalusian")
+            .unwrap()
+            .build(Note::A, &VoiceLeadingOptions::default())
+            .unwrap();
+
+        let chords: Vec<(Note, ChordType)> = progression
+            .iter()
+            .map(|c: &ProgressionChord| (c.root_note, c.chord_type))
+            .collect();
+        assert_eq!(
+            chords,
+            vec![
+                (Note::A, ChordType::Minor),
+                (Note::G, ChordType::Major),
+                (Note::F, ChordType::Major),
+                (Note::E, ChordType::Major),
+            ]
+        );
+    }
+
+    const USER_LIBRARY_TOML: &str = r#"
+[[progression]]
+name = "our-verse"
+formula = "I-vi-IV-V7"
+tags = ["band", "pop"]
+description = "Verse of our opener"
+key = "G"
+mode = "major"
+
+[[progression]]
+name = "circle"
+formula = "I-vi-ii7-V7"
+"#;
+
+    #[test]
+    fn test_library_parse_toml() {
+        let library: ProgressionLibrary =
+            ProgressionLibrary::parse(USER_LIBRARY_TOML, LibraryFormat::Toml).unwrap();
+        assert_eq!(library.definitions.len(), 2);
+
+        let verse: &ProgressionDefinition = library.find("our-verse").unwrap();
+        assert_eq!(verse.key, Some(Note::G));
+        assert_eq!(verse.mode, Some(ScaleType::Major));
+        assert!(verse.has_tag("band"));
+        assert_eq!(
+            verse
+                .build(Note::G, &VoiceLeadingOptions::default())
+                .unwrap()
+                .len(),
+            4
+        );
+    }
+
+    #[test]
+    fn test_library_parse_json() {
+        let json: &str =
+            r#"{"progressions": [{"name": "vamp", "formula": "i7 IV7", "tags": ["funk"]}]}"#;
+        let library: ProgressionLibrary =
+            ProgressionLibrary::parse(json, LibraryFormat::Json).unwrap();
+        assert_eq!(library.find("vamp").unwrap().formula, "i7 IV7");
+        assert_eq!(library.find("vamp").unwrap().key, None);
+    }
+
+    #[test]
+    fn test_library_parse_rejects_invalid_entries() {
+        let bad_formula: &str = "[[progression]]\nname = \"x\"\nformula = \"I-XI\"\n";
+        assert!(
+            ProgressionLibrary::parse(bad_formula, LibraryFormat::Toml)
+                .unwrap_err()
+                .contains("'x'")
+        );
+
+        let bad_key: &str = "[[progression]]\nname = \"x\"\nformula = \"I\"\nkey = \"H\"\n";
+        assert!(ProgressionLibrary::parse(bad_key, LibraryFormat::Toml).is_err());
+
+        let duplicate: &str = "[[progression]]\nname = \"x\"\nformula = \"I\"\n\n[[progression]]\nname = \"X\"\nformula = \"IV\"\n";
+        assert!(ProgressionLibrary::parse(duplicate, LibraryFormat::Toml).is_err());
+
+        let unknown_field: &str = "[[progression]]\nname = \"x\"\nformla = \"I\"\n";
+        assert!(ProgressionLibrary::parse(unknown_field, LibraryFormat::Toml).is_err());
+    }
+
+    #[test]
+    fn test_library_merge_overrides_by_name() {
+        let mut library: ProgressionLibrary = ProgressionLibrary::builtin();
+        let builtin_count: usize = library.definitions.len();
+        library.merge(ProgressionLibrary::parse(USER_LIBRARY_TOML, LibraryFormat::Toml).unwrap());
+
+        // "our-verse" is new, "circle" replaces the built-in one
+        assert_eq!(library.definitions.len(), builtin_count + 1);
+        assert_eq!(library.find("circle").unwrap().formula, "I-vi-ii7-V7");
+    }
+
+    #[test]
+    fn test_library_format_from_path() {
+        assert_eq!(
+            LibraryFormat::from_path(Path::new("band.TOML")),
+            Some(LibraryFormat::Toml)
+        );
+        assert_eq!(
+            LibraryFormat::from_path(Path::new("dir/band.json")),
+            Some(LibraryFormat::Json)
+        );
+        assert_eq!(LibraryFormat::from_path(Path::new("band.txt")), None);
+    }
+}
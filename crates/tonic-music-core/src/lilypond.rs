@@ -0,0 +1,257 @@
+/*
+ * tonic-music-core
+ * Copyright (C) 2025 Israel Alberto Roldan Vega
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * Repository: https://github.com/airvzxf/music-theory-calculator/
+ */
+
+/*
+ * crates/tonic-music-core/src/lilypond.rs
+ *
+ * This module writes a score as a LilyPond (.ly) file: chord names above
+ * the staff, the spelled notes in absolute octaves and the Roman numerals
+ * below, ready to engrave with `lilypond file.ly`.
+ */
+
+use crate::ChordType;
+use crate::notation::{ChordSymbol, Measure, Score, SpelledNote, SpelledPitch, Staves};
+
+/// Quotes a LilyPond string.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The LilyPond name of a note (Dutch names: c, fis, bes, eeses...).
+pub fn note_name(note: SpelledNote) -> String {
+    let suffix: &str = if note.alter > 0 { "is" } else { "es" };
+    let mut name: String = note.step.to_ascii_lowercase().to_string();
+    for _ in 0..note.alter.abs() {
+        name.push_str(suffix);
+    }
+    name
+}
+
+/// The LilyPond name of a pitch in absolute octaves: c is C3, c' is C4, c, is C2.
+pub fn pitch_name(pitch: &SpelledPitch) -> String {
+    let marks: i8 = pitch.octave - 3;
+    let mark: &str = if marks > 0 { "'" } else { "," };
+    format!(
+        "{}{}",
+        note_name(pitch.note),
+        mark.repeat(marks.unsigned_abs() as usize)
+    )
+}
+
+/// The chord mode modifier of a chord ("" for major, ":m7", ":m7.5-"...).
+pub fn chord_modifier(chord_type: ChordType) -> &'static str {
+    match chord_type {
+        ChordType::Major => "",
+        ChordType::Minor => ":m",
+        ChordType::Diminished => ":dim",
+        ChordType::Augmented => ":aug",
+        ChordType::Major7 => ":maj7",
+        ChordType::Minor7 => ":m7",
+        ChordType::Dominant7 => ":7",
+        ChordType::Minor7b5 => ":m7.5-",
+        ChordType::Diminished7 => ":dim7",
+        ChordType::MinorMajor7 => ":m7+",
+        ChordType::AugmentedMajor7 => ":maj7.5+",
+    }
+}
+
+/// A whole-measure chord name (`bes1:7/d`), or a spacer without a symbol.
+fn chord_name(symbol: Option<&ChordSymbol>) -> String {
+    match symbol {
+        Some(symbol) => format!(
+            "{}1{}{}",
+            note_name(symbol.root),
+            chord_modifier(symbol.chord_type),
+            symbol
+                .bass
+                .map_or(String::new(), |b: SpelledNote| format!("/{}", note_name(b)))
+        ),
+        None => "s1".to_string(),
+    }
+}
+
+/// A whole-measure note, chord or rest, with the label below it.
+fn staff_event(pitches: &[&SpelledPitch], label: Option<&str>) -> String {
+    let mut event: String = match pitches {
+        [] => "R1".to_string(),
+        [pitch] => format!("{}1", pitch_name(pitch)),
+        _ => format!(
+            "<{}>1",
+            pitches
+                .iter()
+                .map(|p: &&SpelledPitch| pitch_name(p))
+                .collect::<Vec<String>>()
+                .join(" ")
+        ),
+    };
+    if let Some(label) = label {
+        event.push_str(&format!("_{}", quote(label)));
+    }
+    event
+}
+
+/// The pitches of a measure written on a single staff.
+fn all_pitches(measure: &Measure) -> Vec<&SpelledPitch> {
+    measure.pitches.iter().collect()
+}
+
+/// Writes the measures four to a line, each closed by a bar check.
+fn push_lines(ly: &mut String, measures: &[String], indent: &str) {
+    for line in measures.chunks(4) {
+        let bars: Vec<String> = line.iter().map(|m: &String| format!("{} |", m)).collect();
+        ly.push_str(&format!("{}{}\n", indent, bars.join(" ")));
+    }
+}
+
+/// Writes one staff: clef, key and meter, the measures and a final bar line.
+/// `labels` puts the Roman numerals below this staff.
+fn push_staff(
+    ly: &mut String,
+    score: &Score,
+    clef: &str,
+    pitches: fn(&Measure) -> Vec<&SpelledPitch>,
+    labels: bool,
+    indent: &str,
+) {
+    let events: Vec<String> = score
+        .measures
+        .iter()
+        .map(|m: &Measure| {
+            let label: Option<&str> = m.label.as_deref().filter(|_: &&str| labels);
+            staff_event(&pitches(m), label)
+        })
+        .collect();
+    ly.push_str(&format!("{}\\new Staff {{\n", indent));
+    ly.push_str(&format!(
+        "{}  \\clef {} \\key {} \\{} \\time 4/4\n",
+        indent,
+        clef,
+        note_name(score.tonic),
+        if score.is_minor() { "minor" } else { "major" }
+    ));
+    push_lines(ly, &events, &format!("{}  ", indent));
+    ly.push_str(&format!("{}  \\bar \"|.\"\n{}}}\n", indent, indent));
+}
+
+/// Writes the score as a LilyPond file: one whole-note measure per note or
+/// chord in 4/4, chord names above and Roman numerals below the staff.
+/// Parts that go above and below middle C get a piano staff.
+pub fn write_lilypond(score: &Score) -> String {
+    // 1. Header
+    let mut ly: String = String::from("\\version \"2.24.0\"\n\n");
+    ly.push_str(&format!(
+        "\\header {{\n  title = {}\n  tagline = ##f\n}}\n\n",
+        quote(&score.title)
+    ));
+    ly.push_str("\\score {\n  <<\n");
+
+    // 2. Chord names, when the score has chord symbols
+    if score.measures.iter().any(|m: &Measure| m.symbol.is_some()) {
+        let names: Vec<String> = score
+            .measures
+            .iter()
+            .map(|m: &Measure| chord_name(m.symbol.as_ref()))
+            .collect();
+        ly.push_str("    \\new ChordNames \\chordmode {\n");
+        push_lines(&mut ly, &names, "      ");
+        ly.push_str("    }\n");
+    }
+
+    // 3. The staff, or the two staves of a piano staff
+    match score.staves() {
+        Staves::Grand => {
+            ly.push_str("    \\new PianoStaff <<\n");
+            push_staff(&mut ly, score, "treble", Measure::treble, false, "      ");
+            push_staff(&mut ly, score, "bass", Measure::bass, true, "      ");
+            ly.push_str("    >>\n");
+        }
+        Staves::Bass => push_staff(&mut ly, score, "bass", all_pitches, true, "    "),
+        Staves::Treble => push_staff(&mut ly, score, "treble", all_pitches, true, "    "),
+    }
+    ly.push_str("  >>\n  \\layout { }\n}\n");
+    ly
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::spell_tonic;
+    use crate::pitch::{Pitch, stack_notes};
+    use crate::voice_leading::VoiceLeadingOptions;
+    use crate::{HarmonicFormula, Note, ScaleType, build_progression, build_scale};
+
+    #[test]
+    fn test_lilypond_scale() {
+        let pitches: Vec<Pitch> = stack_notes(&build_scale(Note::DSharp, ScaleType::Major), 4);
+        let score: Score = Score::scale(
+            "Eb \"Major\" Scale",
+            spell_tonic(Note::DSharp, ScaleType::Major),
+            ScaleType::Major,
+            &pitches,
+        );
+        let ly: String = write_lilypond(&score);
+        assert!(ly.contains("title = \"Eb \\\"Major\\\" Scale\""));
+        assert!(ly.contains("\\clef treble \\key ees \\major \\time 4/4"));
+        assert!(ly.contains("ees'1 | f'1 | g'1 | aes'1 |\n"));
+        assert!(ly.contains("bes'1 | c''1 | d''1 |\n"));
+        assert!(!ly.contains("ChordNames"));
+        assert_eq!(ly.matches("\\bar \"|.\"").count(), 1);
+    }
+
+    #[test]
+    fn test_lilypond_progression() {
+        let progression = build_progression(
+            Note::A,
+            HarmonicFormula::Circle,
+            &VoiceLeadingOptions::default(),
+        );
+        let score: Score = Score::progression(
+            "Circle",
+            spell_tonic(Note::A, ScaleType::Major),
+            None,
+            &progression,
+            None,
+        );
+        let ly: String = write_lilypond(&score);
+        assert!(ly.contains("\\key a \\major"));
+        assert!(ly.contains("\\new ChordNames \\chordmode {\n      a1 |"));
+        // Chord tones are spelled in the key, and the numerals go below
+        assert!(ly.contains("<a' cis'' e''>1_\"I\""));
+
+        // Low and high notes make a piano staff, numerals under the bass
+        let low: Vec<Pitch> = vec![
+            Pitch::new(Note::G, 2),
+            Pitch::new(Note::B, 3),
+            Pitch::new(Note::F, 4),
+        ];
+        let score: Score = Score::chord(
+            "G7",
+            spell_tonic(Note::G, ScaleType::Major),
+            ChordType::Dominant7,
+            &[low],
+        );
+        let ly: String = write_lilypond(&score);
+        assert!(ly.contains("\\new PianoStaff <<"));
+        assert!(ly.contains("g1:7 |"));
+        assert!(ly.contains("\\clef bass"));
+        assert!(ly.contains("<g, b>1 |"));
+        assert!(ly.contains("f'1 |"));
+    }
+}
@@ -0,0 +1,736 @@
+/*
+ * tonic-music-core
+ * Copyright (C) 2025 Israel Alberto Roldan Vega
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * Repository: https://github.com/airvzxf/music-theory-calculator/
+ */
+
+/*
+ * crates/tonic-music-core/src/midi.rs
+ *
+ * This module writes Standard MIDI Files (type 0 and 1)
+ * from scales, chords and voiced progressions, and reads
+ * the notes back from any Standard MIDI File.
+ */
+
+use crate::pitch::Pitch;
+use serde::Serialize;
+
+/// How the MIDI file is written. Lengths are in beats (quarter notes).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MidiOptions {
+    /// Beats per minute
+    pub tempo: u32,
+    /// Time signature, e.g. (3, 4) for 3/4
+    pub time_signature: (u8, u8),
+    /// Ticks per quarter note
+    pub ticks_per_quarter: u16,
+    /// Note-on velocity (1-127)
+    pub velocity: u8,
+    /// MIDI channel (0-15; shown as 1-16 by most software)
+    pub channel: u8,
+    /// 0: everything in one track; 1: a tempo track plus one track per part
+    pub file_type: u8,
+}
+
+impl Default for MidiOptions {
+    fn default() -> Self {
+        MidiOptions {
+            tempo: 120,
+            time_signature: (4, 4),
+            ticks_per_quarter: 480,
+            velocity: 100,
+            channel: 0,
+            file_type: 1,
+        }
+    }
+}
+
+impl MidiOptions {
+    /// The length of one bar in beats (quarter notes), e.g. 3 in 3/4 or 3 in 6/8.
+    pub fn bar_length(&self) -> f64 {
+        self.time_signature.0 as f64 * 4.0 / self.time_signature.1 as f64
+    }
+}
+
+/// A note of a track: start and length in beats.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct MidiNote {
+    pub pitch: Pitch,
+    pub start: f64,
+    pub length: f64,
+}
+
+/// A named part (one track in type 1 files).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MidiTrack {
+    pub name: String,
+    pub notes: Vec<MidiNote>,
+}
+
+/// Plays the pitches one after the other (a scale or melody).
+pub fn sequence(pitches: &[Pitch], length: f64) -> Vec<MidiNote> {
+    pitches
+        .iter()
+        .enumerate()
+        .map(|(i, p): (usize, &Pitch)| MidiNote {
+            pitch: *p,
+            start: i as f64 * length,
+            length,
+        })
+        .collect()
+}
+
+/// Plays each chord as a block, one after the other.
+pub fn block_chords(chords: &[Vec<Pitch>], length: f64) -> Vec<MidiNote> {
+    let mut notes: Vec<MidiNote> = Vec::<MidiNote>::new();
+    for (i, chord) in chords.iter().enumerate() {
+        for pitch in chord {
+            notes.push(MidiNote {
+                pitch: *pitch,
+                start: i as f64 * length,
+                length,
+            });
+        }
+    }
+    notes
+}
+
+/// Plays each chord as an ascending arpeggio: `note_length` per note,
+/// `chord_length` per chord.
+pub fn arpeggios(chords: &[Vec<Pitch>], note_length: f64, chord_length: f64) -> Vec<MidiNote> {
+    let mut notes: Vec<MidiNote> = Vec::<MidiNote>::new();
+    for (i, chord) in chords.iter().enumerate() {
+        let mut pitches: Vec<Pitch> = chord.clone();
+        pitches.sort();
+        for (j, pitch) in pitches.iter().enumerate() {
+            let start: f64 = j as f64 * note_length;
+            if start >= chord_length {
+                break;
+            }
+            notes.push(MidiNote {
+                pitch: *pitch,
+                start: i as f64 * chord_length + start,
+                length: note_length.min(chord_length - start),
+            });
+        }
+    }
+    notes
+}
+
+/// The latest tick a note may reach: the largest four-byte variable-length quantity.
+const MAX_TICK: u32 = 0x0fff_ffff;
+
+/// Writes a variable-length quantity (7 bits per byte, high bit = "more bytes follow").
+fn write_vlq(bytes: &mut Vec<u8>, value: u32) {
+    let mut groups: Vec<u8> = vec![(value & 0x7f) as u8];
+    let mut rest: u32 = value >> 7;
+    while rest > 0 {
+        groups.push((rest & 0x7f) as u8 | 0x80);
+        rest >>= 7;
+    }
+    bytes.extend(groups.iter().rev());
+}
+
+/// Wraps track events (already delta-timed) into an "MTrk" chunk.
+fn track_chunk(events: &[(u32, Vec<u8>)]) -> Vec<u8> {
+    let mut data: Vec<u8> = Vec::<u8>::new();
+    let mut last: u32 = 0;
+    for (tick, event) in events {
+        write_vlq(&mut data, tick - last);
+        data.extend(event);
+        last = *tick;
+    }
+    // End of track
+    data.extend([0x00, 0xff, 0x2f, 0x00]);
+
+    let mut chunk: Vec<u8> = b"MTrk".to_vec();
+    chunk.extend((data.len() as u32).to_be_bytes());
+    chunk.extend(data);
+    chunk
+}
+
+/// A meta event (type, data) at tick 0.
+fn meta(kind: u8, data: &[u8]) -> (u32, Vec<u8>) {
+    let mut event: Vec<u8> = vec![0xff, kind];
+    write_vlq(&mut event, data.len() as u32);
+    event.extend(data);
+    (0, event)
+}
+
+/// The tempo and time signature events.
+fn conductor_events(options: &MidiOptions) -> Result<Vec<(u32, Vec<u8>)>, String> {
+    let (numerator, denominator): (u8, u8) = options.time_signature;
+    if numerator == 0 || !denominator.is_power_of_two() || denominator > 64 {
+        return Err(format!(
+            "Invalid time signature {}/{}",
+            numerator, denominator
+        ));
+    }
+    if options.tempo == 0 {
+        return Err("The tempo must be greater than 0".to_string());
+    }
+    let micros: u32 = 60_000_000 / options.tempo;
+    Ok(vec![
+        meta(0x51, &micros.to_be_bytes()[1..]),
+        meta(
+            0x58,
+            &[numerator, denominator.trailing_zeros() as u8, 24, 8],
+        ),
+    ])
+}
+
+/// The note-on/off events of a track, sorted by tick (note-offs first).
+fn note_events(notes: &[MidiNote], options: &MidiOptions) -> Result<Vec<(u32, Vec<u8>)>, String> {
+    let ticks = |beats: f64| -> f64 { (beats * options.ticks_per_quarter as f64).round() };
+    let mut events: Vec<(u32, u8, Vec<u8>)> = Vec::<(u32, u8, Vec<u8>)>::new();
+    for note in notes {
+        let key: i16 = note.pitch.midi();
+        if !(0..=127).contains(&key) {
+            return Err(format!(
+                "{} is outside the MIDI range (C-1 to G9)",
+                note.pitch
+            ));
+        }
+        if !(note.start >= 0.0 && note.length > 0.0) {
+            return Err(format!("Invalid timing for {}", note.pitch));
+        }
+        // Every tick (and so every delta time) must fit in a variable-length quantity
+        let (start, end): (f64, f64) = (ticks(note.start), ticks(note.start + note.length));
+        if !end.is_finite() || end >= MAX_TICK as f64 {
+            return Err(format!(
+                "{} ends too late for a MIDI file ({} ticks at most)",
+                note.pitch, MAX_TICK
+            ));
+        }
+        let start: u32 = start as u32;
+        let end: u32 = (end as u32).max(start + 1);
+        events.push((
+            start,
+            1,
+            vec![0x90 | options.channel, key as u8, options.velocity],
+        ));
+        events.push((end, 0, vec![0x80 | options.channel, key as u8, 0x40]));
+    }
+    events.sort_by_key(|(tick, order, event): &(u32, u8, Vec<u8>)| (*tick, *order, event[1]));
+    Ok(events
+        .into_iter()
+        .map(|(tick, _, event): (u32, u8, Vec<u8>)| (tick, event))
+        .collect())
+}
+
+/// Writes a Standard MIDI File. Type 0 merges every track into one; type 1
+/// writes a tempo track followed by one track per part.
+pub fn write_midi(tracks: &[MidiTrack], options: &MidiOptions) -> Result<Vec<u8>, String> {
+    // 1. Validate the options
+    if options.channel > 15 {
+        return Err(format!(
+            "Invalid MIDI channel {} (1-16)",
+            options.channel as u16 + 1
+        ));
+    }
+    if options.velocity == 0 || options.velocity > 127 {
+        return Err(format!("Invalid velocity {} (1-127)", options.velocity));
+    }
+    if options.ticks_per_quarter == 0 || options.ticks_per_quarter > 0x7fff {
+        return Err(format!(
+            "Invalid resolution {} ticks per quarter note",
+            options.ticks_per_quarter
+        ));
+    }
+    let conductor: Vec<(u32, Vec<u8>)> = conductor_events(options)?;
+
+    // 2. Build the track chunks
+    let mut chunks: Vec<Vec<u8>> = Vec::<Vec<u8>>::new();
+    match options.file_type {
+        0 => {
+            let notes: Vec<MidiNote> = tracks
+                .iter()
+                .flat_map(|t: &MidiTrack| t.notes.iter().copied())
+                .collect();
+            let mut events: Vec<(u32, Vec<u8>)> = conductor;
+            if let Some(track) = tracks.first() {
+                events.insert(0, meta(0x03, track.name.as_bytes()));
+            }
+            events.extend(note_events(&notes, options)?);
+            chunks.push(track_chunk(&events));
+        }
+        1 => {
+            chunks.push(track_chunk(&conductor));
+            for track in tracks {
+                let mut events: Vec<(u32, Vec<u8>)> = vec![meta(0x03, track.name.as_bytes())];
+                events.extend(note_events(&track.notes, options)?);
+                chunks.push(track_chunk(&events));
+            }
+        }
+        other => {
+            return Err(format!("Unsupported MIDI file type {} (0 or 1)", other));
+        }
+    }
+
+    // 3. Header: format, number of tracks, division
+    let mut bytes: Vec<u8> = b"MThd".to_vec();
+    bytes.extend(6u32.to_be_bytes());
+    bytes.extend((options.file_type as u16).to_be_bytes());
+    bytes.extend((chunks.len() as u16).to_be_bytes());
+    bytes.extend(options.ticks_per_quarter.to_be_bytes());
+    for chunk in chunks {
+        bytes.extend(chunk);
+    }
+    Ok(bytes)
+}
+
+/// The contents of a Standard MIDI File. Note times are in beats (quarter notes).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MidiFile {
+    pub file_type: u16,
+    pub ticks_per_quarter: u16,
+    /// The first tempo of the file (120 BPM if it has none)
+    pub tempo: f64,
+    /// The first time signature of the file (4/4 if it has none)
+    pub time_signature: (u8, u8),
+    /// The tracks that play notes (percussion on channel 10 is left out)
+    pub tracks: Vec<MidiTrack>,
+}
+
+impl MidiFile {
+    /// Every note of every track, ordered by start time.
+    pub fn notes(&self) -> Vec<MidiNote> {
+        let mut notes: Vec<MidiNote> = self
+            .tracks
+            .iter()
+            .flat_map(|t: &MidiTrack| t.notes.iter().copied())
+            .collect();
+        notes.sort_by(|a: &MidiNote, b: &MidiNote| {
+            a.start.total_cmp(&b.start).then(a.pitch.cmp(&b.pitch))
+        });
+        notes
+    }
+}
+
+/// Reads big-endian values and variable-length quantities from a chunk.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, String> {
+        let byte: u8 = *self
+            .bytes
+            .get(self.position)
+            .ok_or_else(|| "Unexpected end of the MIDI data".to_string())?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let end: usize = self.position + count;
+        let slice: &[u8] = self
+            .bytes
+            .get(self.position..end)
+            .ok_or_else(|| "Unexpected end of the MIDI data".to_string())?;
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let bytes: &[u8] = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes: &[u8] = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn vlq(&mut self) -> Result<u32, String> {
+        let mut value: u32 = 0;
+        for _ in 0..4 {
+            let byte: u8 = self.byte()?;
+            value = (value << 7) | (byte & 0x7f) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("Invalid variable-length quantity in the MIDI data".to_string())
+    }
+
+    fn at_end(&self) -> bool {
+        self.position >= self.bytes.len()
+    }
+}
+
+/// The notes and meta data found in one track chunk.
+struct ParsedTrack {
+    name: Option<String>,
+    tempo: Option<u32>,
+    time_signature: Option<(u8, u8)>,
+    notes: Vec<MidiNote>,
+}
+
+/// Parses the events of a track chunk (with running status) into notes.
+fn parse_track(data: &[u8], ticks_per_quarter: u16) -> Result<ParsedTrack, String> {
+    let mut reader: Reader = Reader {
+        bytes: data,
+        position: 0,
+    };
+    let mut track: ParsedTrack = ParsedTrack {
+        name: None,
+        tempo: None,
+        time_signature: None,
+        notes: Vec::<MidiNote>::new(),
+    };
+    let beats = |tick: u64| -> f64 { tick as f64 / ticks_per_quarter as f64 };
+    // Start ticks of the sounding notes, by (channel, key)
+    let mut sounding: Vec<(u8, u8, u64)> = Vec::<(u8, u8, u64)>::new();
+    let mut tick: u64 = 0;
+    let mut status: u8 = 0;
+
+    while !reader.at_end() {
+        tick += reader.vlq()? as u64;
+        let mut first: u8 = reader.byte()?;
+        if first & 0x80 != 0 {
+            status = first;
+            if status < 0xf0 {
+                first = reader.byte()?;
+            }
+        } else if status == 0 || status >= 0xf0 {
+            return Err("MIDI data byte without a status byte".to_string());
+        }
+
+        match status {
+            0xff => {
+                let kind: u8 = reader.byte()?;
+                let length: usize = reader.vlq()? as usize;
+                let data: &[u8] = reader.take(length)?;
+                match kind {
+                    0x03 if track.name.is_none() => {
+                        track.name = Some(String::from_utf8_lossy(data).into_owned());
+                    }
+                    0x51 if data.len() == 3 && track.tempo.is_none() => {
+                        track.tempo = Some(u32::from_be_bytes([0, data[0], data[1], data[2]]));
+                    }
+                    // A numerator of 0 is not a meter: keep looking (or 4/4)
+                    0x58 if data.len() >= 2 && data[0] > 0 && track.time_signature.is_none() => {
+                        track.time_signature =
+                            Some((data[0], 1u8.checked_shl(data[1] as u32).unwrap_or(4)));
+                    }
+                    0x2f => break,
+                    _ => {}
+                }
+                status = 0;
+            }
+            0xf0 | 0xf7 => {
+                let length: usize = reader.vlq()? as usize;
+                reader.take(length)?;
+                status = 0;
+            }
+            _ => {
+                let channel: u8 = status & 0x0f;
+                match status & 0xf0 {
+                    0x80 | 0x90 => {
+                        let key: u8 = first;
+                        let velocity: u8 = reader.byte()?;
+                        let index: Option<usize> = sounding
+                            .iter()
+                            .position(|(c, k, _): &(u8, u8, u64)| *c == channel && *k == key);
+                        // A note-on while the key sounds ends the previous note
+                        if let Some(index) = index {
+                            let (_, _, start): (u8, u8, u64) = sounding.remove(index);
+                            if tick > start && channel != 9 {
+                                track.notes.push(MidiNote {
+                                    pitch: Pitch::from_midi(key as i16),
+                                    start: beats(start),
+                                    length: beats(tick - start),
+                                });
+                            }
+                        }
+                        if status & 0xf0 == 0x90 && velocity > 0 {
+                            sounding.push((channel, key, tick));
+                        }
+                    }
+                    0xa0 | 0xb0 | 0xe0 => {
+                        reader.byte()?;
+                    }
+                    // Program change, channel pressure: one data byte (already read)
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    // Notes still sounding at the end of the track last until its end
+    for (channel, key, start) in sounding {
+        if tick > start && channel != 9 {
+            track.notes.push(MidiNote {
+                pitch: Pitch::from_midi(key as i16),
+                start: beats(start),
+                length: beats(tick - start),
+            });
+        }
+    }
+    track.notes.sort_by(|a: &MidiNote, b: &MidiNote| {
+        a.start.total_cmp(&b.start).then(a.pitch.cmp(&b.pitch))
+    });
+    Ok(track)
+}
+
+/// Reads the notes of a Standard MIDI File (type 0, 1 or 2). Percussion
+/// (channel 10) is skipped, and only the first tempo and time signature are kept.
+pub fn read_midi(bytes: &[u8]) -> Result<MidiFile, String> {
+    let mut reader: Reader = Reader { bytes, position: 0 };
+
+    // 1. Header chunk
+    if reader.take(4).ok() != Some(b"MThd".as_slice()) {
+        return Err("Not a Standard MIDI File (missing the MThd header)".to_string());
+    }
+    let header_length: usize = reader.u32()? as usize;
+    let file_type: u16 = reader.u16()?;
+    let track_count: u16 = reader.u16()?;
+    let division: u16 = reader.u16()?;
+    reader.take(header_length.saturating_sub(6))?;
+    if file_type > 2 {
+        return Err(format!("Unsupported MIDI file type {}", file_type));
+    }
+    if division & 0x8000 != 0 || division == 0 {
+        return Err("SMPTE time division is not supported".to_string());
+    }
+
+    // 2. Track chunks (unknown chunk types are skipped)
+    let mut file: MidiFile = MidiFile {
+        file_type,
+        ticks_per_quarter: division,
+        tempo: 120.0,
+        time_signature: (4, 4),
+        tracks: Vec::<MidiTrack>::new(),
+    };
+    let (mut tempo, mut time_signature): (Option<u32>, Option<(u8, u8)>) = (None, None);
+    let mut found: u16 = 0;
+    while !reader.at_end() && found < track_count {
+        let kind: &[u8] = reader.take(4)?;
+        let length: usize = reader.u32()? as usize;
+        let data: &[u8] = reader.take(length)?;
+        if kind != b"MTrk" {
+            continue;
+        }
+        found += 1;
+        let track: ParsedTrack = parse_track(data, division)?;
+        tempo = tempo.or(track.tempo);
+        time_signature = time_signature.or(track.time_signature);
+        if !track.notes.is_empty() {
+            file.tracks.push(MidiTrack {
+                name: track.name.unwrap_or_else(|| format!("Track {}", found)),
+                notes: track.notes,
+            });
+        }
+    }
+
+    if let Some(micros) = tempo.filter(|m: &u32| *m > 0) {
+        file.tempo = 60_000_000.0 / micros as f64;
+    }
+    if let Some(signature) = time_signature {
+        file.time_signature = signature;
+    }
+    Ok(file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Note;
+    use crate::pitch::stack_notes;
+
+    #[test]
+    fn test_midi_vlq() {
+        let encode = |value: u32| -> Vec<u8> {
+            let mut bytes: Vec<u8> = Vec::<u8>::new();
+            write_vlq(&mut bytes, value);
+            bytes
+        };
+        assert_eq!(encode(0), [0x00]);
+        assert_eq!(encode(0x7f), [0x7f]);
+        assert_eq!(encode(0x80), [0x81, 0x00]);
+        assert_eq!(encode(480), [0x83, 0x60]);
+        assert_eq!(encode(0x0fff_ffff), [0xff, 0xff, 0xff, 0x7f]);
+    }
+
+    #[test]
+    fn test_midi_type0_bytes() {
+        let track: MidiTrack = MidiTrack {
+            name: "C".to_string(),
+            notes: sequence(&[Pitch::new(Note::C, 4)], 1.0),
+        };
+        let bytes: Vec<u8> = write_midi(
+            &[track],
+            &MidiOptions {
+                file_type: 0,
+                channel: 2,
+                ..MidiOptions::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            &bytes[..14],
+            b"MThd\x00\x00\x00\x06\x00\x00\x00\x01\x01\xe0"
+        );
+        assert_eq!(&bytes[14..18], b"MTrk");
+        let length: usize = u32::from_be_bytes(bytes[18..22].try_into().unwrap()) as usize;
+        assert_eq!(bytes.len(), 22 + length);
+        let data: &[u8] = &bytes[22..];
+        assert_eq!(
+            data,
+            [
+                0x00, 0xff, 0x03, 0x01, b'C', // Track name
+                0x00, 0xff, 0x51, 0x03, 0x07, 0xa1, 0x20, // 500000 us per quarter
+                0x00, 0xff, 0x58, 0x04, 0x04, 0x02, 0x18, 0x08, // 4/4
+                0x00, 0x92, 0x3c, 0x64, // Note on C4, channel 3
+                0x83, 0x60, 0x82, 0x3c, 0x40, // Note off after 480 ticks
+                0x00, 0xff, 0x2f, 0x00,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_midi_type1_tracks() {
+        let chords: Vec<Vec<Pitch>> = vec![
+            stack_notes(&[Note::C, Note::E, Note::G], 4),
+            stack_notes(&[Note::B, Note::D, Note::G], 3),
+        ];
+        let tracks: Vec<MidiTrack> = vec![
+            MidiTrack {
+                name: "Chords".to_string(),
+                notes: block_chords(&chords, 4.0),
+            },
+            MidiTrack {
+                name: "Arpeggios".to_string(),
+                notes: arpeggios(&chords, 1.0, 4.0),
+            },
+        ];
+        let bytes: Vec<u8> = write_midi(&tracks, &MidiOptions::default()).unwrap();
+        // Header, tempo track and two parts
+        assert_eq!(&bytes[8..12], [0x00, 0x01, 0x00, 0x03]);
+        assert_eq!(
+            bytes.windows(4).filter(|w: &&[u8]| *w == b"MTrk").count(),
+            3
+        );
+        // The arpeggios restart on each chord
+        assert_eq!(tracks[1].notes.len(), 6);
+        assert_eq!(tracks[1].notes[3].start, 4.0);
+        assert_eq!(tracks[1].notes[3].pitch, Pitch::new(Note::B, 3));
+    }
+
+    #[test]
+    fn test_midi_read_round_trip() {
+        let chords: Vec<Vec<Pitch>> = vec![
+            stack_notes(&[Note::C, Note::E, Note::G], 4),
+            stack_notes(&[Note::F, Note::A, Note::C], 4),
+        ];
+        let options: MidiOptions = MidiOptions {
+            tempo: 90,
+            time_signature: (3, 4),
+            ..MidiOptions::default()
+        };
+        for file_type in [0, 1] {
+            let tracks: Vec<MidiTrack> = vec![MidiTrack {
+                name: "Chords".to_string(),
+                notes: block_chords(&chords, 3.0),
+            }];
+            let bytes: Vec<u8> = write_midi(
+                &tracks,
+                &MidiOptions {
+                    file_type,
+                    ..options.clone()
+                },
+            )
+            .unwrap();
+            let file: MidiFile = read_midi(&bytes).unwrap();
+            assert_eq!(file.file_type, file_type as u16);
+            assert!((file.tempo - 90.0).abs() < 0.01);
+            assert_eq!(file.time_signature, (3, 4));
+            assert_eq!(file.tracks.len(), 1);
+            assert_eq!(file.tracks[0].name, "Chords");
+            assert_eq!(file.notes(), block_chords(&chords, 3.0));
+        }
+    }
+
+    #[test]
+    fn test_midi_read_running_status() {
+        // Note-on with running status, a note-on with velocity 0 as note-off,
+        // a program change and a drum note that is skipped
+        let events: Vec<u8> = vec![
+            0x00, 0xc0, 0x05, // Program change
+            0x00, 0x90, 0x3c, 0x64, // C4 on
+            0x00, 0x40, 0x64, // E4 on (running status)
+            0x00, 0x99, 0x24, 0x64, // Kick drum on channel 10
+            0x60, 0x90, 0x3c, 0x00, // C4 off after 96 ticks
+            0x00, 0x40, 0x00, // E4 off
+            0x00, 0x89, 0x24, 0x40, // Kick off
+            0x00, 0xff, 0x2f, 0x00,
+        ];
+        let mut bytes: Vec<u8> = b"MThd\x00\x00\x00\x06\x00\x00\x00\x01\x00\x60MTrk".to_vec();
+        bytes.extend((events.len() as u32).to_be_bytes());
+        bytes.extend(events);
+
+        let file: MidiFile = read_midi(&bytes).unwrap();
+        assert_eq!(file.tempo, 120.0);
+        assert_eq!(
+            file.notes(),
+            sequence(&[Pitch::new(Note::C, 4)], 1.0)
+                .into_iter()
+                .chain(sequence(&[Pitch::new(Note::E, 4)], 1.0))
+                .collect::<Vec<MidiNote>>()
+        );
+        assert!(read_midi(b"RIFF").is_err());
+        assert!(read_midi(&bytes[..30]).is_err());
+    }
+
+    #[test]
+    fn test_midi_errors() {
+        let track: MidiTrack = MidiTrack {
+            name: "High".to_string(),
+            notes: sequence(&[Pitch::new(Note::C, 10)], 1.0),
+        };
+        assert!(write_midi(&[track], &MidiOptions::default()).is_err());
+        for (start, length) in [
+            (0.0, 1e12),
+            (1e12, 1.0),
+            (f64::NAN, 1.0),
+            (0.0, f64::INFINITY),
+        ] {
+            let track: MidiTrack = MidiTrack {
+                name: "Long".to_string(),
+                notes: vec![MidiNote {
+                    pitch: Pitch::new(Note::C, 4),
+                    start,
+                    length,
+                }],
+            };
+            assert!(write_midi(&[track], &MidiOptions::default()).is_err());
+        }
+        let bad_meter: MidiOptions = MidiOptions {
+            time_signature: (4, 3),
+            ..MidiOptions::default()
+        };
+        assert!(write_midi(&[], &bad_meter).is_err());
+        let bad_type: MidiOptions = MidiOptions {
+            file_type: 2,
+            ..MidiOptions::default()
+        };
+        assert!(write_midi(&[], &bad_type).is_err());
+    }
+}
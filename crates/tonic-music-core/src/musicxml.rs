@@ -0,0 +1,938 @@
+/*
+ * tonic-music-core
+ * Copyright (C) 2025 Israel Alberto Roldan Vega
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * Repository: https://github.com/airvzxf/music-theory-calculator/
+ */
+
+/*
+ * crates/tonic-music-core/src/musicxml.rs
+ *
+ * This module writes a score as MusicXML (partwise, version 4.0),
+ * which notation programs such as MuseScore and Finale can open,
+ * and reads the chord symbols and notes of MusicXML charts.
+ */
+
+use crate::analysis::{harmonic_function, roman_numeral};
+use crate::midi::MidiNote;
+use crate::notation::{
+    Accidentals, ChordSymbol, Score, SpelledNote, SpelledPitch, Staves, chord_suffix,
+    key_from_fifths, spell_chord,
+};
+use crate::pitch::Pitch;
+use crate::recognition::{
+    AnalyzedChord, Segment, Segmentation, analyze_segments, estimate_key, segment_notes,
+};
+use crate::{ChordType, Note, ProgressionChord, ScaleType, build_chord};
+
+/// Escapes the characters with a meaning in XML.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The MusicXML kind of a chord (an augmented major seventh is written as
+/// a major seventh with a raised fifth).
+pub fn harmony_kind(chord_type: ChordType) -> &'static str {
+    match chord_type {
+        ChordType::Major => "major",
+        ChordType::Minor => "minor",
+        ChordType::Diminished => "diminished",
+        ChordType::Augmented => "augmented",
+        ChordType::Major7 | ChordType::AugmentedMajor7 => "major-seventh",
+        ChordType::Minor7 => "minor-seventh",
+        ChordType::Dominant7 => "dominant",
+        ChordType::Minor7b5 => "half-diminished",
+        ChordType::Diminished7 => "diminished-seventh",
+        ChordType::MinorMajor7 => "major-minor",
+    }
+}
+
+fn accidental_name(alter: i8) -> &'static str {
+    match alter {
+        -2 => "flat-flat",
+        -1 => "flat",
+        1 => "sharp",
+        2 => "double-sharp",
+        _ => "natural",
+    }
+}
+
+/// Writes `<root-step>`/`<root-alter>` style elements for a spelled note.
+fn push_step(xml: &mut String, prefix: &str, note: &SpelledNote, indent: &str) {
+    xml.push_str(&format!(
+        "{}<{}-step>{}</{}-step>\n",
+        indent, prefix, note.step, prefix
+    ));
+    if note.alter != 0 {
+        xml.push_str(&format!(
+            "{}<{}-alter>{}</{}-alter>\n",
+            indent, prefix, note.alter, prefix
+        ));
+    }
+}
+
+fn push_harmony(xml: &mut String, symbol: &ChordSymbol) {
+    let (kind, text): (&str, &str) = (
+        harmony_kind(symbol.chord_type),
+        chord_suffix(symbol.chord_type),
+    );
+    xml.push_str("      <harmony>\n        <root>\n");
+    push_step(xml, "root", &symbol.root, "          ");
+    xml.push_str("        </root>\n");
+    xml.push_str(&format!(
+        "        <kind text=\"{}\">{}</kind>\n",
+        escape(text),
+        kind
+    ));
+    if let Some(bass) = &symbol.bass {
+        xml.push_str("        <bass>\n");
+        push_step(xml, "bass", bass, "          ");
+        xml.push_str("        </bass>\n");
+    }
+    if symbol.chord_type == ChordType::AugmentedMajor7 {
+        xml.push_str(concat!(
+            "        <degree>\n",
+            "          <degree-value>5</degree-value>\n",
+            "          <degree-alter>1</degree-alter>\n",
+            "          <degree-type>alter</degree-type>\n",
+            "        </degree>\n"
+        ));
+    }
+    xml.push_str("      </harmony>\n");
+}
+
+/// Writes the notes of one staff as a whole-measure chord (or a whole rest).
+fn push_notes(
+    xml: &mut String,
+    pitches: &[&SpelledPitch],
+    staff: Option<u8>,
+    label: Option<&str>,
+    accidentals: &mut Accidentals,
+) {
+    let voice: u8 = staff.unwrap_or(1);
+    let staff_line: String = staff.map_or(String::new(), |s: u8| {
+        format!("        <staff>{}</staff>\n", s)
+    });
+    if pitches.is_empty() {
+        xml.push_str(&format!(
+            "      <note>\n        <rest measure=\"yes\"/>\n        <duration>4</duration>\n        <voice>{}</voice>\n{}      </note>\n",
+            voice, staff_line
+        ));
+        return;
+    }
+    for (i, pitch) in pitches.iter().enumerate() {
+        xml.push_str("      <note>\n");
+        if i > 0 {
+            xml.push_str("        <chord/>\n");
+        }
+        xml.push_str("        <pitch>\n");
+        xml.push_str(&format!("          <step>{}</step>\n", pitch.note.step));
+        if pitch.note.alter != 0 {
+            xml.push_str(&format!("          <alter>{}</alter>\n", pitch.note.alter));
+        }
+        xml.push_str(&format!("          <octave>{}</octave>\n", pitch.octave));
+        xml.push_str("        </pitch>\n");
+        xml.push_str(&format!(
+            "        <duration>4</duration>\n        <voice>{}</voice>\n        <type>whole</type>\n",
+            voice
+        ));
+        // 1. Show the accidental when it differs from the key or from earlier in the measure
+        if let Some(alter) = accidentals.show(pitch) {
+            xml.push_str(&format!(
+                "        <accidental>{}</accidental>\n",
+                accidental_name(alter)
+            ));
+        }
+        xml.push_str(&staff_line);
+        if let (0, Some(label)) = (i, label) {
+            xml.push_str(&format!(
+                "        <lyric number=\"1\">\n          <syllabic>single</syllabic>\n          <text>{}</text>\n        </lyric>\n",
+                escape(label)
+            ));
+        }
+        xml.push_str("      </note>\n");
+    }
+}
+
+/// Writes the score as a MusicXML document: one part in 4/4, one whole-note
+/// measure per note or chord, chord symbols above and Roman numerals below
+/// as lyrics. Parts that go above and below middle C get a grand staff.
+pub fn write_musicxml(score: &Score) -> String {
+    let staves: Staves = score.staves();
+
+    // 1. Header and part list
+    let mut xml: String = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n",
+        "<!DOCTYPE score-partwise PUBLIC \"-//Recordare//DTD MusicXML 4.0 Partwise//EN\" ",
+        "\"http://www.musicxml.org/dtds/partwise.dtd\">\n",
+        "<score-partwise version=\"4.0\">\n"
+    ));
+    xml.push_str(&format!(
+        "  <work>\n    <work-title>{}</work-title>\n  </work>\n",
+        escape(&score.title)
+    ));
+    xml.push_str(concat!(
+        "  <identification>\n    <encoding>\n",
+        "      <software>tonic-music</software>\n",
+        "    </encoding>\n  </identification>\n",
+        "  <part-list>\n    <score-part id=\"P1\">\n",
+        "      <part-name>Music</part-name>\n",
+        "    </score-part>\n  </part-list>\n",
+        "  <part id=\"P1\">\n"
+    ));
+
+    for (number, measure) in score.measures.iter().enumerate() {
+        xml.push_str(&format!("    <measure number=\"{}\">\n", number + 1));
+
+        // 2. Key, meter and clefs open the first measure
+        if number == 0 {
+            xml.push_str("      <attributes>\n        <divisions>1</divisions>\n");
+            xml.push_str(&format!(
+                "        <key>\n          <fifths>{}</fifths>\n          <mode>{}</mode>\n        </key>\n",
+                score.fifths,
+                if score.is_minor() { "minor" } else { "major" }
+            ));
+            xml.push_str(
+                "        <time>\n          <beats>4</beats>\n          <beat-type>4</beat-type>\n        </time>\n",
+            );
+            if staves == Staves::Grand {
+                xml.push_str(concat!(
+                    "        <staves>2</staves>\n",
+                    "        <clef number=\"1\">\n          <sign>G</sign>\n          <line>2</line>\n        </clef>\n",
+                    "        <clef number=\"2\">\n          <sign>F</sign>\n          <line>4</line>\n        </clef>\n"
+                ));
+            } else if staves == Staves::Bass {
+                xml.push_str("        <clef>\n          <sign>F</sign>\n          <line>4</line>\n        </clef>\n");
+            } else {
+                xml.push_str("        <clef>\n          <sign>G</sign>\n          <line>2</line>\n        </clef>\n");
+            }
+            xml.push_str("      </attributes>\n");
+        }
+
+        // 3. Chord symbol, then the notes of each staff
+        if let Some(symbol) = &measure.symbol {
+            push_harmony(&mut xml, symbol);
+        }
+        let mut accidentals: Accidentals = Accidentals::new(score.fifths);
+        let label: Option<&str> = measure.label.as_deref();
+        if staves == Staves::Grand {
+            push_notes(&mut xml, &measure.treble(), Some(1), None, &mut accidentals);
+            xml.push_str("      <backup>\n        <duration>4</duration>\n      </backup>\n");
+            push_notes(&mut xml, &measure.bass(), Some(2), label, &mut accidentals);
+        } else {
+            let all: Vec<&SpelledPitch> = measure.pitches.iter().collect();
+            push_notes(&mut xml, &all, None, label, &mut accidentals);
+        }
+
+        if number + 1 == score.measures.len() {
+            xml.push_str(
+                "      <barline location=\"right\">\n        <bar-style>light-heavy</bar-style>\n      </barline>\n",
+            );
+        }
+        xml.push_str("    </measure>\n");
+    }
+    xml.push_str("  </part>\n</score-partwise>\n");
+    xml
+}
+
+/// A chord symbol read from a `<harmony>` element.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedChord {
+    /// The measure, counting from 1
+    pub measure: usize,
+    /// Position in beats (quarter notes) from the start of the score
+    pub start: f64,
+    pub symbol: ChordSymbol,
+}
+
+/// The chord symbols and notes of a MusicXML score.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedScore {
+    pub title: Option<String>,
+    /// The first key signature of the score
+    pub key: Option<(SpelledNote, ScaleType)>,
+    pub time_signature: (u8, u8),
+    pub chords: Vec<ImportedChord>,
+    /// The notes of every part, in beats, with tied notes joined
+    pub notes: Vec<MidiNote>,
+    /// The elements that were skipped or simplified
+    pub warnings: Vec<String>,
+}
+
+type XmlNode<'a, 'input> = roxmltree::Node<'a, 'input>;
+
+fn child<'a, 'input>(node: XmlNode<'a, 'input>, name: &str) -> Option<XmlNode<'a, 'input>> {
+    node.children().find(|c: &XmlNode| c.has_tag_name(name))
+}
+
+fn child_text<'a>(node: XmlNode<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name)
+        .and_then(|c: XmlNode<'a, '_>| c.text())
+        .map(str::trim)
+}
+
+fn child_number(node: XmlNode, name: &str) -> Option<f64> {
+    child_text(node, name).and_then(|t: &str| t.parse::<f64>().ok())
+}
+
+/// Adds a warning once.
+fn warn(warnings: &mut Vec<String>, message: String) {
+    if !warnings.contains(&message) {
+        warnings.push(message);
+    }
+}
+
+/// Reads a `<step>`/`<alter>` pair, or a prefixed one (`<root-step>`/`<root-alter>`).
+fn read_step(node: XmlNode, prefix: &str) -> Option<SpelledNote> {
+    let name = |n: &str| -> String {
+        if prefix.is_empty() {
+            n.to_string()
+        } else {
+            format!("{}-{}", prefix, n)
+        }
+    };
+    let step: char = child_text(node, &name("step"))?
+        .chars()
+        .next()?
+        .to_ascii_uppercase();
+    if !('A'..='G').contains(&step) {
+        return None;
+    }
+    let alter: f64 = child_number(node, &name("alter")).unwrap_or(0.0);
+    Some(SpelledNote {
+        step,
+        alter: alter.round() as i8,
+    })
+}
+
+/// Reads a `<kind>`. Chords the core does not have are simplified (a ninth
+/// to its seventh chord, a sixth to its triad): the flag is true for those.
+fn read_kind(kind: &str) -> Option<(ChordType, bool)> {
+    let read: (ChordType, bool) = match kind {
+        "major" | "" => (ChordType::Major, false),
+        "minor" => (ChordType::Minor, false),
+        "augmented" => (ChordType::Augmented, false),
+        "diminished" => (ChordType::Diminished, false),
+        "dominant" => (ChordType::Dominant7, false),
+        "major-seventh" => (ChordType::Major7, false),
+        "minor-seventh" => (ChordType::Minor7, false),
+        "diminished-seventh" => (ChordType::Diminished7, false),
+        "half-diminished" => (ChordType::Minor7b5, false),
+        "major-minor" => (ChordType::MinorMajor7, false),
+        "major-sixth" | "Neapolitan" => (ChordType::Major, true),
+        "minor-sixth" => (ChordType::Minor, true),
+        "augmented-seventh" => (ChordType::Augmented, true),
+        "dominant-ninth" | "dominant-11th" | "dominant-13th" => (ChordType::Dominant7, true),
+        "major-ninth" | "major-11th" | "major-13th" => (ChordType::Major7, true),
+        "minor-ninth" | "minor-11th" | "minor-13th" => (ChordType::Minor7, true),
+        _ => return None,
+    };
+    Some(read)
+}
+
+/// Reads a chord symbol. Chords without a root (N.C., Roman numerals),
+/// suspended, power and other unsupported chords are skipped with a warning.
+fn read_harmony(
+    harmony: XmlNode,
+    measure: usize,
+    warnings: &mut Vec<String>,
+) -> Option<ChordSymbol> {
+    let kind: &str = child_text(harmony, "kind").unwrap_or("");
+    if kind == "none" {
+        return None;
+    }
+    let Some(root) = child(harmony, "root").and_then(|r: XmlNode| read_step(r, "root")) else {
+        warn(
+            warnings,
+            format!(
+                "Measure {}: a chord symbol without a root was skipped",
+                measure
+            ),
+        );
+        return None;
+    };
+    let Some((mut chord_type, simplified)) = read_kind(kind) else {
+        warn(
+            warnings,
+            format!(
+                "Measure {}: {} {} is not supported and was skipped",
+                measure, root, kind
+            ),
+        );
+        return None;
+    };
+
+    // 1. Added, altered and removed degrees: only the raised fifth of +maj7 is kept
+    for degree in harmony
+        .children()
+        .filter(|c: &XmlNode| c.has_tag_name("degree"))
+    {
+        let value: f64 = child_number(degree, "degree-value").unwrap_or(0.0);
+        let alter: f64 = child_number(degree, "degree-alter").unwrap_or(0.0);
+        let degree_type: &str = child_text(degree, "degree-type").unwrap_or("");
+        if chord_type == ChordType::Major7 && value == 5.0 && alter == 1.0 && degree_type == "alter"
+        {
+            chord_type = ChordType::AugmentedMajor7;
+        } else {
+            warn(
+                warnings,
+                format!(
+                    "Measure {}: the {} {} of {}{} was ignored",
+                    measure,
+                    degree_type,
+                    value,
+                    root,
+                    chord_suffix(chord_type)
+                ),
+            );
+        }
+    }
+    if simplified {
+        warn(
+            warnings,
+            format!(
+                "Measure {}: {} {} was read as {}{}",
+                measure,
+                root,
+                kind,
+                root,
+                chord_suffix(chord_type)
+            ),
+        );
+    }
+
+    // 2. The bass, given as a note or as an inversion
+    let tones: Vec<SpelledNote> = spell_chord(root, chord_type);
+    let bass: Option<SpelledNote> = child(harmony, "bass")
+        .and_then(|b: XmlNode| read_step(b, "bass"))
+        .or_else(|| {
+            child_number(harmony, "inversion").and_then(|i: f64| tones.get(i as usize).copied())
+        })
+        .filter(|b: &SpelledNote| b.note() != root.note());
+    Some(ChordSymbol {
+        root,
+        chord_type,
+        bass,
+    })
+}
+
+/// Reads one part, adding its chords and notes to the score.
+fn read_part(part: XmlNode, score: &mut ImportedScore) {
+    let mut divisions: f64 = 1.0;
+    let mut measure_start: f64 = 0.0;
+    let mut last_start: f64 = 0.0;
+
+    for (index, measure) in part
+        .children()
+        .filter(|c: &XmlNode| c.has_tag_name("measure"))
+        .enumerate()
+    {
+        let number: usize = index + 1;
+        let mut time: f64 = measure_start;
+        let mut end: f64 = measure_start;
+
+        for element in measure.children().filter(XmlNode::is_element) {
+            let duration: f64 = child_number(element, "duration").unwrap_or(0.0) / divisions;
+            match element.tag_name().name() {
+                "attributes" => {
+                    if let Some(d) = child_number(element, "divisions")
+                        && d > 0.0
+                    {
+                        divisions = d;
+                    }
+                    if let Some(key) = child(element, "key") {
+                        read_key(key, number, score);
+                    }
+                    if let Some(meter) = child(element, "time") {
+                        read_time(meter, number, score);
+                    }
+                }
+                "harmony" => {
+                    let offset: f64 = child_number(element, "offset").unwrap_or(0.0) / divisions;
+                    if let Some(symbol) = read_harmony(element, number, &mut score.warnings) {
+                        score.chords.push(ImportedChord {
+                            measure: number,
+                            start: time + offset,
+                            symbol,
+                        });
+                    }
+                }
+                "note" => {
+                    // Grace and cue notes take no time in the measure
+                    if child(element, "grace").is_some() || child(element, "cue").is_some() {
+                        continue;
+                    }
+                    let start: f64 = if child(element, "chord").is_some() {
+                        last_start
+                    } else {
+                        last_start = time;
+                        time += duration;
+                        last_start
+                    };
+                    end = end.max(start + duration);
+                    if let Some(pitch) = child(element, "pitch") {
+                        read_note(element, pitch, start, duration, score);
+                    } else if child(element, "unpitched").is_some() {
+                        warn(
+                            &mut score.warnings,
+                            "Unpitched (percussion) notes were ignored".to_string(),
+                        );
+                    }
+                }
+                "backup" => time -= duration,
+                "forward" => time += duration,
+                "barline"
+                    if child(element, "repeat").is_some() || child(element, "ending").is_some() =>
+                {
+                    warn(
+                        &mut score.warnings,
+                        format!("Measure {}: repeats and endings are not expanded", number),
+                    );
+                }
+                "figured-bass" => warn(
+                    &mut score.warnings,
+                    format!("Measure {}: figured bass was ignored", number),
+                ),
+                _ => {}
+            }
+            end = end.max(time);
+        }
+        measure_start = end;
+    }
+}
+
+/// Keeps the first key signature of the score.
+fn read_key(key: XmlNode, measure: usize, score: &mut ImportedScore) {
+    let Some(fifths) = child_number(key, "fifths") else {
+        warn(
+            &mut score.warnings,
+            format!(
+                "Measure {}: a non-traditional key signature was ignored",
+                measure
+            ),
+        );
+        return;
+    };
+    let mode: ScaleType = match child_text(key, "mode") {
+        Some("minor") | Some("aeolian") => ScaleType::MinorNatural,
+        _ => ScaleType::Major,
+    };
+    let read: (SpelledNote, ScaleType) = (key_from_fifths(fifths as i8, mode), mode);
+    match score.key {
+        None => score.key = Some(read),
+        Some(first) if first != read => warn(
+            &mut score.warnings,
+            format!(
+                "Measure {}: the key change to {} was ignored (the first key is used)",
+                measure, read.0
+            ),
+        ),
+        Some(_) => {}
+    }
+}
+
+/// Keeps the first time signature of the score.
+fn read_time(meter: XmlNode, measure: usize, score: &mut ImportedScore) {
+    let beats: Option<u8> = child_text(meter, "beats")
+        .and_then(|b: &str| b.parse::<u8>().ok())
+        .filter(|b: &u8| *b > 0);
+    let beat_type: Option<u8> = child_text(meter, "beat-type")
+        .and_then(|b: &str| b.parse::<u8>().ok())
+        .filter(|b: &u8| *b > 0);
+    let (Some(beats), Some(beat_type)) = (beats, beat_type) else {
+        warn(
+            &mut score.warnings,
+            format!(
+                "Measure {}: an unsupported time signature was ignored",
+                measure
+            ),
+        );
+        return;
+    };
+    if measure == 1 {
+        score.time_signature = (beats, beat_type);
+    } else if score.time_signature != (beats, beat_type) {
+        warn(
+            &mut score.warnings,
+            format!(
+                "Measure {}: the time signature change to {}/{} was ignored",
+                measure, beats, beat_type
+            ),
+        );
+    }
+}
+
+/// Adds a pitched note, joining it to the note it is tied from.
+fn read_note(note: XmlNode, pitch: XmlNode, start: f64, length: f64, score: &mut ImportedScore) {
+    let (Some(spelled), Some(octave)) = (read_step(pitch, ""), child_number(pitch, "octave"))
+    else {
+        return;
+    };
+    let spelled: SpelledPitch = SpelledPitch {
+        note: spelled,
+        octave: octave as i8,
+    };
+    let pitch: Pitch = Pitch::from_midi(spelled.midi());
+    let tied: bool = note
+        .children()
+        .any(|c: XmlNode| c.has_tag_name("tie") && c.attribute("type") == Some("stop"));
+    if tied
+        && let Some(previous) =
+            score.notes.iter_mut().rev().find(|n: &&mut MidiNote| {
+                n.pitch == pitch && (n.start + n.length - start).abs() < 1e-6
+            })
+    {
+        previous.length += length;
+        return;
+    }
+    score.notes.push(MidiNote {
+        pitch,
+        start,
+        length,
+    });
+}
+
+/// Reads the chord symbols (`<harmony>`) and notes of a partwise MusicXML
+/// score. Elements that cannot be represented (suspended chords, repeats,
+/// key changes...) are skipped or simplified and listed in `warnings`.
+pub fn read_musicxml(xml: &str) -> Result<ImportedScore, String> {
+    if xml.starts_with("PK") {
+        return Err(
+            "Compressed MusicXML (.mxl) is not supported: export an uncompressed .musicxml file"
+                .to_string(),
+        );
+    }
+    let options: roxmltree::ParsingOptions = roxmltree::ParsingOptions {
+        allow_dtd: true,
+        ..roxmltree::ParsingOptions::default()
+    };
+    let document: roxmltree::Document = roxmltree::Document::parse_with_options(xml, options)
+        .map_err(|e: roxmltree::Error| format!("Invalid MusicXML: {}", e))?;
+    let root: XmlNode = document.root_element();
+    if root.has_tag_name("score-timewise") {
+        return Err("Timewise MusicXML is not supported: export a partwise score".to_string());
+    }
+    if !root.has_tag_name("score-partwise") {
+        return Err(format!(
+            "Not a MusicXML score: the document is a <{}>",
+            root.tag_name().name()
+        ));
+    }
+
+    let mut score: ImportedScore = ImportedScore {
+        title: child(root, "work")
+            .and_then(|w: XmlNode| child_text(w, "work-title"))
+            .or_else(|| child_text(root, "movement-title"))
+            .map(str::to_string),
+        key: None,
+        time_signature: (4, 4),
+        chords: Vec::<ImportedChord>::new(),
+        notes: Vec::<MidiNote>::new(),
+        warnings: Vec::<String>::new(),
+    };
+    for part in root.children().filter(|c: &XmlNode| c.has_tag_name("part")) {
+        read_part(part, &mut score);
+    }
+
+    // Parts may repeat the same chord symbols
+    score
+        .chords
+        .sort_by(|a: &ImportedChord, b: &ImportedChord| a.start.total_cmp(&b.start));
+    score
+        .chords
+        .dedup_by(|a: &mut ImportedChord, b: &mut ImportedChord| {
+            a.symbol == b.symbol && (a.start - b.start).abs() < 1e-6
+        });
+    score.notes.sort_by(|a: &MidiNote, b: &MidiNote| {
+        a.start.total_cmp(&b.start).then(a.pitch.cmp(&b.pitch))
+    });
+    Ok(score)
+}
+
+impl ImportedScore {
+    /// The key of the score: its first key signature, or else the key that
+    /// best fits its notes and chord symbols.
+    pub fn key(&self) -> (Note, ScaleType) {
+        if let Some((tonic, mode)) = self.key {
+            return (tonic.note(), mode);
+        }
+        let mut profile: [f64; 12] = [0.0; 12];
+        for note in &self.notes {
+            profile[note.pitch.note.as_u8() as usize] += note.length;
+        }
+        for chord in &self.chords {
+            for note in build_chord(chord.symbol.root.note(), chord.symbol.chord_type) {
+                profile[note.as_u8() as usize] += 1.0;
+            }
+        }
+        estimate_key(&profile)
+    }
+
+    /// The chord symbols as a progression in `key`. Without chord symbols,
+    /// the chords are recognized bar by bar from the notes. A bass note that
+    /// belongs to the chord becomes its inversion. Fails when the notes are
+    /// too long to split into bars (see `segment_notes`).
+    pub fn progression(&self, key: (Note, ScaleType)) -> Result<Vec<ProgressionChord>, String> {
+        let chord = |root: Note, chord_type: ChordType, bass: Option<Note>, degree: String| {
+            let mut notes: Vec<Note> = build_chord(root, chord_type);
+            if let Some(i) = notes.iter().position(|n: &Note| Some(*n) == bass) {
+                notes.rotate_left(i);
+            }
+            ProgressionChord {
+                degree,
+                root_note: root,
+                chord_type,
+                function: harmonic_function(key.0, root, chord_type),
+                notes,
+            }
+        };
+
+        if !self.chords.is_empty() {
+            return Ok(self
+                .chords
+                .iter()
+                .map(|c: &ImportedChord| {
+                    let root: Note = c.symbol.root.note();
+                    chord(
+                        root,
+                        c.symbol.chord_type,
+                        c.symbol.bass.map(|b: SpelledNote| b.note()),
+                        roman_numeral(key.0, root, c.symbol.chord_type),
+                    )
+                })
+                .collect());
+        }
+        let segments: Vec<Segment> =
+            segment_notes(&self.notes, Segmentation::Bar, self.time_signature)?;
+        Ok(analyze_segments(&segments, Some(key))
+            .chords
+            .into_iter()
+            .map(|c: AnalyzedChord| chord(c.root_note, c.chord_type, c.bass, c.degree))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::spell_tonic;
+    use crate::parser::parse_progression_formula;
+    use crate::pitch::{Pitch, stack_notes};
+    use crate::voice_leading::{VoiceLeadingOptions, VoiceRange, VoicedChord, voice_progression};
+    use crate::{
+        HarmonicFormula, Note, ProgressionChord, ScaleType, build_custom_progression,
+        build_progression, build_scale,
+    };
+
+    #[test]
+    fn test_musicxml_scale() {
+        let pitches: Vec<Pitch> = stack_notes(&build_scale(Note::ASharp, ScaleType::Major), 4);
+        let xml: String = write_musicxml(&Score::scale(
+            "Bb Major",
+            spell_tonic(Note::ASharp, ScaleType::Major),
+            ScaleType::Major,
+            &pitches,
+        ));
+        assert!(xml.starts_with("<?xml"));
+        assert!(xml.contains("<fifths>-2</fifths>"));
+        assert!(xml.contains("<mode>major</mode>"));
+        assert_eq!(xml.matches("<measure ").count(), 7);
+        // Bb and Eb are in the key signature: no accidentals are printed
+        assert!(xml.contains("<step>B</step>\n          <alter>-1</alter>"));
+        assert!(!xml.contains("<accidental>"));
+        assert!(!xml.contains("<harmony>"));
+        assert!(xml.ends_with("</score-partwise>\n"));
+    }
+
+    #[test]
+    fn test_musicxml_progression_symbols_and_staves() {
+        let progression: Vec<ProgressionChord> = build_custom_progression(
+            Note::A,
+            parse_progression_formula("i-iv-V7-i").unwrap(),
+            &VoiceLeadingOptions::default(),
+        );
+        let xml: String = write_musicxml(&Score::progression(
+            "Am",
+            spell_tonic(Note::A, ScaleType::MinorNatural),
+            None,
+            &progression,
+            None,
+        ));
+        assert!(xml.contains("<mode>minor</mode>"));
+        assert!(xml.contains("<kind text=\"7\">dominant</kind>"));
+        assert!(xml.contains(
+            "<bass>\n          <bass-step>G</bass-step>\n          <bass-alter>1</bass-alter>"
+        ));
+        // The leading tone needs its sharp in A minor
+        assert!(xml.contains("<accidental>sharp</accidental>"));
+        assert!(xml.contains("<text>V7</text>"));
+        assert!(!xml.contains("<staves>"));
+
+        let c_major: Vec<ProgressionChord> = build_progression(
+            Note::C,
+            HarmonicFormula::Circle,
+            &VoiceLeadingOptions::default(),
+        );
+        let satb: Vec<VoicedChord> = voice_progression(&c_major, &VoiceRange::satb()).unwrap();
+        let xml: String = write_musicxml(&Score::progression(
+            "SATB",
+            spell_tonic(Note::C, ScaleType::Major),
+            None,
+            &c_major,
+            Some(&satb),
+        ));
+        assert!(xml.contains("<staves>2</staves>"));
+        assert_eq!(xml.matches("<backup>").count(), c_major.len());
+        assert_eq!(xml.matches("<staff>1</staff>").count(), 2 * c_major.len());
+        assert_eq!(xml.matches("<staff>2</staff>").count(), 2 * c_major.len());
+    }
+
+    #[test]
+    fn test_read_musicxml_round_trip() {
+        let progression: Vec<ProgressionChord> = build_custom_progression(
+            Note::A,
+            parse_progression_formula("i-iv-V7-i").unwrap(),
+            &VoiceLeadingOptions::default(),
+        );
+        let xml: String = write_musicxml(&Score::progression(
+            "Am",
+            spell_tonic(Note::A, ScaleType::MinorNatural),
+            None,
+            &progression,
+            None,
+        ));
+        let score: ImportedScore = read_musicxml(&xml).unwrap();
+        assert_eq!(score.title.as_deref(), Some("Am"));
+        assert!(score.warnings.is_empty());
+        assert_eq!(score.key(), (Note::A, ScaleType::MinorNatural));
+        let symbols: Vec<String> = score
+            .chords
+            .iter()
+            .map(|c: &ImportedChord| c.symbol.to_string())
+            .collect();
+        assert_eq!(symbols, ["Am", "Dm/A", "E7/G#", "Am"]);
+        assert_eq!(score.chords[2].start, 8.0);
+        assert_eq!(score.notes.len(), 13);
+
+        let read: Vec<ProgressionChord> = score.progression(score.key()).unwrap();
+        assert_eq!(read[2].degree, "V7");
+        assert_eq!(read[2].notes, progression[2].notes);
+        assert_eq!(read[1].function, progression[1].function);
+    }
+
+    #[test]
+    fn test_read_musicxml_lead_sheet() {
+        let xml: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<score-partwise version="3.1">
+  <movement-title>Lead Sheet</movement-title>
+  <part-list><score-part id="P1"><part-name>Voice</part-name></score-part></part-list>
+  <part id="P1">
+    <measure number="1">
+      <attributes>
+        <divisions>2</divisions>
+        <key><fifths>-1</fifths></key>
+        <time><beats>3</beats><beat-type>4</beat-type></time>
+      </attributes>
+      <harmony><root><root-step>F</root-step></root><kind>major-sixth</kind></harmony>
+      <note><pitch><step>A</step><octave>4</octave></pitch><duration>4</duration></note>
+      <harmony><root><root-step>B</root-step><root-alter>-1</root-alter></root><kind>major</kind><inversion>1</inversion><offset>1</offset></harmony>
+      <note><pitch><step>B</step><alter>-1</alter><octave>4</octave></pitch><duration>2</duration><tie type="start"/></note>
+    </measure>
+    <measure number="2">
+      <harmony><root><root-step>C</root-step></root><kind>suspended-fourth</kind></harmony>
+      <harmony><root><root-step>C</root-step></root><kind>dominant</kind><degree><degree-value>9</degree-value><degree-alter>0</degree-alter><degree-type>add</degree-type></degree></harmony>
+      <note><pitch><step>B</step><alter>-1</alter><octave>4</octave></pitch><duration>2</duration><tie type="stop"/></note>
+      <note><rest/><duration>4</duration></note>
+      <backup><duration>6</duration></backup>
+      <note><pitch><step>C</step><octave>3</octave></pitch><duration>6</duration><voice>2</voice></note>
+      <note><chord/><pitch><step>G</step><octave>3</octave></pitch><duration>6</duration><voice>2</voice></note>
+    </measure>
+    <measure number="3">
+      <attributes><key><fifths>0</fifths></key></attributes>
+      <harmony><kind>none</kind></harmony>
+      <note><unpitched><display-step>C</display-step><display-octave>5</display-octave></unpitched><duration>6</duration></note>
+      <barline location="right"><repeat direction="backward"/></barline>
+    </measure>
+  </part>
+</score-partwise>"#;
+        let score: ImportedScore = read_musicxml(xml).unwrap();
+        assert_eq!(score.title.as_deref(), Some("Lead Sheet"));
+        assert_eq!(score.time_signature, (3, 4));
+        assert_eq!(score.key().0, Note::F);
+
+        // F6 is simplified, sus4 skipped, the added ninth ignored and N.C. dropped
+        let symbols: Vec<String> = score
+            .chords
+            .iter()
+            .map(|c: &ImportedChord| c.symbol.to_string())
+            .collect();
+        assert_eq!(symbols, ["F", "Bb/D", "C7"]);
+        assert_eq!(score.chords[1].start, 2.5);
+        assert_eq!(score.chords[2].measure, 2);
+        assert_eq!(score.warnings.len(), 6, "{:?}", score.warnings);
+        assert!(score.warnings[0].contains("major-sixth was read as F"));
+
+        // The tied Bb lasts two beats; the backup puts the fifth under the melody
+        let notes: Vec<(String, f64, f64)> = score
+            .notes
+            .iter()
+            .map(|n: &MidiNote| (n.pitch.to_string(), n.start, n.length))
+            .collect();
+        assert_eq!(
+            notes,
+            [
+                ("A4".to_string(), 0.0, 2.0),
+                ("A#4".to_string(), 2.0, 2.0),
+                ("C3".to_string(), 3.0, 3.0),
+                ("G3".to_string(), 3.0, 3.0),
+            ]
+        );
+
+        let progression: Vec<ProgressionChord> = score.progression(score.key()).unwrap();
+        assert_eq!(progression[1].notes, [Note::D, Note::F, Note::ASharp]);
+        assert_eq!(progression[2].degree, "V7");
+    }
+
+    #[test]
+    fn test_read_musicxml_notes_only_and_errors() {
+        let pitches: Vec<Vec<Pitch>> = vec![
+            stack_notes(&[Note::C, Note::E, Note::G], 4),
+            stack_notes(&[Note::G, Note::B, Note::D, Note::F], 3),
+        ];
+        let mut score: Score = Score::chord(
+            "C",
+            spell_tonic(Note::C, ScaleType::Major),
+            ChordType::Major,
+            &pitches,
+        );
+        for measure in &mut score.measures {
+            measure.symbol = None;
+        }
+        let imported: ImportedScore = read_musicxml(&write_musicxml(&score)).unwrap();
+        assert!(imported.chords.is_empty());
+        let progression: Vec<ProgressionChord> = imported.progression(imported.key()).unwrap();
+        assert_eq!(progression.len(), 2);
+        assert_eq!(progression[1].chord_type, ChordType::Dominant7);
+
+        assert!(read_musicxml("PK\u{3}\u{4}").is_err());
+        assert!(read_musicxml("<score-timewise/>").is_err());
+        assert!(read_musicxml("<svg/>").is_err());
+        assert!(read_musicxml("<score-partwise>").is_err());
+    }
+}
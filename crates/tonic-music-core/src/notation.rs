@@ -0,0 +1,693 @@
+/*
+ * tonic-music-core
+ * Copyright (C) 2025 Israel Alberto Roldan Vega
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * Repository: https://github.com/airvzxf/music-theory-calculator/
+ */
+
+/*
+ * crates/tonic-music-core/src/notation.rs
+ *
+ * This module spells notes for a key (Bb instead of A#) and lays out
+ * scales, chords, harmonizations and progressions as measures of a score,
+ * ready for the notation writers (MusicXML, LilyPond, ABC).
+ */
+
+use crate::pitch::{Pitch, stack_notes};
+use crate::voice_leading::VoicedChord;
+use crate::{ChordType, HarmonizedDegree, Interval, Note, ProgressionChord, ScaleType};
+use std::collections::HashMap;
+
+/// The seven note letters, from C.
+const STEPS: [char; 7] = ['C', 'D', 'E', 'F', 'G', 'A', 'B'];
+/// Semitones of the natural notes above C.
+const NATURALS: [u8; 7] = [0, 2, 4, 5, 7, 9, 11];
+/// The letter of the major key of each pitch class with the fewest
+/// accidentals (Db rather than C#, F# rather than Gb).
+const MAJOR_KEYS: [usize; 12] = [0, 1, 1, 2, 2, 3, 3, 4, 5, 5, 6, 6];
+/// MIDI number of middle C: lower notes go to the bass staff.
+pub const MIDDLE_C: i16 = 60;
+
+/// A note with its letter name (step) and alteration in semitones (-1 = flat, 1 = sharp).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SpelledNote {
+    pub step: char,
+    pub alter: i8,
+}
+
+impl SpelledNote {
+    /// Spells a note with the letter at `index` (0 = C ... 6 = B).
+    fn from_index(note: Note, index: usize) -> Self {
+        let index: usize = index % 7;
+        let alter: i8 = (note.as_u8() as i8 - NATURALS[index] as i8).rem_euclid(12);
+        SpelledNote {
+            step: STEPS[index],
+            alter: if alter > 6 { alter - 12 } else { alter },
+        }
+    }
+
+    /// Position of the letter, from C = 0 to B = 6.
+    fn index(&self) -> usize {
+        STEPS
+            .iter()
+            .position(|s: &char| *s == self.step)
+            .unwrap_or(0)
+    }
+
+    /// Spells a note with the letter `steps` letters above this one
+    /// (e.g. 2 letters above E for G#: a third).
+    pub fn above(&self, steps: usize, note: Note) -> Self {
+        SpelledNote::from_index(note, self.index() + steps)
+    }
+
+    /// The number of letters from this note up to `other` (C to A is 5).
+    pub fn steps_to(&self, other: SpelledNote) -> usize {
+        (other.index() + 7 - self.index()) % 7
+    }
+
+    /// The pitch class of the spelled note (Bb and A# are both A#).
+    pub fn note(&self) -> Note {
+        Note::from_u8((NATURALS[self.index()] as i8 + self.alter).rem_euclid(12) as u8)
+    }
+}
+
+impl std::fmt::Display for SpelledNote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let accidental: &str = if self.alter < 0 { "b" } else { "#" };
+        write!(
+            f,
+            "{}{}",
+            self.step,
+            accidental.repeat(self.alter.unsigned_abs() as usize)
+        )
+    }
+}
+
+/// A spelled note in a specific octave. The octave follows the letter,
+/// so B#3 and C4 are the same key on the piano.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SpelledPitch {
+    pub note: SpelledNote,
+    pub octave: i8,
+}
+
+impl SpelledPitch {
+    /// Spells a pitch with the given name, which must be the same pitch class.
+    pub fn new(pitch: Pitch, note: SpelledNote) -> Self {
+        let natural: i16 = pitch.midi() - note.alter as i16;
+        SpelledPitch {
+            note,
+            octave: ((natural - NATURALS[note.index()] as i16).div_euclid(12) - 1) as i8,
+        }
+    }
+
+    /// The MIDI note number of the spelled pitch (B#3 is 60, like C4).
+    pub fn midi(&self) -> i16 {
+        (self.octave as i16 + 1) * 12 + NATURALS[self.note.index()] as i16 + self.note.alter as i16
+    }
+}
+
+impl std::fmt::Display for SpelledPitch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.note, self.octave)
+    }
+}
+
+/// Returns true for the scales and chords written with a minor key signature.
+fn is_minor(scale_type: ScaleType) -> bool {
+    !matches!(scale_type, ScaleType::Major | ScaleType::PentatonicMajor)
+}
+
+/// Spells the tonic of a key with the fewest accidentals: the major key,
+/// or the tonic of the relative minor (A# minor is written Bb minor).
+pub fn spell_tonic(tonic: Note, scale_type: ScaleType) -> SpelledNote {
+    if is_minor(scale_type) {
+        let relative: Note = Note::from_u8((tonic.as_u8() + 3) % 12);
+        // The minor tonic is a major sixth (five letters) above its relative major
+        spell_tonic(relative, ScaleType::Major).above(5, tonic)
+    } else {
+        SpelledNote::from_index(tonic, MAJOR_KEYS[tonic.as_u8() as usize])
+    }
+}
+
+/// The key signature of a key, in fifths: sharps are positive, flats negative
+/// (e.g. -2 for Bb major and G minor, 6 for D# minor and -6 for Eb minor).
+pub fn key_fifths(tonic: SpelledNote, scale_type: ScaleType) -> i8 {
+    // The major key of each natural letter in the circle of fifths (F = -1 ... B = 5)
+    const LETTER_FIFTHS: [i8; 7] = [0, 2, 4, -1, 1, 3, 5];
+    let major: i8 = LETTER_FIFTHS[tonic.index()] + 7 * tonic.alter;
+    if is_minor(scale_type) {
+        major - 3
+    } else {
+        major
+    }
+}
+
+/// The tonic of a key signature (-2 fifths is Bb major or G minor).
+pub fn key_from_fifths(fifths: i8, scale_type: ScaleType) -> SpelledNote {
+    // Each fifth is four letters and seven semitones above C
+    let letters: usize = (fifths as i16 * 4).rem_euclid(7) as usize;
+    let major: SpelledNote = SpelledNote::from_index(
+        Note::from_u8((fifths as i16 * 7).rem_euclid(12) as u8),
+        letters,
+    );
+    if is_minor(scale_type) {
+        major.above(5, Note::from_u8((major.note().as_u8() + 9) % 12))
+    } else {
+        major
+    }
+}
+
+/// The alteration the key signature gives to a letter (1 for F in G major).
+pub fn key_alter(fifths: i8, step: char) -> i8 {
+    const SHARPS: [char; 7] = ['F', 'C', 'G', 'D', 'A', 'E', 'B'];
+    let order: usize = SHARPS.iter().position(|s: &char| *s == step).unwrap_or(0);
+    if fifths > 0 && order < fifths as usize {
+        1
+    } else if fifths < 0 && 6 - order < fifths.unsigned_abs() as usize {
+        -1
+    } else {
+        0
+    }
+}
+
+/// Spells any note as a degree of the key: the diatonic degrees, the lowered
+/// 2nd, 3rd, 6th and 7th, and the raised 4th (bVII in C is Bb, V/V has an F#).
+pub fn spell_in_key(note: Note, tonic: SpelledNote) -> SpelledNote {
+    const DEGREE_STEPS: [usize; 12] = [0, 1, 1, 2, 2, 3, 3, 4, 5, 5, 6, 6];
+    let semitones: u8 = (note.as_u8() + 12 - tonic.note().as_u8()) % 12;
+    tonic.above(DEGREE_STEPS[semitones as usize], note)
+}
+
+/// Number of letters spanned by a scale interval (a tritone is a raised 4th).
+fn interval_steps(interval: Interval) -> usize {
+    match interval {
+        Interval::Unison | Interval::Octave => 0,
+        Interval::MinorSecond | Interval::MajorSecond => 1,
+        Interval::MinorThird | Interval::MajorThird => 2,
+        Interval::PerfectFourth | Interval::Tritone => 3,
+        Interval::PerfectFifth => 4,
+        Interval::MinorSixth | Interval::MajorSixth => 5,
+        Interval::MinorSeventh | Interval::MajorSeventh => 6,
+    }
+}
+
+/// Spells a scale with one letter per degree (A harmonic minor has a G#, not an Ab).
+pub fn spell_scale(tonic: SpelledNote, scale_type: ScaleType) -> Vec<SpelledNote> {
+    let root: Note = tonic.note();
+    scale_type
+        .intervals()
+        .iter()
+        .map(|i: &Interval| tonic.above(interval_steps(*i), crate::transpose(root, *i)))
+        .collect()
+}
+
+/// Spells a chord in thirds from its root (E7 is E G# B D; G#dim7 is G# B D F).
+pub fn spell_chord(root: SpelledNote, chord_type: ChordType) -> Vec<SpelledNote> {
+    crate::build_chord(root.note(), chord_type)
+        .into_iter()
+        .enumerate()
+        .map(|(i, note): (usize, Note)| root.above(i * 2, note))
+        .collect()
+}
+
+/// A chord symbol written above the staff (e.g. "E7/G#").
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChordSymbol {
+    pub root: SpelledNote,
+    pub chord_type: ChordType,
+    /// The bass note, when it is not the root
+    pub bass: Option<SpelledNote>,
+}
+
+/// The suffix of a chord symbol in lead sheets ("m7", "dim", "+maj7"...).
+pub fn chord_suffix(chord_type: ChordType) -> &'static str {
+    match chord_type {
+        ChordType::Major => "",
+        ChordType::Minor => "m",
+        ChordType::Diminished => "dim",
+        ChordType::Augmented => "+",
+        ChordType::Major7 => "maj7",
+        ChordType::Minor7 => "m7",
+        ChordType::Dominant7 => "7",
+        ChordType::Minor7b5 => "m7b5",
+        ChordType::Diminished7 => "dim7",
+        ChordType::MinorMajor7 => "m(maj7)",
+        ChordType::AugmentedMajor7 => "+maj7",
+    }
+}
+
+impl std::fmt::Display for ChordSymbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.root, chord_suffix(self.chord_type))?;
+        if let Some(bass) = &self.bass {
+            write!(f, "/{}", bass)?;
+        }
+        Ok(())
+    }
+}
+
+/// One measure of a score: a single note or a block chord lasting the whole measure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Measure {
+    pub symbol: Option<ChordSymbol>,
+    /// The pitches sounding together, lowest first
+    pub pitches: Vec<SpelledPitch>,
+    /// Text written below the staff (the Roman numeral of a progression chord)
+    pub label: Option<String>,
+}
+
+impl Measure {
+    /// The pitches of the upper staff of a grand staff (middle C and above).
+    pub fn treble(&self) -> Vec<&SpelledPitch> {
+        self.pitches
+            .iter()
+            .filter(|p: &&SpelledPitch| p.midi() >= MIDDLE_C)
+            .collect()
+    }
+
+    /// The pitches of the lower staff of a grand staff (below middle C).
+    pub fn bass(&self) -> Vec<&SpelledPitch> {
+        self.pitches
+            .iter()
+            .filter(|p: &&SpelledPitch| p.midi() < MIDDLE_C)
+            .collect()
+    }
+}
+
+/// The staff (or staves) a score is written on.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Staves {
+    Treble,
+    Bass,
+    /// Treble and bass staff joined by a brace, split at middle C
+    Grand,
+}
+
+/// Keeps the accidentals in force in a measure, so the writers only
+/// print the ones the key signature or an earlier note does not give.
+#[derive(Debug, Clone)]
+pub struct Accidentals {
+    fifths: i8,
+    shown: HashMap<(char, i8), i8>,
+}
+
+impl Accidentals {
+    pub fn new(fifths: i8) -> Self {
+        Accidentals {
+            fifths,
+            shown: HashMap::<(char, i8), i8>::new(),
+        }
+    }
+
+    /// The accidental to print before the pitch (0 for a natural sign), if any.
+    pub fn show(&mut self, pitch: &SpelledPitch) -> Option<i8> {
+        let key: (char, i8) = (pitch.note.step, pitch.octave);
+        let current: i8 = *self
+            .shown
+            .get(&key)
+            .unwrap_or(&key_alter(self.fifths, pitch.note.step));
+        if current == pitch.note.alter {
+            return None;
+        }
+        self.shown.insert(key, pitch.note.alter);
+        Some(pitch.note.alter)
+    }
+}
+
+/// A single-part score in 4/4, one measure per note or chord.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Score {
+    pub title: String,
+    pub tonic: SpelledNote,
+    pub mode: ScaleType,
+    /// The key signature in fifths (see `key_fifths`)
+    pub fifths: i8,
+    pub measures: Vec<Measure>,
+}
+
+impl Score {
+    /// Starts a score in the key of `tonic` as spelled. Keys with more than
+    /// six sharps or flats are written enharmonically (C# major as Db major).
+    fn new(title: &str, tonic: SpelledNote, mode: ScaleType) -> Self {
+        let tonic: SpelledNote = if key_fifths(tonic, mode).abs() > 6 {
+            spell_tonic(tonic.note(), mode)
+        } else {
+            tonic
+        };
+        Score {
+            title: title.to_string(),
+            tonic,
+            mode,
+            fifths: key_fifths(tonic, mode),
+            measures: Vec::<Measure>::new(),
+        }
+    }
+
+    /// True if the key signature is minor.
+    pub fn is_minor(&self) -> bool {
+        is_minor(self.mode)
+    }
+
+    /// The staves for the pitches: a grand staff when they go both below
+    /// and above middle C.
+    pub fn staves(&self) -> Staves {
+        let pitches: Vec<&SpelledPitch> = self
+            .measures
+            .iter()
+            .flat_map(|m: &Measure| m.pitches.iter())
+            .collect();
+        let low: bool = pitches.iter().any(|p: &&SpelledPitch| p.midi() < MIDDLE_C);
+        let high: bool = pitches.iter().any(|p: &&SpelledPitch| p.midi() >= MIDDLE_C);
+        match (low, high) {
+            (true, true) => Staves::Grand,
+            (true, false) => Staves::Bass,
+            _ => Staves::Treble,
+        }
+    }
+
+    /// Adds a chord measure: the pitches are spelled as chord tones of the symbol.
+    fn push_chord(
+        &mut self,
+        root: Note,
+        chord_type: ChordType,
+        pitches: &[Pitch],
+        label: Option<String>,
+    ) {
+        let root: SpelledNote = spell_in_key(root, self.tonic);
+        let tones: Vec<SpelledNote> = spell_chord(root, chord_type);
+        let spell = |note: Note| -> SpelledNote {
+            tones
+                .iter()
+                .find(|t: &&SpelledNote| t.note() == note)
+                .copied()
+                .unwrap_or_else(|| spell_in_key(note, self.tonic))
+        };
+        let bass: Option<SpelledNote> = pitches
+            .iter()
+            .min()
+            .map(|p: &Pitch| spell(p.note))
+            .filter(|b: &SpelledNote| *b != root);
+        let measure: Measure = Measure {
+            symbol: Some(ChordSymbol {
+                root,
+                chord_type,
+                bass,
+            }),
+            pitches: pitches
+                .iter()
+                .map(|p: &Pitch| SpelledPitch::new(*p, spell(p.note)))
+                .collect(),
+            label,
+        };
+        self.measures.push(measure);
+    }
+
+    /// A scale as a line of notes, one measure per note.
+    pub fn scale(
+        title: &str,
+        tonic: SpelledNote,
+        scale_type: ScaleType,
+        pitches: &[Pitch],
+    ) -> Self {
+        let mut score: Score = Score::new(title, tonic, scale_type);
+        let spelled: Vec<SpelledNote> = spell_scale(score.tonic, scale_type);
+        for pitch in pitches {
+            let note: SpelledNote = spelled
+                .iter()
+                .find(|s: &&SpelledNote| s.note() == pitch.note)
+                .copied()
+                .unwrap_or_else(|| spell_in_key(pitch.note, score.tonic));
+            score.measures.push(Measure {
+                symbol: None,
+                pitches: vec![SpelledPitch::new(*pitch, note)],
+                label: None,
+            });
+        }
+        score
+    }
+
+    /// A chord (or its inversions), one measure per voicing, in the key of its root.
+    pub fn chord(
+        title: &str,
+        root: SpelledNote,
+        chord_type: ChordType,
+        voicings: &[Vec<Pitch>],
+    ) -> Self {
+        let mode: ScaleType = if matches!(
+            chord_type,
+            ChordType::Major
+                | ChordType::Augmented
+                | ChordType::Major7
+                | ChordType::Dominant7
+                | ChordType::AugmentedMajor7
+        ) {
+            ScaleType::Major
+        } else {
+            ScaleType::MinorNatural
+        };
+        let mut score: Score = Score::new(title, root, mode);
+        for pitches in voicings {
+            score.push_chord(root.note(), chord_type, pitches, None);
+        }
+        score
+    }
+
+    /// A harmonized scale as block chords from the 4th octave, with chord symbols.
+    pub fn harmony(
+        title: &str,
+        tonic: SpelledNote,
+        scale_type: ScaleType,
+        degrees: &[HarmonizedDegree],
+    ) -> Self {
+        let mut score: Score = Score::new(title, tonic, scale_type);
+        for degree in degrees {
+            let pitches: Vec<Pitch> = stack_notes(&degree.notes, 4);
+            score.push_chord(degree.root_note, degree.chord_type, &pitches, None);
+        }
+        score
+    }
+
+    /// A progression as block chords with chord symbols and Roman numerals.
+    /// Without a voicing, each chord is stacked from the 4th octave in the
+    /// inversion the voice leading chose. Without a `mode`, the key is minor
+    /// when the chord on the tonic is.
+    pub fn progression(
+        title: &str,
+        tonic: SpelledNote,
+        mode: Option<ScaleType>,
+        progression: &[ProgressionChord],
+        voicing: Option<&[VoicedChord]>,
+    ) -> Self {
+        let mode: ScaleType = mode.unwrap_or_else(|| {
+            let minor: bool = progression
+                .iter()
+                .find(|c: &&ProgressionChord| c.root_note == tonic.note())
+                .is_some_and(|c: &ProgressionChord| {
+                    matches!(
+                        c.chord_type,
+                        ChordType::Minor | ChordType::Minor7 | ChordType::MinorMajor7
+                    )
+                });
+            if minor {
+                ScaleType::MinorNatural
+            } else {
+                ScaleType::Major
+            }
+        });
+        let mut score: Score = Score::new(title, tonic, mode);
+        for (i, chord) in progression.iter().enumerate() {
+            let pitches: Vec<Pitch> = match voicing {
+                Some(voicing) => voicing[i].pitches.clone(),
+                None => stack_notes(&chord.notes, 4),
+            };
+            score.push_chord(
+                chord.root_note,
+                chord.chord_type,
+                &pitches,
+                Some(chord.degree.clone()),
+            );
+        }
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_spelled_note;
+    use crate::voice_leading::VoiceLeadingOptions;
+    use crate::{HarmonicFormula, build_progression, build_scale, harmonize_scale};
+
+    fn names(notes: &[SpelledNote]) -> Vec<String> {
+        notes.iter().map(|n: &SpelledNote| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_key_signatures() {
+        let fifths = |name: &str, scale_type: ScaleType| -> i8 {
+            key_fifths(parse_spelled_note(name).unwrap(), scale_type)
+        };
+        assert_eq!(fifths("C", ScaleType::Major), 0);
+        assert_eq!(fifths("G", ScaleType::Major), 1);
+        assert_eq!(fifths("F", ScaleType::Major), -1);
+        assert_eq!(fifths("Bb", ScaleType::Major), -2);
+        assert_eq!(fifths("Db", ScaleType::Major), -5);
+        assert_eq!(fifths("F#", ScaleType::Major), 6);
+        assert_eq!(fifths("Gb", ScaleType::Major), -6);
+        assert_eq!(fifths("A", ScaleType::MinorNatural), 0);
+        assert_eq!(fifths("D", ScaleType::MinorHarmonic), -1);
+        assert_eq!(fifths("D#", ScaleType::MinorNatural), 6);
+        assert_eq!(fifths("Eb", ScaleType::MinorNatural), -6);
+        assert_eq!(fifths("G#", ScaleType::PentatonicMinor), 5);
+
+        assert_eq!(
+            spell_tonic(Note::ASharp, ScaleType::Major).to_string(),
+            "Bb"
+        );
+        assert_eq!(
+            spell_tonic(Note::ASharp, ScaleType::MinorNatural).to_string(),
+            "Bb"
+        );
+        assert_eq!(
+            spell_tonic(Note::DSharp, ScaleType::MinorNatural).to_string(),
+            "D#"
+        );
+        assert_eq!(key_from_fifths(-2, ScaleType::Major).to_string(), "Bb");
+        assert_eq!(
+            key_from_fifths(-2, ScaleType::MinorNatural).to_string(),
+            "G"
+        );
+        assert_eq!(
+            key_from_fifths(6, ScaleType::MinorNatural).to_string(),
+            "D#"
+        );
+        assert_eq!(key_from_fifths(-7, ScaleType::Major).to_string(), "Cb");
+        assert_eq!(key_alter(-2, 'B'), -1);
+        assert_eq!(key_alter(-2, 'A'), 0);
+        assert_eq!(key_alter(3, 'G'), 1);
+        assert_eq!(key_alter(3, 'D'), 0);
+    }
+
+    #[test]
+    fn test_spell_scales_and_chords() {
+        let f: SpelledNote = spell_tonic(Note::F, ScaleType::Major);
+        assert_eq!(
+            names(&spell_scale(f, ScaleType::Major)),
+            ["F", "G", "A", "Bb", "C", "D", "E"]
+        );
+        let a: SpelledNote = spell_tonic(Note::A, ScaleType::MinorHarmonic);
+        assert_eq!(
+            names(&spell_scale(a, ScaleType::MinorHarmonic)),
+            ["A", "B", "C", "D", "E", "F", "G#"]
+        );
+        let d_sharp: SpelledNote = spell_tonic(Note::DSharp, ScaleType::MinorHarmonic);
+        assert_eq!(
+            spell_scale(d_sharp, ScaleType::MinorHarmonic)[6].to_string(),
+            "C##"
+        );
+
+        let g_sharp: SpelledNote = spell_in_key(Note::GSharp, a);
+        assert_eq!(
+            names(&spell_chord(g_sharp, ChordType::Diminished7)),
+            ["G#", "B", "D", "F"]
+        );
+        let c: SpelledNote = spell_tonic(Note::C, ScaleType::Major);
+        assert_eq!(spell_in_key(Note::ASharp, c).to_string(), "Bb");
+        assert_eq!(spell_in_key(Note::FSharp, c).to_string(), "F#");
+        assert_eq!(spell_in_key(Note::CSharp, c).to_string(), "Db");
+
+        // The octave follows the letter
+        let b_sharp: SpelledNote = SpelledNote {
+            step: 'B',
+            alter: 1,
+        };
+        assert_eq!(b_sharp.note(), Note::C);
+        assert_eq!(
+            SpelledPitch::new(Pitch::new(Note::C, 4), b_sharp).to_string(),
+            "B#3"
+        );
+        assert_eq!(
+            SpelledPitch::new(Pitch::new(Note::C, 4), b_sharp).midi(),
+            60
+        );
+    }
+
+    #[test]
+    fn test_score_layouts() {
+        let eb_major: Vec<Pitch> = stack_notes(&build_scale(Note::DSharp, ScaleType::Major), 4);
+        let scale: Score = Score::scale(
+            "Eb Major Scale",
+            spell_tonic(Note::DSharp, ScaleType::Major),
+            ScaleType::Major,
+            &eb_major,
+        );
+        assert_eq!(scale.fifths, -3);
+        assert_eq!(scale.measures.len(), 7);
+        assert_eq!(scale.measures[0].pitches[0].to_string(), "Eb4");
+        assert_eq!(scale.measures[3].pitches[0].to_string(), "Ab4");
+
+        // The key is written as given, unless it needs more than six sharps or flats
+        let d_sharp: SpelledNote = parse_spelled_note("D#").unwrap();
+        let scale: Score = Score::scale("D# Major", d_sharp, ScaleType::Major, &eb_major);
+        assert_eq!(scale.tonic.to_string(), "Eb");
+        let scale: Score = Score::scale("D# Minor", d_sharp, ScaleType::MinorNatural, &[]);
+        assert_eq!(scale.fifths, 6);
+
+        let harmony: Score = Score::harmony(
+            "A Harmonic Minor",
+            spell_tonic(Note::A, ScaleType::MinorHarmonic),
+            ScaleType::MinorHarmonic,
+            &harmonize_scale(&build_scale(Note::A, ScaleType::MinorHarmonic), true),
+        );
+        assert!(harmony.is_minor());
+        let fifth: &Measure = &harmony.measures[4];
+        let symbol: &ChordSymbol = fifth.symbol.as_ref().unwrap();
+        assert_eq!(symbol.to_string(), "E7");
+        assert_eq!(fifth.pitches[1].to_string(), "G#4");
+
+        let progression: Vec<ProgressionChord> = build_progression(
+            Note::C,
+            HarmonicFormula::Circle,
+            &VoiceLeadingOptions::default(),
+        );
+        let score: Score = Score::progression(
+            "Circle",
+            spell_tonic(Note::C, ScaleType::Major),
+            None,
+            &progression,
+            None,
+        );
+        assert!(!score.is_minor());
+        // A mode given by the caller wins over the guess
+        let minor: Score = Score::progression(
+            "Circle",
+            spell_tonic(Note::C, ScaleType::MinorNatural),
+            Some(ScaleType::MinorNatural),
+            &progression,
+            None,
+        );
+        assert!(minor.is_minor());
+        assert_eq!(minor.fifths, -3);
+        assert_eq!(score.measures.len(), progression.len());
+        assert_eq!(score.measures[0].label.as_deref(), Some("I"));
+        // An inverted chord names its bass
+        for (measure, chord) in score.measures.iter().zip(&progression) {
+            let symbol: &ChordSymbol = measure.symbol.as_ref().unwrap();
+            assert_eq!(symbol.bass.is_some(), chord.notes[0] != chord.root_note);
+        }
+    }
+}
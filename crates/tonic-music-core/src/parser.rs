@@ -26,7 +26,9 @@
  */
 
 // We need to import the types from our library
-use crate::{ChordType, Interval, Note};
+use crate::notation::SpelledNote;
+use crate::pitch::Pitch;
+use crate::{ChordType, Interval, Note, ScaleType};
 use clap::ValueEnum;
 
 /// Parses a string into a Note enum. Returns Err if invalid.
@@ -49,12 +51,179 @@ pub fn parse_note(s: &str) -> Result<Note, String> {
     }
 }
 
+/// Parses a note name keeping its spelling (e.g. "Bb" stays Bb instead of A#).
+/// Up to two sharps or flats are accepted ("F##", "Cb", "Ebb").
+pub fn parse_spelled_note(s: &str) -> Result<SpelledNote, String> {
+    let s: &str = s.trim();
+    let mut chars: std::str::Chars = s.chars();
+    let step: char = chars
+        .next()
+        .map(|c: char| c.to_ascii_uppercase())
+        .filter(|c: &char| ('A'..='G').contains(c))
+        .ok_or_else(|| format!("Invalid note name: {}", s))?;
+    let alter: i8 = match chars.as_str() {
+        "" => 0,
+        "#" => 1,
+        "##" | "x" => 2,
+        "b" => -1,
+        "bb" => -2,
+        _ => return Err(format!("Invalid note name: {}", s)),
+    };
+    Ok(SpelledNote { step, alter })
+}
+
+/// Parses the quality written after the root of a chord symbol ("", "m",
+/// "maj7", "m7b5", "°7"...). Returns `None` for chords outside the eleven
+/// chord types, such as "sus4", "6" or "add9".
+pub fn parse_chord_quality(suffix: &str) -> Option<ChordType> {
+    match suffix.trim() {
+        "" | "M" | "maj" => Some(ChordType::Major),
+        "m" | "min" | "mi" | "-" => Some(ChordType::Minor),
+        "dim" | "°" | "o" => Some(ChordType::Diminished),
+        "aug" | "+" | "(#5)" => Some(ChordType::Augmented),
+        "maj7" | "M7" | "Maj7" | "ma7" | "Δ" | "Δ7" => Some(ChordType::Major7),
+        "m7" | "min7" | "-7" | "mi7" => Some(ChordType::Minor7),
+        "7" | "dom7" => Some(ChordType::Dominant7),
+        "m7b5" | "m7-5" | "m7(b5)" | "-7b5" | "ø" | "ø7" => Some(ChordType::Minor7b5),
+        "dim7" | "°7" | "o7" => Some(ChordType::Diminished7),
+        "m(maj7)" | "mmaj7" | "mM7" | "m(M7)" | "-maj7" | "minmaj7" => Some(ChordType::MinorMajor7),
+        "+maj7" | "maj7#5" | "maj7(#5)" | "augmaj7" | "aug(maj7)" | "+M7" => {
+            Some(ChordType::AugmentedMajor7)
+        }
+        _ => None,
+    }
+}
+
+/// Parses a pitch in scientific notation (e.g. "C4", "Bb3", "F#-1").
+pub fn parse_pitch(s: &str) -> Result<Pitch, String> {
+    let s: &str = s.trim();
+    // The octave starts at the first digit or minus sign after the note name
+    let split: usize = s
+        .char_indices()
+        .skip(1)
+        .find(|(_, c): &(usize, char)| c.is_ascii_digit() || *c == '-')
+        .map(|(i, _): (usize, char)| i)
+        .ok_or_else(|| format!("Invalid pitch (missing octave): {}", s))?;
+
+    let note: Note = parse_note(&s[..split])?;
+    let octave: i8 = s[split..]
+        .parse::<i8>()
+        .map_err(|_| format!("Invalid octave in pitch: {}", s))?;
+    if !(-1..=9).contains(&octave) {
+        return Err(format!("Octave out of range (-1 to 9): {}", s));
+    }
+
+    Ok(Pitch::new(note, octave))
+}
+
+/// Parses a tuning as space- or comma-separated pitches, from the lowest
+/// string of a chord diagram to the 1st string (e.g. "E2 A2 D3 G3 B3 E4").
+pub fn parse_tuning(s: &str) -> Result<Vec<Pitch>, String> {
+    let tuning: Vec<Pitch> = s
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|p: &&str| !p.is_empty())
+        .map(parse_pitch)
+        .collect::<Result<Vec<Pitch>, String>>()?;
+    if tuning.is_empty() {
+        return Err("Empty tuning".to_string());
+    }
+    Ok(tuning)
+}
+
+/// Parses a key such as "C", "Eb major", "F#m" or "A minor" into its tonic and
+/// scale (major or natural minor).
+pub fn parse_key(s: &str) -> Result<(Note, ScaleType), String> {
+    let key: String = s.trim().to_lowercase();
+    let (tonic, mode): (&str, ScaleType) = [
+        (" minor", ScaleType::MinorNatural),
+        ("min", ScaleType::MinorNatural),
+        ("m", ScaleType::MinorNatural),
+        (" major", ScaleType::Major),
+        ("maj", ScaleType::Major),
+    ]
+    .iter()
+    .find_map(|(suffix, mode): &(&str, ScaleType)| {
+        key.strip_suffix(suffix).map(|t: &str| (t, *mode))
+    })
+    .unwrap_or((key.as_str(), ScaleType::Major));
+    let tonic: Note = parse_note(tonic.trim())
+        .map_err(|_| format!("Invalid key: '{}' (e.g. C, Am, Eb major)", s))?;
+    Ok((tonic, mode))
+}
+
+/// Parses a time signature such as "4/4", "3/4" or "6/8" (the denominator
+/// must be a power of two).
+pub fn parse_time_signature(s: &str) -> Result<(u8, u8), String> {
+    let invalid = || format!("Invalid time signature: '{}' (e.g. 4/4, 6/8)", s);
+    let (numerator, denominator): (&str, &str) = s.trim().split_once('/').ok_or_else(invalid)?;
+    let numerator: u8 = numerator.trim().parse().map_err(|_| invalid())?;
+    let denominator: u8 = denominator.trim().parse().map_err(|_| invalid())?;
+    if numerator == 0 || !denominator.is_power_of_two() || denominator > 64 {
+        return Err(invalid());
+    }
+    Ok((numerator, denominator))
+}
+
 /// Represents the parsed components of a roman numeral chord symbol.
 #[derive(Debug, PartialEq, Eq)]
 pub struct ParsedRomanChord {
     pub interval_from_root: Interval,
     pub chord_type: ChordType,
     pub degree: String,
+    /// The bass of a slash chord ("1/3"), from the key's root
+    pub bass: Option<Interval>,
+}
+
+/// A rhythm mark written around a Nashville number.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NashvilleMark {
+    /// `<1>`: struck once and held (a diamond)
+    Diamond,
+    /// `^4`: pushed, played just ahead of the beat
+    Push,
+}
+
+/// Represents a parsed Nashville number ("6-", "57", "1/3", "<4>").
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParsedNashvilleChord {
+    pub chord: ParsedRomanChord,
+    pub marks: Vec<NashvilleMark>,
+}
+
+/// The interval of a scale degree (1-7) of the major scale, raised or lowered
+/// by `accidental_offset` semitones.
+fn degree_interval(degree: usize, accidental_offset: i8) -> Interval {
+    // Map degree 1-7 to Major Scale intervals (semitones)
+    let semitones_maj: u8 = match degree {
+        1 => 0,
+        2 => 2,
+        3 => 4,
+        4 => 5,
+        5 => 7,
+        6 => 9,
+        7 => 11,
+        _ => unreachable!(),
+    };
+
+    let mut semitones_i8: i8 = semitones_maj as i8 + accidental_offset;
+    // Normalize to 0-11
+    semitones_i8 = (semitones_i8 + 12) % 12;
+
+    match semitones_i8 {
+        0 => Interval::Unison,
+        1 => Interval::MinorSecond,
+        2 => Interval::MajorSecond,
+        3 => Interval::MinorThird,
+        4 => Interval::MajorThird,
+        5 => Interval::PerfectFourth,
+        6 => Interval::Tritone,
+        7 => Interval::PerfectFifth,
+        8 => Interval::MinorSixth,
+        9 => Interval::MajorSixth,
+        10 => Interval::MinorSeventh,
+        11 => Interval::MajorSeventh,
+        _ => unreachable!(),
+    }
 }
 
 /// Parses a roman numeral string (e.g. "IV", "vii", "bVI7") into a chord specification.
@@ -104,37 +273,7 @@ pub fn parse_roman_chord(input: &str) -> Result<ParsedRomanChord, String> {
     let is_uppercase: bool = numeral_str.chars().next().unwrap().is_uppercase();
 
     // 4. Determine Interval
-    // Map degree 1-7 to Major Scale intervals (semitones)
-    let semitones_maj: u8 = match base_degree {
-        1 => 0,
-        2 => 2,
-        3 => 4,
-        4 => 5,
-        5 => 7,
-        6 => 9,
-        7 => 11,
-        _ => unreachable!(),
-    };
-
-    let mut semitones_i8: i8 = semitones_maj as i8 + accidental_offset;
-    // Normalize to 0-11
-    semitones_i8 = (semitones_i8 + 12) % 12;
-
-    let interval: Interval = match semitones_i8 {
-        0 => Interval::Unison,
-        1 => Interval::MinorSecond,
-        2 => Interval::MajorSecond,
-        3 => Interval::MinorThird,
-        4 => Interval::MajorThird,
-        5 => Interval::PerfectFourth,
-        6 => Interval::Tritone,
-        7 => Interval::PerfectFifth,
-        8 => Interval::MinorSixth,
-        9 => Interval::MajorSixth,
-        10 => Interval::MinorSeventh,
-        11 => Interval::MajorSeventh,
-        _ => unreachable!(),
-    };
+    let interval: Interval = degree_interval(base_degree, accidental_offset);
 
     // 5. Determine Chord Type
     let basic_triad: ChordType = if is_uppercase {
@@ -175,14 +314,135 @@ pub fn parse_roman_chord(input: &str) -> Result<ParsedRomanChord, String> {
         interval_from_root: interval,
         chord_type,
         degree: input.to_string(),
+        bass: None,
     })
 }
 
+/// Reads an optional accidental and a scale degree (e.g. "b7", "#4", "5"),
+/// returning the interval and the rest of the text.
+fn parse_nashville_degree<'a>(input: &'a str, text: &str) -> Result<(Interval, &'a str), String> {
+    let (accidental_offset, rest): (i8, &str) = match input.strip_prefix('#') {
+        Some(rest) => (1, rest),
+        None => input
+            .strip_prefix('b')
+            .map_or((0, input), |rest: &str| (-1, rest)),
+    };
+    let degree: usize = rest
+        .chars()
+        .next()
+        .and_then(|c: char| c.to_digit(10))
+        .filter(|d: &u32| (1..=7).contains(d))
+        .ok_or_else(|| format!("Invalid Nashville number: '{}' (e.g. 1, 6-, b7, 5/7)", text))?
+        as usize;
+    Ok((degree_interval(degree, accidental_offset), &rest[1..]))
+}
+
+/// Parses a Nashville number (e.g. "1", "6-", "57", "b7maj7", "1/3").
+/// Minor chords take a dash (or "m"), the bass of a slash chord is a number,
+/// and diamonds ("<1>") and pushes ("^4") are kept as marks.
+pub fn parse_nashville_chord(input: &str) -> Result<ParsedNashvilleChord, String> {
+    let mut rest: &str = input.trim();
+    let mut marks: Vec<NashvilleMark> = Vec::<NashvilleMark>::new();
+
+    // 1. Rhythm marks around the number
+    if let Some(pushed) = rest.strip_prefix('^') {
+        marks.push(NashvilleMark::Push);
+        rest = pushed;
+    }
+    if let Some(held) = rest
+        .strip_prefix('<')
+        .and_then(|r: &str| r.strip_suffix('>'))
+    {
+        marks.push(NashvilleMark::Diamond);
+        rest = held;
+    }
+
+    // 2. Degree, quality and bass
+    let (interval, rest): (Interval, &str) = parse_nashville_degree(rest, input)?;
+    let (suffix, bass): (&str, Option<&str>) = match rest.split_once('/') {
+        Some((suffix, bass)) => (suffix, Some(bass)),
+        None => (rest, None),
+    };
+    let chord_type: ChordType =
+        parse_chord_quality(suffix).ok_or_else(|| format!("Unknown chord suffix: {}", suffix))?;
+    let bass: Option<Interval> = match bass {
+        Some(bass) => match parse_nashville_degree(bass, input)? {
+            (interval, "") => Some(interval),
+            _ => return Err(format!("Invalid Nashville bass: '{}'", input)),
+        },
+        None => None,
+    };
+
+    Ok(ParsedNashvilleChord {
+        chord: ParsedRomanChord {
+            interval_from_root: interval,
+            chord_type,
+            degree: input.trim().to_string(),
+            bass,
+        },
+        marks,
+    })
+}
+
+/// True if a progression formula is written in Nashville numbers ("1 6- 2- 5")
+/// rather than Roman numerals ("I-vi-ii-V").
+pub fn is_nashville_formula(formula: &str) -> bool {
+    formula
+        .trim_start_matches(|c: char| c.is_whitespace() || "|^<b#".contains(c))
+        .starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// Splits a progression formula into its chord symbols.
+/// Nashville charts separate chords with spaces and bar lines (the dash means minor),
+/// Roman formulas with dashes and/or whitespace.
+pub fn formula_tokens(formula: &str) -> Vec<&str> {
+    let nashville: bool = is_nashville_formula(formula);
+    formula
+        .split(|c: char| c.is_whitespace() || if nashville { c == '|' } else { c == '-' })
+        .filter(|s: &&str| !s.is_empty())
+        .collect()
+}
+
+/// Parses a whole progression formula (e.g. "ii7-V7-Imaj7" or "I IV V7"),
+/// or a chart in Nashville numbers (e.g. "1 6- 2- 5", "| 1 | 4/1 | 57 | 1 |").
+/// Chords may be separated by dashes and/or whitespace.
+pub fn parse_progression_formula(formula: &str) -> Result<Vec<ParsedRomanChord>, String> {
+    let tokens: Vec<&str> = formula_tokens(formula);
+    let specs: Vec<ParsedRomanChord> = if is_nashville_formula(formula) {
+        tokens
+            .into_iter()
+            .map(|s: &str| parse_nashville_chord(s).map(|n: ParsedNashvilleChord| n.chord))
+            .collect::<Result<Vec<ParsedRomanChord>, String>>()?
+    } else {
+        tokens
+            .into_iter()
+            .map(parse_roman_chord)
+            .collect::<Result<Vec<ParsedRomanChord>, String>>()?
+    };
+
+    if specs.is_empty() {
+        return Err(format!("Empty progression formula: '{}'", formula));
+    }
+    Ok(specs)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::pitch::Pitch;
     use crate::{ChordType, Interval, Note};
 
+    #[test]
+    fn test_parse_chord_quality() {
+        assert_eq!(parse_chord_quality(""), Some(ChordType::Major));
+        assert_eq!(parse_chord_quality("m"), Some(ChordType::Minor));
+        assert_eq!(parse_chord_quality("M7"), Some(ChordType::Major7));
+        assert_eq!(parse_chord_quality("ø7"), Some(ChordType::Minor7b5));
+        assert_eq!(parse_chord_quality("°7"), Some(ChordType::Diminished7));
+        assert_eq!(parse_chord_quality("m(maj7)"), Some(ChordType::MinorMajor7));
+        assert_eq!(parse_chord_quality("sus4"), None);
+    }
+
     #[test]
     fn test_parse_note_simple() {
         assert_eq!(parse_note("C"), Ok(Note::C));
@@ -209,6 +469,59 @@ mod tests {
         assert!(parse_note("H").is_err());
     }
 
+    #[test]
+    fn test_parse_spelled_note() {
+        let bb: SpelledNote = parse_spelled_note("bb").unwrap();
+        assert_eq!((bb.step, bb.alter), ('B', -1));
+        assert_eq!(bb.note(), Note::ASharp);
+        assert_eq!(parse_spelled_note("E#").unwrap().note(), Note::F);
+        assert_eq!(parse_spelled_note("Cb").unwrap().note(), Note::B);
+        assert_eq!(parse_spelled_note("F##").unwrap().to_string(), "F##");
+        assert!(parse_spelled_note("H").is_err());
+        assert!(parse_spelled_note("C#b").is_err());
+        assert!(parse_spelled_note("").is_err());
+    }
+
+    #[test]
+    fn test_parse_pitch() {
+        assert_eq!(parse_pitch("C4"), Ok(Pitch::new(Note::C, 4)));
+        assert_eq!(parse_pitch("bb3"), Ok(Pitch::new(Note::ASharp, 3)));
+        assert_eq!(parse_pitch("F#-1"), Ok(Pitch::new(Note::FSharp, -1)));
+        assert!(parse_pitch("C").is_err());
+        assert!(parse_pitch("H4").is_err());
+        assert!(parse_pitch("C12").is_err());
+    }
+
+    #[test]
+    fn test_parse_tuning() {
+        let tuning: Vec<Pitch> = parse_tuning("D2, A2 D3 G3 B3 E4").unwrap();
+        assert_eq!(tuning.len(), 6);
+        assert_eq!(tuning[0], Pitch::new(Note::D, 2));
+        assert!(parse_tuning("  ").is_err());
+        assert!(parse_tuning("E2 A").is_err());
+    }
+
+    #[test]
+    fn test_parse_key() {
+        assert_eq!(parse_key("C"), Ok((Note::C, ScaleType::Major)));
+        assert_eq!(parse_key("Eb major"), Ok((Note::DSharp, ScaleType::Major)));
+        assert_eq!(
+            parse_key("F#m"),
+            Ok((Note::FSharp, ScaleType::MinorNatural))
+        );
+        assert_eq!(parse_key("A minor"), Ok((Note::A, ScaleType::MinorNatural)));
+        assert!(parse_key("H").is_err());
+    }
+
+    #[test]
+    fn test_parse_time_signature() {
+        assert_eq!(parse_time_signature("4/4"), Ok((4, 4)));
+        assert_eq!(parse_time_signature(" 6 / 8 "), Ok((6, 8)));
+        assert!(parse_time_signature("3/5").is_err());
+        assert!(parse_time_signature("0/4").is_err());
+        assert!(parse_time_signature("4").is_err());
+    }
+
     #[test]
     fn test_parse_roman_simple() {
         // I -> Unison, Major
@@ -253,4 +566,54 @@ mod tests {
         let res: ParsedRomanChord = parse_roman_chord("viidim").unwrap();
         assert_eq!(res.chord_type, ChordType::Diminished);
     }
+
+    #[test]
+    fn test_parse_progression_formula() {
+        let specs: Vec<ParsedRomanChord> = parse_progression_formula("ii7-V7 Imaj7").unwrap();
+        let types: Vec<ChordType> = specs
+            .iter()
+            .map(|s: &ParsedRomanChord| s.chord_type)
+            .collect();
+        assert_eq!(
+            types,
+            vec![ChordType::Minor7, ChordType::Dominant7, ChordType::Major7]
+        );
+
+        assert!(parse_progression_formula(" - ").is_err());
+        assert!(parse_progression_formula("I-X").is_err());
+    }
+
+    #[test]
+    fn test_parse_nashville() {
+        let res: ParsedNashvilleChord = parse_nashville_chord("b7").unwrap();
+        assert_eq!(res.chord.interval_from_root, Interval::MinorSeventh);
+        assert_eq!(res.chord.chord_type, ChordType::Major);
+
+        // A slash number is the bass, measured from the key's root
+        let res: ParsedNashvilleChord = parse_nashville_chord("2-7/5").unwrap();
+        assert_eq!(res.chord.interval_from_root, Interval::MajorSecond);
+        assert_eq!(res.chord.chord_type, ChordType::Minor7);
+        assert_eq!(res.chord.bass, Some(Interval::PerfectFifth));
+
+        // Diamonds and pushes are kept with the chord
+        let res: ParsedNashvilleChord = parse_nashville_chord("^<4>").unwrap();
+        assert_eq!(res.chord.degree, "^<4>");
+        assert_eq!(res.marks, vec![NashvilleMark::Push, NashvilleMark::Diamond]);
+
+        assert!(parse_nashville_chord("8").is_err());
+        assert!(parse_nashville_chord("1/9").is_err());
+
+        // Charts are told apart from Roman numerals
+        assert!(is_nashville_formula("1 6- | 2- 5"));
+        assert!(!is_nashville_formula("I vi ii V7"));
+        assert!(is_nashville_formula("| 1 | 4 |"));
+        assert_eq!(
+            formula_tokens("| 1 | 6- 2- | 5 |"),
+            vec!["1", "6-", "2-", "5"]
+        );
+        assert_eq!(formula_tokens("ii7-V7 I"), vec!["ii7", "V7", "I"]);
+        let specs: Vec<ParsedRomanChord> = parse_progression_formula("1 6- | 2- 5").unwrap();
+        assert_eq!(specs.len(), 4);
+        assert_eq!(specs[1].chord_type, ChordType::Minor);
+    }
 }
@@ -0,0 +1,606 @@
+/*
+ * tonic-music-core
+ * Copyright (C) 2025 Israel Alberto Roldan Vega
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * Repository: https://github.com/airvzxf/music-theory-calculator/
+ */
+
+/*
+ * crates/tonic-music-core/src/part_writing.rs
+ *
+ * This module checks voiced chords (e.g. SATB) against
+ * the common-practice part-writing rules.
+ */
+
+use crate::analysis::{HarmonicFunction, harmonic_function, roman_numeral};
+use crate::parser::parse_note;
+use crate::pitch::Pitch;
+use crate::voice_leading::{MAX_UPPER_SPACING, VoiceRange, VoicedChord};
+use crate::{ChordType, Note, build_chord};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// The part-writing rules that can be broken.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub enum PartWritingRule {
+    ParallelFifths,
+    ParallelOctaves,
+    HiddenFifths,
+    HiddenOctaves,
+    VoiceCrossing,
+    VoiceOverlap,
+    Spacing,
+    UnresolvedLeadingTone,
+    UnresolvedSeventh,
+    DoubledLeadingTone,
+}
+
+impl std::fmt::Display for PartWritingRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s: &str = match self {
+            PartWritingRule::ParallelFifths => "Parallel Fifths",
+            PartWritingRule::ParallelOctaves => "Parallel Octaves",
+            PartWritingRule::HiddenFifths => "Hidden Fifths",
+            PartWritingRule::HiddenOctaves => "Hidden Octaves",
+            PartWritingRule::VoiceCrossing => "Voice Crossing",
+            PartWritingRule::VoiceOverlap => "Voice Overlap",
+            PartWritingRule::Spacing => "Spacing",
+            PartWritingRule::UnresolvedLeadingTone => "Unresolved Leading Tone",
+            PartWritingRule::UnresolvedSeventh => "Unresolved Seventh",
+            PartWritingRule::DoubledLeadingTone => "Doubled Leading Tone",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// A broken rule and where it happens.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Violation {
+    pub rule: PartWritingRule,
+    /// Index of the chord (0-based). For rules about motion, the chord the voices move from.
+    pub chord: usize,
+    /// Indices of the voices involved (0 = lowest voice)
+    pub voices: Vec<usize>,
+    pub description: String,
+}
+
+/// The name of a voice: B/T/A/S for four voices, "Voice N" otherwise.
+pub fn voice_name(voice: usize, voice_count: usize) -> String {
+    if voice_count == 4 {
+        VoiceRange::satb()[voice].name.clone()
+    } else {
+        format!("Voice {}", voice + 1)
+    }
+}
+
+/// Semitones from `from` up to `to` (0-11).
+fn pitch_class_interval(from: Note, to: Note) -> u8 {
+    (to.as_u8() + 12 - from.as_u8()) % 12
+}
+
+/// Checks a sequence of voiced chords (lowest voice first) in the given key.
+/// Every chord must have the same number of voices.
+pub fn check_part_writing(key_center: Note, chords: &[VoicedChord]) -> Vec<Violation> {
+    let mut violations: Vec<Violation> = Vec::<Violation>::new();
+    let leading_tone: Note = Note::from_u8((key_center.as_u8() + 11) % 12);
+
+    for (index, chord) in chords.iter().enumerate() {
+        check_chord(index, chord, leading_tone, &mut violations);
+        if let Some(next) = chords.get(index + 1) {
+            check_motion(index, chord, next, &mut violations);
+            check_resolutions(
+                index,
+                chord,
+                next,
+                key_center,
+                leading_tone,
+                &mut violations,
+            );
+        }
+    }
+
+    violations
+}
+
+/// Rules inside a single chord: crossing, spacing, doubled leading tone.
+fn check_chord(
+    index: usize,
+    chord: &VoicedChord,
+    leading_tone: Note,
+    violations: &mut Vec<Violation>,
+) {
+    let count: usize = chord.pitches.len();
+
+    for lower in 0..count.saturating_sub(1) {
+        let upper: usize = lower + 1;
+        let low: Pitch = chord.pitches[lower];
+        let high: Pitch = chord.pitches[upper];
+
+        if low > high {
+            violations.push(Violation {
+                rule: PartWritingRule::VoiceCrossing,
+                chord: index,
+                voices: vec![lower, upper],
+                description: format!(
+                    "{} ({}) is above {} ({})",
+                    voice_name(lower, count),
+                    low,
+                    voice_name(upper, count),
+                    high
+                ),
+            });
+        }
+
+        // The bass may sit far below the tenor; the upper voices stay within an octave
+        if lower > 0 && high.midi() - low.midi() > MAX_UPPER_SPACING {
+            violations.push(Violation {
+                rule: PartWritingRule::Spacing,
+                chord: index,
+                voices: vec![lower, upper],
+                description: format!(
+                    "More than an octave between {} ({}) and {} ({})",
+                    voice_name(lower, count),
+                    low,
+                    voice_name(upper, count),
+                    high
+                ),
+            });
+        }
+    }
+
+    if chord.function == HarmonicFunction::Dominant {
+        let doubled: Vec<usize> = (0..count)
+            .filter(|v: &usize| chord.pitches[*v].note == leading_tone)
+            .collect();
+        if doubled.len() > 1 {
+            violations.push(Violation {
+                rule: PartWritingRule::DoubledLeadingTone,
+                chord: index,
+                description: format!("The leading tone ({}) is doubled", leading_tone),
+                voices: doubled,
+            });
+        }
+    }
+}
+
+/// Rules about the motion between two chords: parallels, hidden intervals, overlap.
+fn check_motion(
+    index: usize,
+    chord: &VoicedChord,
+    next: &VoicedChord,
+    violations: &mut Vec<Violation>,
+) {
+    let count: usize = chord.pitches.len().min(next.pitches.len());
+
+    // 1. Parallel fifths and octaves between every pair of voices
+    for lower in 0..count {
+        for upper in (lower + 1)..count {
+            let (a1, a2): (Pitch, Pitch) = (chord.pitches[lower], next.pitches[lower]);
+            let (b1, b2): (Pitch, Pitch) = (chord.pitches[upper], next.pitches[upper]);
+            let motion_a: i16 = a2.midi() - a1.midi();
+            let motion_b: i16 = b2.midi() - b1.midi();
+            if motion_a == 0 || motion_b == 0 || motion_a.signum() != motion_b.signum() {
+                continue;
+            }
+
+            let before: i16 = (b1.midi() - a1.midi()).rem_euclid(12);
+            let after: i16 = (b2.midi() - a2.midi()).rem_euclid(12);
+            let rule: Option<PartWritingRule> = match (before, after) {
+                (7, 7) => Some(PartWritingRule::ParallelFifths),
+                (0, 0) => Some(PartWritingRule::ParallelOctaves),
+                _ => None,
+            };
+            if let Some(rule) = rule {
+                violations.push(Violation {
+                    rule,
+                    chord: index,
+                    voices: vec![lower, upper],
+                    description: format!(
+                        "{} and {} move {} {} -> {} {}",
+                        voice_name(lower, count),
+                        voice_name(upper, count),
+                        a1,
+                        b1,
+                        a2,
+                        b2
+                    ),
+                });
+            }
+        }
+    }
+
+    // 2. Hidden fifths and octaves: outer voices move the same way into a
+    //    perfect interval while the top voice leaps
+    if count >= 2 {
+        let top: usize = count - 1;
+        let bass_motion: i16 = next.pitches[0].midi() - chord.pitches[0].midi();
+        let top_motion: i16 = next.pitches[top].midi() - chord.pitches[top].midi();
+        let arriving: i16 = (next.pitches[top].midi() - next.pitches[0].midi()).rem_euclid(12);
+        let leaving: i16 = (chord.pitches[top].midi() - chord.pitches[0].midi()).rem_euclid(12);
+
+        let similar: bool =
+            bass_motion != 0 && top_motion != 0 && bass_motion.signum() == top_motion.signum();
+        if similar && top_motion.abs() > 2 && arriving != leaving {
+            let rule: Option<PartWritingRule> = match arriving {
+                7 => Some(PartWritingRule::HiddenFifths),
+                0 => Some(PartWritingRule::HiddenOctaves),
+                _ => None,
+            };
+            if let Some(rule) = rule {
+                violations.push(Violation {
+                    rule,
+                    chord: index,
+                    voices: vec![0, top],
+                    description: format!(
+                        "{} and {} move in similar motion into {} {} with a leap in the {}",
+                        voice_name(0, count),
+                        voice_name(top, count),
+                        next.pitches[0],
+                        next.pitches[top],
+                        voice_name(top, count)
+                    ),
+                });
+            }
+        }
+    }
+
+    // 3. Overlap: a voice moves past where its neighbour just was
+    for lower in 0..count.saturating_sub(1) {
+        let upper: usize = lower + 1;
+        if next.pitches[lower] > chord.pitches[upper] {
+            violations.push(Violation {
+                rule: PartWritingRule::VoiceOverlap,
+                chord: index,
+                voices: vec![lower, upper],
+                description: format!(
+                    "{} moves to {}, above the previous {} ({})",
+                    voice_name(lower, count),
+                    next.pitches[lower],
+                    voice_name(upper, count),
+                    chord.pitches[upper]
+                ),
+            });
+        }
+        if next.pitches[upper] < chord.pitches[lower] {
+            violations.push(Violation {
+                rule: PartWritingRule::VoiceOverlap,
+                chord: index,
+                voices: vec![lower, upper],
+                description: format!(
+                    "{} moves to {}, below the previous {} ({})",
+                    voice_name(upper, count),
+                    next.pitches[upper],
+                    voice_name(lower, count),
+                    chord.pitches[lower]
+                ),
+            });
+        }
+    }
+}
+
+/// Rules about tendency tones: the leading tone and chord sevenths.
+fn check_resolutions(
+    index: usize,
+    chord: &VoicedChord,
+    next: &VoicedChord,
+    key_center: Note,
+    leading_tone: Note,
+    violations: &mut Vec<Violation>,
+) {
+    let count: usize = chord.pitches.len().min(next.pitches.len());
+    let same_chord: bool = chord.root_note == next.root_note && chord.chord_type == next.chord_type;
+    if same_chord {
+        return;
+    }
+
+    // 1. In a dominant chord going to the tonic, the leading tone rises to the tonic.
+    //    Inner voices may fall to the fifth instead (the "frustrated" leading tone).
+    let resolves_to_tonic: bool = next.root_note == key_center;
+    if chord.function == HarmonicFunction::Dominant && resolves_to_tonic {
+        for voice in 0..count {
+            if chord.pitches[voice].note != leading_tone {
+                continue;
+            }
+            let motion: i16 = next.pitches[voice].midi() - chord.pitches[voice].midi();
+            let is_inner: bool = voice > 0 && voice < count - 1;
+            let frustrated: bool =
+                is_inner && pitch_class_interval(key_center, next.pitches[voice].note) == 7;
+            // Held as the major seventh of the next chord (e.g. V7 - Imaj7)
+            let held: bool =
+                motion == 0 && build_chord(next.root_note, next.chord_type).contains(&leading_tone);
+            if motion != 1 && !frustrated && !held {
+                violations.push(Violation {
+                    rule: PartWritingRule::UnresolvedLeadingTone,
+                    chord: index,
+                    voices: vec![voice],
+                    description: format!(
+                        "{} leading tone {} goes to {} instead of {}",
+                        voice_name(voice, count),
+                        chord.pitches[voice],
+                        next.pitches[voice],
+                        chord.pitches[voice].transpose(1)
+                    ),
+                });
+            }
+        }
+    }
+
+    // 2. The seventh of a seventh chord steps down
+    let tones: Vec<Note> = build_chord(chord.root_note, chord.chord_type);
+    if tones.len() == 4 {
+        let seventh: Note = tones[3];
+        for voice in 0..count {
+            if chord.pitches[voice].note != seventh {
+                continue;
+            }
+            let motion: i16 = next.pitches[voice].midi() - chord.pitches[voice].midi();
+            if !(motion == -1 || motion == -2) {
+                violations.push(Violation {
+                    rule: PartWritingRule::UnresolvedSeventh,
+                    chord: index,
+                    voices: vec![voice],
+                    description: format!(
+                        "{} chord seventh {} goes to {} instead of stepping down",
+                        voice_name(voice, count),
+                        chord.pitches[voice],
+                        next.pitches[voice]
+                    ),
+                });
+            }
+        }
+    }
+}
+
+/// Names the chord formed by the pitches (root, type), preferring the bass as root.
+/// A missing fifth is allowed. Returns None if the pitches do not form a known chord.
+pub fn identify_chord(pitches: &[Pitch]) -> Option<(Note, ChordType)> {
+    let mut classes: Vec<Note> = Vec::<Note>::new();
+    for pitch in pitches {
+        if !classes.contains(&pitch.note) {
+            classes.push(pitch.note);
+        }
+    }
+
+    let mut found: Option<(Note, ChordType)> = None;
+    for root in &classes {
+        for chord_type in ChordType::value_variants() {
+            let tones: Vec<Note> = build_chord(*root, *chord_type);
+            let covers: bool = classes.iter().all(|n: &Note| tones.contains(n));
+            let missing_only_fifth: bool = tones
+                .iter()
+                .all(|n: &Note| classes.contains(n) || pitch_class_interval(*root, *n) == 7);
+            if covers && missing_only_fifth {
+                let is_bass_root: bool = pitches.first().map(|p: &Pitch| p.note) == Some(*root);
+                let complete: bool = tones.len() == classes.len();
+                // Prefer complete chords, then the bass as root
+                let better: bool = match found {
+                    None => true,
+                    Some((found_root, found_type)) => {
+                        let found_complete: bool =
+                            build_chord(found_root, found_type).len() == classes.len();
+                        let found_bass_root: bool =
+                            pitches.first().map(|p: &Pitch| p.note) == Some(found_root);
+                        (complete, is_bass_root) > (found_complete, found_bass_root)
+                    }
+                };
+                if better {
+                    found = Some((*root, *chord_type));
+                }
+            }
+        }
+    }
+
+    found
+}
+
+/// Labels raw pitches as a chord of the key (degree, function...).
+pub fn voiced_chord_from_pitches(
+    key_center: Note,
+    pitches: Vec<Pitch>,
+) -> Result<VoicedChord, String> {
+    let (root_note, chord_type): (Note, ChordType) = identify_chord(&pitches).ok_or_else(|| {
+        let names: Vec<String> = pitches.iter().map(|p: &Pitch| p.to_string()).collect();
+        format!("Cannot identify the chord {}", names.join(" "))
+    })?;
+
+    Ok(VoicedChord {
+        degree: roman_numeral(key_center, root_note, chord_type),
+        root_note,
+        chord_type,
+        function: harmonic_function(key_center, root_note, chord_type),
+        pitches,
+    })
+}
+
+/// A voicing exercise as written in JSON:
+/// `{ "key": "C", "chords": [["C3", "G3", "E4", "C5"], ...] }` (lowest voice first).
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VoicingFile {
+    key: String,
+    chords: Vec<Vec<Pitch>>,
+}
+
+/// Parses a JSON voicing exercise into its key and labeled chords.
+pub fn parse_voicing_json(contents: &str) -> Result<(Note, Vec<VoicedChord>), String> {
+    let file: VoicingFile =
+        serde_json::from_str(contents).map_err(|e| format!("Invalid voicing JSON: {}", e))?;
+    let key_center: Note = parse_note(&file.key)?;
+
+    let voice_count: Option<usize> = file.chords.first().map(|c: &Vec<Pitch>| c.len());
+    let mut chords: Vec<VoicedChord> = Vec::<VoicedChord>::new();
+    for (i, pitches) in file.chords.into_iter().enumerate() {
+        if Some(pitches.len()) != voice_count {
+            return Err(format!(
+                "Chord {} has {} voices, expected {}",
+                i + 1,
+                pitches.len(),
+                voice_count.unwrap_or(0)
+            ));
+        }
+        chords.push(voiced_chord_from_pitches(key_center, pitches)?);
+    }
+
+    Ok((key_center, chords))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_custom_progression;
+    use crate::parser::{parse_pitch, parse_progression_formula};
+    use crate::voice_leading::{VoiceLeadingOptions, voice_progression};
+
+    fn chords(key: Note, voicings: &[&str]) -> Vec<VoicedChord> {
+        voicings
+            .iter()
+            .map(|v: &&str| {
+                let pitches: Vec<Pitch> = v
+                    .split(' ')
+                    .map(|p: &str| parse_pitch(p).unwrap())
+                    .collect();
+                voiced_chord_from_pitches(key, pitches).unwrap()
+            })
+            .collect()
+    }
+
+    fn rules(violations: &[Violation]) -> Vec<PartWritingRule> {
+        violations.iter().map(|v: &Violation| v.rule).collect()
+    }
+
+    #[test]
+    fn test_part_writing_clean_cadence() {
+        let clean: Vec<VoicedChord> = chords(
+            Note::C,
+            &["C3 G3 E4 C5", "F3 A3 F4 C5", "G3 G3 D4 B4", "C3 G3 E4 C5"],
+        );
+        assert_eq!(check_part_writing(Note::C, &clean), Vec::<Violation>::new());
+    }
+
+    #[test]
+    fn test_part_writing_parallels() {
+        // C-E-G-C to D-F-A-D with every voice moving up by step
+        let parallel: Vec<VoicedChord> = chords(Note::C, &["C3 G3 E4 C5", "D3 A3 F4 D5"]);
+        let violations: Vec<Violation> = check_part_writing(Note::C, &parallel);
+        let found: Vec<PartWritingRule> = rules(&violations);
+        assert!(found.contains(&PartWritingRule::ParallelFifths));
+        assert!(found.contains(&PartWritingRule::ParallelOctaves));
+
+        let fifths: &Violation = violations
+            .iter()
+            .find(|v: &&Violation| v.rule == PartWritingRule::ParallelFifths)
+            .unwrap();
+        assert_eq!(fifths.chord, 0);
+        assert_eq!(fifths.voices, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_part_writing_hidden_and_overlap() {
+        // Bass and soprano both rise; the soprano leaps into an octave with the bass
+        let hidden: Vec<VoicedChord> = chords(Note::C, &["C3 E3 G3 E4", "G3 B3 D4 G4"]);
+        assert!(
+            rules(&check_part_writing(Note::C, &hidden)).contains(&PartWritingRule::HiddenOctaves)
+        );
+
+        // The tenor rises above where the alto just was
+        let overlap: Vec<VoicedChord> = chords(Note::C, &["C3 E3 G3 C4", "F3 A3 A3 C4"]);
+        assert!(
+            rules(&check_part_writing(Note::C, &overlap)).contains(&PartWritingRule::VoiceOverlap)
+        );
+    }
+
+    #[test]
+    fn test_part_writing_chord_rules() {
+        let crossing: Vec<VoicedChord> = chords(Note::C, &["C3 G4 E4 C5"]);
+        assert_eq!(
+            rules(&check_part_writing(Note::C, &crossing)),
+            vec![PartWritingRule::VoiceCrossing]
+        );
+
+        let spacing: Vec<VoicedChord> = chords(Note::C, &["C3 E3 G4 C5"]);
+        assert_eq!(
+            rules(&check_part_writing(Note::C, &spacing)),
+            vec![PartWritingRule::Spacing]
+        );
+
+        let doubled: Vec<VoicedChord> = chords(Note::C, &["G2 B3 D4 B4"]);
+        assert_eq!(
+            rules(&check_part_writing(Note::C, &doubled)),
+            vec![PartWritingRule::DoubledLeadingTone]
+        );
+    }
+
+    #[test]
+    fn test_part_writing_tendency_tones() {
+        // The soprano leading tone falls, and the seventh (F) leaps up
+        let unresolved: Vec<VoicedChord> = chords(Note::C, &["G2 F3 D4 B4", "C3 G3 E4 G4"]);
+        let found: Vec<PartWritingRule> = rules(&check_part_writing(Note::C, &unresolved));
+        assert!(found.contains(&PartWritingRule::UnresolvedLeadingTone));
+        assert!(found.contains(&PartWritingRule::UnresolvedSeventh));
+
+        // Proper resolution: B -> C, F -> E
+        let resolved: Vec<VoicedChord> = chords(Note::C, &["G2 F3 D4 B4", "C3 E3 E4 C5"]);
+        let found: Vec<PartWritingRule> = rules(&check_part_writing(Note::C, &resolved));
+        assert!(!found.contains(&PartWritingRule::UnresolvedLeadingTone));
+        assert!(!found.contains(&PartWritingRule::UnresolvedSeventh));
+    }
+
+    #[test]
+    fn test_part_writing_builder_output() {
+        let progression = build_custom_progression(
+            Note::C,
+            parse_progression_formula("I-IV-V-I").unwrap(),
+            &VoiceLeadingOptions::default(),
+        );
+        let voiced: Vec<VoicedChord> =
+            voice_progression(&progression, &VoiceRange::satb()).unwrap();
+        let found: Vec<PartWritingRule> = rules(&check_part_writing(Note::C, &voiced));
+        assert!(!found.contains(&PartWritingRule::VoiceCrossing));
+        assert!(!found.contains(&PartWritingRule::Spacing));
+    }
+
+    #[test]
+    fn test_part_writing_json() {
+        let json: &str =
+            r#"{ "key": "G", "chords": [["G2", "D3", "B3", "G4"], ["D3", "F#3", "A3", "D4"]] }"#;
+        let (key, parsed): (Note, Vec<VoicedChord>) = parse_voicing_json(json).unwrap();
+        assert_eq!(key, Note::G);
+        assert_eq!(parsed[1].degree, "V");
+        assert_eq!(parsed[1].function, HarmonicFunction::Dominant);
+
+        assert!(parse_voicing_json(r#"{ "key": "G", "chords": [["G2", "B3"], ["C3"]] }"#).is_err());
+        assert!(parse_voicing_json(r#"{ "key": "G", "chords": [["C3", "C#3", "D3"]] }"#).is_err());
+    }
+
+    #[test]
+    fn test_part_writing_identify_chord() {
+        let pitches: Vec<Pitch> = ["E3", "G3", "C4", "C5"]
+            .iter()
+            .map(|p: &&str| parse_pitch(p).unwrap())
+            .collect();
+        assert_eq!(identify_chord(&pitches), Some((Note::C, ChordType::Major)));
+
+        // Missing fifth
+        let pitches: Vec<Pitch> = ["G2", "F3", "B3", "G4"]
+            .iter()
+            .map(|p: &&str| parse_pitch(p).unwrap())
+            .collect();
+        assert_eq!(
+            identify_chord(&pitches),
+            Some((Note::G, ChordType::Dominant7))
+        );
+    }
+}
@@ -0,0 +1,208 @@
+/*
+ * tonic-music-core
+ * Copyright (C) 2025 Israel Alberto Roldan Vega
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * Repository: https://github.com/airvzxf/music-theory-calculator/
+ */
+
+/*
+ * crates/tonic-music-core/src/pitch.rs
+ *
+ * This module defines octave-specific pitches
+ * (a Note plus an octave, e.g. "C4").
+ */
+
+use crate::Note;
+use crate::parser::parse_pitch;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A note in a specific octave, in scientific pitch notation (middle C = C4).
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Pitch {
+    pub note: Note,
+    pub octave: i8,
+}
+
+impl Pitch {
+    pub fn new(note: Note, octave: i8) -> Self {
+        Pitch { note, octave }
+    }
+
+    /// Returns the MIDI note number (C4 = 60, A4 = 69).
+    pub fn midi(&self) -> i16 {
+        (self.octave as i16 + 1) * 12 + self.note.as_u8() as i16
+    }
+
+    /// Builds a pitch from a MIDI note number. Numbers beyond the octaves
+    /// a pitch can hold saturate to the lowest (C-128) or highest (B127) pitch.
+    pub fn from_midi(midi: i16) -> Self {
+        let lowest: i16 = Pitch::new(Note::C, i8::MIN).midi();
+        let highest: i16 = Pitch::new(Note::B, i8::MAX).midi();
+        let midi: i16 = midi.clamp(lowest, highest);
+        Pitch {
+            note: Note::from_u8(midi.rem_euclid(12) as u8),
+            octave: (midi.div_euclid(12) - 1) as i8,
+        }
+    }
+
+    /// Moves the pitch up (positive) or down (negative) by semitones,
+    /// saturating like `from_midi`.
+    pub fn transpose(&self, semitones: i16) -> Self {
+        Pitch::from_midi(self.midi().saturating_add(semitones))
+    }
+
+    /// Returns the frequency in Hz in equal temperament, given the tuning of A4.
+    pub fn frequency(&self, a4: f64) -> f64 {
+        a4 * 2f64.powf((self.midi() - 69) as f64 / 12.0)
+    }
+
+    /// Returns the nearest pitch to a frequency in Hz, given the tuning of A4,
+    /// and how far the frequency is from it in cents (-50 to +50).
+    pub fn from_frequency(frequency: f64, a4: f64) -> (Self, f64) {
+        let midi: f64 = 69.0 + 12.0 * (frequency / a4).log2();
+        let nearest: f64 = midi.round();
+        (Pitch::from_midi(nearest as i16), (midi - nearest) * 100.0)
+    }
+}
+
+impl PartialOrd for Pitch {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pitch {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.midi().cmp(&other.midi())
+    }
+}
+
+impl std::fmt::Debug for Pitch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl std::fmt::Display for Pitch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.note, self.octave)
+    }
+}
+
+/// Stacks notes upwards in close position, the first one in the given octave
+/// (e.g. [E, G, C] from octave 4 gives E4 G4 C5).
+pub fn stack_notes(notes: &[Note], octave: i8) -> Vec<Pitch> {
+    let mut pitches: Vec<Pitch> = Vec::<Pitch>::with_capacity(notes.len());
+    for note in notes {
+        let pitch: Pitch = match pitches.last() {
+            None => Pitch::new(*note, octave),
+            Some(below) => {
+                let up: i16 = (note.as_u8() as i16 - below.note.as_u8() as i16).rem_euclid(12);
+                below.transpose(if up == 0 { 12 } else { up })
+            }
+        };
+        pitches.push(pitch);
+    }
+    pitches
+}
+
+// Pitches are written as strings ("C#4") in JSON, so voicings stay readable.
+impl Serialize for Pitch {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Pitch {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s: String = String::deserialize(deserializer)?;
+        parse_pitch(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pitch_midi_round_trip() {
+        assert_eq!(Pitch::new(Note::C, 4).midi(), 60);
+        assert_eq!(Pitch::new(Note::A, 4).midi(), 69);
+        assert_eq!(Pitch::new(Note::C, -1).midi(), 0);
+        for midi in 0..128 {
+            assert_eq!(Pitch::from_midi(midi).midi(), midi);
+        }
+        assert_eq!(Pitch::from_midi(59), Pitch::new(Note::B, 3));
+    }
+
+    #[test]
+    fn test_pitch_order_and_transpose() {
+        assert!(Pitch::new(Note::B, 3) < Pitch::new(Note::C, 4));
+        assert_eq!(Pitch::new(Note::A, 3).transpose(5), Pitch::new(Note::D, 4));
+        assert_eq!(Pitch::new(Note::C, 4).transpose(-1), Pitch::new(Note::B, 3));
+        // Out-of-range results saturate instead of wrapping the octave
+        assert_eq!(
+            Pitch::new(Note::G, 126).transpose(24),
+            Pitch::new(Note::B, 127)
+        );
+        assert_eq!(
+            Pitch::new(Note::C, -127).transpose(-24),
+            Pitch::new(Note::C, -128)
+        );
+        assert_eq!(Pitch::from_midi(i16::MAX), Pitch::new(Note::B, 127));
+        assert_eq!(
+            Pitch::new(Note::C, 4).transpose(i16::MAX),
+            Pitch::new(Note::B, 127)
+        );
+    }
+
+    #[test]
+    fn test_pitch_stack_notes() {
+        assert_eq!(
+            stack_notes(&[Note::E, Note::G, Note::C], 4),
+            [
+                Pitch::new(Note::E, 4),
+                Pitch::new(Note::G, 4),
+                Pitch::new(Note::C, 5)
+            ]
+        );
+        assert!(stack_notes(&[], 4).is_empty());
+    }
+
+    #[test]
+    fn test_pitch_frequency() {
+        assert!((Pitch::new(Note::A, 4).frequency(440.0) - 440.0).abs() < 1e-9);
+        assert!((Pitch::new(Note::A, 5).frequency(440.0) - 880.0).abs() < 1e-9);
+        assert!((Pitch::new(Note::C, 4).frequency(440.0) - 261.6256).abs() < 1e-3);
+
+        let (pitch, cents): (Pitch, f64) = Pitch::from_frequency(445.0, 440.0);
+        assert_eq!(pitch, Pitch::new(Note::A, 4));
+        assert!((cents - 19.56).abs() < 0.01);
+        let (pitch, cents): (Pitch, f64) = Pitch::from_frequency(259.0, 432.0);
+        assert_eq!(pitch, Pitch::new(Note::C, 4));
+        assert!((cents - 14.3).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_pitch_serde() {
+        let pitch: Pitch = Pitch::new(Note::FSharp, 3);
+        let json: String = serde_json::to_string(&pitch).unwrap();
+        assert_eq!(json, "\"F#3\"");
+        let back: Pitch = serde_json::from_str("\"Gb3\"").unwrap();
+        assert_eq!(back, pitch);
+        assert!(serde_json::from_str::<Pitch>("\"H3\"").is_err());
+    }
+}
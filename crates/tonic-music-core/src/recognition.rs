@@ -0,0 +1,530 @@
+/*
+ * tonic-music-core
+ * Copyright (C) 2025 Israel Alberto Roldan Vega
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * Repository: https://github.com/airvzxf/music-theory-calculator/
+ */
+
+/*
+ * crates/tonic-music-core/src/recognition.rs
+ *
+ * This module names the chords of played music: it splits notes
+ * into time segments, matches each pitch-class profile against
+ * the ChordType templates and labels the result in the key.
+ */
+
+use crate::analysis::{HarmonicFunction, harmonic_function, roman_numeral};
+use crate::midi::MidiNote;
+use crate::{ChordType, Note, ScaleType, build_chord};
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// How notes are grouped before naming chords.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum, Serialize)]
+#[clap(rename_all = "kebab-case")]
+pub enum Segmentation {
+    /// One segment per beat
+    Beat,
+    /// One segment per bar
+    #[value(alias("measure"))]
+    Bar,
+    /// A new segment at each note onset (onsets closer than an eighth of a beat are merged)
+    #[value(alias("onsets"))]
+    Onset,
+}
+
+/// Onsets closer than this (in beats) start the same segment, e.g. a strummed chord.
+const ONSET_TOLERANCE: f64 = 0.125;
+
+/// The most beats or bars a file may be split into.
+pub const MAX_SEGMENTS: usize = 10_000;
+/// A chord tone with less than this share of the profile counts as missing.
+const MISSING_SHARE: f64 = 0.05;
+/// Score lost per missing chord tone (less for a missing fifth).
+const MISSING_TONE: f64 = 0.15;
+const MISSING_FIFTH: f64 = 0.05;
+/// Score bonus when the bass note is the root.
+const BASS_ROOT: f64 = 0.05;
+
+/// A time span and how long each pitch class (C = 0) sounds in it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Segment {
+    pub start: f64,
+    pub end: f64,
+    pub profile: [f64; 12],
+    /// The lowest sounding note
+    pub bass: Option<Note>,
+}
+
+/// The best chord template for a profile.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ChordMatch {
+    pub root: Note,
+    pub chord_type: ChordType,
+    /// 1.0 for a profile made only of the chord tones; lower with extra or missing notes
+    pub score: f64,
+}
+
+/// A recognized chord over a time span, labeled in the key.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AnalyzedChord {
+    pub start: f64,
+    pub end: f64,
+    pub root_note: Note,
+    pub chord_type: ChordType,
+    pub bass: Option<Note>,
+    /// Roman numeral relative to the key (e.g. "V7", "bVI")
+    pub degree: String,
+    pub function: HarmonicFunction,
+    pub score: f64,
+}
+
+impl AnalyzedChord {
+    /// Labels a recognized chord with its Roman numeral and function in the key.
+    pub fn new(key: Note, start: f64, end: f64, found: ChordMatch, bass: Option<Note>) -> Self {
+        AnalyzedChord {
+            start,
+            end,
+            root_note: found.root,
+            chord_type: found.chord_type,
+            bass,
+            degree: roman_numeral(key, found.root, found.chord_type),
+            function: harmonic_function(key, found.root, found.chord_type),
+            score: found.score,
+        }
+    }
+}
+
+/// The key and the chord sequence of a piece.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HarmonicAnalysis {
+    pub key: Note,
+    /// Major or MinorNatural
+    pub mode: ScaleType,
+    pub chords: Vec<AnalyzedChord>,
+}
+
+/// Splits notes (times in beats) into segments by beat, bar or onset. Fails
+/// when the time signature gives beats or bars no length (e.g. 0/4), or when
+/// the notes last more than `MAX_SEGMENTS` beats or bars.
+pub fn segment_notes(
+    notes: &[MidiNote],
+    segmentation: Segmentation,
+    time_signature: (u8, u8),
+) -> Result<Vec<Segment>, String> {
+    let end: f64 = notes
+        .iter()
+        .map(|n: &MidiNote| n.start + n.length)
+        .fold(0.0, f64::max);
+
+    // 1. The segment boundaries
+    let mut boundaries: Vec<f64> = Vec::<f64>::new();
+    match segmentation {
+        Segmentation::Beat | Segmentation::Bar => {
+            let beat: f64 = 4.0 / time_signature.1 as f64;
+            let step: f64 = match segmentation {
+                Segmentation::Bar => beat * time_signature.0 as f64,
+                _ => beat,
+            };
+            if !(step.is_finite() && step > 0.0) {
+                return Err(format!(
+                    "Invalid time signature {}/{}: cannot split the notes by {:?}",
+                    time_signature.0, time_signature.1, segmentation
+                ));
+            }
+            // Check the count before building the boundaries
+            let count: f64 = (end / step).ceil();
+            if !(count.is_finite() && count <= MAX_SEGMENTS as f64) {
+                return Err(format!(
+                    "The notes are too long to split by {:?} ({} segments at most)",
+                    segmentation, MAX_SEGMENTS
+                ));
+            }
+            let mut time: f64 = 0.0;
+            while time < end {
+                boundaries.push(time);
+                time += step;
+            }
+        }
+        Segmentation::Onset => {
+            let mut onsets: Vec<f64> = notes.iter().map(|n: &MidiNote| n.start).collect();
+            onsets.sort_by(f64::total_cmp);
+            for onset in onsets {
+                if boundaries
+                    .last()
+                    .is_none_or(|last: &f64| onset - last >= ONSET_TOLERANCE)
+                {
+                    boundaries.push(onset);
+                }
+            }
+        }
+    }
+    boundaries.push(end);
+
+    // 2. How long each pitch class sounds inside each segment
+    Ok(boundaries
+        .windows(2)
+        .filter(|w: &&[f64]| w[1] > w[0])
+        .map(|w: &[f64]| {
+            let (start, end): (f64, f64) = (w[0], w[1]);
+            let mut profile: [f64; 12] = [0.0; 12];
+            let mut lowest: Option<&MidiNote> = None;
+            for note in notes {
+                let overlap: f64 = (note.start + note.length).min(end) - note.start.max(start);
+                if overlap > 1e-9 {
+                    profile[note.pitch.note.as_u8() as usize] += overlap;
+                    if lowest.is_none_or(|l: &MidiNote| note.pitch < l.pitch) {
+                        lowest = Some(note);
+                    }
+                }
+            }
+            Segment {
+                start,
+                end,
+                profile,
+                bass: lowest.map(|n: &MidiNote| n.pitch.note),
+            }
+        })
+        .collect())
+}
+
+/// Finds the chord (any root, any ChordType) whose tones best cover the profile:
+/// the share of the chord tones minus the share of the other notes, minus a
+/// penalty per missing tone. Returns None when fewer than two pitch classes sound.
+pub fn recognize_chord(profile: &[f64; 12], bass: Option<Note>) -> Option<ChordMatch> {
+    let total: f64 = profile.iter().sum();
+    if profile.iter().filter(|w: &&f64| **w > 0.0).count() < 2 {
+        return None;
+    }
+    let share: Vec<f64> = profile.iter().map(|w: &f64| w / total).collect();
+
+    let mut best: Option<ChordMatch> = None;
+    for root in 0..12 {
+        let root: Note = Note::from_u8(root);
+        for chord_type in ChordType::value_variants() {
+            let tones: Vec<Note> = build_chord(root, *chord_type);
+            let inside: f64 = tones.iter().map(|n: &Note| share[n.as_u8() as usize]).sum();
+            let missing: f64 = tones
+                .iter()
+                .filter(|n: &&Note| share[n.as_u8() as usize] < MISSING_SHARE)
+                .map(|n: &Note| {
+                    if (n.as_u8() + 12 - root.as_u8()) % 12 == 7 {
+                        MISSING_FIFTH
+                    } else {
+                        MISSING_TONE
+                    }
+                })
+                .sum();
+            let bonus: f64 = if bass == Some(root) { BASS_ROOT } else { 0.0 };
+            let score: f64 = inside - (1.0 - inside) - missing + bonus;
+            if best.is_none_or(|b: ChordMatch| score > b.score + 1e-9) {
+                best = Some(ChordMatch {
+                    root,
+                    chord_type: *chord_type,
+                    score,
+                });
+            }
+        }
+    }
+    best
+}
+
+/// Estimates the key of a pitch-class profile by correlating it with the
+/// Krumhansl-Kessler major and minor key profiles.
+pub fn estimate_key(profile: &[f64; 12]) -> (Note, ScaleType) {
+    const MAJOR: [f64; 12] = [
+        6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+    ];
+    const MINOR: [f64; 12] = [
+        6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+    ];
+    let correlation = |a: &[f64], b: &[f64]| -> f64 {
+        let mean_a: f64 = a.iter().sum::<f64>() / 12.0;
+        let mean_b: f64 = b.iter().sum::<f64>() / 12.0;
+        let (mut cov, mut var_a, mut var_b): (f64, f64, f64) = (0.0, 0.0, 0.0);
+        for i in 0..12 {
+            cov += (a[i] - mean_a) * (b[i] - mean_b);
+            var_a += (a[i] - mean_a).powi(2);
+            var_b += (b[i] - mean_b).powi(2);
+        }
+        if var_a == 0.0 || var_b == 0.0 {
+            0.0
+        } else {
+            cov / (var_a * var_b).sqrt()
+        }
+    };
+
+    let mut best: (f64, Note, ScaleType) = (f64::MIN, Note::C, ScaleType::Major);
+    for tonic in 0..12 {
+        // The profile seen from this tonic
+        let rotated: Vec<f64> = (0..12).map(|i: usize| profile[(i + tonic) % 12]).collect();
+        for (template, mode) in [
+            (&MAJOR, ScaleType::Major),
+            (&MINOR, ScaleType::MinorNatural),
+        ] {
+            let r: f64 = correlation(&rotated, template);
+            if r > best.0 {
+                best = (r, Note::from_u8(tonic as u8), mode);
+            }
+        }
+    }
+    (best.1, best.2)
+}
+
+/// Names the chord of each segment, merges repeated chords and labels them in
+/// the key (estimated from all the segments unless given). Silent segments are skipped.
+pub fn analyze_segments(segments: &[Segment], key: Option<(Note, ScaleType)>) -> HarmonicAnalysis {
+    let (key, mode): (Note, ScaleType) = key.unwrap_or_else(|| {
+        let mut total: [f64; 12] = [0.0; 12];
+        for segment in segments {
+            for (i, weight) in segment.profile.iter().enumerate() {
+                total[i] += weight;
+            }
+        }
+        estimate_key(&total)
+    });
+
+    let mut chords: Vec<AnalyzedChord> = Vec::<AnalyzedChord>::new();
+    for segment in segments {
+        let Some(found) = recognize_chord(&segment.profile, segment.bass) else {
+            continue;
+        };
+        if let Some(last) = chords.last_mut()
+            && last.root_note == found.root
+            && last.chord_type == found.chord_type
+            && last.bass == segment.bass
+            && (last.end - segment.start).abs() < 1e-9
+        {
+            last.end = segment.end;
+            last.score = last.score.max(found.score);
+            continue;
+        }
+        chords.push(AnalyzedChord::new(
+            key,
+            segment.start,
+            segment.end,
+            found,
+            segment.bass,
+        ));
+    }
+
+    HarmonicAnalysis { key, mode, chords }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::midi::{MidiFile, MidiNote, block_chords, read_midi, sequence};
+    use crate::pitch::{Pitch, stack_notes};
+
+    fn profile(notes: &[Note]) -> [f64; 12] {
+        let mut profile: [f64; 12] = [0.0; 12];
+        for note in notes {
+            profile[note.as_u8() as usize] += 1.0;
+        }
+        profile
+    }
+
+    #[test]
+    fn test_recognize_chord_templates() {
+        // Every chord type on every root is recognized from its own notes (in root position)
+        for root in 0..12 {
+            let root: Note = Note::from_u8(root);
+            for chord_type in ChordType::value_variants() {
+                let notes: Vec<Note> = build_chord(root, *chord_type);
+                let found: ChordMatch = recognize_chord(&profile(&notes), Some(root)).unwrap();
+                assert_eq!(
+                    (found.root, found.chord_type),
+                    (root, *chord_type),
+                    "{:?} {:?}",
+                    root,
+                    chord_type
+                );
+            }
+        }
+        assert!(recognize_chord(&[0.0; 12], None).is_none());
+        assert!(recognize_chord(&profile(&[Note::C]), Some(Note::C)).is_none());
+    }
+
+    #[test]
+    fn test_recognize_chord_inversions_and_extra_notes() {
+        // First inversion C major: the bass does not change the root
+        let found: ChordMatch =
+            recognize_chord(&profile(&[Note::E, Note::G, Note::C]), Some(Note::E)).unwrap();
+        assert_eq!((found.root, found.chord_type), (Note::C, ChordType::Major));
+
+        // G7 without its fifth
+        let found: ChordMatch =
+            recognize_chord(&profile(&[Note::G, Note::B, Note::F]), Some(Note::G)).unwrap();
+        assert_eq!(
+            (found.root, found.chord_type),
+            (Note::G, ChordType::Dominant7)
+        );
+
+        // E7 over G# is not a G# diminished seventh
+        let found: ChordMatch = recognize_chord(
+            &profile(&[Note::GSharp, Note::B, Note::D, Note::E]),
+            Some(Note::GSharp),
+        )
+        .unwrap();
+        assert_eq!(
+            (found.root, found.chord_type),
+            (Note::E, ChordType::Dominant7)
+        );
+
+        // A minor with a short passing D
+        let mut am: [f64; 12] = profile(&[Note::A, Note::C, Note::E]);
+        am[Note::D.as_u8() as usize] += 0.25;
+        let found: ChordMatch = recognize_chord(&am, Some(Note::A)).unwrap();
+        assert_eq!((found.root, found.chord_type), (Note::A, ChordType::Minor));
+    }
+
+    #[test]
+    fn test_estimate_key() {
+        let c_major: [f64; 12] = profile(&build_chord(Note::C, ChordType::Major));
+        assert_eq!(estimate_key(&c_major).0, Note::C);
+        let mut a_minor: [f64; 12] =
+            profile(&crate::build_scale(Note::A, ScaleType::MinorHarmonic));
+        a_minor[Note::A.as_u8() as usize] += 2.0;
+        a_minor[Note::E.as_u8() as usize] += 1.0;
+        assert_eq!(estimate_key(&a_minor), (Note::A, ScaleType::MinorNatural));
+    }
+
+    #[test]
+    fn test_analyze_block_chords_by_beat_and_bar() {
+        // I-vi-IV-V7 in C, one chord per bar
+        let chords: Vec<Vec<Pitch>> = vec![
+            stack_notes(&[Note::C, Note::E, Note::G], 3),
+            stack_notes(&[Note::A, Note::C, Note::E], 3),
+            stack_notes(&[Note::F, Note::A, Note::C], 3),
+            stack_notes(&[Note::G, Note::B, Note::D, Note::F], 3),
+        ];
+        let block: Vec<MidiNote> = block_chords(&chords, 4.0);
+        // The same chords under a melody with a non-chord tone in each bar
+        let bars: [[Note; 4]; 4] = [
+            [Note::E, Note::D, Note::E, Note::C],
+            [Note::C, Note::B, Note::C, Note::A],
+            [Note::A, Note::G, Note::A, Note::F],
+            [Note::B, Note::A, Note::B, Note::G],
+        ];
+        let tune: Vec<Pitch> = bars
+            .iter()
+            .flatten()
+            .map(|n: &Note| Pitch::new(*n, 5))
+            .collect();
+        let mut melody: Vec<MidiNote> = block.clone();
+        melody.extend(sequence(&tune, 1.0));
+
+        for (notes, segmentation) in [(&block, Segmentation::Beat), (&melody, Segmentation::Bar)] {
+            let segments: Vec<Segment> = segment_notes(notes, segmentation, (4, 4)).unwrap();
+            let analysis: HarmonicAnalysis = analyze_segments(&segments, None);
+            assert_eq!(analysis.key, Note::C);
+            let degrees: Vec<&str> = analysis
+                .chords
+                .iter()
+                .map(|c: &AnalyzedChord| c.degree.as_str())
+                .collect();
+            assert_eq!(degrees, ["I", "vi", "IV", "V7"], "{:?}", segmentation);
+            assert_eq!(analysis.chords[1].start, 4.0);
+            assert_eq!(analysis.chords[1].end, 8.0);
+            assert_eq!(analysis.chords[3].function, HarmonicFunction::Dominant);
+        }
+    }
+
+    #[test]
+    fn test_segment_zero_meter() {
+        // A file whose time signature has a numerator of 0: read as 4/4
+        let events: Vec<u8> = vec![
+            0x00, 0xff, 0x58, 0x04, 0x00, 0x02, 0x18, 0x08, // Time signature 0/4
+            0x00, 0x90, 0x3c, 0x64, // C4 on
+            0x00, 0x90, 0x40, 0x64, // E4 on
+            0x83, 0x60, 0x80, 0x3c, 0x40, // C4 off after a beat
+            0x00, 0x80, 0x40, 0x40, // E4 off
+            0x00, 0xff, 0x2f, 0x00,
+        ];
+        let mut bytes: Vec<u8> = b"MThd\x00\x00\x00\x06\x00\x00\x00\x01\x01\xe0MTrk".to_vec();
+        bytes.extend((events.len() as u32).to_be_bytes());
+        bytes.extend(events);
+        let file: MidiFile = read_midi(&bytes).unwrap();
+        assert_eq!(file.time_signature, (4, 4));
+        let segments: Vec<Segment> =
+            segment_notes(&file.notes(), Segmentation::Bar, file.time_signature).unwrap();
+        assert_eq!(segments.len(), 1);
+
+        // A meter without length cannot be split into bars or beats
+        assert!(segment_notes(&file.notes(), Segmentation::Bar, (0, 4)).is_err());
+        assert!(segment_notes(&file.notes(), Segmentation::Beat, (4, 0)).is_err());
+        assert!(segment_notes(&file.notes(), Segmentation::Onset, (0, 4)).is_ok());
+
+        // One very long note would make millions of beats
+        let long: Vec<MidiNote> = vec![MidiNote {
+            pitch: Pitch::new(Note::C, 4),
+            start: 0.0,
+            length: 1e9,
+        }];
+        assert!(segment_notes(&long, Segmentation::Beat, (4, 4)).is_err());
+        assert!(segment_notes(&long, Segmentation::Bar, (4, 4)).is_err());
+        assert_eq!(
+            segment_notes(&long, Segmentation::Onset, (4, 4))
+                .unwrap()
+                .len(),
+            1
+        );
+        let last: Vec<MidiNote> = vec![MidiNote {
+            pitch: Pitch::new(Note::C, 4),
+            start: 0.0,
+            length: MAX_SEGMENTS as f64,
+        }];
+        assert_eq!(
+            segment_notes(&last, Segmentation::Beat, (4, 4))
+                .unwrap()
+                .len(),
+            MAX_SEGMENTS
+        );
+    }
+
+    #[test]
+    fn test_segment_by_onsets() {
+        // A strummed chord (onsets 0.05 beats apart) and a chord on beat 2.5
+        let mut notes: Vec<MidiNote> = Vec::<MidiNote>::new();
+        for (i, pitch) in stack_notes(&[Note::D, Note::F, Note::A], 4)
+            .iter()
+            .enumerate()
+        {
+            notes.push(MidiNote {
+                pitch: *pitch,
+                start: i as f64 * 0.05,
+                length: 2.5 - i as f64 * 0.05,
+            });
+        }
+        for pitch in stack_notes(&[Note::G, Note::B, Note::D], 3) {
+            notes.push(MidiNote {
+                pitch,
+                start: 2.5,
+                length: 1.5,
+            });
+        }
+        let segments: Vec<Segment> = segment_notes(&notes, Segmentation::Onset, (4, 4)).unwrap();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[1].start, 2.5);
+        assert_eq!(segments[1].bass, Some(Note::G));
+
+        let analysis: HarmonicAnalysis =
+            analyze_segments(&segments, Some((Note::C, ScaleType::Major)));
+        assert_eq!(analysis.chords[0].degree, "ii");
+        assert_eq!(analysis.chords[1].degree, "V");
+    }
+}
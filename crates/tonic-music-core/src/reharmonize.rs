@@ -0,0 +1,589 @@
+/*
+ * tonic-music-core
+ * Copyright (C) 2025 Israel Alberto Roldan Vega
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * Repository: https://github.com/airvzxf/music-theory-calculator/
+ */
+
+/*
+ * crates/tonic-music-core/src/reharmonize.rs
+ *
+ * This module generates alternative harmonizations of a
+ * progression by applying classic substitution rules.
+ */
+
+use crate::analysis::roman_numeral;
+use crate::voice_leading::VoiceLeadingOptions;
+use crate::{ChordType, Interval, Note, ProgressionChord, solve_voice_leading, transpose};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// The deepest chain of substitutions: each level multiplies the suggestions.
+pub const MAX_DEPTH: usize = 3;
+/// The most suggestions returned, shallowest first.
+pub const MAX_SUGGESTIONS: usize = 200;
+
+/// A substitution rule the reharmonization engine can apply.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum, Serialize)]
+#[clap(rename_all = "kebab-case")]
+pub enum SubstitutionRule {
+    /// Replace a dominant 7th with the dominant 7th a tritone away (G7 -> Db7).
+    #[value(alias("tritone"))]
+    TritoneSubstitution,
+    /// Swap a major chord for its relative minor and vice versa (C -> Am).
+    #[value(alias("relative"))]
+    RelativeSwap,
+    /// Insert a diminished 7th between chords a whole step apart (C -> C#dim7 -> Dm).
+    #[value(alias("diminished"))]
+    DiminishedPassing,
+    /// Insert the ii-V of a chord right before it.
+    #[value(alias("ii-v"))]
+    IiVInsertion,
+    /// Replace a V7 with the bVII7 of its target (G7 -> Bb7 -> C).
+    #[value(alias("backdoor"))]
+    BackdoorDominant,
+    /// Replace a V-I with John Coltrane's major-thirds cycle.
+    #[value(alias("coltrane"))]
+    ColtraneSubstitution,
+}
+
+impl SubstitutionRule {
+    /// Every rule, in the order the engine tries them.
+    pub fn all() -> &'static [SubstitutionRule] {
+        &[
+            SubstitutionRule::TritoneSubstitution,
+            SubstitutionRule::RelativeSwap,
+            SubstitutionRule::DiminishedPassing,
+            SubstitutionRule::IiVInsertion,
+            SubstitutionRule::BackdoorDominant,
+            SubstitutionRule::ColtraneSubstitution,
+        ]
+    }
+}
+
+impl std::fmt::Display for SubstitutionRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s: &str = match self {
+            SubstitutionRule::TritoneSubstitution => "Tritone Substitution",
+            SubstitutionRule::RelativeSwap => "Relative Major/Minor Swap",
+            SubstitutionRule::DiminishedPassing => "Diminished Passing Chord",
+            SubstitutionRule::IiVInsertion => "ii-V Insertion",
+            SubstitutionRule::BackdoorDominant => "Backdoor Dominant",
+            SubstitutionRule::ColtraneSubstitution => "Coltrane Substitution",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// One rule application: which rule, and at which chord (0-indexed) of the
+/// progression it was applied to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+pub struct SubstitutionStep {
+    pub rule: SubstitutionRule,
+    pub position: usize,
+}
+
+/// An alternative progression together with the steps that produced it.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Reharmonization {
+    pub steps: Vec<SubstitutionStep>,
+    pub progression: Vec<ProgressionChord>,
+}
+
+/// The (degree, root, type) triple used by `solve_voice_leading`.
+type ChordSpec = (String, Note, ChordType);
+
+/// Semitones (0-11) from `from` up to `to`.
+fn semitones_up(from: Note, to: Note) -> u8 {
+    (to.as_u8() + 12 - from.as_u8()) % 12
+}
+
+fn new_spec(key_center: Note, root: Note, chord_type: ChordType) -> ChordSpec {
+    (
+        roman_numeral(key_center, root, chord_type),
+        root,
+        chord_type,
+    )
+}
+
+fn is_minor_quality(chord_type: ChordType) -> bool {
+    matches!(
+        chord_type,
+        ChordType::Minor | ChordType::Minor7 | ChordType::MinorMajor7
+    )
+}
+
+/// Returns true if `chord` is a V (or V7) of `target_root`.
+fn is_dominant_of(chord: &ChordSpec, target_root: Note) -> bool {
+    semitones_up(target_root, chord.1) == 7
+        && matches!(chord.2, ChordType::Major | ChordType::Dominant7)
+}
+
+/// Applies `rule` at every position where it fits, returning one
+/// `(position, new_specs)` candidate per application.
+fn apply_rule(
+    key_center: Note,
+    specs: &[ChordSpec],
+    rule: SubstitutionRule,
+) -> Vec<(usize, Vec<ChordSpec>)> {
+    let mut candidates: Vec<(usize, Vec<ChordSpec>)> = Vec::<(usize, Vec<ChordSpec>)>::new();
+
+    // Replaces the chord at `index` with `replacement` (one or more chords).
+    let splice = |index: usize, remove: usize, replacement: Vec<ChordSpec>| -> Vec<ChordSpec> {
+        let mut result: Vec<ChordSpec> = specs[..index].to_vec();
+        result.extend(replacement);
+        result.extend_from_slice(&specs[index + remove..]);
+        result
+    };
+
+    for i in 0..specs.len() {
+        let i: usize = i;
+        let (_, root, chord_type): &ChordSpec = &specs[i];
+        let next: Option<&ChordSpec> = specs.get(i + 1);
+
+        match rule {
+            SubstitutionRule::TritoneSubstitution => {
+                if *chord_type == ChordType::Dominant7 {
+                    let sub: Note = transpose(*root, Interval::Tritone);
+                    candidates.push((
+                        i,
+                        splice(i, 1, vec![new_spec(key_center, sub, ChordType::Dominant7)]),
+                    ));
+                }
+            }
+            SubstitutionRule::RelativeSwap => {
+                let swap: Option<(Note, ChordType)> = match chord_type {
+                    ChordType::Major => {
+                        Some((transpose(*root, Interval::MajorSixth), ChordType::Minor))
+                    }
+                    ChordType::Major7 => {
+                        Some((transpose(*root, Interval::MajorSixth), ChordType::Minor7))
+                    }
+                    ChordType::Minor => {
+                        Some((transpose(*root, Interval::MinorThird), ChordType::Major))
+                    }
+                    ChordType::Minor7 => {
+                        Some((transpose(*root, Interval::MinorThird), ChordType::Major7))
+                    }
+                    _ => None,
+                };
+                if let Some((new_root, new_type)) = swap {
+                    candidates.push((
+                        i,
+                        splice(i, 1, vec![new_spec(key_center, new_root, new_type)]),
+                    ));
+                }
+            }
+            SubstitutionRule::DiminishedPassing => {
+                if let Some(next) = next {
+                    // Ascending (I - #Idim7 - ii) or descending (iii - biiidim7 - ii) whole step
+                    let passing: Option<Note> = match semitones_up(*root, next.1) {
+                        2 => Some(transpose(*root, Interval::MinorSecond)),
+                        10 => Some(transpose(next.1, Interval::MinorSecond)),
+                        _ => None,
+                    };
+                    if let Some(passing) = passing {
+                        let mut replacement: Vec<ChordSpec> = vec![specs[i].clone()];
+                        replacement.push(new_spec(key_center, passing, ChordType::Diminished7));
+                        candidates.push((i + 1, splice(i, 1, replacement)));
+                    }
+                }
+            }
+            SubstitutionRule::IiVInsertion => {
+                let already_prepared: bool = i > 0 && is_dominant_of(&specs[i - 1], *root);
+                let is_stable_target: bool = !matches!(
+                    chord_type,
+                    ChordType::Diminished | ChordType::Diminished7 | ChordType::Minor7b5
+                );
+                if !already_prepared && is_stable_target {
+                    let two_type: ChordType = if is_minor_quality(*chord_type) {
+                        ChordType::Minor7b5
+                    } else {
+                        ChordType::Minor7
+                    };
+                    let replacement: Vec<ChordSpec> = vec![
+                        new_spec(
+                            key_center,
+                            transpose(*root, Interval::MajorSecond),
+                            two_type,
+                        ),
+                        new_spec(
+                            key_center,
+                            transpose(*root, Interval::PerfectFifth),
+                            ChordType::Dominant7,
+                        ),
+                        specs[i].clone(),
+                    ];
+                    candidates.push((i, splice(i, 1, replacement)));
+                }
+            }
+            SubstitutionRule::BackdoorDominant => {
+                if let Some(next) = next
+                    && is_dominant_of(&specs[i], next.1)
+                    && !is_minor_quality(next.2)
+                {
+                    let backdoor: Note = transpose(next.1, Interval::MinorSeventh);
+                    candidates.push((
+                        i,
+                        splice(
+                            i,
+                            1,
+                            vec![new_spec(key_center, backdoor, ChordType::Dominant7)],
+                        ),
+                    ));
+                }
+            }
+            SubstitutionRule::ColtraneSubstitution => {
+                if let Some(next) = next
+                    && is_dominant_of(&specs[i], next.1)
+                    && matches!(next.2, ChordType::Major | ChordType::Major7)
+                {
+                    // G7 -> C becomes Eb7 Abmaj7 B7 Emaj7 G7 -> C
+                    let target: Note = next.1;
+                    let replacement: Vec<ChordSpec> = vec![
+                        new_spec(
+                            key_center,
+                            transpose(target, Interval::MinorThird),
+                            ChordType::Dominant7,
+                        ),
+                        new_spec(
+                            key_center,
+                            transpose(target, Interval::MinorSixth),
+                            ChordType::Major7,
+                        ),
+                        new_spec(
+                            key_center,
+                            transpose(target, Interval::MajorSeventh),
+                            ChordType::Dominant7,
+                        ),
+                        new_spec(
+                            key_center,
+                            transpose(target, Interval::MajorThird),
+                            ChordType::Major7,
+                        ),
+                        new_spec(
+                            key_center,
+                            transpose(target, Interval::PerfectFifth),
+                            ChordType::Dominant7,
+                        ),
+                    ];
+                    candidates.push((i, splice(i, 1, replacement)));
+                }
+            }
+        }
+    }
+
+    candidates
+}
+
+/// Generates alternative harmonizations of `progression` in the key of `key_center`.
+///
+/// Each rule in `rules` is tried at every chord where it applies. With
+/// `depth > 1` the rules are applied again on top of earlier suggestions,
+/// so a suggestion may carry several steps (up to `MAX_DEPTH`). Duplicates
+/// (and results equal to the original) are dropped; suggestions are ordered
+/// by depth and stop at `MAX_SUGGESTIONS`. Each suggestion is voiced with `options`.
+pub fn reharmonize(
+    key_center: Note,
+    progression: &[ProgressionChord],
+    rules: &[SubstitutionRule],
+    depth: usize,
+    options: &VoiceLeadingOptions,
+) -> Vec<Reharmonization> {
+    let original: Vec<ChordSpec> = progression
+        .iter()
+        .map(|c: &ProgressionChord| (c.degree.clone(), c.root_note, c.chord_type))
+        .collect();
+
+    // Chords only, so different spellings of the same degree count as duplicates
+    let signature = |specs: &[ChordSpec]| -> Vec<(Note, ChordType)> {
+        specs.iter().map(|s: &ChordSpec| (s.1, s.2)).collect()
+    };
+
+    let mut seen: HashSet<Vec<(Note, ChordType)>> = HashSet::from([signature(&original)]);
+    let mut results: Vec<(Vec<SubstitutionStep>, Vec<ChordSpec>)> =
+        Vec::<(Vec<SubstitutionStep>, Vec<ChordSpec>)>::new();
+    let mut frontier: Vec<(Vec<SubstitutionStep>, Vec<ChordSpec>)> = vec![(Vec::new(), original)];
+
+    'search: for _ in 0..depth.min(MAX_DEPTH) {
+        let mut next_frontier: Vec<(Vec<SubstitutionStep>, Vec<ChordSpec>)> =
+            Vec::<(Vec<SubstitutionStep>, Vec<ChordSpec>)>::new();
+
+        for (steps, specs) in frontier.iter() {
+            for rule in rules {
+                let rule: SubstitutionRule = *rule;
+                for (position, candidate) in apply_rule(key_center, specs, rule) {
+                    if !seen.insert(signature(&candidate)) {
+                        continue;
+                    }
+
+                    let mut new_steps: Vec<SubstitutionStep> = steps.clone();
+                    new_steps.push(SubstitutionStep { rule, position });
+                    results.push((new_steps.clone(), candidate.clone()));
+                    if results.len() == MAX_SUGGESTIONS {
+                        break 'search;
+                    }
+                    next_frontier.push((new_steps, candidate));
+                }
+            }
+        }
+
+        frontier = next_frontier;
+    }
+
+    results
+        .into_iter()
+        .map(|(steps, specs)| Reharmonization {
+            steps,
+            progression: solve_voice_leading(key_center, specs, options),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{ParsedRomanChord, parse_roman_chord};
+    use crate::{HarmonicFormula, build_custom_progression, build_progression};
+
+    fn custom(root: Note, formula: &str) -> Vec<ProgressionChord> {
+        let specs: Vec<ParsedRomanChord> = formula
+            .split('-')
+            .map(|s: &str| parse_roman_chord(s).unwrap())
+            .collect();
+        build_custom_progression(root, specs, &VoiceLeadingOptions::default())
+    }
+
+    fn roots(progression: &[ProgressionChord]) -> Vec<Note> {
+        progression
+            .iter()
+            .map(|c: &ProgressionChord| c.root_note)
+            .collect()
+    }
+
+    #[test]
+    fn test_reharmonize_tritone_substitution() {
+        let progression: Vec<ProgressionChord> = build_progression(
+            Note::C,
+            HarmonicFormula::Circle,
+            &VoiceLeadingOptions::default(),
+        );
+        let results: Vec<Reharmonization> = reharmonize(
+            Note::C,
+            &progression,
+            &[SubstitutionRule::TritoneSubstitution],
+            1,
+            &VoiceLeadingOptions::default(),
+        );
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].steps,
+            vec![SubstitutionStep {
+                rule: SubstitutionRule::TritoneSubstitution,
+                position: 3
+            }]
+        );
+        assert_eq!(
+            roots(&results[0].progression),
+            vec![Note::C, Note::A, Note::D, Note::CSharp]
+        );
+        assert_eq!(results[0].progression[3].degree, "bII7");
+    }
+
+    #[test]
+    fn test_reharmonize_voice_leading_options() {
+        let progression: Vec<ProgressionChord> = custom(Note::C, "I-vi-ii-V7");
+        let rules: &[SubstitutionRule] = &[SubstitutionRule::TritoneSubstitution];
+        let plain: Vec<Reharmonization> = reharmonize(
+            Note::C,
+            &progression,
+            rules,
+            1,
+            &VoiceLeadingOptions::default(),
+        );
+        let first_inversion: VoiceLeadingOptions = VoiceLeadingOptions {
+            inversion: 100,
+            preferred_inversion: 1,
+            ..VoiceLeadingOptions::default()
+        };
+        let thirds: Vec<Reharmonization> =
+            reharmonize(Note::C, &progression, rules, 1, &first_inversion);
+
+        // Same chords, voiced with the thirds in the bass
+        assert_eq!(roots(&plain[0].progression), roots(&thirds[0].progression));
+        assert_eq!(plain[0].progression[0].notes[0], Note::C);
+        assert_eq!(thirds[0].progression[0].notes[0], Note::E);
+        assert_eq!(thirds[0].progression[3].notes[0], Note::F);
+    }
+
+    #[test]
+    fn test_reharmonize_ii_v_insertion() {
+        let progression: Vec<ProgressionChord> = custom(Note::C, "I-vi");
+        let results: Vec<Reharmonization> = reharmonize(
+            Note::C,
+            &progression,
+            &[SubstitutionRule::IiVInsertion],
+            1,
+            &VoiceLeadingOptions::default(),
+        );
+
+        // Before I: Dm7 G7 C. Before vi (minor target): Bm7b5 E7 Am.
+        assert_eq!(results.len(), 2);
+        assert_eq!(
+            roots(&results[1].progression),
+            vec![Note::C, Note::B, Note::E, Note::A]
+        );
+        assert_eq!(results[1].progression[1].chord_type, ChordType::Minor7b5);
+        assert_eq!(results[1].progression[2].chord_type, ChordType::Dominant7);
+        assert_eq!(results[1].progression[1].degree, "viim7b5");
+    }
+
+    #[test]
+    fn test_reharmonize_diminished_passing() {
+        let progression: Vec<ProgressionChord> = custom(Note::C, "I-ii");
+        let results: Vec<Reharmonization> = reharmonize(
+            Note::C,
+            &progression,
+            &[SubstitutionRule::DiminishedPassing],
+            1,
+            &VoiceLeadingOptions::default(),
+        );
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            roots(&results[0].progression),
+            vec![Note::C, Note::CSharp, Note::D]
+        );
+        assert_eq!(results[0].progression[1].chord_type, ChordType::Diminished7);
+    }
+
+    #[test]
+    fn test_reharmonize_backdoor_and_coltrane() {
+        let progression: Vec<ProgressionChord> = custom(Note::C, "ii7-V7-Imaj7");
+
+        let backdoor: Vec<Reharmonization> = reharmonize(
+            Note::C,
+            &progression,
+            &[SubstitutionRule::BackdoorDominant],
+            1,
+            &VoiceLeadingOptions::default(),
+        );
+        assert_eq!(
+            roots(&backdoor[0].progression),
+            vec![Note::D, Note::ASharp, Note::C]
+        );
+
+        let coltrane: Vec<Reharmonization> = reharmonize(
+            Note::C,
+            &progression,
+            &[SubstitutionRule::ColtraneSubstitution],
+            1,
+            &VoiceLeadingOptions::default(),
+        );
+        assert_eq!(
+            roots(&coltrane[0].progression),
+            vec![
+                Note::D,
+                Note::DSharp,
+                Note::GSharp,
+                Note::B,
+                Note::E,
+                Note::G,
+                Note::C
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reharmonize_relative_swap() {
+        let progression: Vec<ProgressionChord> = custom(Note::C, "I-vi");
+        let results: Vec<Reharmonization> = reharmonize(
+            Note::C,
+            &progression,
+            &[SubstitutionRule::RelativeSwap],
+            1,
+            &VoiceLeadingOptions::default(),
+        );
+        // C -> Am and Am -> C
+        assert_eq!(roots(&results[0].progression), vec![Note::A, Note::A]);
+        assert_eq!(roots(&results[1].progression), vec![Note::C, Note::C]);
+    }
+
+    #[test]
+    fn test_reharmonize_depth_chains_rules() {
+        let progression: Vec<ProgressionChord> = custom(Note::C, "ii7-V7-I");
+        let shallow: Vec<Reharmonization> = reharmonize(
+            Note::C,
+            &progression,
+            SubstitutionRule::all(),
+            1,
+            &VoiceLeadingOptions::default(),
+        );
+        let deep: Vec<Reharmonization> = reharmonize(
+            Note::C,
+            &progression,
+            SubstitutionRule::all(),
+            2,
+            &VoiceLeadingOptions::default(),
+        );
+
+        assert!(deep.len() > shallow.len());
+        assert!(shallow.iter().all(|r: &Reharmonization| r.steps.len() == 1));
+        assert!(deep.iter().any(|r: &Reharmonization| r.steps.len() == 2));
+        assert!(
+            reharmonize(
+                Note::C,
+                &progression,
+                SubstitutionRule::all(),
+                0,
+                &VoiceLeadingOptions::default()
+            )
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_reharmonize_caps_suggestions() {
+        let progression: Vec<ProgressionChord> = build_progression(
+            Note::C,
+            HarmonicFormula::Circle,
+            &VoiceLeadingOptions::default(),
+        );
+        let capped: Vec<Reharmonization> = reharmonize(
+            Note::C,
+            &progression,
+            SubstitutionRule::all(),
+            3,
+            &VoiceLeadingOptions::default(),
+        );
+        assert_eq!(capped.len(), MAX_SUGGESTIONS);
+        // Deeper searches stop at the same depth and the same cap
+        let deeper: Vec<Reharmonization> = reharmonize(
+            Note::C,
+            &progression,
+            SubstitutionRule::all(),
+            50,
+            &VoiceLeadingOptions::default(),
+        );
+        assert_eq!(deeper, capped);
+        assert!(
+            capped
+                .windows(2)
+                .all(|w: &[Reharmonization]| w[0].steps.len() <= w[1].steps.len())
+        );
+    }
+}
@@ -0,0 +1,381 @@
+/*
+ * tonic-music-core
+ * Copyright (C) 2025 Israel Alberto Roldan Vega
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * Repository: https://github.com/airvzxf/music-theory-calculator/
+ */
+
+/*
+ * crates/tonic-music-core/src/scale_shapes.rs
+ *
+ * This module splits a scale into the fretboard positions
+ * guitarists practice: CAGED shapes, pentatonic boxes and 3NPS patterns.
+ */
+
+use crate::fretboard::{FretPosition, Fretboard};
+use crate::pitch::Pitch;
+use crate::{Note, ScaleType, build_scale};
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// The ways of splitting a scale into positions.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum, Serialize)]
+#[clap(rename_all = "kebab-case")]
+pub enum ShapeSystem {
+    /// The five positions around the C, A, G, E and D chord shapes (standard tuning)
+    Caged,
+    /// The five pentatonic boxes (two notes per string)
+    #[value(alias("boxes"), alias("box"))]
+    Pentatonic,
+    /// Three notes per string, one pattern per scale degree
+    #[value(alias("3nps"))]
+    ThreeNps,
+}
+
+/// One position of a scale on the neck.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ScaleShape {
+    /// e.g. "E Shape", "Box 1", "Pattern 3"
+    pub name: String,
+    /// The scale note the shape starts from on the lowest string
+    pub start: Note,
+    /// The notes of the shape, string by string from the lowest string, then by fret
+    pub positions: Vec<FretPosition>,
+    pub lowest_fret: u8,
+    pub highest_fret: u8,
+}
+
+/// The CAGED chord shapes: the string that holds the root (0 = 6th string),
+/// the first fret of the four-fret core window relative to the root, and the
+/// one extra fret (relative to the root) reached by a stretch for notes the core lacks.
+const CAGED_SHAPES: [(&str, usize, i16, i16); 5] = [
+    ("C", 1, -3, 1),
+    ("A", 1, -1, 3),
+    ("G", 0, -3, -4),
+    ("E", 0, -1, 3),
+    ("D", 2, 0, -1),
+];
+
+/// Semitones between adjacent strings in standard guitar tuning.
+const STANDARD_INTERVALS: [i16; 5] = [5, 5, 5, 4, 5];
+
+impl ScaleShape {
+    fn new(name: String, start: Note, mut positions: Vec<FretPosition>) -> Self {
+        positions.sort_by_key(|p: &FretPosition| (p.string, p.fret));
+        let frets: Vec<u8> = positions.iter().map(|p: &FretPosition| p.fret).collect();
+        ScaleShape {
+            name,
+            start,
+            lowest_fret: frets.iter().min().copied().unwrap_or(0),
+            highest_fret: frets.iter().max().copied().unwrap_or(0),
+            positions,
+        }
+    }
+}
+
+/// The lowest fret at or above the capo where `note` sounds on a string.
+fn first_fret_of(fretboard: &Fretboard, string: usize, note: Note) -> u8 {
+    (fretboard.capo..fretboard.capo + 12)
+        .find(|f: &u8| fretboard.tuning[string].transpose(*f as i16).note == note)
+        .unwrap()
+}
+
+/// Walks up the scale from `start_degree` on the lowest string, putting
+/// `per_string` notes on each string. Returns None if it does not fit on the neck.
+fn sequential_shape(
+    fretboard: &Fretboard,
+    scale: &[Note],
+    start_degree: usize,
+    per_string: usize,
+) -> Option<Vec<FretPosition>> {
+    // 1. The ascending scale pitches from the start note
+    let first: u8 = first_fret_of(fretboard, 0, scale[start_degree]);
+    let mut pitch: Pitch = fretboard.tuning[0].transpose(first as i16);
+    let mut sequence: Vec<Pitch> = Vec::<Pitch>::new();
+    for i in 0..fretboard.tuning.len() * per_string {
+        sequence.push(pitch);
+        let (from, to): (Note, Note) = (
+            scale[(start_degree + i) % scale.len()],
+            scale[(start_degree + i + 1) % scale.len()],
+        );
+        pitch = pitch.transpose((to.as_u8() as i16 - from.as_u8() as i16).rem_euclid(12));
+    }
+
+    // 2. Place them string by string, an octave higher if a string would fall behind the capo
+    let frets: Vec<i16> = sequence
+        .iter()
+        .enumerate()
+        .map(|(i, p): (usize, &Pitch)| p.midi() - fretboard.tuning[i / per_string].midi())
+        .collect();
+    let shift: i16 = if frets.iter().any(|f: &i16| *f < fretboard.capo as i16) {
+        12
+    } else {
+        0
+    };
+    if frets
+        .iter()
+        .any(|f: &i16| f + shift > fretboard.frets as i16)
+    {
+        return None;
+    }
+
+    Some(
+        frets
+            .iter()
+            .enumerate()
+            .map(|(i, f): (usize, &i16)| FretPosition {
+                string: i / per_string,
+                fret: (f + shift) as u8,
+                pitch: sequence[i].transpose(shift),
+            })
+            .collect(),
+    )
+}
+
+/// Builds the CAGED positions of a scale, anchored on the chord shapes of its root.
+fn caged_shapes(
+    fretboard: &Fretboard,
+    root: Note,
+    scale: &[Note],
+) -> Result<Vec<ScaleShape>, String> {
+    let intervals: Vec<i16> = fretboard
+        .tuning
+        .windows(2)
+        .map(|w: &[Pitch]| w[1].midi() - w[0].midi())
+        .collect();
+    if intervals != STANDARD_INTERVALS {
+        return Err("CAGED shapes need a 6-string guitar in standard tuning intervals".to_string());
+    }
+
+    let mut shapes: Vec<ScaleShape> = Vec::<ScaleShape>::new();
+    for (name, root_string, core, extra) in CAGED_SHAPES {
+        // 1. The lowest root on the shape's string with the whole window on the neck
+        let Some(root_fret) = (fretboard.capo as i16..=fretboard.frets as i16).find(|r: &i16| {
+            fretboard.tuning[root_string].transpose(*r).note == root
+                && r + core.min(extra) >= fretboard.capo as i16
+        }) else {
+            continue;
+        };
+        if root_fret + (core + 3).max(extra) > fretboard.frets as i16 {
+            continue;
+        }
+
+        // 2. The scale notes of the core window, then the stretch fret for missing pitches
+        let mut positions: Vec<FretPosition> = Vec::<FretPosition>::new();
+        for fret in (root_fret + core)..=(root_fret + core + 3) {
+            for string in 0..fretboard.tuning.len() {
+                let pitch: Pitch = fretboard.pitch_at(string, fret as u8).unwrap();
+                if scale.contains(&pitch.note) {
+                    positions.push(FretPosition {
+                        string,
+                        fret: fret as u8,
+                        pitch,
+                    });
+                }
+            }
+        }
+        for string in 0..fretboard.tuning.len() {
+            let fret: u8 = (root_fret + extra) as u8;
+            let pitch: Pitch = fretboard.pitch_at(string, fret).unwrap();
+            if scale.contains(&pitch.note)
+                && !positions.iter().any(|p: &FretPosition| p.pitch == pitch)
+            {
+                positions.push(FretPosition {
+                    string,
+                    fret,
+                    pitch,
+                });
+            }
+        }
+
+        shapes.push(ScaleShape::new(format!("{} Shape", name), root, positions));
+    }
+    Ok(shapes)
+}
+
+/// Splits a scale into the positions of a shape system.
+/// Pentatonic box 1 and 3NPS pattern 1 start from the root on the lowest string;
+/// the next boxes or patterns start from the next scale degrees.
+pub fn scale_shapes(
+    fretboard: &Fretboard,
+    root: Note,
+    scale_type: ScaleType,
+    system: ShapeSystem,
+) -> Result<Vec<ScaleShape>, String> {
+    let scale: Vec<Note> = build_scale(root, scale_type);
+    let per_string: usize = match system {
+        ShapeSystem::Caged => return caged_shapes(fretboard, root, &scale),
+        ShapeSystem::Pentatonic if scale.len() == 5 => 2,
+        ShapeSystem::ThreeNps if scale.len() == 7 => 3,
+        ShapeSystem::Pentatonic => {
+            return Err(format!(
+                "Pentatonic boxes need a five-note scale, not {:?}",
+                scale_type
+            ));
+        }
+        ShapeSystem::ThreeNps => {
+            return Err(format!(
+                "3NPS patterns need a seven-note scale, not {:?}",
+                scale_type
+            ));
+        }
+    };
+    if fretboard.tuning.windows(2).any(|w: &[Pitch]| w[1] <= w[0]) {
+        return Err(
+            "Scale patterns need strings that rise in pitch (no re-entrant tunings)".to_string(),
+        );
+    }
+
+    let label: &str = match system {
+        ShapeSystem::Pentatonic => "Box",
+        _ => "Pattern",
+    };
+    Ok((0..scale.len())
+        .filter_map(|degree: usize| {
+            sequential_shape(fretboard, &scale, degree, per_string).map(
+                |positions: Vec<FretPosition>| {
+                    ScaleShape::new(
+                        format!("{} {}", label, degree + 1),
+                        scale[degree],
+                        positions,
+                    )
+                },
+            )
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fretboard::TuningPreset;
+
+    fn guitar() -> Fretboard {
+        Fretboard::from_preset(TuningPreset::Standard)
+    }
+
+    /// The frets of a shape, one string per group (e.g. "5 8 | 5 7 | ...").
+    fn frets(shape: &ScaleShape) -> String {
+        let mut strings: Vec<Vec<String>> = vec![Vec::<String>::new(); 6];
+        for p in &shape.positions {
+            strings[p.string].push(p.fret.to_string());
+        }
+        strings
+            .iter()
+            .map(|s: &Vec<String>| s.join(" "))
+            .collect::<Vec<String>>()
+            .join(" | ")
+    }
+
+    #[test]
+    fn test_shapes_pentatonic_boxes() {
+        let boxes: Vec<ScaleShape> = scale_shapes(
+            &guitar(),
+            Note::A,
+            ScaleType::PentatonicMinor,
+            ShapeSystem::Pentatonic,
+        )
+        .unwrap();
+        assert_eq!(boxes.len(), 5);
+        assert_eq!(boxes[0].name, "Box 1");
+        assert_eq!(frets(&boxes[0]), "5 8 | 5 7 | 5 7 | 5 7 | 5 8 | 5 8");
+        assert_eq!(frets(&boxes[1]), "8 10 | 7 10 | 7 10 | 7 9 | 8 10 | 8 10");
+        assert!(
+            scale_shapes(
+                &guitar(),
+                Note::A,
+                ScaleType::Major,
+                ShapeSystem::Pentatonic
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_shapes_three_notes_per_string() {
+        let patterns: Vec<ScaleShape> =
+            scale_shapes(&guitar(), Note::G, ScaleType::Major, ShapeSystem::ThreeNps).unwrap();
+        assert_eq!(patterns.len(), 7);
+        assert_eq!(
+            frets(&patterns[0]),
+            "3 5 7 | 3 5 7 | 4 5 7 | 4 5 7 | 5 7 8 | 5 7 8"
+        );
+        for pattern in &patterns {
+            assert_eq!(pattern.positions.len(), 18);
+            // Every note climbs from the previous one
+            for pair in pattern.positions.windows(2) {
+                assert!(pair[0].pitch < pair[1].pitch);
+            }
+        }
+        let ukulele: Fretboard = Fretboard::from_preset(TuningPreset::Ukulele);
+        assert!(scale_shapes(&ukulele, Note::C, ScaleType::Major, ShapeSystem::ThreeNps).is_err());
+    }
+
+    #[test]
+    fn test_shapes_caged() {
+        let shapes: Vec<ScaleShape> =
+            scale_shapes(&guitar(), Note::C, ScaleType::Major, ShapeSystem::Caged).unwrap();
+        let names: Vec<&str> = shapes
+            .iter()
+            .map(|s: &ScaleShape| s.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            ["C Shape", "A Shape", "G Shape", "E Shape", "D Shape"]
+        );
+
+        // The open C major scale, and the E shape around the 8th fret root
+        assert_eq!(
+            frets(&shapes[0]),
+            "0 1 3 | 0 2 3 | 0 2 3 | 0 2 | 0 1 3 | 0 1 3"
+        );
+        assert_eq!(
+            frets(&shapes[3]),
+            "7 8 10 | 7 8 10 | 7 9 10 | 7 9 10 | 8 10 | 7 8 10"
+        );
+        // The A shape reaches the F on the B string with a stretch
+        assert!(
+            shapes[1]
+                .positions
+                .iter()
+                .any(|p: &FretPosition| p.string == 4 && p.fret == 6)
+        );
+
+        // Every shape holds the whole scale, each pitch once
+        let scale: Vec<Note> = build_scale(Note::C, ScaleType::Major);
+        for shape in &shapes {
+            for note in &scale {
+                assert!(
+                    shape
+                        .positions
+                        .iter()
+                        .any(|p: &FretPosition| p.pitch.note == *note)
+                );
+            }
+            let mut pitches: Vec<Pitch> = shape
+                .positions
+                .iter()
+                .map(|p: &FretPosition| p.pitch)
+                .collect();
+            pitches.sort();
+            pitches.dedup();
+            assert_eq!(pitches.len(), shape.positions.len());
+        }
+
+        let drop_d: Fretboard = Fretboard::from_preset(TuningPreset::DropD);
+        assert!(scale_shapes(&drop_d, Note::C, ScaleType::Major, ShapeSystem::Caged).is_err());
+    }
+}
@@ -0,0 +1,353 @@
+/*
+ * tonic-music-core
+ * Copyright (C) 2025 Israel Alberto Roldan Vega
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * Repository: https://github.com/airvzxf/music-theory-calculator/
+ */
+
+/*
+ * crates/tonic-music-core/src/synth.rs
+ *
+ * This module is a small offline synthesizer: it renders the notes
+ * of a MIDI track (scales, chords, voiced progressions) to mono samples
+ * with simple waveforms, an ADSR envelope and an optional strum.
+ */
+
+use crate::midi::MidiNote;
+use crate::pitch::Pitch;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::f64::consts::TAU;
+
+/// The longest rendering, in seconds (ten minutes).
+pub const MAX_DURATION: f64 = 600.0;
+/// The highest sample rate.
+pub const MAX_SAMPLE_RATE: u32 = 192_000;
+
+/// The waveform of every voice.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, ValueEnum, Serialize)]
+#[clap(rename_all = "kebab-case")]
+pub enum Waveform {
+    #[default]
+    Sine,
+    Triangle,
+    Square,
+    #[value(alias("saw"))]
+    Sawtooth,
+    /// Additive: the first four harmonics, softer as they go up
+    Organ,
+}
+
+impl Waveform {
+    /// The value (-1.0 to 1.0) at `phase`, in cycles (0.0 to 1.0).
+    pub fn sample(&self, phase: f64) -> f64 {
+        match self {
+            Waveform::Sine => (TAU * phase).sin(),
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Waveform::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Sawtooth => 2.0 * phase - 1.0,
+            Waveform::Organ => {
+                let harmonics: [f64; 4] = [1.0, 0.5, 0.25, 0.125];
+                harmonics
+                    .iter()
+                    .enumerate()
+                    .map(|(i, a): (usize, &f64)| a * (TAU * phase * (i + 1) as f64).sin())
+                    .sum::<f64>()
+                    / harmonics.iter().sum::<f64>()
+            }
+        }
+    }
+}
+
+/// An ADSR envelope. Times are in seconds, `sustain` is a level (0.0 to 1.0).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Envelope {
+    pub attack: f64,
+    pub decay: f64,
+    pub sustain: f64,
+    pub release: f64,
+}
+
+impl Default for Envelope {
+    fn default() -> Self {
+        Envelope {
+            attack: 0.01,
+            decay: 0.1,
+            sustain: 0.7,
+            release: 0.2,
+        }
+    }
+}
+
+impl Envelope {
+    /// The level `time` seconds into the attack, decay and sustain.
+    fn held(&self, time: f64) -> f64 {
+        if time < self.attack {
+            time / self.attack
+        } else if time < self.attack + self.decay {
+            1.0 - (1.0 - self.sustain) * (time - self.attack) / self.decay
+        } else {
+            self.sustain
+        }
+    }
+
+    /// The level `time` seconds after a note of `length` seconds starts. After
+    /// the note ends, the level reached so far fades out over the release.
+    pub fn level(&self, time: f64, length: f64) -> f64 {
+        if time < 0.0 {
+            0.0
+        } else if time < length {
+            self.held(time)
+        } else if time < length + self.release {
+            self.held(length) * (1.0 - (time - length) / self.release)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// How the notes are rendered.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SynthOptions {
+    /// Samples per second
+    pub sample_rate: u32,
+    /// Beats per minute: note starts and lengths are in beats
+    pub tempo: f64,
+    pub waveform: Waveform,
+    pub envelope: Envelope,
+    /// Seconds between the notes of a chord, strummed from the lowest up
+    pub strum: f64,
+    /// The frequency of A4 in Hz
+    pub a4: f64,
+    /// The level of the loudest sample (0.0 to 1.0)
+    pub gain: f64,
+}
+
+impl Default for SynthOptions {
+    fn default() -> Self {
+        SynthOptions {
+            sample_rate: 44100,
+            tempo: 120.0,
+            waveform: Waveform::Sine,
+            envelope: Envelope::default(),
+            strum: 0.0,
+            a4: 440.0,
+            gain: 0.8,
+        }
+    }
+}
+
+/// Renders the notes to mono samples. Notes that start together are strummed
+/// from the lowest up. The mix is scaled so its loudest sample is `gain`.
+pub fn render(notes: &[MidiNote], options: &SynthOptions) -> Result<Vec<f32>, String> {
+    // 1. Validate the options
+    if options.sample_rate == 0 || options.sample_rate > MAX_SAMPLE_RATE {
+        return Err(format!(
+            "Invalid sample rate {} (1-{})",
+            options.sample_rate, MAX_SAMPLE_RATE
+        ));
+    }
+    if options.tempo <= 0.0 {
+        return Err("The tempo must be greater than 0".to_string());
+    }
+    let envelope: &Envelope = &options.envelope;
+    if [
+        envelope.attack,
+        envelope.decay,
+        envelope.release,
+        options.strum,
+    ]
+    .iter()
+    .any(|t: &f64| !t.is_finite() || *t < 0.0)
+        || !(0.0..=1.0).contains(&envelope.sustain)
+    {
+        return Err("Envelope times must be positive and the sustain 0-1".to_string());
+    }
+
+    // 2. Beats to seconds, with the strum offsets
+    let beat: f64 = 60.0 / options.tempo;
+    let mut sorted: Vec<MidiNote> = notes.to_vec();
+    sorted.sort_by(|a: &MidiNote, b: &MidiNote| {
+        a.start.total_cmp(&b.start).then(a.pitch.cmp(&b.pitch))
+    });
+    let mut timed: Vec<(Pitch, f64, f64)> = Vec::<(Pitch, f64, f64)>::new();
+    let mut strummed: usize = 0;
+    for (i, note) in sorted.iter().enumerate() {
+        strummed = match i {
+            0 => 0,
+            _ if note.start == sorted[i - 1].start => strummed + 1,
+            _ => 0,
+        };
+        let offset: f64 = strummed as f64 * options.strum;
+        let length: f64 = note.length * beat - offset;
+        timed.push((note.pitch, note.start * beat + offset, length.max(0.0)));
+    }
+
+    // 3. Mix the voices
+    let rate: f64 = options.sample_rate as f64;
+    let end: f64 = timed
+        .iter()
+        .map(|(_, start, length): &(Pitch, f64, f64)| start + length + envelope.release)
+        .fold(0.0, f64::max);
+    // Check the length before allocating the samples
+    if !(end.is_finite() && end <= MAX_DURATION) {
+        return Err(format!(
+            "The rendering would last {:.0} seconds ({} at most)",
+            end, MAX_DURATION
+        ));
+    }
+    let mut mix: Vec<f64> = vec![0.0; (end * rate).ceil() as usize];
+    for (pitch, start, length) in timed {
+        let frequency: f64 = pitch.frequency(options.a4);
+        let first: usize = (start * rate).round() as usize;
+        let last: usize =
+            (((start + length + envelope.release) * rate).ceil() as usize).min(mix.len());
+        for (n, sample) in mix.iter_mut().enumerate().take(last).skip(first) {
+            let time: f64 = (n - first) as f64 / rate;
+            let phase: f64 = (frequency * time).fract();
+            *sample += options.waveform.sample(phase) * envelope.level(time, length);
+        }
+    }
+
+    // 4. Normalize
+    let peak: f64 = mix.iter().fold(0.0, |p: f64, s: &f64| p.max(s.abs()));
+    let scale: f64 = if peak > 0.0 { options.gain / peak } else { 0.0 };
+    Ok(mix.iter().map(|s: &f64| (s * scale) as f32).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Note;
+    use crate::midi::{block_chords, sequence};
+
+    /// Counts the upward zero crossings between two sample indexes.
+    fn crossings(samples: &[f32], from: usize, to: usize) -> usize {
+        samples[from..to]
+            .windows(2)
+            .filter(|w: &&[f32]| w[0] < 0.0 && w[1] >= 0.0)
+            .count()
+    }
+
+    #[test]
+    fn test_render_sine_note() {
+        let options: SynthOptions = SynthOptions {
+            sample_rate: 8000,
+            ..SynthOptions::default()
+        };
+        // One beat at 120 BPM: half a second, plus the release
+        let notes: Vec<MidiNote> = sequence(&[Pitch::new(Note::A, 4)], 1.0);
+        let samples: Vec<f32> = render(&notes, &options).unwrap();
+        assert_eq!(samples.len(), 5600);
+        assert_eq!(samples[0], 0.0);
+        assert!((crossings(&samples, 0, 4000) as i32 - 220).abs() <= 1);
+
+        // The loudest sample is the gain, reached at the end of the attack
+        let peak: f32 = samples.iter().fold(0.0, |p: f32, s: &f32| p.max(s.abs()));
+        assert!((peak - 0.8).abs() < 1e-3);
+        // Sustain, then silence at the end of the release
+        let late: f32 = samples[3000..3020]
+            .iter()
+            .fold(0.0, |p: f32, s: &f32| p.max(s.abs()));
+        assert!((late - 0.8 * 0.7).abs() < 0.02);
+        assert!(samples[5599].abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_render_strum() {
+        let options: SynthOptions = SynthOptions {
+            sample_rate: 8000,
+            strum: 0.05,
+            envelope: Envelope {
+                attack: 0.0,
+                decay: 0.0,
+                sustain: 1.0,
+                release: 0.0,
+            },
+            ..SynthOptions::default()
+        };
+        let chord: Vec<Pitch> = vec![Pitch::new(Note::E, 4), Pitch::new(Note::C, 4)];
+        let samples: Vec<f32> = render(&block_chords(&[chord], 1.0), &options).unwrap();
+        // C4 plays alone for 50 ms before E4 joins
+        assert!((crossings(&samples, 0, 400) as i32 - 13).abs() <= 1);
+        assert!(crossings(&samples, 400, 4000) > 13 * 9);
+
+        let silent: SynthOptions = SynthOptions {
+            tempo: 0.0,
+            ..SynthOptions::default()
+        };
+        assert!(render(&[], &silent).is_err());
+    }
+
+    #[test]
+    fn test_render_limits() {
+        let options: SynthOptions = SynthOptions::default();
+        // Ten minutes at 120 BPM is 1200 beats, release included
+        let long: Vec<MidiNote> = sequence(&[Pitch::new(Note::A, 4)], 1e7);
+        assert!(render(&long, &options).is_err());
+        let late: Vec<MidiNote> = vec![MidiNote {
+            pitch: Pitch::new(Note::A, 4),
+            start: f64::INFINITY,
+            length: 1.0,
+        }];
+        assert!(render(&late, &options).is_err());
+
+        for time in [f64::NAN, f64::INFINITY, -1.0] {
+            let attack: SynthOptions = SynthOptions {
+                envelope: Envelope {
+                    attack: time,
+                    ..Envelope::default()
+                },
+                ..SynthOptions::default()
+            };
+            assert!(render(&[], &attack).is_err());
+            let strum: SynthOptions = SynthOptions {
+                strum: time,
+                ..SynthOptions::default()
+            };
+            assert!(render(&[], &strum).is_err());
+        }
+
+        let fast: SynthOptions = SynthOptions {
+            sample_rate: MAX_SAMPLE_RATE + 1,
+            ..SynthOptions::default()
+        };
+        assert!(render(&[], &fast).is_err());
+    }
+
+    #[test]
+    fn test_envelope_release() {
+        let envelope: Envelope = Envelope {
+            attack: 0.1,
+            decay: 0.1,
+            sustain: 0.5,
+            release: 0.2,
+        };
+        assert_eq!(envelope.level(0.05, 1.0), 0.5);
+        assert!((envelope.level(0.15, 1.0) - 0.75).abs() < 1e-9);
+        assert_eq!(envelope.level(0.5, 1.0), 0.5);
+        assert!((envelope.level(1.1, 1.0) - 0.25).abs() < 1e-9);
+        assert_eq!(envelope.level(1.3, 1.0), 0.0);
+        // A note shorter than the attack fades out from where it got to
+        assert!((envelope.level(0.05, 0.05) - 0.5).abs() < 1e-9);
+    }
+}
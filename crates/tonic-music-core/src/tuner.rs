@@ -0,0 +1,280 @@
+/*
+ * tonic-music-core
+ * Copyright (C) 2025 Israel Alberto Roldan Vega
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * Repository: https://github.com/airvzxf/music-theory-calculator/
+ */
+
+/*
+ * crates/tonic-music-core/src/tuner.rs
+ *
+ * This module detects the fundamental frequency of a mono recording
+ * over time with the YIN algorithm, and names the nearest pitch
+ * with its deviation in cents, like a tuner.
+ */
+
+use crate::pitch::Pitch;
+use serde::Serialize;
+
+/// How the recording is cut into frames and which frequencies are searched.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TunerOptions {
+    /// Samples per analysis frame: it must hold two periods of the lowest frequency
+    pub window: usize,
+    /// Samples between the starts of two frames
+    pub hop: usize,
+    /// YIN threshold on the normalized difference (lower is stricter)
+    pub threshold: f64,
+    /// The lowest frequency searched, in Hz
+    pub min_frequency: f64,
+    /// The highest frequency searched, in Hz
+    pub max_frequency: f64,
+    /// Frames quieter than this RMS level are silent
+    pub silence: f64,
+    /// The frequency of A4 in Hz
+    pub a4: f64,
+}
+
+impl Default for TunerOptions {
+    fn default() -> Self {
+        TunerOptions {
+            window: 2048,
+            hop: 512,
+            threshold: 0.15,
+            min_frequency: 50.0,
+            max_frequency: 2000.0,
+            silence: 0.01,
+            a4: 440.0,
+        }
+    }
+}
+
+/// The pitch detected in one frame. Silent or unpitched frames have no frequency.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct PitchFrame {
+    /// Start of the frame in seconds
+    pub time: f64,
+    pub frequency: Option<f64>,
+    /// The nearest equal-tempered pitch
+    pub pitch: Option<Pitch>,
+    /// Distance from that pitch in cents (-50 to +50)
+    pub cents: Option<f64>,
+    /// How periodic the frame is (0.0 to 1.0)
+    pub clarity: f64,
+}
+
+/// Finds the period of a frame in samples with YIN: the first lag, between
+/// `min_lag` and `max_lag`, whose cumulative mean normalized difference falls
+/// below `threshold`, refined by parabolic interpolation. Returns the period
+/// and the clarity of the frame.
+pub fn yin_period(
+    frame: &[f32],
+    min_lag: usize,
+    max_lag: usize,
+    threshold: f64,
+) -> Option<(f64, f64)> {
+    if max_lag < 2 || frame.len() <= max_lag + 1 || min_lag > max_lag {
+        return None;
+    }
+
+    // 1. The difference function d(lag), over a fixed integration window
+    let width: usize = frame.len() - max_lag - 1;
+    let mut differences: Vec<f64> = vec![0.0; max_lag + 2];
+    let mut normalized: Vec<f64> = vec![1.0; max_lag + 2];
+    let mut running: f64 = 0.0;
+    for lag in 1..=max_lag + 1 {
+        let difference: f64 = (0..width)
+            .map(|j: usize| {
+                let delta: f64 = frame[j] as f64 - frame[j + lag] as f64;
+                delta * delta
+            })
+            .sum();
+        differences[lag] = difference;
+
+        // 2. Cumulative mean normalization: d'(lag) = d(lag) / mean(d(1..=lag))
+        running += difference;
+        normalized[lag] = if running > 0.0 {
+            difference * lag as f64 / running
+        } else {
+            1.0
+        };
+    }
+
+    // 3. The first dip under the threshold, followed down to its minimum
+    let mut lag: usize = (min_lag.max(2)..=max_lag).find(|l: &usize| normalized[*l] < threshold)?;
+    while lag < max_lag && normalized[lag + 1] < normalized[lag] {
+        lag += 1;
+    }
+
+    // 4. Parabolic interpolation of the raw difference around the lag
+    let (before, at, after): (f64, f64, f64) =
+        (differences[lag - 1], differences[lag], differences[lag + 1]);
+    let curvature: f64 = before - 2.0 * at + after;
+    let shift: f64 = if curvature > 0.0 {
+        (0.5 * (before - after) / curvature).clamp(-1.0, 1.0)
+    } else {
+        0.0
+    };
+    Some((lag as f64 + shift, (1.0 - normalized[lag]).clamp(0.0, 1.0)))
+}
+
+/// Detects the pitch of each frame of a mono recording.
+pub fn detect_pitches(
+    samples: &[f32],
+    sample_rate: u32,
+    options: &TunerOptions,
+) -> Result<Vec<PitchFrame>, String> {
+    // 1. Validate the options
+    if options.hop == 0 {
+        return Err("The hop must be at least one sample".to_string());
+    }
+    if options.min_frequency <= 0.0 || options.max_frequency <= options.min_frequency {
+        return Err("The frequency range must be positive and not empty".to_string());
+    }
+    if options.a4 <= 0.0 {
+        return Err("The A4 reference must be greater than 0".to_string());
+    }
+    let rate: f64 = sample_rate as f64;
+    let min_lag: usize = (rate / options.max_frequency).floor() as usize;
+    let max_lag: usize = (rate / options.min_frequency).ceil() as usize;
+    if options.window < 2 * max_lag + 2 {
+        return Err(format!(
+            "A window of {} samples is too short for {} Hz at {} Hz: use at least {}",
+            options.window,
+            options.min_frequency,
+            sample_rate,
+            2 * max_lag + 2
+        ));
+    }
+
+    // 2. Analyze each frame
+    let mut frames: Vec<PitchFrame> = Vec::<PitchFrame>::new();
+    let mut start: usize = 0;
+    while start + options.window <= samples.len() {
+        let frame: &[f32] = &samples[start..start + options.window];
+        let rms: f64 = (frame.iter().map(|s: &f32| (*s as f64).powi(2)).sum::<f64>()
+            / frame.len() as f64)
+            .sqrt();
+        let period: Option<(f64, f64)> = if rms < options.silence {
+            None
+        } else {
+            yin_period(frame, min_lag, max_lag, options.threshold)
+        };
+        let time: f64 = start as f64 / rate;
+        frames.push(match period {
+            Some((period, clarity)) => {
+                let frequency: f64 = rate / period;
+                let (pitch, cents): (Pitch, f64) = Pitch::from_frequency(frequency, options.a4);
+                PitchFrame {
+                    time,
+                    frequency: Some(frequency),
+                    pitch: Some(pitch),
+                    cents: Some(cents),
+                    clarity,
+                }
+            }
+            None => PitchFrame {
+                time,
+                frequency: None,
+                pitch: None,
+                cents: None,
+                clarity: 0.0,
+            },
+        });
+        start += options.hop;
+    }
+    Ok(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Note;
+    use crate::midi::sequence;
+    use crate::synth::{SynthOptions, Waveform, render};
+
+    fn sine(frequency: f64, sample_rate: u32, length: usize) -> Vec<f32> {
+        (0..length)
+            .map(|n: usize| {
+                (std::f64::consts::TAU * frequency * n as f64 / sample_rate as f64).sin() as f32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_tuner_sine() {
+        let options: TunerOptions = TunerOptions {
+            window: 1024,
+            hop: 1024,
+            min_frequency: 80.0,
+            ..TunerOptions::default()
+        };
+        let frames: Vec<PitchFrame> =
+            detect_pitches(&sine(445.0, 8000, 4096), 8000, &options).unwrap();
+        assert_eq!(frames.len(), 4);
+        let frame: PitchFrame = frames[1];
+        assert!((frame.time - 0.128).abs() < 1e-9);
+        assert!((frame.frequency.unwrap() - 445.0).abs() < 0.5);
+        assert_eq!(frame.pitch, Some(Pitch::new(Note::A, 4)));
+        assert!((frame.cents.unwrap() - 19.6).abs() < 2.0);
+        assert!(frame.clarity > 0.9);
+
+        // A4 = 445 Hz puts the same tone in tune
+        let tuned: TunerOptions = TunerOptions {
+            a4: 445.0,
+            ..options.clone()
+        };
+        let frames: Vec<PitchFrame> =
+            detect_pitches(&sine(445.0, 8000, 1024), 8000, &tuned).unwrap();
+        assert!(frames[0].cents.unwrap().abs() < 2.0);
+
+        // Silence has no pitch, and the window must fit the lowest frequency
+        let frames: Vec<PitchFrame> = detect_pitches(&[0.0; 2048], 8000, &options).unwrap();
+        assert!(frames.iter().all(|f: &PitchFrame| f.pitch.is_none()));
+        let short: TunerOptions = TunerOptions {
+            window: 128,
+            ..options
+        };
+        assert!(detect_pitches(&[0.0; 2048], 8000, &short).is_err());
+    }
+
+    #[test]
+    fn test_tuner_melody() {
+        // C3 then G3 on a sawtooth: rich in harmonics, still named by its fundamental
+        let synth: SynthOptions = SynthOptions {
+            sample_rate: 8000,
+            waveform: Waveform::Sawtooth,
+            ..SynthOptions::default()
+        };
+        let notes = sequence(&[Pitch::new(Note::C, 3), Pitch::new(Note::G, 3)], 1.0);
+        let samples: Vec<f32> = render(&notes, &synth).unwrap();
+        let options: TunerOptions = TunerOptions {
+            window: 1024,
+            hop: 400,
+            min_frequency: 80.0,
+            ..TunerOptions::default()
+        };
+        let frames: Vec<PitchFrame> = detect_pitches(&samples, 8000, &options).unwrap();
+        let at = |time: f64| -> Option<Pitch> {
+            frames
+                .iter()
+                .find(|f: &&PitchFrame| f.time >= time)
+                .and_then(|f: &PitchFrame| f.pitch)
+        };
+        assert_eq!(at(0.1), Some(Pitch::new(Note::C, 3)));
+        assert_eq!(at(0.6), Some(Pitch::new(Note::G, 3)));
+    }
+}
@@ -0,0 +1,524 @@
+/*
+ * tonic-music-core
+ * Copyright (C) 2025 Israel Alberto Roldan Vega
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * Repository: https://github.com/airvzxf/music-theory-calculator/
+ */
+
+/*
+ * crates/tonic-music-core/src/voice_leading.rs
+ *
+ * This module voices a progression for several voices
+ * (e.g. SATB) with octave-specific pitches, minimizing
+ * the total movement over the whole progression.
+ */
+
+use crate::analysis::HarmonicFunction;
+use crate::pitch::Pitch;
+use crate::{ChordType, Note, ProgressionChord, build_chord};
+use serde::{Deserialize, Serialize};
+
+/// Cost of moving one upper voice by one semitone.
+const MOVEMENT_WEIGHT: u32 = 4;
+/// Cost of moving the bass by one semitone (bass leaps between roots are idiomatic).
+const BASS_MOVEMENT_WEIGHT: u32 = 1;
+/// Cost of each semitone a voice sits away from the middle of its range.
+const REGISTER_WEIGHT: u32 = 1;
+/// Cost of a third in the bass (twice that for a fifth or seventh).
+const INVERSION_PENALTY: u32 = 12;
+/// Cost of doubling the fifth (twice that for the third or seventh).
+const DOUBLING_PENALTY: u32 = 4;
+/// Cost of each pair of voices moving in parallel fifths or octaves.
+const PARALLEL_PENALTY: u32 = 40;
+/// Largest gap (in semitones) allowed between adjacent upper voices
+/// (also checked by `part_writing`).
+pub(crate) const MAX_UPPER_SPACING: i16 = 12;
+
+/// Weights of the inversion choice made by `build_progression` and
+/// `build_custom_progression`. Every candidate inversion gets a cost
+/// (lower is better); the defaults reproduce the classic behavior:
+/// smallest bass motion first, then the bass closest to the tonic.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VoiceLeadingOptions {
+    /// Cost per semitone the bass moves from the previous chord
+    pub bass_motion: u32,
+    /// Cost per semitone the bass sits away from the tonic (keeps the bass in range)
+    pub range: u32,
+    /// Reward per chord tone held in the same voice as in the previous chord
+    pub common_tones: u32,
+    /// Reward when the bass and the top voice move in opposite directions
+    pub contrary_motion: u32,
+    /// Cost per step away from the preferred inversion
+    pub inversion: u32,
+    /// The preferred inversion (0 = root position, 1 = first inversion...)
+    pub preferred_inversion: usize,
+}
+
+impl Default for VoiceLeadingOptions {
+    fn default() -> Self {
+        VoiceLeadingOptions {
+            // Bass distances are at most 6 semitones, so a weight of 7 makes
+            // bass motion always win over the distance to the tonic
+            bass_motion: 7,
+            range: 1,
+            common_tones: 0,
+            contrary_motion: 0,
+            inversion: 0,
+            preferred_inversion: 0,
+        }
+    }
+}
+
+/// A voice with the register it can sing or play.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VoiceRange {
+    pub name: String,
+    pub low: Pitch,
+    pub high: Pitch,
+}
+
+impl VoiceRange {
+    pub fn new(name: &str, low: Pitch, high: Pitch) -> Self {
+        VoiceRange {
+            name: name.to_string(),
+            low,
+            high,
+        }
+    }
+
+    /// The four choir voices, from the bass up.
+    pub fn satb() -> Vec<VoiceRange> {
+        vec![
+            VoiceRange::new("Bass", Pitch::new(Note::E, 2), Pitch::new(Note::C, 4)),
+            VoiceRange::new("Tenor", Pitch::new(Note::C, 3), Pitch::new(Note::G, 4)),
+            VoiceRange::new("Alto", Pitch::new(Note::G, 3), Pitch::new(Note::C, 5)),
+            VoiceRange::new("Soprano", Pitch::new(Note::C, 4), Pitch::new(Note::G, 5)),
+        ]
+    }
+
+    /// Returns true if the pitch is within the range (inclusive).
+    pub fn contains(&self, pitch: Pitch) -> bool {
+        self.low <= pitch && pitch <= self.high
+    }
+
+    fn center(&self) -> i16 {
+        (self.low.midi() + self.high.midi()) / 2
+    }
+}
+
+/// A chord of a progression voiced with one pitch per voice.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VoicedChord {
+    pub degree: String,
+    pub root_note: Note,
+    pub chord_type: ChordType,
+    pub function: HarmonicFunction,
+    /// One pitch per voice, in the same order as the voice ranges (lowest voice first)
+    pub pitches: Vec<Pitch>,
+}
+
+/// Semitones from the chord root to `note` (0-11).
+fn interval_above_root(root: Note, note: Note) -> u8 {
+    (note.as_u8() + 12 - root.as_u8()) % 12
+}
+
+/// Lists every voicing of the chord that fits the voices:
+/// each voice in range, no crossing, upper voices within an octave,
+/// and every chord tone present (the fifth may be dropped if voices are short).
+pub fn chord_voicings(root: Note, chord_type: ChordType, voices: &[VoiceRange]) -> Vec<Vec<Pitch>> {
+    let tones: Vec<Note> = build_chord(root, chord_type);
+
+    // 1. Which pitch classes must appear
+    let mut required: Vec<Note> = tones.clone();
+    if required.len() > voices.len() {
+        required.retain(|n: &Note| interval_above_root(root, *n) != 7);
+    }
+    if required.len() > voices.len() {
+        return Vec::<Vec<Pitch>>::new();
+    }
+
+    // 2. The chord tones each voice can reach
+    let options: Vec<Vec<Pitch>> = voices
+        .iter()
+        .map(|voice: &VoiceRange| {
+            (voice.low.midi()..=voice.high.midi())
+                .map(Pitch::from_midi)
+                .filter(|p: &Pitch| tones.contains(&p.note))
+                .collect()
+        })
+        .collect();
+
+    // 3. Walk the combinations from the bass up, pruning crossings and wide gaps
+    let mut voicings: Vec<Vec<Pitch>> = Vec::<Vec<Pitch>>::new();
+    let mut current: Vec<Pitch> = Vec::<Pitch>::new();
+    collect_voicings(&options, &required, &mut current, &mut voicings);
+    voicings
+}
+
+fn collect_voicings(
+    options: &[Vec<Pitch>],
+    required: &[Note],
+    current: &mut Vec<Pitch>,
+    voicings: &mut Vec<Vec<Pitch>>,
+) {
+    let index: usize = current.len();
+    if index == options.len() {
+        let complete: bool = required
+            .iter()
+            .all(|n: &Note| current.iter().any(|p: &Pitch| p.note == *n));
+        if complete {
+            voicings.push(current.clone());
+        }
+        return;
+    }
+
+    for pitch in &options[index] {
+        let pitch: Pitch = *pitch;
+        if let Some(below) = current.last() {
+            if pitch < *below {
+                continue;
+            }
+            // The bass may sit far below the tenor; the upper voices stay close
+            if index >= 2 && pitch.midi() - below.midi() > MAX_UPPER_SPACING {
+                continue;
+            }
+        }
+        current.push(pitch);
+        collect_voicings(options, required, current, voicings);
+        current.pop();
+    }
+}
+
+/// The cost of a voicing on its own: inversion, doubling and register.
+fn voicing_cost(root: Note, pitches: &[Pitch], voices: &[VoiceRange]) -> u32 {
+    let mut cost: u32 = 0;
+
+    // 1. Prefer the root in the bass
+    cost += match interval_above_root(root, pitches[0].note) {
+        0 => 0,
+        3 | 4 => INVERSION_PENALTY,
+        _ => 2 * INVERSION_PENALTY,
+    };
+
+    // 2. Prefer doubling the root, then the fifth
+    for (i, pitch) in pitches.iter().enumerate() {
+        let doubled: bool = pitches[..i].iter().any(|p: &Pitch| p.note == pitch.note);
+        if doubled {
+            cost += match interval_above_root(root, pitch.note) {
+                0 => 0,
+                7 => DOUBLING_PENALTY,
+                _ => 2 * DOUBLING_PENALTY,
+            };
+        }
+    }
+
+    // 3. Keep each voice near the middle of its range
+    for (pitch, voice) in pitches.iter().zip(voices) {
+        cost += REGISTER_WEIGHT * (pitch.midi() - voice.center()).unsigned_abs() as u32;
+    }
+
+    cost
+}
+
+/// The cost of moving from one voicing to the next.
+fn movement_cost(from: &[Pitch], to: &[Pitch]) -> u32 {
+    let mut cost: u32 = 0;
+
+    // 1. How far each voice moves
+    for (i, (a, b)) in from.iter().zip(to).enumerate() {
+        let weight: u32 = if i == 0 {
+            BASS_MOVEMENT_WEIGHT
+        } else {
+            MOVEMENT_WEIGHT
+        };
+        cost += weight * (a.midi() - b.midi()).unsigned_abs() as u32;
+    }
+
+    // 2. Parallel fifths and octaves between any two voices
+    for lower in 0..from.len() {
+        for upper in (lower + 1)..from.len() {
+            let motion_lower: i16 = to[lower].midi() - from[lower].midi();
+            let motion_upper: i16 = to[upper].midi() - from[upper].midi();
+            if motion_lower == 0 || motion_lower.signum() != motion_upper.signum() {
+                continue;
+            }
+            let before: i16 = (from[upper].midi() - from[lower].midi()).rem_euclid(12);
+            let after: i16 = (to[upper].midi() - to[lower].midi()).rem_euclid(12);
+            if before == after && (before == 0 || before == 7) {
+                cost += PARALLEL_PENALTY;
+            }
+        }
+    }
+
+    cost
+}
+
+/// Voices every chord of the progression for the given voices (lowest first).
+///
+/// Unlike the bass-only greedy choice of `build_progression`, this searches
+/// all voicings of all chords (dynamic programming) for the path with the
+/// least total cost, where cost is mostly voice movement.
+pub fn voice_progression(
+    progression: &[ProgressionChord],
+    voices: &[VoiceRange],
+) -> Result<Vec<VoicedChord>, String> {
+    if voices.is_empty() {
+        return Err("At least one voice is required".to_string());
+    }
+
+    // 1. Candidate voicings for every chord
+    let mut candidates: Vec<Vec<Vec<Pitch>>> = Vec::<Vec<Vec<Pitch>>>::new();
+    for chord in progression {
+        let chord: &ProgressionChord = chord;
+        let voicings: Vec<Vec<Pitch>> = chord_voicings(chord.root_note, chord.chord_type, voices);
+        if voicings.is_empty() {
+            return Err(format!(
+                "Chord {} ({} {:?}) cannot be voiced within the voice ranges",
+                chord.degree, chord.root_note, chord.chord_type
+            ));
+        }
+        candidates.push(voicings);
+    }
+
+    // 2. Forward pass: best cost to reach each voicing, with back pointers
+    let mut costs: Vec<Vec<u32>> = Vec::<Vec<u32>>::new();
+    let mut back: Vec<Vec<usize>> = Vec::<Vec<usize>>::new();
+    for (i, voicings) in candidates.iter().enumerate() {
+        let root: Note = progression[i].root_note;
+        let mut layer_costs: Vec<u32> = Vec::<u32>::with_capacity(voicings.len());
+        let mut layer_back: Vec<usize> = Vec::<usize>::with_capacity(voicings.len());
+
+        for voicing in voicings {
+            let own: u32 = voicing_cost(root, voicing, voices);
+            if i == 0 {
+                layer_costs.push(own);
+                layer_back.push(0);
+                continue;
+            }
+
+            let (best_previous, best_cost): (usize, u32) = candidates[i - 1]
+                .iter()
+                .enumerate()
+                .map(|(j, previous): (usize, &Vec<Pitch>)| {
+                    (j, costs[i - 1][j] + movement_cost(previous, voicing))
+                })
+                .min_by_key(|(_, cost): &(usize, u32)| *cost)
+                .unwrap();
+            layer_costs.push(best_cost + own);
+            layer_back.push(best_previous);
+        }
+
+        costs.push(layer_costs);
+        back.push(layer_back);
+    }
+
+    // 3. Backtrack from the cheapest final voicing
+    let mut chosen: Vec<usize> = vec![0; progression.len()];
+    if let Some(last) = costs.last() {
+        chosen[progression.len() - 1] = last
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, cost): &(usize, &u32)| **cost)
+            .map(|(j, _): (usize, &u32)| j)
+            .unwrap();
+        for i in (1..progression.len()).rev() {
+            chosen[i - 1] = back[i][chosen[i]];
+        }
+    }
+
+    Ok(progression
+        .iter()
+        .zip(chosen)
+        .enumerate()
+        .map(
+            |(i, (chord, j)): (usize, (&ProgressionChord, usize))| VoicedChord {
+                degree: chord.degree.clone(),
+                root_note: chord.root_note,
+                chord_type: chord.chord_type,
+                function: chord.function,
+                pitches: candidates[i][j].clone(),
+            },
+        )
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_custom_progression;
+    use crate::parser::parse_progression_formula;
+
+    fn progression(root: Note, formula: &str) -> Vec<ProgressionChord> {
+        build_custom_progression(
+            root,
+            parse_progression_formula(formula).unwrap(),
+            &VoiceLeadingOptions::default(),
+        )
+    }
+
+    #[test]
+    fn test_voice_leading_candidates_respect_ranges() {
+        let voices: Vec<VoiceRange> = VoiceRange::satb();
+        let voicings: Vec<Vec<Pitch>> = chord_voicings(Note::C, ChordType::Major, &voices);
+        assert!(!voicings.is_empty());
+
+        for voicing in &voicings {
+            assert_eq!(voicing.len(), 4);
+            for (pitch, voice) in voicing.iter().zip(&voices) {
+                assert!(voice.contains(*pitch));
+            }
+            for pair in voicing.windows(2) {
+                assert!(pair[0] <= pair[1]);
+            }
+            for note in [Note::C, Note::E, Note::G] {
+                assert!(voicing.iter().any(|p: &Pitch| p.note == note));
+            }
+        }
+
+        // A seventh chord in three voices drops the fifth
+        let three: Vec<VoiceRange> = voices[1..].to_vec();
+        for voicing in chord_voicings(Note::G, ChordType::Dominant7, &three) {
+            assert!(!voicing.iter().any(|p: &Pitch| p.note == Note::D));
+        }
+    }
+
+    #[test]
+    fn test_voice_leading_satb_cadence() {
+        let voiced: Vec<VoicedChord> =
+            voice_progression(&progression(Note::C, "I-IV-V-I"), &VoiceRange::satb()).unwrap();
+        assert_eq!(voiced.len(), 4);
+
+        // Root position throughout, smooth upper voices
+        for (chord, root) in voiced.iter().zip([Note::C, Note::F, Note::G, Note::C]) {
+            assert_eq!(chord.pitches[0].note, root);
+        }
+        for pair in voiced.windows(2) {
+            for voice in 1..4 {
+                let leap: u16 =
+                    (pair[0].pitches[voice].midi() - pair[1].pitches[voice].midi()).unsigned_abs();
+                assert!(leap <= 4, "{:?} -> {:?}", pair[0].pitches, pair[1].pitches);
+            }
+        }
+    }
+
+    #[test]
+    fn test_voice_leading_beats_greedy_choice() {
+        let chords: Vec<ProgressionChord> = progression(Note::C, "ii7-V7-Imaj7-vi7-ii7-V7-I");
+        let voices: Vec<VoiceRange> = VoiceRange::satb();
+        let voiced: Vec<VoicedChord> = voice_progression(&chords, &voices).unwrap();
+
+        let total = |path: &[Vec<Pitch>]| -> u32 {
+            let own: u32 = path
+                .iter()
+                .zip(&chords)
+                .map(|(v, c): (&Vec<Pitch>, &ProgressionChord)| {
+                    voicing_cost(c.root_note, v, &voices)
+                })
+                .sum();
+            let movement: u32 = path
+                .windows(2)
+                .map(|pair: &[Vec<Pitch>]| movement_cost(&pair[0], &pair[1]))
+                .sum();
+            own + movement
+        };
+
+        // Greedy baseline: same start, then each chord takes the cheapest next step
+        let mut greedy: Vec<Vec<Pitch>> = vec![voiced[0].pitches.clone()];
+        for chord in &chords[1..] {
+            let previous: &Vec<Pitch> = greedy.last().unwrap();
+            let next: Vec<Pitch> = chord_voicings(chord.root_note, chord.chord_type, &voices)
+                .into_iter()
+                .min_by_key(|v: &Vec<Pitch>| {
+                    movement_cost(previous, v) + voicing_cost(chord.root_note, v, &voices)
+                })
+                .unwrap();
+            greedy.push(next);
+        }
+
+        let solved: Vec<Vec<Pitch>> = voiced
+            .iter()
+            .map(|c: &VoicedChord| c.pitches.clone())
+            .collect();
+        assert!(total(&solved) <= total(&greedy));
+    }
+
+    #[test]
+    fn test_voice_leading_options_weights() {
+        let specs = || parse_progression_formula("I-IV-V-vi").unwrap();
+
+        // Default: the bass walks (I -> IV in 2nd inversion keeps C in the bass)
+        let default: Vec<ProgressionChord> =
+            build_custom_progression(Note::C, specs(), &VoiceLeadingOptions::default());
+        assert_eq!(default[1].notes, vec![Note::C, Note::F, Note::A]);
+
+        // A strong preference for root position overrides bass smoothness
+        let root_position: VoiceLeadingOptions = VoiceLeadingOptions {
+            inversion: 100,
+            ..VoiceLeadingOptions::default()
+        };
+        for chord in build_custom_progression(Note::C, specs(), &root_position) {
+            assert_eq!(chord.notes[0], chord.root_note);
+        }
+
+        // Preferring first inversion puts the thirds in the bass
+        let first_inversion: VoiceLeadingOptions = VoiceLeadingOptions {
+            inversion: 100,
+            preferred_inversion: 1,
+            ..VoiceLeadingOptions::default()
+        };
+        let thirds: Vec<ProgressionChord> =
+            build_custom_progression(Note::C, specs(), &first_inversion);
+        assert_eq!(thirds[0].notes[0], Note::E);
+        assert_eq!(thirds[2].notes[0], Note::B);
+
+        // Without any weights every inversion ties, and root position comes first
+        let flat: VoiceLeadingOptions = VoiceLeadingOptions {
+            bass_motion: 0,
+            range: 0,
+            ..VoiceLeadingOptions::default()
+        };
+        for chord in build_custom_progression(Note::C, specs(), &flat) {
+            assert_eq!(chord.notes[0], chord.root_note);
+        }
+    }
+
+    #[test]
+    fn test_voice_leading_options_json() {
+        let options: VoiceLeadingOptions =
+            serde_json::from_str(r#"{ "common_tones": 3, "preferred_inversion": 1 }"#).unwrap();
+        assert_eq!(options.common_tones, 3);
+        assert_eq!(options.preferred_inversion, 1);
+        assert_eq!(
+            options.bass_motion,
+            VoiceLeadingOptions::default().bass_motion
+        );
+        assert!(serde_json::from_str::<VoiceLeadingOptions>(r#"{ "bass": 1 }"#).is_err());
+    }
+
+    #[test]
+    fn test_voice_leading_errors() {
+        let chords: Vec<ProgressionChord> = progression(Note::C, "I-V");
+        assert!(voice_progression(&chords, &[]).is_err());
+
+        let narrow: Vec<VoiceRange> = vec![VoiceRange::new(
+            "Narrow",
+            Pitch::new(Note::C, 4),
+            Pitch::new(Note::D, 4),
+        )];
+        assert!(voice_progression(&chords, &narrow).is_err());
+    }
+}
@@ -0,0 +1,314 @@
+/*
+ * tonic-music-core
+ * Copyright (C) 2025 Israel Alberto Roldan Vega
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * Repository: https://github.com/airvzxf/music-theory-calculator/
+ */
+
+/*
+ * crates/tonic-music-core/src/voicing.rs
+ *
+ * This module builds named piano voicings (shell, drop-2,
+ * rootless, quartal...) with octave-specific pitches.
+ */
+
+use crate::pitch::Pitch;
+use crate::voice_leading::VoicedChord;
+use crate::{ChordType, Interval, Note, ProgressionChord};
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// The available voicing strategies.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum, Serialize)]
+#[clap(rename_all = "kebab-case")]
+pub enum VoicingStyle {
+    /// Close position, root in the bass
+    Close,
+    /// Root, third and seventh (1-3-7)
+    Shell,
+    /// Close position with the second voice from the top dropped an octave
+    #[value(alias("drop-2"))]
+    Drop2,
+    /// Close position with the third voice from the top dropped an octave
+    #[value(alias("drop-3"))]
+    Drop3,
+    /// Close position with the second and fourth voices from the top dropped an octave
+    #[value(alias("drop-2-4"), alias("drop2-4"))]
+    Drop24,
+    /// Rootless voicing with the third at the bottom (3-5-7-9, or 3-13-7-9 on dominants)
+    #[value(alias("a"))]
+    RootlessA,
+    /// Rootless voicing with the seventh at the bottom (7-9-3-5, or 7-9-3-13 on dominants)
+    #[value(alias("b"))]
+    RootlessB,
+    /// Stacked fourths ("So What" on minor chords)
+    #[value(alias("so-what"))]
+    Quartal,
+    /// Root low, the other tones spread over two octaves above it
+    Spread,
+}
+
+/// Semitones from the root of each chord tone (root, third, fifth, seventh).
+fn chord_offsets(chord_type: ChordType) -> Vec<i16> {
+    chord_type
+        .intervals()
+        .iter()
+        .map(|i: &Interval| i.as_u8() as i16)
+        .collect()
+}
+
+/// The semitone of the seventh, or an error for triads.
+fn seventh_offset(chord_type: ChordType, style: VoicingStyle) -> Result<i16, String> {
+    chord_offsets(chord_type)
+        .get(3)
+        .copied()
+        .ok_or_else(|| format!("{:?} voicings need a seventh chord", style))
+}
+
+/// Semitone offsets from the root (in the reference octave) of each voicing.
+fn voicing_offsets(chord_type: ChordType, style: VoicingStyle) -> Result<Vec<i16>, String> {
+    let tones: Vec<i16> = chord_offsets(chord_type);
+    let (third, fifth): (i16, i16) = (tones[1], tones[2]);
+
+    // Close position with four voices (a triad doubles its root on top)
+    let mut close: Vec<i16> = tones.clone();
+    if close.len() == 3 {
+        close.push(12);
+    }
+
+    let offsets: Vec<i16> = match style {
+        VoicingStyle::Close => tones,
+        VoicingStyle::Shell => vec![0, third, seventh_offset(chord_type, style)?],
+        VoicingStyle::Drop2 => drop_voices(&close, &[2]),
+        VoicingStyle::Drop3 => drop_voices(&close, &[3]),
+        VoicingStyle::Drop24 => drop_voices(&close, &[2, 4]),
+        VoicingStyle::RootlessA | VoicingStyle::RootlessB => {
+            let seventh: i16 = seventh_offset(chord_type, style)?;
+            // The ninth (or eleventh on half-diminished) replaces the root;
+            // dominants also take the thirteenth instead of the fifth
+            let color: i16 = match chord_type {
+                ChordType::Minor7b5 => 17,
+                _ => 14,
+            };
+            let middle: i16 = match chord_type {
+                ChordType::Dominant7 => 9,
+                _ => fifth,
+            };
+            match style {
+                VoicingStyle::RootlessA => vec![third, middle, seventh, color],
+                _ => vec![seventh - 12, color - 12, third, middle],
+            }
+        }
+        VoicingStyle::Quartal => match chord_type {
+            // "So What": three fourths from the root and a major third on top
+            ChordType::Minor | ChordType::Minor7 => vec![0, 5, 10, 15, 19],
+            // The same shape from the third (3-6-9-5-7)
+            ChordType::Major | ChordType::Major7 => vec![4, 9, 14, 19, 23],
+            // Tritone and fourths from the seventh (b7-3-13-9)
+            ChordType::Dominant7 => vec![-2, 4, 9, 14],
+            _ => return Err(format!("No quartal voicing for {:?} chords", chord_type)),
+        },
+        VoicingStyle::Spread => {
+            let top: i16 = tones.get(3).copied().unwrap_or(12);
+            vec![0, fifth, third + 12, top + 12]
+        }
+    };
+
+    Ok(offsets)
+}
+
+/// Drops the given voices (counted from the top, 1 = top) of a close voicing
+/// by an octave and re-sorts the result from the bottom up.
+fn drop_voices(close: &[i16], from_top: &[usize]) -> Vec<i16> {
+    let mut voices: Vec<i16> = close.to_vec();
+    for position in from_top {
+        let index: usize = voices.len() - position;
+        voices[index] -= 12;
+    }
+    voices.sort();
+    voices
+}
+
+/// Builds a voicing of the chord with octave-specific pitches.
+/// `octave` places the root (e.g. 3 for C3); the voicing is built around it.
+pub fn voice_chord(
+    root: Note,
+    chord_type: ChordType,
+    style: VoicingStyle,
+    octave: i8,
+) -> Result<Vec<Pitch>, String> {
+    let reference: Pitch = Pitch::new(root, octave);
+    let offsets: Vec<i16> = voicing_offsets(chord_type, style)?;
+    Ok(offsets
+        .iter()
+        .map(|o: &i16| reference.transpose(*o))
+        .collect())
+}
+
+/// The average MIDI number of a voicing (its "center of gravity").
+fn center(pitches: &[Pitch]) -> i16 {
+    let total: i16 = pitches.iter().map(|p: &Pitch| p.midi()).sum();
+    total / pitches.len().max(1) as i16
+}
+
+/// Voices every chord of a progression in the same style. Each chord is moved
+/// up or down an octave if that keeps it closer to the previous voicing.
+pub fn voice_progression_in_style(
+    progression: &[ProgressionChord],
+    style: VoicingStyle,
+    octave: i8,
+) -> Result<Vec<VoicedChord>, String> {
+    let mut voiced: Vec<VoicedChord> = Vec::<VoicedChord>::new();
+    let mut previous_center: Option<i16> = None;
+
+    for chord in progression {
+        let chord: &ProgressionChord = chord;
+        let base: Vec<Pitch> = voice_chord(chord.root_note, chord.chord_type, style, octave)
+            .map_err(|e: String| format!("Chord {}: {}", chord.degree, e))?;
+
+        let pitches: Vec<Pitch> = match previous_center {
+            None => base,
+            Some(target) => [0, -12, 12]
+                .iter()
+                .map(|shift: &i16| {
+                    base.iter()
+                        .map(|p: &Pitch| p.transpose(*shift))
+                        .collect::<Vec<Pitch>>()
+                })
+                .min_by_key(|candidate: &Vec<Pitch>| (center(candidate) - target).abs())
+                .unwrap(),
+        };
+        previous_center = Some(center(&pitches));
+
+        voiced.push(VoicedChord {
+            degree: chord.degree.clone(),
+            root_note: chord.root_note,
+            chord_type: chord.chord_type,
+            function: chord.function,
+            pitches,
+        });
+    }
+
+    Ok(voiced)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_custom_progression;
+    use crate::parser::{parse_pitch, parse_progression_formula};
+    use crate::voice_leading::VoiceLeadingOptions;
+
+    fn pitches(names: &str) -> Vec<Pitch> {
+        names
+            .split(' ')
+            .map(|p: &str| parse_pitch(p).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_voicing_close_shell_spread() {
+        let cmaj7: Vec<Pitch> =
+            voice_chord(Note::C, ChordType::Major7, VoicingStyle::Close, 3).unwrap();
+        assert_eq!(cmaj7, pitches("C3 E3 G3 B3"));
+
+        let shell: Vec<Pitch> =
+            voice_chord(Note::G, ChordType::Dominant7, VoicingStyle::Shell, 2).unwrap();
+        assert_eq!(shell, pitches("G2 B2 F3"));
+        assert!(voice_chord(Note::C, ChordType::Major, VoicingStyle::Shell, 3).is_err());
+
+        let spread: Vec<Pitch> =
+            voice_chord(Note::C, ChordType::Major7, VoicingStyle::Spread, 2).unwrap();
+        assert_eq!(spread, pitches("C2 G2 E3 B3"));
+    }
+
+    #[test]
+    fn test_voicing_drops() {
+        // Cmaj7 close C E G B: drop the G (2nd from top), the E (3rd), or the G and C
+        assert_eq!(
+            voice_chord(Note::C, ChordType::Major7, VoicingStyle::Drop2, 4).unwrap(),
+            pitches("G3 C4 E4 B4")
+        );
+        assert_eq!(
+            voice_chord(Note::C, ChordType::Major7, VoicingStyle::Drop3, 4).unwrap(),
+            pitches("E3 C4 G4 B4")
+        );
+        assert_eq!(
+            voice_chord(Note::C, ChordType::Major7, VoicingStyle::Drop24, 4).unwrap(),
+            pitches("C3 G3 E4 B4")
+        );
+        // Triads double the root on top
+        assert_eq!(
+            voice_chord(Note::C, ChordType::Major, VoicingStyle::Drop2, 4).unwrap(),
+            pitches("G3 C4 E4 C5")
+        );
+    }
+
+    #[test]
+    fn test_voicing_rootless() {
+        // Dm7: A = F A C E, B = C E F A
+        assert_eq!(
+            voice_chord(Note::D, ChordType::Minor7, VoicingStyle::RootlessA, 3).unwrap(),
+            pitches("F3 A3 C4 E4")
+        );
+        assert_eq!(
+            voice_chord(Note::D, ChordType::Minor7, VoicingStyle::RootlessB, 4).unwrap(),
+            pitches("C4 E4 F4 A4")
+        );
+        // G13: A = B E F A, B = F A B E
+        assert_eq!(
+            voice_chord(Note::G, ChordType::Dominant7, VoicingStyle::RootlessA, 3).unwrap(),
+            pitches("B3 E4 F4 A4")
+        );
+        assert_eq!(
+            voice_chord(Note::G, ChordType::Dominant7, VoicingStyle::RootlessB, 4).unwrap(),
+            pitches("F4 A4 B4 E5")
+        );
+        assert!(voice_chord(Note::G, ChordType::Major, VoicingStyle::RootlessA, 3).is_err());
+    }
+
+    #[test]
+    fn test_voicing_quartal() {
+        // "So What" on Em7: E A D G B
+        assert_eq!(
+            voice_chord(Note::E, ChordType::Minor7, VoicingStyle::Quartal, 3).unwrap(),
+            pitches("E3 A3 D4 G4 B4")
+        );
+        assert!(voice_chord(Note::C, ChordType::Diminished, VoicingStyle::Quartal, 3).is_err());
+    }
+
+    #[test]
+    fn test_voicing_progression_stays_close() {
+        let progression: Vec<ProgressionChord> = build_custom_progression(
+            Note::C,
+            parse_progression_formula("ii7-V7-Imaj7").unwrap(),
+            &VoiceLeadingOptions::default(),
+        );
+        let voiced: Vec<VoicedChord> =
+            voice_progression_in_style(&progression, VoicingStyle::RootlessA, 3).unwrap();
+        assert_eq!(voiced.len(), 3);
+        for pair in voiced.windows(2) {
+            assert!((center(&pair[0].pitches) - center(&pair[1].pitches)).abs() <= 6);
+        }
+
+        let triads: Vec<ProgressionChord> = build_custom_progression(
+            Note::C,
+            parse_progression_formula("I-IV").unwrap(),
+            &VoiceLeadingOptions::default(),
+        );
+        assert!(voice_progression_in_style(&triads, VoicingStyle::Shell, 3).is_err());
+    }
+}
@@ -0,0 +1,265 @@
+/*
+ * tonic-music-core
+ * Copyright (C) 2025 Israel Alberto Roldan Vega
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ *
+ * Repository: https://github.com/airvzxf/music-theory-calculator/
+ */
+
+/*
+ * crates/tonic-music-core/src/wav.rs
+ *
+ * This module writes mono audio as a RIFF WAVE (.wav) file,
+ * with 16-bit integer or 32-bit float samples, and reads
+ * PCM and float WAV files back as mono samples.
+ */
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// The sample format of a WAV file.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, ValueEnum, Serialize)]
+#[clap(rename_all = "kebab-case")]
+pub enum SampleFormat {
+    /// 16-bit integer PCM, read by every player
+    #[default]
+    #[value(alias("16"))]
+    Pcm16,
+    /// 32-bit IEEE float
+    #[value(alias("float"), alias("32"))]
+    Float32,
+}
+
+/// Writes mono samples (-1.0 to 1.0) as a WAV file. Samples outside that
+/// range are clipped.
+pub fn write_wav(samples: &[f32], sample_rate: u32, format: SampleFormat) -> Vec<u8> {
+    // 1. The format chunk: float files carry an empty extension and a fact chunk
+    let (tag, bits): (u16, u16) = match format {
+        SampleFormat::Pcm16 => (1, 16),
+        SampleFormat::Float32 => (3, 32),
+    };
+    let block_align: u16 = bits / 8;
+    let mut fmt: Vec<u8> = Vec::<u8>::new();
+    fmt.extend(tag.to_le_bytes());
+    fmt.extend(1u16.to_le_bytes());
+    fmt.extend(sample_rate.to_le_bytes());
+    fmt.extend((sample_rate * block_align as u32).to_le_bytes());
+    fmt.extend(block_align.to_le_bytes());
+    fmt.extend(bits.to_le_bytes());
+    if format == SampleFormat::Float32 {
+        fmt.extend(0u16.to_le_bytes());
+    }
+
+    // 2. The samples
+    let mut data: Vec<u8> = Vec::<u8>::with_capacity(samples.len() * block_align as usize);
+    for sample in samples {
+        let sample: f32 = sample.clamp(-1.0, 1.0);
+        match format {
+            SampleFormat::Pcm16 => data.extend(((sample * 32767.0).round() as i16).to_le_bytes()),
+            SampleFormat::Float32 => data.extend(sample.to_le_bytes()),
+        }
+    }
+
+    // 3. The RIFF container
+    let mut chunks: Vec<u8> = b"WAVE".to_vec();
+    push_chunk(&mut chunks, b"fmt ", &fmt);
+    if format == SampleFormat::Float32 {
+        push_chunk(&mut chunks, b"fact", &(samples.len() as u32).to_le_bytes());
+    }
+    push_chunk(&mut chunks, b"data", &data);
+    let mut bytes: Vec<u8> = b"RIFF".to_vec();
+    bytes.extend((chunks.len() as u32).to_le_bytes());
+    bytes.extend(chunks);
+    bytes
+}
+
+/// The audio of a WAV file, mixed down to mono.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Wav {
+    pub sample_rate: u32,
+    /// The channels of the file (the samples are their average)
+    pub channels: u16,
+    /// Samples from -1.0 to 1.0
+    pub samples: Vec<f32>,
+}
+
+impl Wav {
+    /// The length in seconds.
+    pub fn duration(&self) -> f64 {
+        self.samples.len() as f64 / self.sample_rate as f64
+    }
+}
+
+/// Decodes one sample of `bits` bits: 8-bit PCM is unsigned, wider PCM signed,
+/// and float samples are 32 or 64 bits.
+fn decode_sample(bytes: &[u8], float: bool) -> f32 {
+    match (float, bytes.len()) {
+        (true, 4) => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        (true, _) => f64::from_le_bytes(bytes.try_into().unwrap_or([0; 8])) as f32,
+        (false, 1) => (bytes[0] as f32 - 128.0) / 128.0,
+        (false, 2) => i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / 32768.0,
+        (false, 3) => {
+            (i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8) as f32 / 8_388_608.0
+        }
+        (false, _) => {
+            i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32 / 2_147_483_648.0
+        }
+    }
+}
+
+/// Reads a WAV file: 8, 16, 24 or 32-bit PCM, or 32 or 64-bit float.
+/// Several channels are mixed down to mono.
+pub fn read_wav(bytes: &[u8]) -> Result<Wav, String> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err("Not a WAV file (missing RIFF/WAVE header)".to_string());
+    }
+
+    // 1. Find the format and data chunks
+    let mut format: Option<&[u8]> = None;
+    let mut data: Option<&[u8]> = None;
+    let mut position: usize = 12;
+    while position + 8 <= bytes.len() {
+        let id: &[u8] = &bytes[position..position + 4];
+        let size: usize = u32::from_le_bytes([
+            bytes[position + 4],
+            bytes[position + 5],
+            bytes[position + 6],
+            bytes[position + 7],
+        ]) as usize;
+        let body: &[u8] = &bytes[position + 8..(position + 8 + size).min(bytes.len())];
+        match id {
+            b"fmt " => format = Some(body),
+            b"data" => data = Some(body),
+            _ => {}
+        }
+        position += 8 + size + size % 2;
+    }
+    let format: &[u8] = format
+        .filter(|f: &&[u8]| f.len() >= 16)
+        .ok_or("The WAV file has no format chunk")?;
+    let data: &[u8] = data.ok_or("The WAV file has no data chunk")?;
+
+    // 2. The sample format (extensible files keep it in the sub-format)
+    let mut tag: u16 = u16::from_le_bytes([format[0], format[1]]);
+    if tag == 0xfffe && format.len() >= 26 {
+        tag = u16::from_le_bytes([format[24], format[25]]);
+    }
+    let channels: u16 = u16::from_le_bytes([format[2], format[3]]);
+    let sample_rate: u32 = u32::from_le_bytes([format[4], format[5], format[6], format[7]]);
+    let bits: u16 = u16::from_le_bytes([format[14], format[15]]);
+    let float: bool = match (tag, bits) {
+        (1, 8 | 16 | 24 | 32) => false,
+        (3, 32 | 64) => true,
+        _ => {
+            return Err(format!(
+                "Unsupported WAV encoding (format {}, {} bits): use PCM or float",
+                tag, bits
+            ));
+        }
+    };
+    if channels == 0 || sample_rate == 0 {
+        return Err("The WAV file has no channels or no sample rate".to_string());
+    }
+
+    // 3. Decode the frames, averaging the channels
+    let width: usize = bits as usize / 8;
+    let frame: usize = width * channels as usize;
+    let samples: Vec<f32> = data
+        .chunks_exact(frame)
+        .map(|f: &[u8]| {
+            f.chunks_exact(width)
+                .map(|s: &[u8]| decode_sample(s, float))
+                .sum::<f32>()
+                / channels as f32
+        })
+        .collect();
+    Ok(Wav {
+        sample_rate,
+        channels,
+        samples,
+    })
+}
+
+/// Appends a RIFF chunk, padded to an even length.
+fn push_chunk(bytes: &mut Vec<u8>, id: &[u8; 4], body: &[u8]) {
+    bytes.extend(id);
+    bytes.extend((body.len() as u32).to_le_bytes());
+    bytes.extend(body);
+    if body.len() % 2 == 1 {
+        bytes.push(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_wav_pcm16() {
+        let bytes: Vec<u8> = write_wav(&[0.0, 1.0, -1.0, 2.0], 8000, SampleFormat::Pcm16);
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(bytes[4..8].try_into().unwrap()), 36 + 8);
+        assert_eq!(&bytes[8..16], b"WAVEfmt ");
+        // PCM, mono, 8000 Hz, 16000 bytes per second, 16 bits
+        assert_eq!(&bytes[20..24], &[1, 0, 1, 0]);
+        assert_eq!(u32::from_le_bytes(bytes[24..28].try_into().unwrap()), 8000);
+        assert_eq!(u32::from_le_bytes(bytes[28..32].try_into().unwrap()), 16000);
+        assert_eq!(&bytes[36..44], b"data\x08\x00\x00\x00");
+        let samples: Vec<i16> = bytes[44..]
+            .chunks(2)
+            .map(|b: &[u8]| i16::from_le_bytes([b[0], b[1]]))
+            .collect();
+        // Samples past full scale are clipped
+        assert_eq!(samples, vec![0, 32767, -32767, 32767]);
+    }
+
+    #[test]
+    fn test_write_wav_float() {
+        let bytes: Vec<u8> = write_wav(&[0.5, -0.25], 44100, SampleFormat::Float32);
+        assert_eq!(&bytes[20..22], &[3, 0]);
+        assert_eq!(&bytes[34..36], &[32, 0]);
+        assert_eq!(&bytes[38..42], b"fact");
+        assert_eq!(&bytes[50..58], b"data\x08\x00\x00\x00");
+        assert_eq!(f32::from_le_bytes(bytes[58..62].try_into().unwrap()), 0.5);
+        assert_eq!(f32::from_le_bytes(bytes[62..66].try_into().unwrap()), -0.25);
+    }
+
+    #[test]
+    fn test_read_wav() {
+        let samples: Vec<f32> = vec![0.0, 0.5, -0.5, 0.25];
+        for format in [SampleFormat::Pcm16, SampleFormat::Float32] {
+            let wav: Wav = read_wav(&write_wav(&samples, 22050, format)).unwrap();
+            assert_eq!(wav.sample_rate, 22050);
+            assert_eq!(wav.channels, 1);
+            for (read, written) in wav.samples.iter().zip(&samples) {
+                assert!((read - written).abs() < 1e-4);
+            }
+        }
+
+        // Stereo 8-bit PCM is mixed down to mono
+        let mut bytes: Vec<u8> = write_wav(&[], 8000, SampleFormat::Pcm16);
+        bytes[22] = 2;
+        bytes[34] = 8;
+        bytes.truncate(40);
+        bytes.extend([4, 0, 0, 0, 255, 129, 0, 128]);
+        let wav: Wav = read_wav(&bytes).unwrap();
+        assert_eq!(wav.channels, 2);
+        assert_eq!(wav.samples.len(), 2);
+        assert!((wav.samples[0] - 0.5).abs() < 1e-3);
+        assert!((wav.samples[1] + 0.5).abs() < 1e-3);
+
+        assert!(read_wav(b"RIFF\0\0\0\0AVI ").is_err());
+    }
+}
@@ -1,4 +1,7 @@
+use tonic_music_core::analysis::HarmonicFunction;
+use tonic_music_core::library::{ProgressionDefinition, ProgressionLibrary};
 use tonic_music_core::parser::parse_roman_chord;
+use tonic_music_core::voice_leading::VoiceLeadingOptions;
 use tonic_music_core::{ChordType, HarmonicFormula, Note, ScaleType};
 // Logic
 use tonic_music_core::{
@@ -80,6 +83,18 @@ pub enum FfiScaleType {
     PentatonicMinor,
 }
 
+impl From<ScaleType> for FfiScaleType {
+    fn from(scale: ScaleType) -> Self {
+        match scale {
+            ScaleType::Major => FfiScaleType::Major,
+            ScaleType::MinorNatural => FfiScaleType::MinorNatural,
+            ScaleType::MinorHarmonic => FfiScaleType::MinorHarmonic,
+            ScaleType::PentatonicMajor => FfiScaleType::PentatonicMajor,
+            ScaleType::PentatonicMinor => FfiScaleType::PentatonicMinor,
+        }
+    }
+}
+
 impl From<FfiScaleType> for ScaleType {
     fn from(scale: FfiScaleType) -> Self {
         match scale {
@@ -166,6 +181,29 @@ impl From<FfiHarmonicFormula> for HarmonicFormula {
     }
 }
 
+// --- HarmonicFunction ---
+
+#[derive(uniffi::Enum)]
+pub enum FfiHarmonicFunction {
+    Tonic,
+    TonicProlongation,
+    Subdominant,
+    Predominant,
+    Dominant,
+}
+
+impl From<HarmonicFunction> for FfiHarmonicFunction {
+    fn from(hf: HarmonicFunction) -> Self {
+        match hf {
+            HarmonicFunction::Tonic => FfiHarmonicFunction::Tonic,
+            HarmonicFunction::TonicProlongation => FfiHarmonicFunction::TonicProlongation,
+            HarmonicFunction::Subdominant => FfiHarmonicFunction::Subdominant,
+            HarmonicFunction::Predominant => FfiHarmonicFunction::Predominant,
+            HarmonicFunction::Dominant => FfiHarmonicFunction::Dominant,
+        }
+    }
+}
+
 // --- Structs ---
 
 #[derive(uniffi::Record)]
@@ -181,9 +219,33 @@ pub struct FfiProgressionChord {
     pub degree: String,
     pub root_note: FfiNote,
     pub chord_type: FfiChordType,
+    pub function: FfiHarmonicFunction,
     pub notes: Vec<FfiNote>,
 }
 
+#[derive(uniffi::Record)]
+pub struct FfiProgressionDefinition {
+    pub name: String,
+    pub formula: String,
+    pub tags: Vec<String>,
+    pub description: String,
+    pub key: Option<FfiNote>,
+    pub mode: Option<FfiScaleType>,
+}
+
+impl From<ProgressionDefinition> for FfiProgressionDefinition {
+    fn from(d: ProgressionDefinition) -> Self {
+        FfiProgressionDefinition {
+            name: d.name,
+            formula: d.formula,
+            tags: d.tags,
+            description: d.description,
+            key: d.key.map(FfiNote::from),
+            mode: d.mode.map(FfiScaleType::from),
+        }
+    }
+}
+
 // --- Functions ---
 
 #[uniffi::export]
@@ -241,7 +303,7 @@ pub fn get_progression(root: FfiNote, formula: FfiHarmonicFormula) -> Vec<FfiPro
     let core_formula: HarmonicFormula = formula.into();
 
     let progression: Vec<tonic_music_core::ProgressionChord> =
-        build_progression(core_root, core_formula);
+        build_progression(core_root, core_formula, &VoiceLeadingOptions::default());
 
     progression
         .into_iter()
@@ -250,6 +312,7 @@ pub fn get_progression(root: FfiNote, formula: FfiHarmonicFormula) -> Vec<FfiPro
                 degree: p.degree,
                 root_note: p.root_note.into(),
                 chord_type: p.chord_type.into(),
+                function: p.function.into(),
                 notes: p
                     .notes
                     .into_iter()
@@ -286,7 +349,7 @@ pub fn get_custom_progression(
     }
 
     let progression: Vec<tonic_music_core::ProgressionChord> =
-        build_custom_progression(core_root, parsed_chords);
+        build_custom_progression(core_root, parsed_chords, &VoiceLeadingOptions::default());
 
     Ok(progression
         .into_iter()
@@ -295,6 +358,57 @@ pub fn get_custom_progression(
                 degree: p.degree,
                 root_note: p.root_note.into(),
                 chord_type: p.chord_type.into(),
+                function: p.function.into(),
+                notes: p
+                    .notes
+                    .into_iter()
+                    .map(|n: Note| FfiNote::from(n))
+                    .collect(),
+            },
+        )
+        .collect())
+}
+
+#[uniffi::export]
+pub fn list_progressions(tag: Option<String>) -> Vec<FfiProgressionDefinition> {
+    let library: ProgressionLibrary = ProgressionLibrary::builtin();
+
+    let definitions: Vec<ProgressionDefinition> = match tag {
+        Some(t) => library.with_tag(&t).into_iter().cloned().collect(),
+        None => library.definitions,
+    };
+
+    definitions
+        .into_iter()
+        .map(FfiProgressionDefinition::from)
+        .collect()
+}
+
+#[uniffi::export]
+pub fn get_library_progression(
+    root: FfiNote,
+    name: String,
+) -> Result<Vec<FfiProgressionChord>, FfiError> {
+    let core_root: Note = root.into();
+
+    let library: ProgressionLibrary = ProgressionLibrary::builtin();
+    let definition: &ProgressionDefinition =
+        library.find(&name).ok_or_else(|| FfiError::Generic {
+            val: format!("Unknown progression '{}'", name),
+        })?;
+
+    let progression: Vec<tonic_music_core::ProgressionChord> = definition
+        .build(core_root, &VoiceLeadingOptions::default())
+        .map_err(|e: String| FfiError::Generic { val: e })?;
+
+    Ok(progression
+        .into_iter()
+        .map(
+            |p: tonic_music_core::ProgressionChord| FfiProgressionChord {
+                degree: p.degree,
+                root_note: p.root_note.into(),
+                chord_type: p.chord_type.into(),
+                function: p.function.into(),
                 notes: p
                     .notes
                     .into_iter()
@@ -354,4 +468,19 @@ mod tests {
         // Check types for the changed chord (2nd chord)
         assert!(matches!(progression[1].chord_type, FfiChordType::Dominant7));
     }
+
+    #[test]
+    fn test_progression_library() {
+        let jazz: Vec<FfiProgressionDefinition> = list_progressions(Some("jazz".to_string()));
+        assert!(jazz
+            .iter()
+            .any(|d: &FfiProgressionDefinition| d.name == "ii-v-i-major"));
+
+        let progression: Vec<FfiProgressionChord> =
+            get_library_progression(FfiNote::C, "ii-v-i-major".to_string()).unwrap();
+        assert_eq!(progression.len(), 3);
+        assert!(matches!(progression[1].chord_type, FfiChordType::Dominant7));
+
+        assert!(get_library_progression(FfiNote::C, "unknown".to_string()).is_err());
+    }
 }
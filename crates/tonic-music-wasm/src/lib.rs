@@ -1,9 +1,17 @@
 use clap::ValueEnum;
 use serde_wasm_bindgen::to_value;
 use tonic_music_core::{
-    ChordType, HarmonicFormula, ScaleType, build_chord, build_custom_progression,
-    build_progression, build_scale, get_inversions, harmonize_scale, parser::parse_note,
+    ChordType, ScaleType, build_chord, build_custom_progression, build_scale,
+    fretboard::{Fretboard, resolve_tuning},
+    get_inversions, harmonize_scale,
+    keyboard::{Hand, render_svg, scale_fingering, scale_pitches},
+    library::{ProgressionDefinition, ProgressionLibrary},
+    parser::parse_note,
     parser::parse_roman_chord,
+    part_writing::{Violation, check_part_writing, parse_voicing_json},
+    scale_shapes::{ScaleShape, ShapeSystem, scale_shapes},
+    voice_leading::{VoiceLeadingOptions, VoicedChord},
+    voicing::{VoicingStyle, voice_chord},
 };
 use wasm_bindgen::prelude::*;
 
@@ -52,22 +60,53 @@ pub fn get_harmonization(root: &str, scale_type: &str, sevenths: bool) -> Result
     Ok(to_value(&harmony)?)
 }
 
+/// Reads optional voice-leading weights from a JSON object, e.g.
+/// { bass_motion: 7, range: 1, common_tones: 2, contrary_motion: 0,
+///   inversion: 0, preferred_inversion: 0 }. Missing fields keep their defaults.
+fn parse_voice_leading_options(options: JsValue) -> Result<VoiceLeadingOptions, JsValue> {
+    if options.is_undefined() || options.is_null() {
+        return Ok(VoiceLeadingOptions::default());
+    }
+    serde_wasm_bindgen::from_value(options)
+        .map_err(|e| JsValue::from_str(&format!("Invalid voice leading options: {}", e)))
+}
+
 #[wasm_bindgen]
-pub fn get_progression(root: &str, formula: &str) -> Result<JsValue, JsValue> {
+pub fn get_progression(root: &str, formula: &str, options: JsValue) -> Result<JsValue, JsValue> {
     let root_note: tonic_music_core::Note =
         parse_note(root).map_err(|e: String| JsValue::from_str(&e))?;
 
-    let formula_enum: HarmonicFormula = HarmonicFormula::from_str(formula, true)
-        .map_err(|e: String| JsValue::from_str(&format!("Invalid formula: {}", e)))?;
+    let library: ProgressionLibrary = ProgressionLibrary::builtin();
+    let definition: &ProgressionDefinition = library
+        .find(formula)
+        .ok_or_else(|| JsValue::from_str(&format!("Invalid formula: {}", formula)))?;
 
-    let progression: Vec<tonic_music_core::ProgressionChord> =
-        build_progression(root_note, formula_enum);
+    let options: VoiceLeadingOptions = parse_voice_leading_options(options)?;
+    let progression: Vec<tonic_music_core::ProgressionChord> = definition
+        .build(root_note, &options)
+        .map_err(|e: String| JsValue::from_str(&format!("Invalid formula: {}", e)))?;
 
     Ok(to_value(&progression)?)
 }
 
 #[wasm_bindgen]
-pub fn get_custom_progression(root: &str, custom_formula: &str) -> Result<JsValue, JsValue> {
+pub fn list_progressions(tag: Option<String>) -> Result<JsValue, JsValue> {
+    let library: ProgressionLibrary = ProgressionLibrary::builtin();
+
+    let progressions: Vec<&ProgressionDefinition> = match tag {
+        Some(t) => library.with_tag(&t),
+        None => library.definitions.iter().collect(),
+    };
+
+    Ok(to_value(&progressions)?)
+}
+
+#[wasm_bindgen]
+pub fn get_custom_progression(
+    root: &str,
+    custom_formula: &str,
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
     let root_note: tonic_music_core::Note =
         parse_note(root).map_err(|e: String| JsValue::from_str(&e))?;
 
@@ -81,12 +120,100 @@ pub fn get_custom_progression(root: &str, custom_formula: &str) -> Result<JsValu
     let specs: Vec<tonic_music_core::parser::ParsedRomanChord> = specs_res
         .map_err(|e: String| JsValue::from_str(&format!("Invalid custom formula: {}", e)))?;
 
+    let options: VoiceLeadingOptions = parse_voice_leading_options(options)?;
     let progression: Vec<tonic_music_core::ProgressionChord> =
-        build_custom_progression(root_note, specs);
+        build_custom_progression(root_note, specs, &options);
 
     Ok(to_value(&progression)?)
 }
 
+/// Checks user voicings given as JSON:
+/// { "key": "C", "chords": [["C3", "G3", "E4", "C5"], ...] } (lowest voice first)
+#[wasm_bindgen]
+pub fn check_voicing(voicing_json: &str) -> Result<JsValue, JsValue> {
+    let (key, chords): (tonic_music_core::Note, Vec<VoicedChord>) =
+        parse_voicing_json(voicing_json).map_err(|e: String| JsValue::from_str(&e))?;
+
+    let violations: Vec<Violation> = check_part_writing(key, &chords);
+
+    Ok(to_value(&violations)?)
+}
+
+/// Splits a scale into guitar positions (system: "caged", "pentatonic" or "three-nps").
+/// The tuning is a preset name ("standard", "drop-d"...) or pitches from the lowest string.
+#[wasm_bindgen]
+pub fn get_scale_shapes(
+    root: &str,
+    scale_type: &str,
+    system: &str,
+    tuning: &str,
+) -> Result<JsValue, JsValue> {
+    let root_note: tonic_music_core::Note =
+        parse_note(root).map_err(|e: String| JsValue::from_str(&e))?;
+
+    let scale: ScaleType = ScaleType::from_str(scale_type, true)
+        .map_err(|e: String| JsValue::from_str(&format!("Invalid scale type: {}", e)))?;
+
+    let system: ShapeSystem = ShapeSystem::from_str(system, true)
+        .map_err(|e: String| JsValue::from_str(&format!("Invalid shape system: {}", e)))?;
+
+    let fretboard: Fretboard = resolve_tuning(tuning)
+        .and_then(|t| Fretboard::new(t, 24, 0))
+        .map_err(|e: String| JsValue::from_str(&e))?;
+
+    let shapes: Vec<ScaleShape> = scale_shapes(&fretboard, root_note, scale, system)
+        .map_err(|e: String| JsValue::from_str(&e))?;
+
+    Ok(to_value(&shapes)?)
+}
+
+/// Draws one octave of a scale on a piano keyboard (SVG). With a hand ("right"
+/// or "left"), the keys show its standard fingering.
+#[wasm_bindgen]
+pub fn get_scale_keyboard_svg(
+    root: &str,
+    scale_type: &str,
+    hand: Option<String>,
+) -> Result<String, JsValue> {
+    let root_note: tonic_music_core::Note =
+        parse_note(root).map_err(|e: String| JsValue::from_str(&e))?;
+
+    let scale: ScaleType = ScaleType::from_str(scale_type, true)
+        .map_err(|e: String| JsValue::from_str(&format!("Invalid scale type: {}", e)))?;
+
+    let labels: Option<Vec<String>> = match hand {
+        Some(hand) => {
+            let hand: Hand = Hand::from_str(&hand, true)
+                .map_err(|e: String| JsValue::from_str(&format!("Invalid hand: {}", e)))?;
+            let fingers: Vec<u8> = scale_fingering(root_note, scale, hand)
+                .map_err(|e: String| JsValue::from_str(&e))?;
+            Some(fingers.iter().map(|f: &u8| f.to_string()).collect())
+        }
+        None => None,
+    };
+
+    Ok(render_svg(
+        &scale_pitches(root_note, scale, 4),
+        labels.as_deref(),
+    ))
+}
+
+/// Draws a chord in close position from the 4th octave on a piano keyboard (SVG).
+#[wasm_bindgen]
+pub fn get_chord_keyboard_svg(root: &str, chord_type: &str) -> Result<String, JsValue> {
+    let root_note: tonic_music_core::Note =
+        parse_note(root).map_err(|e: String| JsValue::from_str(&e))?;
+
+    let chord: ChordType = ChordType::from_str(chord_type, true)
+        .map_err(|e: String| JsValue::from_str(&format!("Invalid chord type: {}", e)))?;
+
+    let pitches: Vec<tonic_music_core::pitch::Pitch> =
+        voice_chord(root_note, chord, VoicingStyle::Close, 4)
+            .map_err(|e: String| JsValue::from_str(&e))?;
+
+    Ok(render_svg(&pitches, None))
+}
+
 #[wasm_bindgen]
 pub fn get_version() -> String {
     env!("CARGO_PKG_VERSION").to_string()